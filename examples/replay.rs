@@ -0,0 +1,86 @@
+//! Replays a send recorded in a journal against a simulated chain (see
+//! `test_util::replay`), to reproduce incidents deterministically:
+//!
+//! ```text
+//! RUST_LOG=trace cargo run --example replay --features test-util,tokio/rt-multi-thread -- \
+//!     --journal journal.jsonl --sender <address> --nonce 42
+//! ```
+//!
+//! Prints the recorded and the replayed state transitions, and exits with an
+//! error if they diverge.
+
+use clap::Parser;
+use ethers::types::{Address, U256};
+use std::process::ExitCode;
+
+use eth_tx_manager::{journal::Transition, test_util::replay::Replay, Chain};
+
+#[derive(Parser)]
+#[command(name = "replay")]
+#[command(about = "Replays a send recorded in the transaction manager's journal")]
+struct Args {
+    /// Path to the journal
+    #[arg(long, env)]
+    journal: String,
+
+    /// Sender of the recorded transaction
+    #[arg(long, env)]
+    sender: Address,
+
+    /// Nonce of the recorded transaction
+    #[arg(long, env)]
+    nonce: u64,
+
+    /// Chain ID of the simulated chain
+    #[arg(long, env, default_value_t = 1337)]
+    chain_id: u64,
+
+    /// Whether the chain is legacy (pre-EIP1559)
+    #[arg(long, env, default_value_t = false)]
+    legacy: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+    let args = Args::parse();
+
+    let replay = match Replay::from_journal(&args.journal, args.sender, U256::from(args.nonce)) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Could not read the send: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let chain = if args.legacy {
+        Chain::legacy(args.chain_id)
+    } else {
+        Chain::new(args.chain_id)
+    };
+    let report = replay.run(chain, |configuration| configuration).await;
+
+    println!("Recorded:");
+    print_transitions(&report.recorded);
+    println!("Replayed:");
+    print_transitions(&report.replayed);
+    match &report.result {
+        Ok(receipt) => println!("Result: mined as {:?}", receipt.transaction_hash),
+        Err(err) => println!("Result: failed with {}", err),
+    }
+
+    match report.divergence() {
+        None => ExitCode::SUCCESS,
+        Some(position) => {
+            eprintln!("The replay diverged at transition {}.", position);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_transitions(transitions: &[Transition]) {
+    for (position, transition) in transitions.iter().enumerate() {
+        println!("  {}: {:?}", position, transition);
+    }
+}
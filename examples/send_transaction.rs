@@ -3,15 +3,15 @@ use ethers::{
     middleware::signer::SignerMiddleware,
     providers::{Http, Provider},
     signers::{LocalWallet, Signer},
-    types::{H160, U256},
+    types::H160,
 };
 
 use eth_tx_manager::{
     database::FileSystemDatabase,
-    gas_oracle::DefaultGasOracle,
-    manager::Configuration,
-    transaction::{Priority, Transaction, Value},
-    Chain, TransactionManager,
+    gas_oracle::NoGasOracle,
+    manager::{Configuration, SendOptions},
+    transaction::{Metadata, Transaction, Value},
+    Chain, Gwei, PendingResolution, TransactionManager,
 };
 
 #[tokio::main]
@@ -22,25 +22,26 @@ async fn main() {
     let wallet = LocalWallet::new(&mut thread_rng()).with_chain_id(chain.id);
     let provider = SignerMiddleware::new(provider, wallet.clone());
 
-    let gas_oracle = DefaultGasOracle::new();
+    let gas_oracle = NoGasOracle;
     let database = FileSystemDatabase::new("database.json".to_string());
     let configuration = Configuration::default();
 
-    let (manager, receipt) =
+    let (manager, resolution) =
         TransactionManager::new(provider, gas_oracle, database, chain, configuration)
             .await
             .unwrap();
-    assert!(receipt.is_none());
+    assert_eq!(resolution, PendingResolution::None);
 
     let transaction = Transaction {
         from: wallet.address(),
-        to: H160::random(),
-        value: Value::Number(U256::from(1e9 as u64)),
+        to: H160::random().into(),
+        value: Value::Number(Gwei::from(1).into()),
         call_data: None,
+        metadata: Metadata::new(),
     };
 
     let result = manager
-        .send_transaction(transaction, 1, Priority::Normal)
+        .send(transaction, SendOptions::new().set_confirmations(1))
         .await;
     assert!(result.is_err());
     println!("{:?}", result.err().unwrap());
@@ -0,0 +1,269 @@
+//! Long-running soak test of the resubmission behavior.
+//!
+//! Sends a transaction per interval (one minute, by default) to a development
+//! node (geth --dev or anvil) for hours, with a deliberately stingy gas oracle
+//! (a tiny max priority fee and no max fee), so that the manager has to
+//! escalate the fees. For each transaction, it records how many times it was
+//! submitted and what it cost to a CSV file, to compare across releases:
+//!
+//! ```text
+//! cargo run --example soak --features tokio/rt-multi-thread -- \
+//!     --private-key <KEY> --hours 4 --output soak.csv
+//! ```
+
+use async_trait::async_trait;
+use clap::Parser;
+use ethers::{
+    middleware::signer::SignerMiddleware,
+    providers::{Http, Provider},
+    signers::{LocalWallet, Signer},
+    types::{H160, H256, U256},
+};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use eth_tx_manager::{
+    database::FileSystemDatabase,
+    events::{Event, EventListener},
+    gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo},
+    manager::{Configuration, SendOptions},
+    time::DefaultTime,
+    transaction::{Metadata, Priority, Transaction, Value},
+    Chain, TransactionManager, Wei,
+};
+
+#[derive(Parser)]
+#[command(name = "soak")]
+#[command(about = "Soak test of the transaction manager's resubmissions")]
+struct Args {
+    /// Development node http endpoint url
+    #[arg(long, env, default_value = "http://localhost:8545")]
+    endpoint: String,
+
+    /// Chain ID of the development node
+    #[arg(long, env, default_value_t = 31337)]
+    chain_id: u64,
+
+    /// Private key of a funded account
+    #[arg(long, env)]
+    private_key: String,
+
+    /// Seconds between transactions
+    #[arg(long, env, default_value_t = 60)]
+    interval: u64,
+
+    /// How long to run for
+    #[arg(long, env, default_value_t = 4.)]
+    hours: f64,
+
+    /// Max priority fee (in wei) offered by the stingy gas oracle
+    #[arg(long, env, default_value_t = 1)]
+    priority_fee: u64,
+
+    /// Confirmations of each transaction
+    #[arg(long, env, default_value_t = 1)]
+    confirmations: usize,
+
+    /// CSV file the results are written to
+    #[arg(long, env, default_value = "soak.csv")]
+    output: String,
+
+    /// Path to the manager's database file
+    #[arg(long, env, default_value = "soak_database.json")]
+    database_path: String,
+}
+
+/// Gas oracle that only offers a fixed (low) max priority fee, leaving the
+/// max fee to the manager.
+#[derive(Debug)]
+struct StingyGasOracle {
+    max_priority_fee: Wei,
+}
+
+#[async_trait]
+impl GasOracle for StingyGasOracle {
+    type Error = Infallible;
+
+    async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
+        Ok(GasOracleInfo {
+            gas_info: GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: None,
+                max_priority_fee: Some(self.max_priority_fee),
+            }),
+            mining_time: None,
+            block_time: None,
+            timestamp: None,
+        })
+    }
+}
+
+/// What the events tell about a transaction.
+#[derive(Clone, Debug, Default)]
+struct Record {
+    nonce: U256,
+    submissions: u32,
+    cost: Option<U256>,
+    mining_time: Option<Duration>,
+    confirmation_time: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+struct Records {
+    by_nonce: HashMap<U256, Record>,
+
+    /// The nonces of the submitted transactions (resubmissions included).
+    nonces: HashMap<H256, U256>,
+}
+
+#[derive(Debug, Default)]
+struct Recorder {
+    records: Mutex<Records>,
+}
+
+impl Recorder {
+    /// Removes the record of the transaction with the given hash.
+    fn take(&self, hash: H256) -> Record {
+        let mut records = self.records.lock().unwrap();
+        let nonce = match records.nonces.get(&hash) {
+            Some(nonce) => *nonce,
+            None => return Record::default(),
+        };
+        records.nonces.retain(|_, other| *other != nonce);
+        records.by_nonce.remove(&nonce).unwrap_or_default()
+    }
+}
+
+impl EventListener for Recorder {
+    fn notify(&self, event: &Event) {
+        let mut records = self.records.lock().unwrap();
+        match event {
+            Event::Submitted { nonce, hash, .. } => {
+                records.nonces.insert(*hash, *nonce);
+                let record = records.by_nonce.entry(*nonce).or_default();
+                record.nonce = *nonce;
+                record.submissions += 1;
+            }
+            Event::Confirmed {
+                nonce,
+                cost,
+                mining_time,
+                confirmation_time,
+                ..
+            } => {
+                let record = records.by_nonce.entry(*nonce).or_default();
+                record.cost = *cost;
+                record.mining_time = *mining_time;
+                record.confirmation_time = *confirmation_time;
+            }
+            _ => {}
+        }
+    }
+}
+
+type Manager = TransactionManager<
+    SignerMiddleware<Provider<Http>, LocalWallet>,
+    StingyGasOracle,
+    FileSystemDatabase,
+    DefaultTime,
+>;
+
+async fn new_manager(args: &Args, wallet: &LocalWallet, recorder: Arc<Recorder>) -> Manager {
+    let provider = Provider::<Http>::try_from(args.endpoint.as_str()).unwrap();
+    let provider = SignerMiddleware::new(provider, wallet.clone());
+    let gas_oracle = StingyGasOracle {
+        max_priority_fee: Wei::from(args.priority_fee),
+    };
+    let database = FileSystemDatabase::new(args.database_path.clone());
+    let configuration = Configuration::default().add_event_listener(recorder);
+    let (manager, resolution) = TransactionManager::new(
+        provider,
+        gas_oracle,
+        database,
+        Chain::new(args.chain_id),
+        configuration,
+    )
+    .await
+    .unwrap();
+    println!("Started the manager ({:?}).", resolution);
+    manager
+}
+
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+fn millis(duration: Option<Duration>) -> String {
+    duration
+        .map(|duration| duration.as_millis().to_string())
+        .unwrap_or_default()
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let wallet = args
+        .private_key
+        .parse::<LocalWallet>()
+        .unwrap()
+        .with_chain_id(args.chain_id);
+    let recorder = Arc::new(Recorder::default());
+
+    let mut output = File::create(&args.output).unwrap();
+    writeln!(
+        output,
+        "timestamp_ms,nonce,hash,submissions,resubmissions,cost_wei,mining_time_ms,confirmation_time_ms,error"
+    )
+    .unwrap();
+
+    let interval = Duration::from_secs(args.interval);
+    let duration = Duration::from_secs_f64(args.hours * 3600.);
+    let start = Instant::now();
+    let mut manager = Some(new_manager(&args, &wallet, recorder.clone()).await);
+    while start.elapsed() < duration {
+        let next = Instant::now() + interval;
+        let transaction = Transaction {
+            from: wallet.address(),
+            to: H160::random().into(),
+            value: Value::Number(Wei::from(1)),
+            call_data: None,
+            metadata: Metadata::new(),
+        };
+        let options = SendOptions::new().set_confirmations(args.confirmations);
+
+        let current = match manager.take() {
+            Some(current) => current,
+            None => new_manager(&args, &wallet, recorder.clone()).await,
+        };
+        let row = match current.send(transaction, options).await {
+            Ok((current, receipt)) => {
+                manager = Some(current);
+                let record = recorder.take(receipt.transaction_hash);
+                format!(
+                    "{},{},{:?},{},{},{},{},{},",
+                    timestamp(),
+                    record.nonce,
+                    receipt.transaction_hash,
+                    record.submissions,
+                    record.submissions.saturating_sub(1),
+                    record.cost.map(|cost| cost.to_string()).unwrap_or_default(),
+                    millis(record.mining_time),
+                    millis(record.confirmation_time),
+                )
+            }
+            // The manager is consumed on errors; a new one (which recovers the
+            // pending transaction, if any) is started on the next iteration.
+            Err(err) => format!("{},,,,,,,,{:?}", timestamp(), err.to_string()),
+        };
+        println!("{}", row);
+        writeln!(output, "{}", row).unwrap();
+
+        tokio::time::sleep(next.saturating_duration_since(Instant::now())).await;
+    }
+}
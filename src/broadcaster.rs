@@ -0,0 +1,28 @@
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    types::{Bytes, H256},
+};
+use std::fmt::Debug;
+
+use crate::dyn_manager::DynError;
+
+/// An endpoint through which the manager can (re)submit signed transactions,
+/// besides its provider (see `Configuration::fallback_broadcasters`).
+#[async_trait]
+pub trait Broadcaster: Debug + Send + Sync {
+    /// Sends the signed transaction and returns its hash.
+    async fn send_raw_transaction(&self, raw_transaction: Bytes) -> Result<H256, DynError>;
+}
+
+#[async_trait]
+impl<M> Broadcaster for M
+where
+    M: Middleware + Debug + Send + Sync,
+    M::Error: 'static,
+{
+    async fn send_raw_transaction(&self, raw_transaction: Bytes) -> Result<H256, DynError> {
+        let pending_transaction = Middleware::send_raw_transaction(self, raw_transaction).await?;
+        Ok(H256(*pending_transaction.as_fixed_bytes()))
+    }
+}
@@ -0,0 +1,151 @@
+//! Claim submission for Cartesi Rollups: builds the `submitClaim` call of the
+//! rollups' `Authority` contract from an epoch's claim, and sends it through
+//! the manager with the recommended settings.
+//!
+//! ```ignore
+//! let claim = Claim {
+//!     dapp,
+//!     epoch_hash,
+//!     first_index,
+//!     last_index,
+//! };
+//! let (manager, receipt) = cartesi::submit_claim(manager, sender, authority, &claim).await?;
+//! ```
+
+use ethers::{
+    abi::{self, Token},
+    providers::Middleware,
+    types::{Address, Bytes, TransactionReceipt, H256},
+    utils::id,
+};
+
+use crate::database::Database;
+use crate::gas_oracle::GasOracle;
+use crate::manager::{Error, Manager, SendOptions};
+use crate::time::Time;
+use crate::transaction::{Metadata, Priority, Transaction, Value};
+
+/// Signature of the claim submission function of the `Authority` contract
+/// (its argument is the ABI-encoded dapp address and claim).
+pub const SUBMIT_CLAIM_SIGNATURE: &str = "submitClaim(bytes)";
+
+/// Confirmations recommended for claims: enough to outlast the usual reorgs
+/// before the node moves on to the next epoch.
+pub const CLAIM_CONFIRMATIONS: usize = 10;
+
+/// The claim of a dapp's epoch: the hash of its outputs, and the range of the
+/// inputs it covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Claim {
+    pub dapp: Address,
+    pub epoch_hash: H256,
+    pub first_index: u128,
+    pub last_index: u128,
+}
+
+impl Claim {
+    /// The call data of the `submitClaim` call.
+    pub fn call_data(&self) -> Bytes {
+        let claim_data = abi::encode(&[
+            Token::Address(self.dapp),
+            Token::Tuple(vec![
+                Token::FixedBytes(self.epoch_hash.as_bytes().to_vec()),
+                Token::Uint(self.first_index.into()),
+                Token::Uint(self.last_index.into()),
+            ]),
+        ]);
+        let arguments = abi::encode(&[Token::Bytes(claim_data)]);
+        [&id(SUBMIT_CLAIM_SIGNATURE)[..], &arguments]
+            .concat()
+            .into()
+    }
+
+    /// The transaction that submits the claim to the `Authority` contract,
+    /// with the claim in its metadata.
+    pub fn transaction(&self, from: Address, authority: Address) -> Transaction {
+        Transaction {
+            from,
+            to: authority.into(),
+            value: Value::Nothing,
+            call_data: Some(self.call_data()),
+            metadata: Metadata::new(),
+        }
+        .with_metadata("dapp", format!("{:?}", self.dapp))
+        .with_metadata("epoch_hash", format!("{:?}", self.epoch_hash))
+        .with_metadata("first_index", self.first_index.to_string())
+        .with_metadata("last_index", self.last_index.to_string())
+    }
+}
+
+/// The send options recommended for claims.
+pub fn claim_send_options() -> SendOptions {
+    SendOptions::new()
+        .set_confirmations(CLAIM_CONFIRMATIONS)
+        .set_priority(Priority::High)
+}
+
+/// Submits the claim to the `Authority` contract (see `claim_send_options`).
+pub async fn submit_claim<M, GO, DB, T>(
+    manager: Manager<M, GO, DB, T>,
+    from: Address,
+    authority: Address,
+    claim: &Claim,
+) -> Result<(Manager<M, GO, DB, T>, TransactionReceipt), Error<M, GO, DB>>
+where
+    M: Middleware + Send + Sync,
+    GO: GasOracle + Send + Sync,
+    DB: Database + Send + Sync,
+    T: Time + Send + Sync,
+{
+    manager
+        .send(claim.transaction(from, authority), claim_send_options())
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::{
+        abi::{self, ParamType, Token},
+        types::{Address, H256, U256},
+        utils::id,
+    };
+
+    use super::{Claim, SUBMIT_CLAIM_SIGNATURE};
+
+    #[test]
+    fn test_claim_call_data() {
+        let claim = Claim {
+            dapp: Address::from_low_u64_be(1),
+            epoch_hash: H256::from_low_u64_be(2),
+            first_index: 3,
+            last_index: 7,
+        };
+        let call_data = claim.call_data();
+        assert_eq!(id(SUBMIT_CLAIM_SIGNATURE), call_data[..4]);
+
+        let arguments = abi::decode(&[ParamType::Bytes], &call_data[4..]).unwrap();
+        let claim_data = arguments[0].clone().into_bytes().unwrap();
+        let claim_type = ParamType::Tuple(vec![
+            ParamType::FixedBytes(32),
+            ParamType::Uint(128),
+            ParamType::Uint(128),
+        ]);
+        let tokens = abi::decode(&[ParamType::Address, claim_type], &claim_data).unwrap();
+        assert_eq!(
+            vec![
+                Token::Address(claim.dapp),
+                Token::Tuple(vec![
+                    Token::FixedBytes(claim.epoch_hash.as_bytes().to_vec()),
+                    Token::Uint(U256::from(3)),
+                    Token::Uint(U256::from(7)),
+                ]),
+            ],
+            tokens
+        );
+
+        let transaction =
+            claim.transaction(Address::from_low_u64_be(4), Address::from_low_u64_be(5));
+        assert_eq!(Some(call_data), transaction.call_data);
+        assert_eq!("7", transaction.metadata["last_index"]);
+    }
+}
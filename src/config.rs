@@ -30,6 +30,16 @@ pub struct TxEnvCLIConfig {
     /// Default confirmations
     #[arg(long, env)]
     pub tx_default_confirmations: Option<usize>,
+
+    /// Human-friendly logs (fees in gwei, values in the native currency, and
+    /// explorer links)
+    #[arg(long, env)]
+    pub tx_human_logs: Option<bool>,
+
+    /// Path to dump the audit log of JSON-RPC requests to (requires the
+    /// `debug-rpc` feature)
+    #[arg(long, env)]
+    pub tx_debug_rpc_dump_path: Option<String>,
 }
 
 #[derive(Clone)]
@@ -40,6 +50,8 @@ pub struct TxManagerConfig {
     pub chain_is_legacy: bool,
     pub database_path: String,
     pub gas_oracle_api_key: String,
+    pub human_logs: bool,
+    pub debug_rpc_dump_path: Option<String>,
 }
 
 impl Debug for TxManagerConfig {
@@ -51,6 +63,8 @@ impl Debug for TxManagerConfig {
             .field("chain_is_legacy", &self.chain_is_legacy)
             .field("database_path", &self.database_path)
             .field("gas_oracle_api_key", &self.gas_oracle_api_key)
+            .field("human_logs", &self.human_logs)
+            .field("debug_rpc_dump_path", &self.debug_rpc_dump_path)
             .finish()
     }
 }
@@ -94,6 +108,9 @@ impl TxManagerConfig {
             .tx_gas_oracle_api_key
             .unwrap_or_else(|| DEFAULT_GAS_ORACLE_API_KEY.to_string());
 
+        let human_logs = env_cli_config.tx_human_logs.unwrap_or(false);
+        let debug_rpc_dump_path = env_cli_config.tx_debug_rpc_dump_path;
+
         Ok(Self {
             default_confirmations,
             provider_http_endpoint,
@@ -101,15 +118,18 @@ impl TxManagerConfig {
             chain_is_legacy,
             database_path,
             gas_oracle_api_key,
+            human_logs,
+            debug_rpc_dump_path,
         })
     }
 }
 
 impl From<&TxManagerConfig> for Chain {
     fn from(config: &TxManagerConfig) -> Self {
-        Self {
-            id: config.chain_id,
-            is_legacy: config.chain_is_legacy,
+        if config.chain_is_legacy {
+            Chain::legacy(config.chain_id)
+        } else {
+            Chain::new(config.chain_id)
         }
     }
 }
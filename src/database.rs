@@ -1,11 +1,26 @@
 use async_trait::async_trait;
+use ethers::types::{H256, U256};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "bincode")]
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::fmt::Debug;
 use std::io::ErrorKind;
+use std::time::Duration;
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::warn;
 
-use crate::transaction::PersistentState;
+use crate::dyn_manager::{BoxedError, DynError};
+#[cfg(feature = "bincode")]
+use crate::manager::HandoffToken;
+use crate::manager::DEFAULT_CONFIRMATIONS;
+#[cfg(feature = "bincode")]
+use crate::transaction::{Attempt, BroadcastStatus, SubmittedTxs};
+use crate::transaction::{PersistentState, Priority, StaticTxData, Transaction};
 
+/// Object-safe: databases chosen at runtime (e.g., from the configuration) can
+/// be used as `Box<dyn DynDatabase>` (see `DynDatabase`).
 #[async_trait]
 pub trait Database: Debug {
     type Error: std::error::Error;
@@ -15,6 +30,216 @@ pub trait Database: Debug {
     async fn get_state(&self) -> Result<Option<PersistentState>, Self::Error>;
 
     async fn clear_state(&mut self) -> Result<(), Self::Error>;
+
+    /// Gets the state with its version (see `set_state_if_version`).
+    ///
+    /// The default implementation, for databases that do not track versions,
+    /// always returns version 0.
+    async fn get_versioned_state(&self) -> Result<Option<(PersistentState, u64)>, Self::Error>
+    where
+        Self: Sync,
+    {
+        Ok(self.get_state().await?.map(|state| (state, 0)))
+    }
+
+    /// Sets the state only if the stored one is at the expected version (0 if
+    /// there is none), so that concurrent writers (e.g., the manager and an
+    /// external tool) do not overwrite each other's changes. Returns the new
+    /// version, or a conflict error.
+    ///
+    /// The default implementation, for databases that do not track versions,
+    /// sets the state unconditionally.
+    async fn set_state_if_version(
+        &mut self,
+        state: &PersistentState,
+        expected_version: u64,
+    ) -> Result<u64, Self::Error>
+    where
+        Self: Send,
+    {
+        self.set_state(state).await?;
+        Ok(expected_version + 1)
+    }
+
+    /// Releases what the database holds for the manager (e.g., locks or
+    /// connections), on `Manager::shutdown`. The state is kept.
+    ///
+    /// The default implementation does nothing.
+    async fn close(&mut self) -> Result<(), Self::Error>
+    where
+        Self: Send,
+    {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<DB: Database + Send + Sync + ?Sized> Database for Box<DB> {
+    type Error = DB::Error;
+
+    async fn set_state(&mut self, state: &PersistentState) -> Result<(), Self::Error> {
+        (**self).set_state(state).await
+    }
+
+    async fn get_state(&self) -> Result<Option<PersistentState>, Self::Error> {
+        (**self).get_state().await
+    }
+
+    async fn clear_state(&mut self) -> Result<(), Self::Error> {
+        (**self).clear_state().await
+    }
+
+    async fn get_versioned_state(&self) -> Result<Option<(PersistentState, u64)>, Self::Error> {
+        (**self).get_versioned_state().await
+    }
+
+    async fn set_state_if_version(
+        &mut self,
+        state: &PersistentState,
+        expected_version: u64,
+    ) -> Result<u64, Self::Error> {
+        (**self).set_state_if_version(state, expected_version).await
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        (**self).close().await
+    }
+}
+
+/// A type-erased database, so that databases of different types can be chosen
+/// at runtime. `Box<dyn DynDatabase>` is itself a `Database`.
+#[async_trait]
+pub trait DynDatabase: Debug + Send + Sync {
+    async fn set_state(&mut self, state: &PersistentState) -> Result<(), DynError>;
+
+    async fn get_state(&self) -> Result<Option<PersistentState>, DynError>;
+
+    async fn clear_state(&mut self) -> Result<(), DynError>;
+
+    async fn get_versioned_state(&self) -> Result<Option<(PersistentState, u64)>, DynError>;
+
+    async fn set_state_if_version(
+        &mut self,
+        state: &PersistentState,
+        expected_version: u64,
+    ) -> Result<u64, DynError>;
+
+    async fn close(&mut self) -> Result<(), DynError>;
+}
+
+#[async_trait]
+impl<DB> DynDatabase for DB
+where
+    DB: Database + Send + Sync,
+    DB::Error: Send + Sync + 'static,
+{
+    async fn set_state(&mut self, state: &PersistentState) -> Result<(), DynError> {
+        Ok(Database::set_state(self, state).await?)
+    }
+
+    async fn get_state(&self) -> Result<Option<PersistentState>, DynError> {
+        Ok(Database::get_state(self).await?)
+    }
+
+    async fn clear_state(&mut self) -> Result<(), DynError> {
+        Ok(Database::clear_state(self).await?)
+    }
+
+    async fn get_versioned_state(&self) -> Result<Option<(PersistentState, u64)>, DynError> {
+        Ok(Database::get_versioned_state(self).await?)
+    }
+
+    async fn set_state_if_version(
+        &mut self,
+        state: &PersistentState,
+        expected_version: u64,
+    ) -> Result<u64, DynError> {
+        Ok(Database::set_state_if_version(self, state, expected_version).await?)
+    }
+
+    async fn close(&mut self) -> Result<(), DynError> {
+        Ok(Database::close(self).await?)
+    }
+}
+
+#[async_trait]
+impl Database for dyn DynDatabase {
+    type Error = BoxedError;
+
+    async fn set_state(&mut self, state: &PersistentState) -> Result<(), Self::Error> {
+        Ok(DynDatabase::set_state(self, state).await?)
+    }
+
+    async fn get_state(&self) -> Result<Option<PersistentState>, Self::Error> {
+        Ok(DynDatabase::get_state(self).await?)
+    }
+
+    async fn clear_state(&mut self) -> Result<(), Self::Error> {
+        Ok(DynDatabase::clear_state(self).await?)
+    }
+
+    async fn get_versioned_state(&self) -> Result<Option<(PersistentState, u64)>, Self::Error> {
+        Ok(DynDatabase::get_versioned_state(self).await?)
+    }
+
+    async fn set_state_if_version(
+        &mut self,
+        state: &PersistentState,
+        expected_version: u64,
+    ) -> Result<u64, Self::Error> {
+        Ok(DynDatabase::set_state_if_version(self, state, expected_version).await?)
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        Ok(DynDatabase::close(self).await?)
+    }
+}
+
+// Statistics of the database operations.
+
+/// A database operation performed by the manager.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum DatabaseOperation {
+    Get,
+    Set,
+    Clear,
+}
+
+/// How many operations of a kind the manager performed, and how long they
+/// took (measured with the configuration's `Time`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OperationStats {
+    pub count: u64,
+    pub total_time: Duration,
+    pub max_time: Duration,
+}
+
+/// Round-trips to the database, by operation (see `Manager::database_stats`
+/// and `SendTimings::database`), so that slow databases (e.g., state files on
+/// a network file system) are visible.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DatabaseStats {
+    pub get: OperationStats,
+    pub set: OperationStats,
+    pub clear: OperationStats,
+}
+
+impl DatabaseStats {
+    pub fn record(&mut self, operation: DatabaseOperation, time: Duration) {
+        let stats = match operation {
+            DatabaseOperation::Get => &mut self.get,
+            DatabaseOperation::Set => &mut self.set,
+            DatabaseOperation::Clear => &mut self.clear,
+        };
+        stats.count += 1;
+        stats.total_time += time;
+        stats.max_time = stats.max_time.max(time);
+    }
+
+    /// The time spent in all operations.
+    pub fn total_time(&self) -> Duration {
+        self.get.total_time + self.set.total_time + self.clear.total_time
+    }
 }
 
 // Implementation using the file system.
@@ -38,82 +263,397 @@ pub enum FileSystemDatabaseError {
 
     #[error("could not delete file: {0}")]
     DeleteFile(std::io::Error),
+
+    #[error("could not lock file: {0}")]
+    LockFile(std::io::Error),
+
+    #[cfg(feature = "bincode")]
+    #[error("could not encode the state with bincode: {0}")]
+    ToBincode(bincode::Error),
+
+    #[cfg(feature = "bincode")]
+    #[error("could not decode the bincode state: {0}")]
+    ParseBincode(bincode::Error),
+
+    #[error("the state is in the bincode format, which requires the `bincode` feature")]
+    BincodeUnsupported,
+
+    #[error("version conflict (expected: {expected}, found: {found})")]
+    VersionConflict { expected: u64, found: u64 },
 }
 
-#[derive(Debug)]
+/// How the `FileSystemDatabase` serializes the state. JSON files are human
+/// friendly, but slow and large for states with many signed transactions.
+///
+/// The format is detected when reading, so switching the format of an existing
+/// file only takes effect on its next write (migrating it).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StateFormat {
+    #[default]
+    Json,
+
+    /// Requires the `bincode` feature.
+    #[cfg(feature = "bincode")]
+    Bincode,
+}
+
+/// Prefix of the bincode files (JSON files start with a `{`).
+const BINCODE_MAGIC: &[u8] = b"\0txm-bincode\0";
+
+/// The bincode format is not self-describing, so it can neither flatten nor
+/// skip fields: the state is encoded as a tuple of the version and all of its
+/// fields, in order.
+#[cfg(feature = "bincode")]
+type BincodeState = (
+    u64,
+    StaticTxData,
+    SubmittedTxs,
+    Option<SubmittedTxs>,
+    SubmittedTxs,
+    BTreeMap<H256, BroadcastStatus>,
+    Vec<Attempt>,
+    u32,
+    Option<HandoffToken>,
+    Option<u64>,
+);
+
+#[cfg(feature = "bincode")]
+fn to_bincode(state: &PersistentState, version: u64) -> Result<Vec<u8>, bincode::Error> {
+    let PersistentState {
+        tx_data,
+        submitted_txs,
+        cancellation_txs,
+        replaced_txs,
+        broadcast_statuses,
+        attempts,
+        resubmissions,
+        handoff,
+        first_submitted_at,
+    } = state.clone();
+    let mut bytes = BINCODE_MAGIC.to_vec();
+    bincode::serialize_into(
+        &mut bytes,
+        &(
+            version,
+            tx_data,
+            submitted_txs,
+            cancellation_txs,
+            replaced_txs,
+            broadcast_statuses,
+            attempts,
+            resubmissions,
+            handoff,
+            first_submitted_at,
+        ),
+    )?;
+    Ok(bytes)
+}
+
+#[cfg(feature = "bincode")]
+fn from_bincode(bytes: &[u8]) -> Result<StoredState, FileSystemDatabaseError> {
+    let (
+        version,
+        tx_data,
+        submitted_txs,
+        cancellation_txs,
+        replaced_txs,
+        broadcast_statuses,
+        attempts,
+        resubmissions,
+        handoff,
+        first_submitted_at,
+    ): BincodeState = bincode::deserialize(bytes).map_err(FileSystemDatabaseError::ParseBincode)?;
+    let state = PersistentState {
+        tx_data,
+        submitted_txs,
+        cancellation_txs,
+        replaced_txs,
+        broadcast_statuses,
+        attempts,
+        resubmissions,
+        handoff,
+        first_submitted_at,
+    };
+    Ok(StoredState { version, state })
+}
+
+#[cfg(not(feature = "bincode"))]
+fn from_bincode(_: &[u8]) -> Result<StoredState, FileSystemDatabaseError> {
+    Err(FileSystemDatabaseError::BincodeUnsupported)
+}
+
+/// The state as stored by the `FileSystemDatabase`: a `PersistentState` with
+/// an additional version field (see `schema/persistent_state.json`).
+#[derive(Serialize)]
+struct VersionedState<'a> {
+    version: u64,
+    #[serde(flatten)]
+    state: &'a PersistentState,
+}
+
+#[derive(Deserialize)]
+struct StoredState {
+    /// Absent in files written before versioning.
+    #[serde(default)]
+    version: u64,
+    #[serde(flatten)]
+    state: PersistentState,
+}
+
+/// The state as stored by the tx-manager before `PersistentState`. It is
+/// converted (best-effort: with the default priority and confirmations) when
+/// read, so that upgraded deployments recover their pending transactions.
+#[derive(Deserialize)]
+struct LegacyState {
+    /// Absent if the transaction was not assigned a nonce (nor sent) yet.
+    nonce: Option<U256>,
+    transaction: Transaction,
+    pending_transactions: Vec<H256>,
+}
+
+impl LegacyState {
+    fn into_stored(self) -> Option<StoredState> {
+        let nonce = match self.nonce {
+            Some(nonce) => nonce,
+            None => {
+                warn!("Ignoring the legacy state of a transaction that was not sent.");
+                return None;
+            }
+        };
+        let tx_data = StaticTxData::new(
+            self.transaction,
+            nonce,
+            DEFAULT_CONFIRMATIONS,
+            Priority::Normal,
+        );
+        let mut state = PersistentState::new(tx_data);
+        state.submitted_txs = self.pending_transactions.into_iter().collect();
+        Some(StoredState { version: 0, state })
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct FileSystemDatabase {
     path: String,
+    format: StateFormat,
 }
 
 impl FileSystemDatabase {
     pub fn new(path: String) -> FileSystemDatabase {
-        FileSystemDatabase { path }
+        FileSystemDatabase {
+            path,
+            format: StateFormat::default(),
+        }
     }
-}
 
-#[async_trait]
-impl Database for FileSystemDatabase {
-    type Error = FileSystemDatabaseError;
+    /// The format of the writes (reads detect it).
+    pub fn set_format(mut self, format: StateFormat) -> FileSystemDatabase {
+        self.format = format;
+        self
+    }
 
-    async fn set_state(&mut self, state: &PersistentState) -> Result<(), Self::Error> {
-        let mut file = fs::File::create(self.path.clone())
+    /// Waits for an exclusive advisory lock on the `<path>.lock` file, which
+    /// is released when the returned file is dropped. The lock file is kept.
+    async fn lock(&self) -> Result<std::fs::File, FileSystemDatabaseError> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(format!("{}.lock", self.path))
+            .await
+            .map_err(FileSystemDatabaseError::CreateFile)?
+            .into_std()
+            .await;
+        tokio::task::spawn_blocking(move || file.lock().map(|()| file))
             .await
-            .map_err(Self::Error::CreateFile)?;
+            .map_err(|err| FileSystemDatabaseError::LockFile(std::io::Error::other(err)))?
+            .map_err(FileSystemDatabaseError::LockFile)
+    }
 
-        let s = serde_json::to_string_pretty(state).map_err(Self::Error::ToJSON)?;
+    /// Writes the state to a temporary file, which then replaces the state
+    /// file, so that readers never see a partial write.
+    async fn write(
+        &self,
+        state: &PersistentState,
+        version: u64,
+    ) -> Result<(), FileSystemDatabaseError> {
+        let temporary_path = format!("{}.tmp", self.path);
+        let mut file = fs::File::create(&temporary_path)
+            .await
+            .map_err(FileSystemDatabaseError::CreateFile)?;
 
-        file.write_all(s.as_bytes())
+        let bytes = match self.format {
+            StateFormat::Json => serde_json::to_vec_pretty(&VersionedState { version, state })
+                .map_err(FileSystemDatabaseError::ToJSON)?,
+            #[cfg(feature = "bincode")]
+            StateFormat::Bincode => {
+                to_bincode(state, version).map_err(FileSystemDatabaseError::ToBincode)?
+            }
+        };
+
+        file.write_all(&bytes)
             .await
-            .map_err(Self::Error::WriteToFile)?;
+            .map_err(FileSystemDatabaseError::WriteToFile)?;
 
-        file.sync_all().await.map_err(Self::Error::WriteToFile)?;
+        file.sync_all()
+            .await
+            .map_err(FileSystemDatabaseError::WriteToFile)?;
 
-        return Ok(());
+        fs::rename(&temporary_path, &self.path)
+            .await
+            .map_err(FileSystemDatabaseError::WriteToFile)
     }
 
-    async fn get_state(&self) -> Result<Option<PersistentState>, Self::Error> {
+    async fn read(&self) -> Result<Option<StoredState>, FileSystemDatabaseError> {
         let file = fs::File::open(self.path.clone()).await;
 
-        return match file {
+        match file {
             Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
 
-            Err(err) => Err(Self::Error::ReadFile(err)),
+            Err(err) => Err(FileSystemDatabaseError::ReadFile(err)),
 
             Ok(mut file) => {
-                let mut s = String::new();
+                let mut bytes = Vec::new();
 
-                file.read_to_string(&mut s)
+                file.read_to_end(&mut bytes)
                     .await
-                    .map_err(Self::Error::ReadFile)?;
+                    .map_err(FileSystemDatabaseError::ReadFile)?;
 
-                let state = serde_json::de::from_str(&s).map_err(Self::Error::ParseJSON)?;
+                if let Some(bytes) = bytes.strip_prefix(BINCODE_MAGIC) {
+                    return from_bincode(bytes).map(Some);
+                }
 
-                return Ok(Some(state));
+                match serde_json::de::from_slice(&bytes) {
+                    Ok(stored) => Ok(Some(stored)),
+                    Err(err) => match serde_json::de::from_slice::<LegacyState>(&bytes) {
+                        Ok(legacy) => {
+                            warn!("Converting the legacy state in {}.", self.path);
+                            Ok(legacy.into_stored())
+                        }
+                        Err(_) => Err(FileSystemDatabaseError::ParseJSON(err)),
+                    },
+                }
             }
+        }
+    }
+}
+
+/// Each write increments the version stored in the file. The writes (with the
+/// version checks of `set_state_if_version`) and the deletions hold an
+/// exclusive advisory lock on the `<path>.lock` file, so that processes sharing
+/// the file do not overwrite each other's changes (advisory locks may not hold
+/// on network file systems).
+#[async_trait]
+impl Database for FileSystemDatabase {
+    type Error = FileSystemDatabaseError;
+
+    async fn set_state(&mut self, state: &PersistentState) -> Result<(), Self::Error> {
+        let _lock = self.lock().await?;
+        // Unconditional: an unreadable state is overwritten.
+        let version = match self.read().await {
+            Ok(Some(stored)) => stored.version,
+            _ => 0,
         };
+        self.write(state, version + 1).await
+    }
+
+    async fn get_state(&self) -> Result<Option<PersistentState>, Self::Error> {
+        Ok(self.read().await?.map(|stored| stored.state))
     }
 
     async fn clear_state(&mut self) -> Result<(), Self::Error> {
+        let _lock = self.lock().await?;
         Ok(fs::remove_file(self.path.clone())
             .await
             .map_err(Self::Error::DeleteFile)?)
     }
+
+    async fn get_versioned_state(&self) -> Result<Option<(PersistentState, u64)>, Self::Error> {
+        Ok(self
+            .read()
+            .await?
+            .map(|stored| (stored.state, stored.version)))
+    }
+
+    async fn set_state_if_version(
+        &mut self,
+        state: &PersistentState,
+        expected_version: u64,
+    ) -> Result<u64, Self::Error> {
+        let _lock = self.lock().await?;
+        let found = self.read().await?.map_or(0, |stored| stored.version);
+        if found != expected_version {
+            return Err(Self::Error::VersionConflict {
+                expected: expected_version,
+                found,
+            });
+        }
+        self.write(state, expected_version + 1).await?;
+        Ok(expected_version + 1)
+    }
+}
+
+// Implementation that does not persist anything.
+
+/// Database that only keeps the state in memory, for one-shot scripts that do
+/// not care about crash recovery. If the process dies while a transaction is
+/// pending, the manager will not be able to recover it.
+#[derive(Debug)]
+pub struct EphemeralDatabase {
+    state: Option<PersistentState>,
+}
+
+impl EphemeralDatabase {
+    pub fn new() -> EphemeralDatabase {
+        warn!(
+            "Using an ephemeral database: pending transactions will NOT be recovered after a crash!"
+        );
+        EphemeralDatabase { state: None }
+    }
+}
+
+impl Default for EphemeralDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Database for EphemeralDatabase {
+    type Error = Infallible;
+
+    async fn set_state(&mut self, state: &PersistentState) -> Result<(), Self::Error> {
+        self.state = Some(state.clone());
+        Ok(())
+    }
+
+    async fn get_state(&self) -> Result<Option<PersistentState>, Self::Error> {
+        Ok(self.state.clone())
+    }
+
+    async fn clear_state(&mut self) -> Result<(), Self::Error> {
+        self.state = None;
+        Ok(())
+    }
 }
 
 // Unit tests for the file system database.
 
 #[cfg(test)]
 mod test {
-    use ethers::types::{H160, H256};
+    use ethers::types::{NameOrAddress, H160, H256, U256};
     use serde_json::error::Category;
     use serial_test::serial;
+    use std::collections::BTreeMap;
     use std::fs::{remove_file, File};
     use std::io::Write;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
-    use crate::database::{Database, FileSystemDatabase, FileSystemDatabaseError};
+    use crate::database::{
+        Database, EphemeralDatabase, FileSystemDatabase, FileSystemDatabaseError,
+    };
+    use crate::transaction::{Metadata, Priority, Transaction, Value};
     use crate::transaction::{PersistentState, StaticTxData, SubmittedTxs};
-    use crate::transaction::{Priority, Transaction, Value};
 
     /// Auxiliary.
     fn setup(str: String) -> (PathBuf, FileSystemDatabase) {
@@ -123,6 +663,12 @@ mod test {
         (path, database)
     }
 
+    /// Removes the state file and its lock file.
+    fn teardown(path: impl AsRef<Path>) {
+        remove_file(path.as_ref()).unwrap();
+        let _ = remove_file(format!("{}.lock", path.as_ref().display()));
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_file_system_database_set_state_ok_empty_state() {
@@ -131,14 +677,23 @@ mod test {
                 nonce: 1u64.into(),
                 transaction: Transaction {
                     from: H160::from_low_u64_ne(1u64),
-                    to: H160::from_low_u64_ne(2u64),
+                    to: H160::from_low_u64_ne(2u64).into(),
                     value: Value::Number(5000u64.into()),
                     call_data: None,
+                    metadata: Metadata::new(),
                 },
                 priority: Priority::Normal,
                 confirmations: 0,
+                precondition: None,
             },
             submitted_txs: SubmittedTxs::new(),
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
         };
 
         let (path, mut database) = setup("./set_database.json".to_string());
@@ -148,7 +703,7 @@ mod test {
         let result = database.set_state(&state).await;
         assert!(result.is_ok());
         assert!(path.is_file());
-        remove_file(path).unwrap();
+        teardown(path);
         assert!(!path.is_file());
     }
 
@@ -160,12 +715,14 @@ mod test {
                 nonce: 2u64.into(),
                 transaction: Transaction {
                     from: H160::from_low_u64_ne(5u64),
-                    to: H160::from_low_u64_ne(6u64),
+                    to: H160::from_low_u64_ne(6u64).into(),
                     value: Value::Number(3000u64.into()),
                     call_data: None,
+                    metadata: Metadata::new(),
                 },
                 priority: Priority::High,
                 confirmations: 5,
+                precondition: None,
             },
             submitted_txs: SubmittedTxs {
                 txs_hashes: vec![
@@ -173,6 +730,13 @@ mod test {
                     H256::from_low_u64_ne(1500u64),
                 ],
             },
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
         };
 
         let (path, mut database) = setup("./set_database.json".to_string());
@@ -185,7 +749,7 @@ mod test {
         let result = database.set_state(&state).await;
         assert!(result.is_ok());
         assert!(path.is_file());
-        remove_file(path).unwrap();
+        teardown(path);
         assert!(!path.is_file());
     }
 
@@ -199,14 +763,23 @@ mod test {
                 nonce: 1u64.into(),
                 transaction: Transaction {
                     from: H160::from_low_u64_ne(1u64),
-                    to: H160::from_low_u64_ne(2u64),
+                    to: H160::from_low_u64_ne(2u64).into(),
                     value: Value::Number(5000u64.into()),
                     call_data: None,
+                    metadata: Metadata::new(),
                 },
                 priority: Priority::Normal,
                 confirmations: 0,
+                precondition: None,
             },
             submitted_txs: SubmittedTxs::new(),
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
         };
 
         let path_str = "/bin/set_database.json".to_string();
@@ -250,12 +823,15 @@ mod test {
                 nonce: 2u64.into(),
                 transaction: Transaction {
                     from: H160::from_low_u64_ne(5u64),
-                    to: H160::from_low_u64_ne(6u64),
+                    to: H160::from_low_u64_ne(6u64).into(),
                     value: Value::Number(3000u64.into()),
                     call_data: None,
-                },
+                    metadata: Metadata::new(),
+                }
+                .with_metadata("epoch", "42"),
                 priority: Priority::High,
                 confirmations: 5,
+                precondition: None,
             },
             submitted_txs: SubmittedTxs {
                 txs_hashes: vec![
@@ -263,6 +839,13 @@ mod test {
                     H256::from_low_u64_ne(1500u64),
                 ],
             },
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
         };
 
         let (path, mut database) = setup("./get_database.json".to_string());
@@ -280,7 +863,91 @@ mod test {
         assert_eq!(original_state, retrieved_state);
 
         assert!(path.is_file());
-        remove_file(path).unwrap();
+        teardown(path);
+        assert!(!path.is_file());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_file_system_database_get_state_ok_without_metadata() {
+        // ok => states persisted before the metadata field existed are read
+        // with empty metadata
+
+        let path_str = "./metadata_database.json".to_string();
+        let (path, database) = setup(path_str.clone());
+        let mut file = File::create(path.as_path()).unwrap();
+        let s = r#"{
+            "tx_data": {
+                "nonce": "0x1",
+                "transaction": {
+                    "from": "0x0000000000000000000000000000000000000001",
+                    "to": "0x0000000000000000000000000000000000000002",
+                    "value": { "Number": "0x1388" },
+                    "call_data": null
+                },
+                "confirmations": 0,
+                "priority": "Normal"
+            },
+            "submitted_txs": { "txs_hashes": [] }
+        }"#;
+        file.write_all(s.as_bytes()).unwrap();
+
+        let result = database.get_state().await;
+        assert!(result.is_ok(), "{:?}", result);
+        let state = result.unwrap().unwrap();
+        assert!(state.tx_data.transaction.metadata.is_empty());
+
+        remove_file(path.as_path()).unwrap();
+        assert!(!path.is_file());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_file_system_database_get_state_ok_legacy_state() {
+        // ok => states persisted by the old tx-manager (State { nonce,
+        // transaction, pending_transactions }) are converted
+
+        let path_str = "./legacy_database.json".to_string();
+        let (path, database) = setup(path_str.clone());
+        let mut file = File::create(path.as_path()).unwrap();
+        let s = r#"{
+            "nonce": "0x3",
+            "transaction": {
+                "from": "0x0000000000000000000000000000000000000001",
+                "to": "0x0000000000000000000000000000000000000002",
+                "value": { "Number": "0x1388" },
+                "call_data": "0x1234"
+            },
+            "pending_transactions": [
+                "0x0000000000000000000000000000000000000000000000000000000000000001",
+                "0x0000000000000000000000000000000000000000000000000000000000000002"
+            ]
+        }"#;
+        file.write_all(s.as_bytes()).unwrap();
+
+        let result = database.get_versioned_state().await;
+        assert!(result.is_ok(), "{:?}", result);
+        let (state, version) = result.unwrap().unwrap();
+        assert_eq!(0, version);
+        assert_eq!(U256::from(3), state.tx_data.nonce);
+        assert_eq!(
+            NameOrAddress::from(H160::from_low_u64_be(2)),
+            state.tx_data.transaction.to
+        );
+        assert_eq!(Priority::Normal, state.tx_data.priority);
+        assert_eq!(
+            vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+            state.submitted_txs.txs_hashes
+        );
+
+        // Without a nonce, there is nothing to recover.
+        let mut file = File::create(path.as_path()).unwrap();
+        let s = s.replace(r#""nonce": "0x3""#, r#""nonce": null"#);
+        file.write_all(s.as_bytes()).unwrap();
+        let result = database.get_state().await;
+        assert!(matches!(result, Ok(None)), "{:?}", result);
+
+        remove_file(path.as_path()).unwrap();
         assert!(!path.is_file());
     }
 
@@ -313,7 +980,7 @@ mod test {
         );
 
         assert!(path.is_file());
-        remove_file(path).unwrap();
+        teardown(path);
         assert!(!path.is_file());
     }
 
@@ -327,6 +994,7 @@ mod test {
         let result = database.clear_state().await;
         assert!(result.is_ok());
         assert!(!path.is_file());
+        let _ = remove_file("./clear_database.json.lock");
     }
 
     #[tokio::test]
@@ -348,5 +1016,179 @@ mod test {
         );
 
         assert!(!path.is_file());
+        let _ = remove_file("./clear_database.json.lock");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_file_system_database_set_state_if_version() {
+        let state = PersistentState {
+            tx_data: StaticTxData {
+                nonce: 1u64.into(),
+                transaction: Transaction {
+                    from: H160::from_low_u64_ne(1u64),
+                    to: H160::from_low_u64_ne(2u64).into(),
+                    value: Value::Number(5000u64.into()),
+                    call_data: None,
+                    metadata: Metadata::new(),
+                },
+                priority: Priority::Normal,
+                confirmations: 0,
+                precondition: None,
+            },
+            submitted_txs: SubmittedTxs::new(),
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
+        };
+
+        let (path, mut database) = setup("./versioned_database.json".to_string());
+        let mut other_writer = database.clone();
+
+        assert_eq!(1, database.set_state_if_version(&state, 0).await.unwrap());
+        assert_eq!(
+            Some((state.clone(), 1)),
+            other_writer.get_versioned_state().await.unwrap()
+        );
+        assert_eq!(
+            2,
+            other_writer.set_state_if_version(&state, 1).await.unwrap()
+        );
+
+        // Acting on an outdated version.
+        let result = database.set_state_if_version(&state, 1).await;
+        assert!(matches!(
+            result,
+            Err(FileSystemDatabaseError::VersionConflict {
+                expected: 1,
+                found: 2
+            })
+        ));
+
+        // Unconditional writes also increment the version.
+        database.set_state(&state).await.unwrap();
+        assert_eq!(
+            Some((state.clone(), 3)),
+            database.get_versioned_state().await.unwrap()
+        );
+
+        // Only one of the concurrent writers of the same version succeeds.
+        let writes = (0..8).map(|_| {
+            let (mut database, state) = (database.clone(), state.clone());
+            tokio::spawn(async move { database.set_state_if_version(&state, 3).await })
+        });
+        let results = futures::future::join_all(writes).await;
+        let succeeded = results
+            .into_iter()
+            .filter(|result| matches!(result, Ok(Ok(4))))
+            .count();
+        assert_eq!(1, succeeded);
+        assert_eq!(
+            Some((state, 4)),
+            database.get_versioned_state().await.unwrap()
+        );
+
+        teardown(&path);
+    }
+
+    #[cfg(feature = "bincode")]
+    #[tokio::test]
+    #[serial]
+    async fn test_file_system_database_bincode_format() {
+        use crate::database::StateFormat;
+        use crate::gas_oracle::{GasInfo, LegacyGasInfo};
+        use crate::transaction::{Attempt, BroadcastStatus};
+
+        let mut state = PersistentState::new(StaticTxData::new(
+            Transaction {
+                from: H160::from_low_u64_ne(1u64),
+                to: H160::from_low_u64_ne(2u64).into(),
+                value: Value::Number(5000u64.into()),
+                call_data: Some(vec![1, 2, 3].into()),
+                metadata: Metadata::from([("epoch".to_string(), "7".to_string())]),
+            },
+            3u64.into(),
+            1,
+            Priority::High,
+        ));
+        let hash = H256::from_low_u64_ne(1u64);
+        state.submitted_txs.add(hash);
+        state.broadcast_statuses.insert(
+            hash,
+            BroadcastStatus::PreparedNotBroadcast(vec![4, 5].into()),
+        );
+        state.attempts.push(Attempt {
+            hash,
+            gas_info: GasInfo::Legacy(LegacyGasInfo {
+                gas_price: 10u64.into(),
+            }),
+        });
+
+        // A JSON state is migrated on the next write.
+        let (path, mut database) = setup("./bincode_database.json".to_string());
+        database.set_state(&state).await.unwrap();
+        let mut database = database.set_format(StateFormat::Bincode);
+        assert_eq!(
+            Some((state.clone(), 1)),
+            database.get_versioned_state().await.unwrap()
+        );
+        state.handoff = Some("token".to_string().into());
+        assert_eq!(2, database.set_state_if_version(&state, 1).await.unwrap());
+        assert!(std::fs::read(&path)
+            .unwrap()
+            .starts_with(super::BINCODE_MAGIC));
+
+        // Reads detect the format.
+        let json_database = FileSystemDatabase::new("./bincode_database.json".to_string());
+        assert_eq!(
+            Some((state.clone(), 2)),
+            json_database.get_versioned_state().await.unwrap()
+        );
+        state.handoff = None;
+        database.set_state(&state).await.unwrap();
+        assert_eq!(
+            Some((state, 3)),
+            json_database.get_versioned_state().await.unwrap()
+        );
+
+        teardown(path);
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_database() {
+        let state = PersistentState {
+            tx_data: StaticTxData {
+                nonce: 1u64.into(),
+                transaction: Transaction {
+                    from: H160::from_low_u64_ne(1u64),
+                    to: H160::from_low_u64_ne(2u64).into(),
+                    value: Value::Number(5000u64.into()),
+                    call_data: None,
+                    metadata: Metadata::new(),
+                },
+                priority: Priority::Normal,
+                confirmations: 0,
+                precondition: None,
+            },
+            submitted_txs: SubmittedTxs::new(),
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
+        };
+
+        let mut database = EphemeralDatabase::new();
+        assert_eq!(None, database.get_state().await.unwrap());
+        database.set_state(&state).await.unwrap();
+        assert_eq!(Some(state), database.get_state().await.unwrap());
+        database.clear_state().await.unwrap();
+        assert_eq!(None, database.get_state().await.unwrap());
     }
 }
@@ -0,0 +1,335 @@
+//! Audit log of the JSON-RPC traffic, for bug reports about weird node
+//! behavior: `DebugClient` wraps the JSON-RPC client of the provider and
+//! records every request/response pair into a ring buffer (an `RpcLog`), which
+//! can be attached to errors or dumped to a file.
+//!
+//! ```ignore
+//! let log = RpcLog::new(1000);
+//! let provider = Provider::new(DebugClient::new(Http::from_str(url)?, log.clone()));
+//! // ...
+//! log.clear();
+//! let result = manager.send(transaction, options).await.map_err(|err| log.attach(err));
+//! ```
+
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, ProviderError};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Display};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How many exchanges an `RpcLog` keeps by default.
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DebugClientError {
+    #[error("{0}")]
+    Client(ProviderError),
+
+    #[error("serde json: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl From<DebugClientError> for ProviderError {
+    fn from(err: DebugClientError) -> Self {
+        match err {
+            DebugClientError::Client(err) => err,
+            err => ProviderError::JsonRpcClientError(Box::new(err)),
+        }
+    }
+}
+
+/// A JSON-RPC request and its response (or error), as seen on the wire.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpcExchange {
+    /// Sequential number of the request (over the lifetime of the log).
+    pub id: u64,
+    pub timestamp: SystemTime,
+    pub method: String,
+    pub params: Value,
+    pub result: Result<Value, String>,
+    pub latency: Duration,
+}
+
+impl RpcExchange {
+    fn to_json(&self) -> Value {
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut value = json!({
+            "id": self.id,
+            "timestamp_ms": timestamp,
+            "method": self.method,
+            "params": self.params,
+            "latency_ms": self.latency.as_millis() as u64,
+        });
+        match &self.result {
+            Ok(response) => value["response"] = response.clone(),
+            Err(err) => value["error"] = Value::String(err.clone()),
+        }
+        value
+    }
+}
+
+/// Ring buffer with the latest JSON-RPC exchanges. Clones share the buffer.
+#[derive(Clone, Debug)]
+pub struct RpcLog {
+    inner: Arc<Mutex<Buffer>>,
+}
+
+#[derive(Debug)]
+struct Buffer {
+    exchanges: VecDeque<RpcExchange>,
+    capacity: usize,
+    next_id: u64,
+}
+
+impl Default for RpcLog {
+    fn default() -> Self {
+        RpcLog::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl RpcLog {
+    /// Creates a log that keeps the latest `capacity` exchanges.
+    pub fn new(capacity: usize) -> Self {
+        RpcLog {
+            inner: Arc::new(Mutex::new(Buffer {
+                exchanges: VecDeque::new(),
+                capacity: capacity.max(1),
+                next_id: 0,
+            })),
+        }
+    }
+
+    /// The recorded exchanges, from the oldest.
+    pub fn exchanges(&self) -> Vec<RpcExchange> {
+        self.inner
+            .lock()
+            .unwrap()
+            .exchanges
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Forgets the recorded exchanges (e.g., before a send, so that the log
+    /// only has its traffic).
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().exchanges.clear();
+    }
+
+    /// Wraps the error with the recorded exchanges.
+    pub fn attach<E>(&self, error: E) -> WithRpcLog<E> {
+        WithRpcLog {
+            error,
+            exchanges: self.exchanges(),
+        }
+    }
+
+    /// Writes the recorded exchanges as JSON lines.
+    pub fn dump(&self, mut writer: impl Write) -> io::Result<()> {
+        for exchange in self.exchanges() {
+            serde_json::to_writer(&mut writer, &exchange.to_json())?;
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Writes the recorded exchanges as JSON lines to the file (see
+    /// `TxManagerConfig::debug_rpc_dump_path`).
+    pub fn dump_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.dump(BufWriter::new(File::create(path)?))
+    }
+
+    fn record(
+        &self,
+        method: &str,
+        params: Value,
+        result: Result<Value, String>,
+        timestamp: SystemTime,
+        latency: Duration,
+    ) {
+        let mut buffer = self.inner.lock().unwrap();
+        let id = buffer.next_id;
+        buffer.next_id += 1;
+        buffer.exchanges.push_back(RpcExchange {
+            id,
+            timestamp,
+            method: method.to_string(),
+            params,
+            result,
+            latency,
+        });
+        while buffer.exchanges.len() > buffer.capacity {
+            buffer.exchanges.pop_front();
+        }
+    }
+}
+
+/// An error with the JSON-RPC exchanges that preceded it (see
+/// `RpcLog::attach`). Displays the error followed by the exchanges, one JSON
+/// object per line.
+#[derive(Debug)]
+pub struct WithRpcLog<E> {
+    pub error: E,
+    pub exchanges: Vec<RpcExchange>,
+}
+
+impl<E: Display> Display for WithRpcLog<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        write!(f, "\nJSON-RPC exchanges ({}):", self.exchanges.len())?;
+        for exchange in &self.exchanges {
+            write!(f, "\n{}", exchange.to_json())?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for WithRpcLog<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A JSON-RPC client that records its requests and responses in an `RpcLog`.
+///
+/// It wraps the client (and not the middleware) because the middlewares do
+/// not see the wire traffic of the calls they delegate.
+#[derive(Debug)]
+pub struct DebugClient<C> {
+    client: C,
+    log: RpcLog,
+}
+
+impl<C: JsonRpcClient> DebugClient<C> {
+    pub fn new(client: C, log: RpcLog) -> Self {
+        DebugClient { client, log }
+    }
+
+    pub fn log(&self) -> &RpcLog {
+        &self.log
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl<C: JsonRpcClient> JsonRpcClient for DebugClient<C> {
+    type Error = DebugClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let params = serde_json::to_value(params)?;
+
+        let timestamp = SystemTime::now();
+        let start = Instant::now();
+        let result = self.client.request::<_, Value>(method, &params).await;
+        let latency = start.elapsed();
+
+        match result {
+            Ok(value) => {
+                self.log
+                    .record(method, params, Ok(value.clone()), timestamp, latency);
+                Ok(serde_json::from_value(value)?)
+            }
+            Err(err) => {
+                let err: ProviderError = err.into();
+                self.log
+                    .record(method, params, Err(err.to_string()), timestamp, latency);
+                Err(DebugClientError::Client(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+    use ethers::providers::{JsonRpcClient, ProviderError};
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::Value;
+    use std::fmt::Debug;
+
+    use super::{DebugClient, RpcLog};
+
+    /// Answers with the parameters, or fails for "eth_fail".
+    #[derive(Debug)]
+    struct Echo;
+
+    #[async_trait]
+    impl JsonRpcClient for Echo {
+        type Error = ProviderError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned,
+        {
+            if method == "eth_fail" {
+                return Err(ProviderError::CustomError("weird node".to_string()));
+            }
+            Ok(serde_json::from_value(serde_json::to_value(params)?)?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debug_client() {
+        let log = RpcLog::new(2);
+        let client = DebugClient::new(Echo, log.clone());
+
+        let result: Result<u64, _> = client.request("eth_echo", 1).await;
+        assert_eq!(1, result.unwrap());
+        let result: Result<u64, _> = client.request("eth_fail", 2).await;
+        assert!(result.is_err());
+        let result: Result<u64, _> = client.request("eth_echo", 3).await;
+        assert_eq!(3, result.unwrap());
+
+        // Keeps the latest exchanges.
+        let exchanges = log.exchanges();
+        assert_eq!(2, exchanges.len());
+        assert_eq!(1, exchanges[0].id);
+        assert_eq!("eth_fail", exchanges[0].method);
+        assert_eq!(Value::from(2), exchanges[0].params);
+        assert!(exchanges[0]
+            .result
+            .as_ref()
+            .unwrap_err()
+            .contains("weird node"));
+        assert_eq!(Ok(Value::from(3)), exchanges[1].result);
+
+        // Dumps one JSON object per line.
+        let mut dump = Vec::new();
+        log.dump(&mut dump).unwrap();
+        let lines: Vec<Value> = String::from_utf8(dump)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(2, lines.len());
+        assert!(lines[0]["error"].as_str().unwrap().contains("weird node"));
+        assert_eq!(Value::from(3), lines[1]["response"]);
+
+        // Attaches the exchanges to errors.
+        let err = log.attach(ProviderError::CustomError("send failed".to_string()));
+        let message = err.to_string();
+        assert!(message.contains("send failed\nJSON-RPC exchanges (2):"));
+        assert!(message.contains("eth_fail"));
+
+        log.clear();
+        assert!(log.exchanges().is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use ethers::{providers::Middleware, types::TransactionReceipt};
+use std::fmt::Debug;
+
+use crate::database::Database;
+use crate::gas_oracle::GasOracle;
+use crate::manager::{Error, HandoffToken, Manager, SendOptions, SendOutcome};
+use crate::time::Time;
+use crate::transaction::{Precondition, Priority, Transaction};
+
+/// Type-erased error returned by the `DynManager` functions. It can be
+/// downcast to the `Error` of the underlying manager.
+pub type DynError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A `DynError` that implements `std::error::Error`, returned by the
+/// type-erased gas oracles and databases (e.g., `dyn DynGasOracle`).
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct BoxedError(#[from] pub DynError);
+
+/// Object-safe handle to a transaction manager, which hides the manager's
+/// generic parameters. Managers of different types can be stored in the same
+/// collection as `Box<dyn DynManager>` (see `Manager::boxed`).
+///
+/// Like their `Manager` counterparts, the functions take ownership of the
+/// manager and give it back on success.
+#[async_trait]
+pub trait DynManager: Debug + Send + Sync {
+    /// See `Manager::send`.
+    async fn send(
+        self: Box<Self>,
+        transaction: Transaction,
+        options: SendOptions,
+    ) -> Result<(Box<dyn DynManager>, TransactionReceipt), DynError>;
+
+    /// See `Manager::send_if`.
+    async fn send_if(
+        self: Box<Self>,
+        transaction: Transaction,
+        precondition: Precondition,
+        options: SendOptions,
+    ) -> Result<(Box<dyn DynManager>, SendOutcome), DynError>;
+
+    /// See `Manager::shutdown`.
+    async fn shutdown(self: Box<Self>) -> Result<(), DynError>;
+
+    /// See `Manager::hand_off`.
+    async fn hand_off(self: Box<Self>) -> Result<Option<HandoffToken>, DynError>;
+
+    /// See `Manager::send_transaction`.
+    #[deprecated(note = "use `DynManager::send` with `SendOptions`")]
+    async fn send_transaction(
+        self: Box<Self>,
+        transaction: Transaction,
+        confirmations: usize,
+        priority: Priority,
+    ) -> Result<(Box<dyn DynManager>, TransactionReceipt), DynError> {
+        let options = SendOptions::new()
+            .set_confirmations(confirmations)
+            .set_priority(priority);
+        self.send(transaction, options).await
+    }
+
+    /// See `Manager::send_transaction_if`.
+    #[deprecated(note = "use `DynManager::send_if` with `SendOptions`")]
+    async fn send_transaction_if(
+        self: Box<Self>,
+        transaction: Transaction,
+        precondition: Precondition,
+        confirmations: usize,
+        priority: Priority,
+    ) -> Result<(Box<dyn DynManager>, SendOutcome), DynError> {
+        let options = SendOptions::new()
+            .set_confirmations(confirmations)
+            .set_priority(priority);
+        self.send_if(transaction, precondition, options).await
+    }
+}
+
+#[async_trait]
+impl<M, GO, DB, T> DynManager for Manager<M, GO, DB, T>
+where
+    M: Middleware + Send + Sync + 'static,
+    GO: GasOracle + Send + Sync + 'static,
+    DB: Database + Send + Sync + 'static,
+    T: Time + Send + Sync + 'static,
+    Error<M, GO, DB>: Send + Sync + 'static,
+{
+    async fn send(
+        self: Box<Self>,
+        transaction: Transaction,
+        options: SendOptions,
+    ) -> Result<(Box<dyn DynManager>, TransactionReceipt), DynError> {
+        let (manager, receipt) = Manager::send(*self, transaction, options).await?;
+        Ok((manager.boxed(), receipt))
+    }
+
+    async fn send_if(
+        self: Box<Self>,
+        transaction: Transaction,
+        precondition: Precondition,
+        options: SendOptions,
+    ) -> Result<(Box<dyn DynManager>, SendOutcome), DynError> {
+        let (manager, outcome) =
+            Manager::send_if(*self, transaction, precondition, options).await?;
+        Ok((manager.boxed(), outcome))
+    }
+
+    async fn shutdown(self: Box<Self>) -> Result<(), DynError> {
+        Ok(Manager::shutdown(*self).await?)
+    }
+
+    async fn hand_off(self: Box<Self>) -> Result<Option<HandoffToken>, DynError> {
+        Ok(Manager::hand_off(*self).await?)
+    }
+}
+
+impl<M, GO, DB, T> Manager<M, GO, DB, T>
+where
+    M: Middleware + Send + Sync + 'static,
+    GO: GasOracle + Send + Sync + 'static,
+    DB: Database + Send + Sync + 'static,
+    T: Time + Send + Sync + 'static,
+    Error<M, GO, DB>: Send + Sync + 'static,
+{
+    /// Erases the manager's type (see `DynManager`).
+    pub fn boxed(self) -> Box<dyn DynManager> {
+        Box::new(self)
+    }
+}
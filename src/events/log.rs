@@ -0,0 +1,270 @@
+use ethers::types::H256;
+use tracing::{info, warn};
+
+use crate::events::{Event, EventListener};
+use crate::transaction::Metadata;
+
+/// Target of the logs of the `EventLogger`.
+pub const EVENT_LOG_TARGET: &str = "eth_tx_manager::events";
+
+/// Event listener that logs the events with structured fields only (under the
+/// `eth_tx_manager::events` target), so that log-based alerts can match on
+/// fields that do not change when the messages of the manager are reworded.
+///
+/// Every log has an `event` field with the (snake case) name of the event.
+/// Nonces and durations (in milliseconds) are logged as integers, amounts in
+/// wei as decimal strings, hashes and addresses as full hex strings, and the
+/// metadata as a JSON object string.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EventLogger;
+
+impl EventListener for EventLogger {
+    fn notify(&self, event: &Event) {
+        match event {
+            Event::Submitted {
+                nonce,
+                hash,
+                eta,
+                metadata,
+                ..
+            } => info!(
+                target: EVENT_LOG_TARGET,
+                event = "submitted",
+                nonce = nonce.low_u64(),
+                hash = %hex(hash),
+                eta_ms = eta.as_millis() as u64,
+                metadata = %json(metadata),
+            ),
+            Event::Confirmed {
+                nonce,
+                hash,
+                block_number,
+                cost,
+                cost_usd,
+                mining_time,
+                confirmation_time,
+                metadata,
+            } => info!(
+                target: EVENT_LOG_TARGET,
+                event = "confirmed",
+                nonce = nonce.low_u64(),
+                hash = %hex(hash),
+                block_number = block_number.map(|n| n.as_u64()),
+                cost = cost.map(|cost| cost.to_string()),
+                cost_usd = *cost_usd,
+                mining_time_ms = mining_time.map(|t| t.as_millis() as u64),
+                confirmation_time_ms = confirmation_time.map(|t| t.as_millis() as u64),
+                metadata = %json(metadata),
+            ),
+            Event::Stalled {
+                nonce,
+                in_flight,
+                metadata,
+            } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "stalled",
+                nonce = nonce.low_u64(),
+                in_flight_ms = in_flight.as_millis() as u64,
+                metadata = %json(metadata),
+            ),
+            Event::WaitingForFeeDrop {
+                nonce,
+                base_fee,
+                max_fee_cap,
+                metadata,
+            } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "waiting_for_fee_drop",
+                nonce = nonce.low_u64(),
+                base_fee = %base_fee,
+                max_fee_cap = %max_fee_cap,
+                metadata = %json(metadata),
+            ),
+            Event::ReplacementLimitReached {
+                nonce,
+                submissions,
+                metadata,
+            } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "replacement_limit_reached",
+                nonce = nonce.low_u64(),
+                submissions = submissions,
+                metadata = %json(metadata),
+            ),
+            Event::ProviderStale { block_number, age } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "provider_stale",
+                block_number = block_number.as_u64(),
+                age_ms = age.as_millis() as u64,
+            ),
+            Event::SlowDatabase {
+                operation,
+                duration,
+            } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "slow_database",
+                operation = ?operation,
+                duration_ms = duration.as_millis() as u64,
+            ),
+            Event::Reorged {
+                nonce,
+                hash,
+                block_hash,
+                metadata,
+            } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "reorged",
+                nonce = nonce.low_u64(),
+                hash = %hex(hash),
+                block_hash = block_hash.as_ref().map(hex),
+                metadata = %json(metadata),
+            ),
+            Event::MultipleReceipts {
+                nonce,
+                hashes,
+                canonical,
+                metadata,
+            } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "multiple_receipts",
+                nonce = nonce.low_u64(),
+                hashes = %serde_json::to_string(&hashes.iter().map(hex).collect::<Vec<_>>()).unwrap(),
+                canonical = canonical.as_ref().map(hex),
+                metadata = %json(metadata),
+            ),
+            Event::Skipped { nonce, metadata } => info!(
+                target: EVENT_LOG_TARGET,
+                event = "skipped",
+                nonce = nonce.low_u64(),
+                metadata = %json(metadata),
+            ),
+            Event::LowBalance {
+                sender,
+                balance,
+                threshold,
+            } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "low_balance",
+                sender = %format!("{:?}", sender),
+                balance = %balance,
+                threshold = %threshold,
+            ),
+            Event::Failed {
+                nonce,
+                error,
+                metadata,
+            } => warn!(
+                target: EVENT_LOG_TARGET,
+                event = "failed",
+                nonce = nonce.map(|nonce| nonce.low_u64()),
+                error = error.as_str(),
+                metadata = %json(metadata),
+            ),
+        }
+    }
+}
+
+fn hex(hash: &H256) -> String {
+    format!("{:?}", hash)
+}
+
+fn json(metadata: &Metadata) -> String {
+    serde_json::to_string(metadata).expect("metadata is serializable")
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{H256, U256, U64};
+    use serde_json::{json, Map, Value};
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tracing::field::{Field, Visit};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    use super::{EventLogger, EVENT_LOG_TARGET};
+    use crate::events::{Event, EventListener};
+
+    /// Collects the fields of the logs as JSON objects.
+    #[derive(Clone, Default)]
+    struct JsonLayer(Arc<Mutex<Vec<Value>>>);
+
+    struct JsonVisitor(Map<String, Value>);
+
+    impl Visit for JsonVisitor {
+        fn record_f64(&mut self, field: &Field, value: f64) {
+            self.0.insert(field.name().to_string(), json!(value));
+        }
+
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            self.0.insert(field.name().to_string(), json!(value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), json!(value));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .insert(field.name().to_string(), json!(format!("{:?}", value)));
+        }
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for JsonLayer {
+        fn on_event(&self, event: &tracing::Event<'_>, _: Context<'_, S>) {
+            assert_eq!(EVENT_LOG_TARGET, event.metadata().target());
+            let mut visitor = JsonVisitor(Map::new());
+            event.record(&mut visitor);
+            self.0.lock().unwrap().push(Value::Object(visitor.0));
+        }
+    }
+
+    #[test]
+    fn test_event_logger_fields() {
+        let layer = JsonLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        let hash = H256::from_low_u64_be(0xabc);
+        let metadata = BTreeMap::from([("job".to_string(), "42".to_string())]);
+        tracing::subscriber::with_default(subscriber, || {
+            EventLogger.notify(&Event::Confirmed {
+                nonce: U256::from(7),
+                hash,
+                block_number: Some(U64::from(100)),
+                cost: Some(U256::exp10(18)),
+                cost_usd: None,
+                mining_time: Some(Duration::from_millis(1500)),
+                confirmation_time: None,
+                metadata: metadata.clone(),
+            });
+            EventLogger.notify(&Event::Failed {
+                nonce: None,
+                error: "Nonce too low".to_string(),
+                metadata,
+            });
+        });
+
+        let logs = layer.0.lock().unwrap();
+        assert_eq!(
+            *logs,
+            vec![
+                json!({
+                    "event": "confirmed",
+                    "nonce": 7,
+                    "hash": format!("{:?}", hash),
+                    "block_number": 100,
+                    "cost": "1000000000000000000",
+                    "mining_time_ms": 1500,
+                    "metadata": r#"{"job":"42"}"#,
+                }),
+                json!({
+                    "event": "failed",
+                    "error": "Nonce too low",
+                    "metadata": r#"{"job":"42"}"#,
+                }),
+            ]
+        );
+        assert!(logs[0]["hash"].as_str().unwrap().ends_with("0abc"));
+    }
+}
@@ -0,0 +1,142 @@
+use ethers::types::{Address, Bytes, H256, U256, U64};
+use serde::Serialize;
+use std::fmt::Debug;
+use std::time::Duration;
+
+use crate::database::DatabaseOperation;
+use crate::transaction::Metadata;
+
+mod log;
+
+pub use log::{EventLogger, EVENT_LOG_TARGET};
+
+#[cfg(feature = "webhooks")]
+mod webhook;
+
+#[cfg(feature = "webhooks")]
+pub use webhook::WebhookNotifier;
+
+/// Transitions in the lifecycle of a transaction sent by the manager.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    /// A transaction was submitted to the transaction pool.
+    Submitted {
+        nonce: U256,
+        hash: H256,
+
+        /// The signed transaction, RLP-encoded (see
+        /// `Configuration::include_raw_transactions`).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        raw_transaction: Option<Bytes>,
+
+        /// Estimated time until the transaction is confirmed (see
+        /// `ConfirmationEstimator`).
+        eta: Duration,
+
+        metadata: Metadata,
+    },
+
+    /// A transaction was mined and has enough confirmations.
+    Confirmed {
+        nonce: U256,
+        hash: H256,
+        block_number: Option<U64>,
+
+        /// Fees paid in wei (gas used times the effective gas price).
+        cost: Option<U256>,
+
+        /// The cost in USD, if the configuration has a price feed.
+        cost_usd: Option<f64>,
+
+        /// How long the transaction took to be mined and confirmed (see
+        /// `SendTimings`).
+        mining_time: Option<Duration>,
+        confirmation_time: Option<Duration>,
+
+        metadata: Metadata,
+    },
+
+    /// A transaction has been in flight for longer than the configured stall
+    /// threshold. The manager keeps trying to confirm it.
+    Stalled {
+        nonce: U256,
+        in_flight: Duration,
+        metadata: Metadata,
+    },
+
+    /// The base fee is above the max fee cap (see
+    /// `Configuration::max_fee_cap`), so the manager holds the resubmissions
+    /// of the transaction until it drops.
+    WaitingForFeeDrop {
+        nonce: U256,
+        base_fee: U256,
+        max_fee_cap: U256,
+        metadata: Metadata,
+    },
+
+    /// The node refused another replacement of the transaction (it caps the
+    /// replacements per nonce), so the manager stops replacing it and waits
+    /// for one of the submitted transactions to be mined.
+    ReplacementLimitReached {
+        nonce: U256,
+        submissions: usize,
+        metadata: Metadata,
+    },
+
+    /// The provider's latest block did not advance for longer than expected
+    /// (see `Configuration::stale_provider_blocks`).
+    ProviderStale { block_number: U64, age: Duration },
+
+    /// A database operation took longer than the configured threshold (see
+    /// `Configuration::slow_database_threshold`).
+    SlowDatabase {
+        operation: DatabaseOperation,
+        duration: Duration,
+    },
+
+    /// A mined transaction was dropped from the chain (or moved to another
+    /// block) by a reorg. The manager keeps trying to confirm it.
+    Reorged {
+        nonce: U256,
+        hash: H256,
+        block_hash: Option<H256>,
+        metadata: Metadata,
+    },
+
+    /// More than one of the transactions sent with the same nonce appeared
+    /// mined (e.g., replacements racing with a reorg, with nodes serving the
+    /// receipts of reorged blocks). The manager picks the one on the canonical
+    /// chain, if any.
+    MultipleReceipts {
+        nonce: U256,
+        hashes: Vec<H256>,
+        canonical: Option<H256>,
+        metadata: Metadata,
+    },
+
+    /// The precondition of a transaction did not hold, so it was skipped (or
+    /// cancelled).
+    Skipped { nonce: U256, metadata: Metadata },
+
+    /// The balance of a sender dropped below the threshold of the
+    /// `FundingMonitor`.
+    LowBalance {
+        sender: Address,
+        balance: U256,
+        threshold: U256,
+    },
+
+    /// The manager gave up on sending a transaction.
+    Failed {
+        nonce: Option<U256>,
+        error: String,
+        metadata: Metadata,
+    },
+}
+
+/// Receives the events emitted by the manager. Implementations must not block,
+/// as they are notified from within the manager's control flow.
+pub trait EventListener: Debug + Send + Sync {
+    fn notify(&self, event: &Event);
+}
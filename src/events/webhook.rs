@@ -0,0 +1,126 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::{trace, warn};
+
+use crate::events::{Event, EventListener};
+
+/// Header that carries the HMAC-SHA256 signature of the payload.
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+const MAX_RETRIES: u32 = 3;
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Event listener that POSTs the events as JSON payloads to a URL.
+///
+/// Requests are sent in the background (requires a tokio runtime) and retried
+/// with exponential backoff. If a secret is set, the payload's signature is
+/// sent in the `X-Signature-256` header as `sha256=<hex digest>`.
+#[derive(Clone, Debug)]
+pub struct WebhookNotifier {
+    url: String,
+    secret: Option<String>,
+    max_retries: u32,
+    retry_interval: Duration,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> WebhookNotifier {
+        WebhookNotifier {
+            url,
+            secret: None,
+            max_retries: MAX_RETRIES,
+            retry_interval: RETRY_INTERVAL,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn set_secret(mut self, secret: String) -> WebhookNotifier {
+        self.secret = Some(secret);
+        self
+    }
+
+    pub fn set_max_retries(mut self, max_retries: u32) -> WebhookNotifier {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn set_retry_interval(mut self, retry_interval: Duration) -> WebhookNotifier {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    async fn post(&self, body: Vec<u8>) {
+        let signature = self.secret.as_ref().map(|secret| sign(secret, &body));
+
+        let mut retry_interval = self.retry_interval;
+        for attempt in 0..=self.max_retries {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, format!("sha256={}", signature));
+            }
+
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    trace!("Webhook notified (attempt {}).", attempt);
+                    return;
+                }
+                Ok(response) => warn!(
+                    "Webhook responded with status code {} (attempt {}).",
+                    response.status(),
+                    attempt
+                ),
+                Err(err) => warn!("Could not notify webhook (attempt {}): {}", attempt, err),
+            }
+
+            if attempt < self.max_retries {
+                tokio::time::sleep(retry_interval).await;
+                retry_interval *= 2;
+            }
+        }
+
+        warn!("Giving up on notifying the webhook.");
+    }
+}
+
+impl EventListener for WebhookNotifier {
+    fn notify(&self, event: &Event) {
+        let body = match serde_json::to_vec(event) {
+            Ok(body) => body,
+            Err(err) => {
+                warn!("Could not serialize event {:?}: {}", event, err);
+                return;
+            }
+        };
+
+        let notifier = self.clone();
+        tokio::spawn(async move { notifier.post(body).await });
+    }
+}
+
+/// Returns the hex encoded HMAC-SHA256 of the payload.
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign;
+
+    #[test]
+    fn test_webhook_sign() {
+        let signature = sign("key", b"The quick brown fox jumps over the lazy dog");
+        assert_eq!(
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8",
+            signature
+        );
+    }
+}
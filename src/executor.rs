@@ -0,0 +1,573 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, TransactionReceipt},
+};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot, Notify};
+use tracing::{error, trace, warn};
+
+use crate::database::Database;
+use crate::dyn_manager::DynError;
+use crate::events::{Event, EventListener};
+use crate::gas_oracle::GasOracle;
+use crate::manager::{Chain, Configuration, Error, Manager, PendingResolution, SendOptions};
+use crate::time::Time;
+use crate::transaction::{Priority, Transaction};
+
+/// Capacity of the combined event stream (see `Executor::subscribe`).
+const EVENTS_CAPACITY: usize = 1024;
+
+/// Metadata key of the tenant (e.g., the integration or namespace) a
+/// transaction is sent for, which the `Executor` schedules fairly (see
+/// `TenantPolicy`). Transactions without it belong to the default tenant (`""`).
+pub const TENANT: &str = "tenant";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExecutorError {
+    #[error("manager: {0}")]
+    Manager(DynError),
+
+    #[error("the executor has no running senders")]
+    NoSenders,
+
+    #[error("sender {0:?} stopped before finishing the transaction")]
+    SenderStopped(Address),
+
+    #[error("tenant `{0}` exceeded its rate limit")]
+    RateLimited(String),
+}
+
+/// How the `Executor` schedules the transactions of a tenant (see `TENANT`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TenantPolicy {
+    /// How many transactions of the tenant a sender sends in a row while other
+    /// tenants are waiting for it (1 by default, round-robin).
+    pub weight: u32,
+
+    /// If set, the tenant can send at most this many transactions per window;
+    /// the executor refuses the others with `ExecutorError::RateLimited`.
+    pub rate_limit: Option<(u32, Duration)>,
+}
+
+impl Default for TenantPolicy {
+    fn default() -> Self {
+        Self {
+            weight: 1,
+            rate_limit: None,
+        }
+    }
+}
+
+/// Per-tenant counters (see `Executor::tenant_stats`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TenantStats {
+    pub tenant: String,
+
+    /// Transactions waiting for (or being sent by) a sender.
+    pub queued: usize,
+
+    pub sent: u64,
+    pub failed: u64,
+    pub rate_limited: u64,
+}
+
+/// An event emitted by the manager of one of the executor's senders.
+#[derive(Clone, Debug)]
+pub struct SenderEvent {
+    pub sender: Address,
+    pub event: Event,
+}
+
+/// Sends transactions in parallel from multiple sender accounts, while each
+/// sender sends its transactions sequentially (one nonce at a time).
+///
+/// Each sender has its own transaction manager (with its own middleware and
+/// database, so nonces and pending states are isolated) running in a
+/// background task (requires a tokio runtime). Transactions are dispatched to
+/// the sender with the shortest queue, preferring the senders that are not
+/// recovering a pending transaction.
+///
+/// The senders serve the tenants of their queues in (weighted) round-robin, so
+/// that one noisy tenant cannot monopolize a shared sender.
+#[derive(Debug)]
+pub struct Executor {
+    senders: Vec<SenderHandle>,
+    events: broadcast::Sender<SenderEvent>,
+    tenants: Arc<Mutex<Tenants>>,
+}
+
+#[derive(Debug)]
+struct SenderHandle {
+    address: Address,
+    queue: Arc<SenderQueue>,
+    queued: Arc<AtomicUsize>,
+    recovering: Arc<AtomicBool>,
+}
+
+#[derive(Debug)]
+struct Job {
+    tenant: String,
+    transaction: Transaction,
+    options: SendOptions,
+    result: oneshot::Sender<Result<TransactionReceipt, DynError>>,
+}
+
+#[derive(Debug, Default)]
+struct Tenants {
+    policies: HashMap<String, TenantPolicy>,
+    stats: BTreeMap<String, TenantStats>,
+
+    /// When the recent transactions of the rate-limited tenants were accepted.
+    accepted: HashMap<String, VecDeque<Instant>>,
+}
+
+impl Tenants {
+    fn policy(&self, tenant: &str) -> TenantPolicy {
+        self.policies.get(tenant).copied().unwrap_or_default()
+    }
+
+    fn stats(&mut self, tenant: &str) -> &mut TenantStats {
+        self.stats
+            .entry(tenant.to_string())
+            .or_insert_with(|| TenantStats {
+                tenant: tenant.to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// Accepts a transaction of the tenant, unless it exceeded its rate limit.
+    fn accept(&mut self, tenant: &str) -> bool {
+        if let Some((limit, window)) = self.policy(tenant).rate_limit {
+            let now = Instant::now();
+            let accepted = self.accepted.entry(tenant.to_string()).or_default();
+            while matches!(accepted.front(), Some(&time) if now.duration_since(time) >= window) {
+                accepted.pop_front();
+            }
+            if accepted.len() >= limit as usize {
+                self.stats(tenant).rate_limited += 1;
+                return false;
+            }
+            accepted.push_back(now);
+        }
+        self.stats(tenant).queued += 1;
+        true
+    }
+
+    fn finish(&mut self, tenant: &str, sent: Option<bool>) {
+        let stats = self.stats(tenant);
+        stats.queued -= 1;
+        match sent {
+            Some(true) => stats.sent += 1,
+            Some(false) => stats.failed += 1,
+            None => {}
+        }
+    }
+}
+
+/// The jobs of a sender, by tenant, served in weighted round-robin.
+#[derive(Debug)]
+struct SenderQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+    tenants: Arc<Mutex<Tenants>>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    jobs: HashMap<String, VecDeque<Job>>,
+
+    /// The tenants with jobs, in serving order (the first one is being
+    /// served), and how many of its jobs were served in a row.
+    order: VecDeque<String>,
+    served: u32,
+
+    closed: bool,
+}
+
+impl SenderQueue {
+    fn new(tenants: Arc<Mutex<Tenants>>) -> SenderQueue {
+        SenderQueue {
+            state: Mutex::new(QueueState::default()),
+            notify: Notify::new(),
+            tenants,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    /// Queues the job, unless the queue is closed.
+    fn push(&self, job: Job) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return false;
+        }
+        if !state.jobs.contains_key(&job.tenant) {
+            state.order.push_back(job.tenant.clone());
+        }
+        state
+            .jobs
+            .entry(job.tenant.clone())
+            .or_default()
+            .push_back(job);
+        self.notify.notify_one();
+        true
+    }
+
+    fn pop(&self) -> Option<Job> {
+        let mut state = self.state.lock().unwrap();
+        let tenant = state.order.front()?.clone();
+        let weight = self.tenants.lock().unwrap().policy(&tenant).weight.max(1);
+        let jobs = state.jobs.get_mut(&tenant)?;
+        let job = jobs.pop_front();
+        let empty = jobs.is_empty();
+
+        state.served += 1;
+        if empty {
+            state.jobs.remove(&tenant);
+            state.order.pop_front();
+            state.served = 0;
+        } else if state.served >= weight {
+            state.order.rotate_left(1);
+            state.served = 0;
+        }
+        job
+    }
+
+    /// Waits for the next job (none once the queue is closed).
+    async fn next(&self) -> Option<Job> {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_closed() {
+                return None;
+            }
+            if let Some(job) = self.pop() {
+                return Some(job);
+            }
+            notified.await;
+        }
+    }
+
+    /// Closes the queue, dropping its jobs (their callers get
+    /// `ExecutorError::SenderStopped`).
+    fn close(&self) {
+        let jobs = {
+            let mut state = self.state.lock().unwrap();
+            state.closed = true;
+            state.order.clear();
+            std::mem::take(&mut state.jobs)
+        };
+        let mut tenants = self.tenants.lock().unwrap();
+        for job in jobs.into_values().flatten() {
+            tenants.finish(&job.tenant, None);
+        }
+        self.notify.notify_one();
+    }
+}
+
+impl Executor {
+    /// Starts the background tasks of the senders, which instantiate their
+    /// managers (recovering their pending transactions, see `Manager::new`)
+    /// before sending the transactions dispatched to them. All the managers
+    /// share the gas oracle and the configuration.
+    ///
+    /// It does not wait for the recoveries, so the senders without a pending
+    /// transaction can send right away. A sender whose manager fails to be
+    /// instantiated stops (see `ExecutorError::SenderStopped`).
+    ///
+    /// The middlewares and databases are cloned to reinstantiate a sender's
+    /// manager after it fails to send a transaction.
+    pub async fn new<M, GO, DB, T>(
+        senders: Vec<(Address, M, DB)>,
+        gas_oracle: GO,
+        chain: Chain,
+        configuration: Configuration<T>,
+    ) -> Result<Executor, Error<M, GO, DB>>
+    where
+        M: Middleware + Clone + Send + Sync + 'static,
+        GO: GasOracle + Clone + Send + Sync + 'static,
+        DB: Database + Clone + Send + Sync + 'static,
+        T: Time + Send + Sync + 'static,
+        Error<M, GO, DB>: Send + Sync + 'static,
+    {
+        let (events, _) = broadcast::channel(EVENTS_CAPACITY);
+        let tenants = Arc::new(Mutex::new(Tenants::default()));
+
+        let mut handles = Vec::with_capacity(senders.len());
+        for (address, provider, db) in senders {
+            let configuration = configuration
+                .clone()
+                .add_event_listener(Arc::new(Forwarder {
+                    sender: address,
+                    events: events.clone(),
+                }));
+            let worker = Worker {
+                address,
+                provider,
+                gas_oracle: gas_oracle.clone(),
+                db,
+                chain,
+                configuration,
+            };
+
+            let queue = Arc::new(SenderQueue::new(tenants.clone()));
+            let queued = Arc::new(AtomicUsize::new(0));
+            let recovering = Arc::new(AtomicBool::new(true));
+            tokio::spawn(worker.run(queue.clone(), queued.clone(), recovering.clone()));
+            handles.push(SenderHandle {
+                address,
+                queue,
+                queued,
+                recovering,
+            });
+        }
+
+        Ok(Executor {
+            senders: handles,
+            events,
+            tenants,
+        })
+    }
+
+    /// Sets the scheduling policy of the tenant (see `TENANT`).
+    pub fn set_tenant_policy(&self, tenant: impl Into<String>, policy: TenantPolicy) {
+        let mut tenants = self.tenants.lock().unwrap();
+        let tenant = tenant.into();
+        tenants.accepted.remove(&tenant);
+        tenants.policies.insert(tenant, policy);
+    }
+
+    /// Returns the counters of the tenants that sent transactions, ordered by
+    /// tenant.
+    pub fn tenant_stats(&self) -> Vec<TenantStats> {
+        self.tenants
+            .lock()
+            .unwrap()
+            .stats
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Addresses of the senders.
+    pub fn senders(&self) -> Vec<Address> {
+        self.senders.iter().map(|sender| sender.address).collect()
+    }
+
+    /// Addresses of the senders still recovering their pending transactions
+    /// (see `Executor::new`).
+    pub fn recovering(&self) -> Vec<Address> {
+        self.senders
+            .iter()
+            .filter(|sender| sender.recovering.load(Ordering::SeqCst))
+            .map(|sender| sender.address)
+            .collect()
+    }
+
+    /// Subscribes to the events of all the senders.
+    pub fn subscribe(&self) -> broadcast::Receiver<SenderEvent> {
+        self.events.subscribe()
+    }
+
+    /// Sends the transaction from the sender with the shortest queue (the
+    /// transaction's `from` is overwritten) and returns its receipt. The
+    /// senders that are recovering a pending transaction are only picked if
+    /// all the senders are.
+    ///
+    /// Fails with `ExecutorError::RateLimited` if the transaction's tenant
+    /// exceeded its rate limit (see `TenantPolicy`).
+    pub async fn send(
+        &self,
+        mut transaction: Transaction,
+        options: SendOptions,
+    ) -> Result<TransactionReceipt, ExecutorError> {
+        let tenant = transaction
+            .metadata
+            .get(TENANT)
+            .cloned()
+            .unwrap_or_default();
+        let sender = self
+            .senders
+            .iter()
+            .filter(|sender| !sender.queue.is_closed())
+            .min_by_key(|sender| {
+                (
+                    sender.recovering.load(Ordering::SeqCst),
+                    sender.queued.load(Ordering::SeqCst),
+                )
+            })
+            .ok_or(ExecutorError::NoSenders)?;
+        trace!(
+            "Dispatching the transaction to sender {:?}.",
+            sender.address
+        );
+
+        if !self.tenants.lock().unwrap().accept(&tenant) {
+            warn!("Tenant `{}` exceeded its rate limit.", tenant);
+            return Err(ExecutorError::RateLimited(tenant));
+        }
+
+        transaction.from = sender.address;
+        let (result, receiver) = oneshot::channel();
+        sender.queued.fetch_add(1, Ordering::SeqCst);
+        let job = Job {
+            tenant: tenant.clone(),
+            transaction,
+            options,
+            result,
+        };
+        if !sender.queue.push(job) {
+            sender.queued.fetch_sub(1, Ordering::SeqCst);
+            self.tenants.lock().unwrap().finish(&tenant, None);
+            return Err(ExecutorError::SenderStopped(sender.address));
+        }
+
+        receiver
+            .await
+            .map_err(|_| ExecutorError::SenderStopped(sender.address))?
+            .map_err(ExecutorError::Manager)
+    }
+
+    /// Sends the transaction with the given confirmations and priority (see
+    /// `Executor::send`).
+    #[deprecated(note = "use `Executor::send` with `SendOptions`")]
+    pub async fn send_transaction(
+        &self,
+        transaction: Transaction,
+        confirmations: usize,
+        priority: Priority,
+    ) -> Result<TransactionReceipt, ExecutorError> {
+        let options = SendOptions::new()
+            .set_confirmations(confirmations)
+            .set_priority(priority);
+        self.send(transaction, options).await
+    }
+}
+
+/// Stops the senders.
+impl Drop for Executor {
+    fn drop(&mut self) {
+        for sender in &self.senders {
+            sender.queue.close();
+        }
+    }
+}
+
+/// Forwards a manager's events to the executor's combined event stream.
+#[derive(Debug)]
+struct Forwarder {
+    sender: Address,
+    events: broadcast::Sender<SenderEvent>,
+}
+
+impl EventListener for Forwarder {
+    fn notify(&self, event: &Event) {
+        // Fails only when there are no subscribers.
+        let _ = self.events.send(SenderEvent {
+            sender: self.sender,
+            event: event.clone(),
+        });
+    }
+}
+
+/// Owns what is needed to (re)instantiate the manager of a sender.
+struct Worker<M: Middleware, GO: GasOracle, DB: Database, T: Time> {
+    address: Address,
+    provider: M,
+    gas_oracle: GO,
+    db: DB,
+    chain: Chain,
+    configuration: Configuration<T>,
+}
+
+impl<M, GO, DB, T> Worker<M, GO, DB, T>
+where
+    M: Middleware + Clone + Send + Sync + 'static,
+    GO: GasOracle + Clone + Send + Sync + 'static,
+    DB: Database + Clone + Send + Sync + 'static,
+    T: Time + Send + Sync + 'static,
+    Error<M, GO, DB>: Send + Sync + 'static,
+{
+    async fn manager(
+        &self,
+    ) -> Result<(Manager<M, GO, DB, T>, PendingResolution), Error<M, GO, DB>> {
+        Manager::new(
+            self.provider.clone(),
+            self.gas_oracle.clone(),
+            self.db.clone(),
+            self.chain,
+            self.configuration.clone(),
+        )
+        .await
+    }
+
+    async fn run(
+        self,
+        queue: Arc<SenderQueue>,
+        queued: Arc<AtomicUsize>,
+        recovering: Arc<AtomicBool>,
+    ) {
+        self.serve(&queue, queued, recovering).await;
+        queue.close();
+    }
+
+    async fn serve(
+        &self,
+        queue: &SenderQueue,
+        queued: Arc<AtomicUsize>,
+        recovering: Arc<AtomicBool>,
+    ) {
+        let mut manager = match self.manager().await {
+            Ok((manager, resolution)) => {
+                trace!("Sender {:?} recovered => {:?}", self.address, resolution);
+                recovering.store(false, Ordering::SeqCst);
+                manager
+            }
+            Err(err) => {
+                error!("Sender {:?} failed to recover: {}", self.address, err);
+                return;
+            }
+        };
+
+        while let Some(job) = queue.next().await {
+            trace!("Sender {:?} serving tenant `{}`.", self.address, job.tenant);
+            let result = manager.send(job.transaction, job.options).await;
+            queued.fetch_sub(1, Ordering::SeqCst);
+            queue
+                .tenants
+                .lock()
+                .unwrap()
+                .finish(&job.tenant, Some(result.is_ok()));
+
+            let err = match result {
+                Ok((m, receipt)) => {
+                    manager = m;
+                    // Fails only when the caller is no longer waiting for the result.
+                    let _ = job.result.send(Ok(receipt));
+                    continue;
+                }
+                Err(err) => err,
+            };
+
+            warn!(
+                "Sender {:?} failed to send a transaction, reinstantiating its manager.",
+                self.address
+            );
+            let reinstantiated = self.manager().await;
+            let _ = job.result.send(Err(Box::new(err)));
+            match reinstantiated {
+                Ok((m, _)) => manager = m,
+                Err(err) => {
+                    error!("Sender {:?} stopped: {}", self.address, err);
+                    return;
+                }
+            }
+        }
+    }
+}
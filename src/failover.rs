@@ -0,0 +1,394 @@
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, ProviderError};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{trace, warn};
+
+use crate::manager::is_transient;
+
+/// How many of the latest calls to an endpoint its score accounts for.
+const SCORE_WINDOW: usize = 100;
+
+/// Latency added to the score of an endpoint for each failed call, so that an
+/// endpoint that always fails ranks below one that answers in this much time.
+const ERROR_PENALTY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum FailoverClientError {
+    #[error("the failover client has no endpoints")]
+    NoEndpoints,
+
+    #[error("endpoint {endpoint}: {source}")]
+    Endpoint {
+        endpoint: String,
+        source: ProviderError,
+    },
+
+    #[error("serde json: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl From<FailoverClientError> for ProviderError {
+    fn from(err: FailoverClientError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(err))
+    }
+}
+
+/// Switches the provider to another endpoint when the manager finds that the
+/// current one stopped following the chain (see
+/// `Configuration::stale_provider_blocks`).
+pub trait Failover: Debug + Send + Sync {
+    /// Fails over to another endpoint, returning false if there is none.
+    fn fail_over(&self) -> bool;
+}
+
+/// The health of an endpoint over its latest calls (see
+/// `FailoverClient::scores`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndpointScore {
+    pub endpoint: String,
+
+    /// How many calls the score accounts for.
+    pub calls: usize,
+
+    /// Mean latency of the successful calls.
+    pub latency: Option<Duration>,
+
+    /// Fraction of the calls that failed (with transient errors).
+    pub error_rate: f64,
+
+    /// Mean latency of the calls, with the failed ones counting as
+    /// `ERROR_PENALTY` (lower is healthier).
+    pub score: Duration,
+}
+
+/// A JSON-RPC client over multiple endpoints (e.g., providers for the same
+/// chain) that tracks the latency and error rate of each one, and sends each
+/// call to the healthiest endpoint first, failing over to the next ones on
+/// transient errors (timeouts, rate limits, unavailability).
+///
+/// Other errors (e.g., "nonce too low") are answers from a healthy endpoint,
+/// so they are returned without failing over.
+///
+/// Its clones share the health of the endpoints, so that a clone can be the
+/// manager's `Failover` while the original serves its provider.
+#[derive(Clone, Debug)]
+pub struct FailoverClient<C> {
+    endpoints: Vec<Endpoint<C>>,
+    window: usize,
+
+    /// The endpoint that answered the last call.
+    current: Arc<Mutex<Option<usize>>>,
+}
+
+#[derive(Clone, Debug)]
+struct Endpoint<C> {
+    name: String,
+    client: C,
+    calls: Arc<Mutex<VecDeque<Call>>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Call {
+    latency: Duration,
+    ok: bool,
+}
+
+impl<C: JsonRpcClient> Default for FailoverClient<C> {
+    fn default() -> Self {
+        FailoverClient::new()
+    }
+}
+
+impl<C: JsonRpcClient> FailoverClient<C> {
+    pub fn new() -> Self {
+        FailoverClient {
+            endpoints: Vec::new(),
+            window: SCORE_WINDOW,
+            current: Arc::default(),
+        }
+    }
+
+    /// Adds an endpoint, identified by the name (e.g., its URL) in the scores
+    /// and errors. Endpoints without calls are tried in the order they were
+    /// added.
+    pub fn add_endpoint(mut self, name: impl Into<String>, client: C) -> Self {
+        self.endpoints.push(Endpoint {
+            name: name.into(),
+            client,
+            calls: Arc::new(Mutex::new(VecDeque::new())),
+        });
+        self
+    }
+
+    /// Sets how many of the latest calls to an endpoint its score accounts
+    /// for.
+    pub fn set_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// The scores of the endpoints, in the order they were added.
+    pub fn scores(&self) -> Vec<EndpointScore> {
+        self.endpoints.iter().map(Endpoint::score).collect()
+    }
+
+    /// The indexes of the endpoints, from the healthiest.
+    fn ranking(&self) -> Vec<usize> {
+        let scores: Vec<_> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.score().score)
+            .collect();
+        let mut ranking: Vec<_> = (0..self.endpoints.len()).collect();
+        ranking.sort_by_key(|&i| scores[i]);
+        ranking
+    }
+}
+
+impl<C: JsonRpcClient> Failover for FailoverClient<C> {
+    /// Demotes the endpoint that answered the last call by filling its window
+    /// with failed calls, so that the next calls go to the others until it
+    /// recovers.
+    fn fail_over(&self) -> bool {
+        let current = *self.current.lock().unwrap();
+        let endpoint = match current.or_else(|| self.ranking().first().copied()) {
+            Some(i) => &self.endpoints[i],
+            None => return false,
+        };
+        warn!("Failing over from {} (stale).", endpoint.name);
+        for _ in 0..self.window {
+            endpoint.record(Duration::ZERO, false, self.window);
+        }
+        self.endpoints.len() > 1
+    }
+}
+
+impl<C> Endpoint<C> {
+    fn record(&self, latency: Duration, ok: bool, window: usize) {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push_back(Call { latency, ok });
+        while calls.len() > window {
+            calls.pop_front();
+        }
+    }
+
+    fn score(&self) -> EndpointScore {
+        let calls = self.calls.lock().unwrap();
+        let successes: Vec<_> = calls.iter().filter(|call| call.ok).collect();
+        let failures = calls.len() - successes.len();
+
+        let latency = (!successes.is_empty()).then(|| {
+            successes.iter().map(|call| call.latency).sum::<Duration>() / successes.len() as u32
+        });
+        let (error_rate, score) = if calls.is_empty() {
+            (0., Duration::ZERO)
+        } else {
+            let total = latency.unwrap_or_default() * successes.len() as u32
+                + ERROR_PENALTY * failures as u32;
+            (
+                failures as f64 / calls.len() as f64,
+                total / calls.len() as u32,
+            )
+        };
+
+        EndpointScore {
+            endpoint: self.name.clone(),
+            calls: calls.len(),
+            latency,
+            error_rate,
+            score,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: JsonRpcClient> JsonRpcClient for FailoverClient<C> {
+    type Error = FailoverClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let params = serde_json::to_value(params)?;
+
+        let mut last_err = FailoverClientError::NoEndpoints;
+        for i in self.ranking() {
+            let endpoint = &self.endpoints[i];
+            let start = Instant::now();
+            let result = endpoint.client.request::<_, Value>(method, &params).await;
+            let latency = start.elapsed();
+
+            let err = match result {
+                Ok(value) => {
+                    endpoint.record(latency, true, self.window);
+                    *self.current.lock().unwrap() = Some(i);
+                    return Ok(serde_json::from_value(value)?);
+                }
+                Err(err) => err,
+            };
+
+            let transient = is_transient(&format!("{:?}", err));
+            endpoint.record(latency, !transient, self.window);
+            let err = FailoverClientError::Endpoint {
+                endpoint: endpoint.name.clone(),
+                source: err.into(),
+            };
+            if !transient {
+                return Err(err);
+            }
+
+            warn!(
+                "Failing over from {} ({}): {:?}",
+                endpoint.name, method, err
+            );
+            last_err = err;
+        }
+
+        trace!("All the endpoints failed ({}).", method);
+        Err(last_err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+    use ethers::providers::{JsonRpcClient, ProviderError};
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::fmt::Debug;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use super::{Failover, FailoverClient, FailoverClientError};
+
+    /// Answers the calls with the scripted errors, and then with its number.
+    #[derive(Debug)]
+    struct Node {
+        number: u64,
+        errors: Mutex<Vec<&'static str>>,
+        calls: AtomicUsize,
+    }
+
+    impl Node {
+        fn new(number: u64, errors: Vec<&'static str>) -> Node {
+            Node {
+                number,
+                errors: Mutex::new(errors),
+                calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for &Node {
+        type Error = ProviderError;
+
+        async fn request<T, R>(&self, _: &str, _: T) -> Result<R, Self::Error>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned,
+        {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut errors = self.errors.lock().unwrap();
+            if !errors.is_empty() {
+                return Err(ProviderError::CustomError(errors.remove(0).to_string()));
+            }
+            Ok(serde_json::from_value(self.number.into())?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover_client() {
+        let node1 = Node::new(1, vec!["connection refused", "nonce too low"]);
+        let node2 = Node::new(2, vec![]);
+        let client = FailoverClient::new()
+            .add_endpoint("node1", &node1)
+            .add_endpoint("node2", &node2);
+        let scores = client.scores();
+        assert_eq!(0, scores[0].calls);
+        assert_eq!(None, scores[0].latency);
+
+        // Fails over from the first endpoint on transient errors.
+        let result: Result<u64, _> = client.request("eth_blockNumber", ()).await;
+        assert_eq!(2, result.unwrap());
+        let scores = client.scores();
+        assert_eq!(1., scores[0].error_rate);
+        assert_eq!(None, scores[0].latency);
+        assert_eq!(0., scores[1].error_rate);
+        assert!(scores[0].score > scores[1].score);
+
+        // Prefers the healthiest endpoint.
+        let result: Result<u64, _> = client.request("eth_blockNumber", ()).await;
+        assert_eq!(2, result.unwrap());
+        assert_eq!(1, node1.calls.load(Ordering::SeqCst));
+        assert_eq!(2, node2.calls.load(Ordering::SeqCst));
+
+        // Does not fail over on other errors.
+        let client = FailoverClient::new()
+            .add_endpoint("node1", &node1)
+            .add_endpoint("node2", &node2);
+        let result: Result<u64, _> = client.request("eth_sendRawTransaction", ()).await;
+        assert!(matches!(
+            result,
+            Err(FailoverClientError::Endpoint { ref endpoint, .. }) if endpoint == "node1"
+        ));
+        assert_eq!(2, node2.calls.load(Ordering::SeqCst));
+        assert_eq!(0., client.scores()[0].error_rate);
+
+        // Without endpoints.
+        let client = FailoverClient::<&Node>::new();
+        let result: Result<u64, _> = client.request("eth_blockNumber", ()).await;
+        assert!(matches!(result, Err(FailoverClientError::NoEndpoints)));
+        assert!(!client.fail_over());
+    }
+
+    #[tokio::test]
+    async fn test_failover_client_fail_over() {
+        let node1 = Node::new(1, vec![]);
+        let node2 = Node::new(2, vec![]);
+        let client = FailoverClient::new()
+            .add_endpoint("node1", &node1)
+            .add_endpoint("node2", &node2);
+        let result: Result<u64, _> = client.request("eth_blockNumber", ()).await;
+        assert_eq!(1, result.unwrap());
+
+        // A clone demotes the healthiest endpoint of the original.
+        assert!(client.clone().fail_over());
+        assert_eq!(1., client.scores()[0].error_rate);
+        let result: Result<u64, _> = client.request("eth_blockNumber", ()).await;
+        assert_eq!(2, result.unwrap());
+
+        // There is nowhere to fail over to with a single endpoint.
+        let client = FailoverClient::new().add_endpoint("node1", &node1);
+        assert!(!client.fail_over());
+    }
+
+    #[tokio::test]
+    async fn test_failover_client_status_codes() {
+        let node1 = Node::new(1, vec!["Status(503)", "execution reverted: 0x4290503a"]);
+        let node2 = Node::new(2, vec![]);
+        let client = FailoverClient::new()
+            .add_endpoint("node1", &node1)
+            .add_endpoint("node2", &node2);
+
+        // Fails over on transient HTTP statuses.
+        let result: Result<u64, _> = client.request("eth_blockNumber", ()).await;
+        assert_eq!(2, result.unwrap());
+
+        // But not on errors that only contain their digits.
+        let client = FailoverClient::new()
+            .add_endpoint("node1", &node1)
+            .add_endpoint("node2", &node2);
+        let result: Result<u64, _> = client.request("eth_call", ()).await;
+        assert!(matches!(
+            result,
+            Err(FailoverClientError::Endpoint { ref endpoint, .. }) if endpoint == "node1"
+        ));
+        assert_eq!(1, node2.calls.load(Ordering::SeqCst));
+    }
+}
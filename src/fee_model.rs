@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use ethers::types::{transaction::eip2718::TypedTransaction, Bytes, Signature};
+use std::fmt::Debug;
+
+use crate::dyn_manager::DynError;
+use crate::gas_oracle::GasInfo;
+use crate::manager::Chain;
+use crate::transaction::StaticTxData;
+
+/// Builds and encodes the transactions the manager signs and submits, so that
+/// chains with non-Ethereum fee mechanics (e.g., zkSync Era's gas per pubdata,
+/// or paying fees in another token) can plug in their own fee computation and
+/// transaction construction, while reusing the manager's persistence,
+/// resubmissions and confirmations.
+///
+/// The manager still signs the transactions with its provider (so a custom
+/// transaction type must be supported by its signer) and derives their hashes
+/// with the configured `HashStrategy`.
+#[async_trait]
+pub trait FeeModel: Debug + Send + Sync {
+    /// Builds the request of a (re)submission of the transaction, with the
+    /// fees from the gas oracle (already bumped and capped by the manager).
+    /// The manager sets the gas limit with the provider's estimate if the
+    /// request has none.
+    async fn build(
+        &self,
+        tx_data: &StaticTxData,
+        chain: &Chain,
+        gas_info: GasInfo,
+    ) -> Result<TypedTransaction, DynError>;
+
+    /// Encodes the signed transaction to be sent to the node.
+    fn encode(&self, transaction: &TypedTransaction, signature: &Signature) -> Bytes {
+        transaction.rlp_signed(signature)
+    }
+}
+
+/// Default model: legacy or EIP-1559 transactions (depending on the gas
+/// oracle's fees), RLP-encoded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EthereumFeeModel;
+
+#[async_trait]
+impl FeeModel for EthereumFeeModel {
+    async fn build(
+        &self,
+        tx_data: &StaticTxData,
+        chain: &Chain,
+        gas_info: GasInfo,
+    ) -> Result<TypedTransaction, DynError> {
+        Ok(tx_data.to_typed_transaction(chain, gas_info))
+    }
+}
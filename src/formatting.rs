@@ -0,0 +1,160 @@
+use ethers::{
+    types::{NameOrAddress, TransactionReceipt, H256, U256},
+    utils::format_units,
+};
+
+use crate::gas_oracle::GasInfo;
+use crate::transaction::StaticTxData;
+use crate::units::{GasLimit, Wei};
+use crate::Chain;
+
+/// Renders the value with the given decimals, without trailing zeros (e.g.,
+/// "1.5" instead of "1.500000000").
+pub fn units(value: U256, decimals: u32) -> String {
+    match format_units(value, decimals) {
+        Ok(units) if units.contains('.') => units
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string(),
+        Ok(units) => units,
+        Err(_) => format!("{}e-{}", value, decimals),
+    }
+}
+
+/// Renders the fee in gwei (e.g., "1.5 gwei").
+pub fn gwei(fee: Wei) -> String {
+    format!("{} gwei", units(fee.into(), 9))
+}
+
+/// Renders the value in the chain's native currency (e.g., "0.5 ETH"), or in
+/// wei if the currency is unknown.
+pub fn value(chain: &Chain, value: Wei) -> String {
+    match chain.currency {
+        Some(currency) => format!(
+            "{} {}",
+            units(value.into(), currency.decimals as u32),
+            currency.symbol
+        ),
+        None => value.to_string(),
+    }
+}
+
+/// Renders the fees of the gas info in gwei.
+pub fn fees(gas_info: &GasInfo) -> String {
+    match gas_info {
+        GasInfo::Legacy(legacy) => format!("gas price = {}", gwei(legacy.gas_price)),
+        GasInfo::EIP1559(eip1559) => match (eip1559.max_fee, eip1559.max_priority_fee) {
+            (Some(max_fee), Some(max_priority_fee)) => format!(
+                "max fee = {}, max priority fee = {}",
+                gwei(max_fee),
+                gwei(max_priority_fee)
+            ),
+            (Some(max_fee), None) => format!("max fee = {}", gwei(max_fee)),
+            (None, Some(max_priority_fee)) => {
+                format!("max priority fee = {}", gwei(max_priority_fee))
+            }
+            (None, None) => "no fees".to_string(),
+        },
+    }
+}
+
+/// Renders the hash followed by its explorer link, if the chain has one.
+pub fn hash(chain: &Chain, hash: H256) -> String {
+    match chain.transaction_url(hash) {
+        Some(transaction_url) => format!("{:?} ({})", hash, transaction_url),
+        None => format!("{:?}", hash),
+    }
+}
+
+/// Renders the address, or the ENS name (of transactions not yet resolved).
+pub fn recipient(to: &NameOrAddress) -> String {
+    match to {
+        NameOrAddress::Address(address) => format!("{:?}", address),
+        NameOrAddress::Name(name) => name.clone(),
+    }
+}
+
+/// Describes a submission (see `Configuration::human_logs`).
+pub fn submission(
+    chain: &Chain,
+    tx_data: &StaticTxData,
+    transaction_hash: H256,
+    gas_info: &GasInfo,
+) -> String {
+    format!(
+        "Submitted transaction {} with nonce {} on {}: {} to {}, {}.",
+        hash(chain, transaction_hash),
+        tx_data.nonce,
+        chain,
+        value(chain, tx_data.transaction.value.into()),
+        recipient(&tx_data.transaction.to),
+        fees(gas_info)
+    )
+}
+
+/// Describes a confirmation (see `Configuration::human_logs`).
+pub fn confirmation(chain: &Chain, tx_data: &StaticTxData, receipt: &TransactionReceipt) -> String {
+    let block_number = receipt
+        .block_number
+        .map_or_else(|| "?".to_string(), |block_number| block_number.to_string());
+    let cost = receipt
+        .gas_used
+        .zip(receipt.effective_gas_price)
+        .map_or_else(
+            || "unknown".to_string(),
+            |(gas_used, effective_gas_price)| {
+                value(chain, GasLimit(gas_used) * Wei(effective_gas_price))
+            },
+        );
+    format!(
+        "Confirmed transaction {} with nonce {} in block {} on {}: gas used = {}, \
+        effective gas price = {}, cost = {}.",
+        hash(chain, receipt.transaction_hash),
+        tx_data.nonce,
+        block_number,
+        chain,
+        receipt.gas_used.unwrap_or_default(),
+        receipt
+            .effective_gas_price
+            .map_or_else(|| "unknown".to_string(), |fee| gwei(Wei(fee))),
+        cost
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::U256;
+
+    use super::{fees, gwei, units, value};
+    use crate::gas_oracle::{EIP1559GasInfo, GasInfo, LegacyGasInfo};
+    use crate::units::Wei;
+    use crate::Chain;
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!("1.5", units(U256::from(1_500_000_000), 9));
+        assert_eq!("2", units(U256::from(2_000_000_000), 9));
+        assert_eq!("0", units(U256::zero(), 9));
+        assert_eq!("0.000000001 gwei", gwei(Wei::from(1)));
+
+        let fee = Wei::from(1_500_000_000);
+        assert_eq!(
+            "gas price = 1.5 gwei",
+            fees(&GasInfo::Legacy(LegacyGasInfo { gas_price: fee }))
+        );
+        assert_eq!(
+            "max fee = 1.5 gwei, max priority fee = 1.5 gwei",
+            fees(&GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: Some(fee),
+                max_priority_fee: Some(fee),
+            }))
+        );
+
+        let half = Wei(U256::exp10(17) * 5);
+        assert_eq!("500000000000000000 wei", value(&Chain::new(1), half));
+        assert_eq!(
+            "0.5 ETH",
+            value(&Chain::new(1).with_currency("ETH", 18), half)
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, trace, trace_span, warn, Instrument};
+
+use crate::events::{Event, EventListener};
+use crate::Chain;
+
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// Called when a sender's balance drops below the threshold, to top it up.
+/// Implementations must not block.
+pub trait TopUp: Debug + Send + Sync {
+    fn top_up(&self, sender: Address, balance: U256);
+}
+
+/// Periodically checks the balances of the senders against a threshold. When
+/// a balance drops below it, emits an `Event::LowBalance` and calls the top-up
+/// callback (once, until the balance recovers).
+#[derive(Debug)]
+pub struct FundingMonitor<M: Middleware> {
+    provider: M,
+    senders: Vec<Address>,
+    threshold: U256,
+    interval: Duration,
+    event_listeners: Vec<Arc<dyn EventListener>>,
+    top_up: Option<Arc<dyn TopUp>>,
+    chain: Chain,
+    low: Mutex<HashSet<Address>>,
+}
+
+impl<M: Middleware> FundingMonitor<M> {
+    pub fn new(provider: M, senders: Vec<Address>, threshold: U256) -> FundingMonitor<M> {
+        FundingMonitor {
+            provider,
+            senders,
+            threshold,
+            interval: INTERVAL,
+            event_listeners: Vec::new(),
+            top_up: None,
+            chain: Chain::default(),
+            low: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn set_interval(mut self, interval: Duration) -> FundingMonitor<M> {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the chain whose native currency is used to render the balances in
+    /// the logs (in wei, by default).
+    pub fn set_chain(mut self, chain: Chain) -> FundingMonitor<M> {
+        self.chain = chain;
+        self
+    }
+
+    pub fn add_event_listener(
+        mut self,
+        event_listener: Arc<dyn EventListener>,
+    ) -> FundingMonitor<M> {
+        self.event_listeners.push(event_listener);
+        self
+    }
+
+    pub fn set_top_up(mut self, top_up: Option<Arc<dyn TopUp>>) -> FundingMonitor<M> {
+        self.top_up = top_up;
+        self
+    }
+
+    /// Checks the balances every interval, forever. Errors are logged.
+    pub async fn run(&self) {
+        loop {
+            if let Err(err) = self.check().await {
+                warn!("Could not check the senders' balances: {}", err);
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    /// Checks the balances once and returns the senders that are below the
+    /// threshold (with their balances).
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn check(&self) -> Result<Vec<(Address, U256)>, M::Error> {
+        let mut low_balances = Vec::new();
+        for &sender in &self.senders {
+            let balance = self
+                .provider
+                .get_balance(sender, None)
+                .instrument(trace_span!("get_balance"))
+                .await?;
+            trace!("Balance of {:?}: {}.", sender, balance);
+
+            let mut low = self.low.lock().unwrap();
+            if balance >= self.threshold {
+                if low.remove(&sender) {
+                    info!(
+                        "The balance of {:?} recovered ({}).",
+                        sender,
+                        self.chain.format_value(balance)
+                    );
+                }
+                continue;
+            }
+
+            low_balances.push((sender, balance));
+            if !low.insert(sender) {
+                continue; // already notified
+            }
+            drop(low);
+
+            warn!(
+                "Low balance! {:?} has {} (threshold: {}).",
+                sender,
+                self.chain.format_value(balance),
+                self.chain.format_value(self.threshold)
+            );
+            let event = Event::LowBalance {
+                sender,
+                balance,
+                threshold: self.threshold,
+            };
+            for event_listener in &self.event_listeners {
+                event_listener.notify(&event);
+            }
+            if let Some(top_up) = &self.top_up {
+                top_up.top_up(sender, balance);
+            }
+        }
+        Ok(low_balances)
+    }
+}
@@ -10,6 +10,8 @@ pub enum DefaultGasOracleError {
     Default,
 }
 
+/// A gas oracle that always defers to the provider's fee estimates (see
+/// `NoGasOracle`, its unit counterpart).
 #[derive(Clone, Debug, Default)]
 pub struct DefaultGasOracle {}
 
@@ -27,3 +29,18 @@ impl GasOracle for DefaultGasOracle {
         Err(DefaultGasOracleError::Default)
     }
 }
+
+/// The gas oracle of managers without one: it always defers to the provider's
+/// fee estimates (e.g., `Manager::new(provider, NoGasOracle, db, chain,
+/// configuration)`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoGasOracle;
+
+#[async_trait]
+impl GasOracle for NoGasOracle {
+    type Error = DefaultGasOracleError;
+
+    async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
+        Err(DefaultGasOracleError::Default)
+    }
+}
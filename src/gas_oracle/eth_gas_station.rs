@@ -1,13 +1,20 @@
 use async_trait::async_trait;
 use core::time::Duration;
 use ethers::types::U256;
-use reqwest::StatusCode;
+use reqwest::{header::AGE, StatusCode};
 use serde::Deserialize;
 use std::fmt::Debug;
+use std::time::SystemTime;
 use tracing::trace;
 
-use crate::gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo};
+use crate::gas_oracle::{
+    EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo, HttpOracleConfig, HttpOracleConfigError,
+};
 use crate::transaction::Priority;
+use crate::units::Wei;
+
+const MAINNET_CHAIN_ID: u64 = 1;
+const URL: &str = "https://ethgasstation.info/api/ethgasAPI.json";
 
 /// Implementation that uses the ETH Gas Station API.
 
@@ -21,16 +28,44 @@ pub enum ETHGasStationError {
 
     #[error("could not parse the request's response: {0}")]
     ParseResponse(serde_json::Error),
+
+    #[error("invalid HTTP configuration: {0}")]
+    Config(HttpOracleConfigError),
 }
 
 #[derive(Clone, Debug)]
 pub struct ETHGasStationOracle {
     api_key: String,
+    url: String,
+    client: reqwest::Client,
 }
 
 impl ETHGasStationOracle {
     pub fn new(api_key: String) -> ETHGasStationOracle {
-        ETHGasStationOracle { api_key }
+        ETHGasStationOracle {
+            api_key,
+            url: URL.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Instantiates the oracle with custom headers, authentication, proxy, or
+    /// timeout (see `HttpOracleConfig`).
+    pub fn with_config(
+        api_key: String,
+        config: &HttpOracleConfig,
+    ) -> Result<ETHGasStationOracle, ETHGasStationError> {
+        let client = config.client().map_err(ETHGasStationError::Config)?;
+        Ok(ETHGasStationOracle {
+            client,
+            ..ETHGasStationOracle::new(api_key)
+        })
+    }
+
+    /// Queries the API at another URL (e.g., an internal mirror).
+    pub fn set_url(mut self, url: String) -> ETHGasStationOracle {
+        self.url = url;
+        self
     }
 }
 
@@ -38,26 +73,42 @@ impl ETHGasStationOracle {
 impl GasOracle for ETHGasStationOracle {
     type Error = ETHGasStationError;
 
-    #[tracing::instrument(level = "trace")]
+    #[tracing::instrument(level = "trace", skip(self))]
     async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, Self::Error> {
-        let url = format!(
-            "https://ethgasstation.info/api/ethgasAPI.json?api-key={}",
-            self.api_key
-        );
-
-        let res = reqwest::get(url)
+        let res = self
+            .client
+            .get(&self.url)
+            .query(&[("api-key", &self.api_key)])
+            .send()
             .await
             .map_err(ETHGasStationError::Request)?;
         if res.status() != StatusCode::OK {
             return Err(ETHGasStationError::StatusCode(res.status()));
         }
 
+        // Responses served by caches are as old as their "Age" header says.
+        let age = res
+            .headers()
+            .get(AGE)
+            .and_then(|age| age.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let timestamp = SystemTime::now().checked_sub(age);
+
         let bytes = &res.bytes().await.map_err(ETHGasStationError::Request)?;
         let response = serde_json::from_slice(bytes).map_err(ETHGasStationError::ParseResponse)?;
-        let gas_info = (response, priority).into();
+        let gas_info = GasOracleInfo {
+            timestamp,
+            ..(response, priority).into()
+        };
         trace!("gas info: {:?}", gas_info);
         return Ok(gas_info);
     }
+
+    /// The ETH Gas Station only has fees for the Ethereum mainnet.
+    fn supports_chain(&self, chain_id: u64) -> bool {
+        chain_id == MAINNET_CHAIN_ID
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -80,34 +131,42 @@ struct ETHGasStationResponse {
 
 impl From<(ETHGasStationResponse, Priority)> for GasOracleInfo {
     fn from((response, priority): (ETHGasStationResponse, Priority)) -> Self {
-        let (gas_price, mining_time) = match priority {
+        let (gas_price, mining_time) = match priority.level() {
             Priority::Low => (response.low, response.low_time),
             Priority::Normal => (response.average, response.average_time),
             Priority::High => (response.fast, response.fast_time),
-            Priority::ASAP => (response.fastest, response.fastest_time),
+            Priority::ASAP | Priority::Custom(_) => (response.fastest, response.fastest_time),
         };
 
         // max fee from 10*gwei to wei
-        let max_fee = U256::from(gas_price).checked_mul(U256::exp10(10)).unwrap();
+        let max_fee = Wei(U256::from(gas_price).checked_mul(U256::exp10(10)).unwrap());
         let max_priority_fee = None;
         let mining_time = Some(Duration::from_secs((mining_time * 60.) as u64));
         let block_time = Some(Duration::from_secs((response.block_time) as u64));
 
         GasOracleInfo {
             gas_info: GasInfo::EIP1559(EIP1559GasInfo {
-                max_fee,
+                max_fee: Some(max_fee),
                 max_priority_fee,
             }),
             mining_time,
             block_time,
+            timestamp: None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::gas_oracle::{EIP1559GasInfo, ETHGasStationOracle, GasOracle, GasOracleInfo};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use crate::gas_oracle::{
+        EIP1559GasInfo, ETHGasStationOracle, GasOracle, GasOracleInfo, HttpOracleConfig,
+    };
     use crate::transaction::Priority;
+    use crate::units::Wei;
 
     use super::ETHGasStationError;
 
@@ -162,6 +221,65 @@ mod tests {
         // eip1559_gas_info_asap.max_fee);
     }
 
+    #[tokio::test]
+    async fn test_eth_gas_station_oracle_http_config() {
+        // Serves a single request, returning its head.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut head = Vec::new();
+            let mut buffer = [0; 1024];
+            while !head.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buffer).unwrap();
+                head.extend_from_slice(&buffer[..n]);
+            }
+            let body = r#"{"block_time": 13, "fastest": 50, "fast": 40, "average": 30,
+                "safeLow": 20, "fastestWait": 0.5, "fastWait": 1, "avgWait": 2,
+                "safeLowWait": 5}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8(head).unwrap().to_lowercase()
+        });
+
+        let config = HttpOracleConfig::default()
+            .set_header("X-Proxy-Tenant", "tx-manager")
+            .set_bearer_token("secret");
+        let gas_oracle = ETHGasStationOracle::with_config("key".to_string(), &config)
+            .unwrap()
+            .set_url(url);
+        let result = gas_oracle.get_info(Priority::Normal).await;
+        let eip1559_gas_info = unwrap_eip1559_gas_info(result);
+        assert_eq!(
+            Some(Wei::from(300_000_000_000u64)),
+            eip1559_gas_info.max_fee
+        );
+
+        let head = server.join().unwrap();
+        assert!(head.starts_with("get /api?api-key=key "), "{}", head);
+        assert!(
+            head.contains("\r\nx-proxy-tenant: tx-manager\r\n"),
+            "{}",
+            head
+        );
+        assert!(
+            head.contains("\r\nauthorization: bearer secret\r\n"),
+            "{}",
+            head
+        );
+
+        // Invalid settings are rejected.
+        let config = HttpOracleConfig::default().set_header("X-Proxy Tenant", "tx-manager");
+        assert!(ETHGasStationOracle::with_config("key".to_string(), &config).is_err());
+        let config = HttpOracleConfig::default().set_proxy("not a url");
+        assert!(ETHGasStationOracle::with_config("key".to_string(), &config).is_err());
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_eth_gas_station_oracle_invalid_api_key() {
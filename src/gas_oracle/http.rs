@@ -0,0 +1,82 @@
+use core::time::Duration;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Client, Proxy};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpOracleConfigError {
+    #[error("invalid header `{0}`")]
+    InvalidHeader(String),
+
+    #[error("invalid bearer token")]
+    InvalidBearerToken,
+
+    #[error("invalid proxy: {0}")]
+    InvalidProxy(reqwest::Error),
+
+    #[error("could not build the HTTP client: {0}")]
+    Client(reqwest::Error),
+}
+
+/// Settings of the HTTP client of the HTTP-based gas oracles (e.g., the
+/// `ETHGasStationOracle`), for APIs reached through authenticating proxies.
+/// The default settings are reqwest's.
+#[derive(Clone, Debug, Default)]
+pub struct HttpOracleConfig {
+    headers: Vec<(String, String)>,
+    bearer_token: Option<String>,
+    proxy: Option<String>,
+    timeout: Option<Duration>,
+}
+
+impl HttpOracleConfig {
+    /// Adds a header to every request.
+    pub fn set_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends the token in the `Authorization` header of every request.
+    pub fn set_bearer_token(mut self, bearer_token: impl Into<String>) -> Self {
+        self.bearer_token = Some(bearer_token.into());
+        self
+    }
+
+    /// Routes the requests (HTTP and HTTPS) through the proxy at the URL.
+    pub fn set_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Fails requests that take longer than the timeout.
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn client(&self) -> Result<Client, HttpOracleConfigError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .map_err(|_| HttpOracleConfigError::InvalidHeader(name.clone()))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .map_err(|_| HttpOracleConfigError::InvalidHeader(name.to_string()))?;
+            headers.append(name, value);
+        }
+        if let Some(bearer_token) = &self.bearer_token {
+            let mut value = HeaderValue::try_from(format!("Bearer {}", bearer_token))
+                .map_err(|_| HttpOracleConfigError::InvalidBearerToken)?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        let mut builder = Client::builder().default_headers(headers);
+        if let Some(proxy) = &self.proxy {
+            let proxy = Proxy::all(proxy).map_err(HttpOracleConfigError::InvalidProxy)?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        builder.build().map_err(HttpOracleConfigError::Client)
+    }
+}
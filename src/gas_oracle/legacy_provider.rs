@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use ethers::providers::Middleware;
+use std::fmt::Debug;
+use tracing::{trace, trace_span, Instrument};
+
+use crate::gas_oracle::{GasInfo, GasOracle, GasOracleInfo, LegacyGasInfo};
+use crate::manager::GasPriceMultipliers;
+use crate::transaction::Priority;
+use crate::units::Wei;
+
+/// Implementation for legacy chains that uses the provider's gas price
+/// (`eth_gasPrice`), scaled by per-priority multipliers.
+#[derive(Clone, Debug)]
+pub struct LegacyProviderGasOracle<M: Middleware + Clone> {
+    provider: M,
+    multipliers: GasPriceMultipliers,
+}
+
+impl<M: Middleware + Clone> LegacyProviderGasOracle<M> {
+    pub fn new(provider: M) -> LegacyProviderGasOracle<M> {
+        LegacyProviderGasOracle {
+            provider,
+            multipliers: GasPriceMultipliers::default(),
+        }
+    }
+
+    pub fn set_multipliers(mut self, multipliers: GasPriceMultipliers) -> Self {
+        self.multipliers = multipliers;
+        self
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + Clone> GasOracle for LegacyProviderGasOracle<M> {
+    type Error = M::Error;
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, Self::Error> {
+        let gas_price = self
+            .provider
+            .get_gas_price()
+            .instrument(trace_span!("get_gas_price"))
+            .await?;
+        let gas_price = Wei(gas_price).multiply(self.multipliers.get(priority));
+        trace!("(gas_price = {})", gas_price);
+        Ok(GasOracleInfo {
+            gas_info: GasInfo::Legacy(LegacyGasInfo { gas_price }),
+            mining_time: None,
+            block_time: None,
+            timestamp: None,
+        })
+    }
+}
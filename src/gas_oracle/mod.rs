@@ -1,22 +1,112 @@
 use async_trait::async_trait;
 use core::time::Duration;
-use ethers::types::U256;
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::SystemTime;
 
+use crate::dyn_manager::{BoxedError, DynError};
 use crate::transaction::Priority;
+use crate::units::Wei;
 
 mod default;
 mod eth_gas_station;
+mod http;
+mod legacy_provider;
+mod quorum;
+mod replacement;
+mod shadow;
 
-pub use default::{DefaultGasOracle, DefaultGasOracleError};
+pub use default::{DefaultGasOracle, DefaultGasOracleError, NoGasOracle};
 pub use eth_gas_station::{ETHGasStationError, ETHGasStationOracle};
+pub use http::{HttpOracleConfig, HttpOracleConfigError};
+pub use legacy_provider::LegacyProviderGasOracle;
+pub use quorum::{Aggregation, QuorumGasOracle, QuorumGasOracleError, SourceTelemetry};
+pub use replacement::{
+    replacement_gas_info, transaction_gas_info, NodeClient, ReplacementRules,
+    PARITY_REPLACEMENT_FEE_BUMP, REPLACEMENT_FEE_BUMP,
+};
+pub use shadow::{ShadowGasOracle, ShadowTelemetry};
 
+/// Object-safe: oracles chosen at runtime (e.g., from the configuration) can be
+/// used as `Arc<dyn DynGasOracle>` (see `DynGasOracle`) or boxed.
 #[async_trait]
-pub trait GasOracle: Clone + Debug {
+pub trait GasOracle: Debug {
     type Error: Error + Send + Sync;
 
     async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, Self::Error>;
+
+    /// Whether the oracle has fees for the chain (e.g., HTTP oracles for a
+    /// single network). Managers refuse oracles that do not support their
+    /// chain (see `Error::UnsupportedGasOracleChain`).
+    fn supports_chain(&self, _chain_id: u64) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl<GO: GasOracle + Send + Sync + ?Sized> GasOracle for Box<GO> {
+    type Error = GO::Error;
+
+    async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, Self::Error> {
+        (**self).get_info(priority).await
+    }
+
+    fn supports_chain(&self, chain_id: u64) -> bool {
+        (**self).supports_chain(chain_id)
+    }
+}
+
+#[async_trait]
+impl<GO: GasOracle + Send + Sync + ?Sized> GasOracle for Arc<GO> {
+    type Error = GO::Error;
+
+    async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, Self::Error> {
+        (**self).get_info(priority).await
+    }
+
+    fn supports_chain(&self, chain_id: u64) -> bool {
+        (**self).supports_chain(chain_id)
+    }
+}
+
+/// A type-erased gas oracle, so that oracles of different types can be
+/// combined (see `QuorumGasOracle`) or chosen at runtime. `Arc<dyn
+/// DynGasOracle>` is itself a `GasOracle`.
+#[async_trait]
+pub trait DynGasOracle: Debug + Send + Sync {
+    async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, DynError>;
+
+    fn supports_chain(&self, chain_id: u64) -> bool;
+}
+
+#[async_trait]
+impl<GO> DynGasOracle for GO
+where
+    GO: GasOracle + Send + Sync,
+    GO::Error: 'static,
+{
+    async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, DynError> {
+        Ok(GasOracle::get_info(self, priority).await?)
+    }
+
+    fn supports_chain(&self, chain_id: u64) -> bool {
+        GasOracle::supports_chain(self, chain_id)
+    }
+}
+
+#[async_trait]
+impl GasOracle for dyn DynGasOracle {
+    type Error = BoxedError;
+
+    async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, Self::Error> {
+        Ok(DynGasOracle::get_info(self, priority).await?)
+    }
+
+    fn supports_chain(&self, chain_id: u64) -> bool {
+        DynGasOracle::supports_chain(self, chain_id)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -24,9 +114,24 @@ pub struct GasOracleInfo {
     pub gas_info: GasInfo,
     pub mining_time: Option<Duration>,
     pub block_time: Option<Duration>,
+
+    /// When the estimates were computed, if known (e.g., oracles behind caches
+    /// may serve old estimates). See `Configuration::gas_oracle_staleness_window`.
+    pub timestamp: Option<SystemTime>,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl GasOracleInfo {
+    /// How old the estimates are (unknown without a timestamp).
+    pub fn age(&self) -> Option<Duration> {
+        self.timestamp.map(|timestamp| {
+            SystemTime::now()
+                .duration_since(timestamp)
+                .unwrap_or_default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GasInfo {
     Legacy(LegacyGasInfo),
     EIP1559(EIP1559GasInfo),
@@ -38,15 +143,19 @@ impl GasInfo {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LegacyGasInfo {
-    pub gas_price: U256,
+    pub gas_price: Wei,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// EIP1559 fees. Oracles may give only one of them: the manager calculates
+/// the missing max priority fee from the max fee and the latest base fee, or
+/// the missing max fee from the max priority fee and the latest base fee
+/// (times `Configuration::max_fee_headroom`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EIP1559GasInfo {
-    pub max_fee: U256,
-    pub max_priority_fee: Option<U256>,
+    pub max_fee: Option<Wei>,
+    pub max_priority_fee: Option<Wei>,
 }
 
 impl TryFrom<GasInfo> for LegacyGasInfo {
@@ -0,0 +1,392 @@
+use async_trait::async_trait;
+use core::time::Duration;
+use ethers::types::U256;
+use futures::future::join_all;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{trace, warn};
+
+use crate::gas_oracle::{
+    DynGasOracle, EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo, LegacyGasInfo,
+};
+use crate::manager::multiply;
+use crate::transaction::Priority;
+use crate::units::Wei;
+
+/// How the `QuorumGasOracle` combines the fees of the accepted sources.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Aggregation {
+    #[default]
+    Median,
+
+    /// The mean after discarding the given fraction (in `[0, 0.5)`) of the
+    /// lowest and of the highest fees.
+    TrimmedMean(f64),
+}
+
+/// Per-source counters, for monitoring the oracles behind a
+/// `QuorumGasOracle` (see `QuorumGasOracle::telemetry`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceTelemetry {
+    pub name: String,
+    pub queries: u64,
+    pub failures: u64,
+
+    /// Responses rejected for deviating from the median.
+    pub outliers: u64,
+
+    pub last_latency: Option<Duration>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum QuorumGasOracleError {
+    #[error("no quorum: {accepted} of {required} required sources accepted")]
+    NoQuorum { accepted: usize, required: usize },
+}
+
+/// Implementation that queries several oracles concurrently and combines their
+/// fees, so that a single misbehaving source cannot make the manager overpay.
+///
+/// Responses whose fees deviate from the median by more than the maximum
+/// deviation (or whose kind, legacy or EIP1559, differs from the majority's)
+/// are rejected as outliers, and the remaining fees are aggregated (by default,
+/// with the median). The oracle fails unless the quorum (by default, a
+/// majority of the sources) is accepted.
+#[derive(Clone, Debug)]
+pub struct QuorumGasOracle {
+    sources: Vec<Arc<dyn DynGasOracle>>,
+    aggregation: Aggregation,
+    quorum: Option<usize>,
+    max_deviation: f64,
+    telemetry: Arc<Mutex<Vec<SourceTelemetry>>>,
+}
+
+impl Default for QuorumGasOracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuorumGasOracle {
+    pub fn new() -> QuorumGasOracle {
+        QuorumGasOracle {
+            sources: Vec::new(),
+            aggregation: Aggregation::default(),
+            quorum: None,
+            max_deviation: 0.5,
+            telemetry: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn add_source(mut self, name: impl Into<String>, oracle: Arc<dyn DynGasOracle>) -> Self {
+        self.telemetry.lock().unwrap().push(SourceTelemetry {
+            name: name.into(),
+            ..Default::default()
+        });
+        self.sources.push(oracle);
+        self
+    }
+
+    pub fn set_aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    /// Sets the minimum number of accepted sources.
+    pub fn set_quorum(mut self, quorum: usize) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    /// Sets the maximum deviation from the median, as a fraction (e.g., 0.5
+    /// rejects fees above 1.5 times, or below 1/1.5 of, the median).
+    pub fn set_max_deviation(mut self, max_deviation: f64) -> Self {
+        self.max_deviation = max_deviation;
+        self
+    }
+
+    pub fn quorum(&self) -> usize {
+        self.quorum.unwrap_or(self.sources.len() / 2 + 1)
+    }
+
+    /// Returns the counters of each source, in the order they were added.
+    pub fn telemetry(&self) -> Vec<SourceTelemetry> {
+        self.telemetry.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl GasOracle for QuorumGasOracle {
+    type Error = QuorumGasOracleError;
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, Self::Error> {
+        let results = join_all(self.sources.iter().map(|oracle| async move {
+            let start = Instant::now();
+            let result = DynGasOracle::get_info(oracle.as_ref(), priority).await;
+            (result, start.elapsed())
+        }))
+        .await;
+
+        let mut responses = Vec::new();
+        {
+            let mut telemetry = self.telemetry.lock().unwrap();
+            for (i, (result, latency)) in results.into_iter().enumerate() {
+                let source = &mut telemetry[i];
+                source.queries += 1;
+                source.last_latency = Some(latency);
+                match result {
+                    Ok(info) => {
+                        trace!(source = %source.name, ?latency, "{:?}", info.gas_info);
+                        source.last_error = None;
+                        responses.push((i, info));
+                    }
+                    Err(err) => {
+                        warn!(source = %source.name, ?latency, "Gas oracle failed: {}", err);
+                        source.failures += 1;
+                        source.last_error = Some(err.to_string());
+                    }
+                }
+            }
+        }
+
+        let accepted = self.reject_outliers(responses);
+        let required = self.quorum();
+        if accepted.is_empty() || accepted.len() < required {
+            return Err(QuorumGasOracleError::NoQuorum {
+                accepted: accepted.len(),
+                required,
+            });
+        }
+
+        let gas_info = if accepted[0].gas_info.is_legacy() {
+            GasInfo::Legacy(LegacyGasInfo {
+                gas_price: Wei(self.aggregate(accepted.iter().filter_map(fee).collect())),
+            })
+        } else {
+            let max_fees: Vec<U256> = accepted.iter().filter_map(fee).collect();
+            let max_priority_fees: Vec<U256> = accepted.iter().filter_map(priority_fee).collect();
+            GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: (!max_fees.is_empty()).then(|| Wei(self.aggregate(max_fees))),
+                max_priority_fee: (!max_priority_fees.is_empty())
+                    .then(|| Wei(self.aggregate(max_priority_fees))),
+            })
+        };
+        trace!(
+            "(aggregated {:?} from {} sources)",
+            gas_info,
+            accepted.len()
+        );
+
+        Ok(GasOracleInfo {
+            gas_info,
+            mining_time: median_duration(accepted.iter().filter_map(|info| info.mining_time)),
+            block_time: median_duration(accepted.iter().filter_map(|info| info.block_time)),
+            // The oldest of the accepted estimates.
+            timestamp: accepted.iter().filter_map(|info| info.timestamp).min(),
+        })
+    }
+
+    /// Whether enough of the sources (to reach the quorum) support the chain.
+    fn supports_chain(&self, chain_id: u64) -> bool {
+        let supporting = self
+            .sources
+            .iter()
+            .filter(|oracle| DynGasOracle::supports_chain(oracle.as_ref(), chain_id))
+            .count();
+        supporting >= self.quorum()
+    }
+}
+
+impl QuorumGasOracle {
+    /// Keeps the responses of the majority's kind whose fees are within the
+    /// maximum deviation from the median, and counts the others as outliers.
+    fn reject_outliers(&self, responses: Vec<(usize, GasOracleInfo)>) -> Vec<GasOracleInfo> {
+        let legacy = responses
+            .iter()
+            .filter(|(_, info)| info.gas_info.is_legacy())
+            .count();
+        let is_legacy = legacy * 2 > responses.len();
+
+        let same_kind = |info: &GasOracleInfo| info.gas_info.is_legacy() == is_legacy;
+        let fees: Vec<U256> = responses
+            .iter()
+            .filter(|(_, info)| same_kind(info))
+            .filter_map(|(_, info)| fee(info))
+            .collect();
+        let priority_fees: Vec<U256> = responses
+            .iter()
+            .filter(|(_, info)| same_kind(info))
+            .filter_map(|(_, info)| priority_fee(info))
+            .collect();
+        let median_fee = (!fees.is_empty()).then(|| median(fees));
+        let median_priority_fee = (!priority_fees.is_empty()).then(|| median(priority_fees));
+
+        let factor = 1. + self.max_deviation;
+        let deviates = |value: U256, median: Option<U256>| match median {
+            Some(median) => value > multiply(median, factor) || multiply(value, factor) < median,
+            None => false,
+        };
+
+        let mut telemetry = self.telemetry.lock().unwrap();
+        let mut accepted = Vec::new();
+        for (i, info) in responses {
+            let outlier = !same_kind(&info)
+                || fee(&info).is_some_and(|v| deviates(v, median_fee))
+                || priority_fee(&info).is_some_and(|v| deviates(v, median_priority_fee));
+            if outlier {
+                warn!(
+                    source = %telemetry[i].name,
+                    "Rejecting outlier gas info: {:?}", info.gas_info
+                );
+                telemetry[i].outliers += 1;
+            } else {
+                accepted.push(info);
+            }
+        }
+        accepted
+    }
+
+    fn aggregate(&self, mut values: Vec<U256>) -> U256 {
+        match self.aggregation {
+            Aggregation::Median => median(values),
+            Aggregation::TrimmedMean(fraction) => {
+                values.sort();
+                let trimmed =
+                    ((values.len() as f64 * fraction) as usize).min((values.len() - 1) / 2);
+                let values = &values[trimmed..values.len() - trimmed];
+                values.iter().fold(U256::zero(), |sum, &value| sum + value) / values.len()
+            }
+        }
+    }
+}
+
+/// The gas price (for legacy gas info) or the max fee, if any.
+fn fee(info: &GasOracleInfo) -> Option<U256> {
+    match info.gas_info {
+        GasInfo::Legacy(legacy) => Some(legacy.gas_price.into()),
+        GasInfo::EIP1559(eip1559) => eip1559.max_fee.map(U256::from),
+    }
+}
+
+fn priority_fee(info: &GasOracleInfo) -> Option<U256> {
+    match info.gas_info {
+        GasInfo::Legacy(_) => None,
+        GasInfo::EIP1559(eip1559) => eip1559.max_priority_fee.map(U256::from),
+    }
+}
+
+fn median(mut values: Vec<U256>) -> U256 {
+    values.sort();
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2
+    } else {
+        values[middle]
+    }
+}
+
+fn median_duration(values: impl Iterator<Item = Duration>) -> Option<Duration> {
+    let mut values: Vec<Duration> = values.collect();
+    values.sort();
+    values.get(values.len() / 2).copied()
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    use super::{Aggregation, QuorumGasOracle, QuorumGasOracleError};
+    use crate::gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo};
+    use crate::transaction::Priority;
+    use crate::units::Wei;
+
+    #[derive(Clone, Debug)]
+    struct FixedGasOracle(Option<(u64, u64)>);
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("unavailable")]
+    struct Unavailable;
+
+    #[async_trait]
+    impl GasOracle for FixedGasOracle {
+        type Error = Unavailable;
+
+        async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
+            let (max_fee, max_priority_fee) = self.0.ok_or(Unavailable)?;
+            Ok(GasOracleInfo {
+                gas_info: GasInfo::EIP1559(EIP1559GasInfo {
+                    max_fee: Some(max_fee.into()),
+                    max_priority_fee: Some(max_priority_fee.into()),
+                }),
+                mining_time: None,
+                block_time: None,
+                timestamp: None,
+            })
+        }
+    }
+
+    fn quorum_gas_oracle(sources: &[Option<(u64, u64)>]) -> QuorumGasOracle {
+        sources
+            .iter()
+            .enumerate()
+            .fold(QuorumGasOracle::new(), |oracle, (i, &source)| {
+                oracle.add_source(i.to_string(), Arc::new(FixedGasOracle(source)))
+            })
+    }
+
+    fn fees(info: GasOracleInfo) -> (Wei, Option<Wei>) {
+        match info.gas_info {
+            GasInfo::EIP1559(info) => (info.max_fee.unwrap(), info.max_priority_fee),
+            GasInfo::Legacy(_) => panic!("expected EIP1559 gas info"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quorum_gas_oracle() {
+        // Rejects the spike.
+        let oracle = quorum_gas_oracle(&[Some((100, 10)), Some((110, 12)), Some((10_000, 11))]);
+        let info = oracle.get_info(Priority::Normal).await.unwrap();
+        assert_eq!((Wei::from(105), Some(Wei::from(11))), fees(info));
+        let telemetry = oracle.telemetry();
+        assert_eq!(
+            vec![0, 0, 1],
+            telemetry.iter().map(|t| t.outliers).collect::<Vec<_>>()
+        );
+        assert!(telemetry.iter().all(|t| t.queries == 1 && t.failures == 0));
+
+        // Trimmed mean.
+        let oracle = quorum_gas_oracle(&[
+            Some((100, 10)),
+            Some((110, 10)),
+            Some((120, 10)),
+            Some((130, 10)),
+        ])
+        .set_aggregation(Aggregation::TrimmedMean(0.25));
+        let info = oracle.get_info(Priority::Normal).await.unwrap();
+        assert_eq!(Wei::from(115), fees(info).0);
+
+        // Fails without a quorum.
+        let oracle = quorum_gas_oracle(&[Some((100, 10)), None, None]);
+        assert!(matches!(
+            oracle.get_info(Priority::Normal).await,
+            Err(QuorumGasOracleError::NoQuorum {
+                accepted: 1,
+                required: 2
+            })
+        ));
+        let telemetry = oracle.telemetry();
+        assert_eq!(
+            vec![0, 1, 1],
+            telemetry.iter().map(|t| t.failures).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("unavailable".to_string()), telemetry[1].last_error);
+
+        // Accepts a smaller quorum.
+        let oracle = oracle.set_quorum(1);
+        assert!(oracle.get_info(Priority::Normal).await.is_ok());
+    }
+}
@@ -0,0 +1,336 @@
+use ethers::types::Transaction;
+
+use super::{EIP1559GasInfo, GasInfo, LegacyGasInfo};
+use crate::units::Wei;
+
+/// Minimum factor by which nodes require the fees of a replacement transaction
+/// (same nonce) to exceed the fees of the replaced one (geth's rule).
+pub const REPLACEMENT_FEE_BUMP: f64 = 1.1;
+
+/// Minimum replacement fee bump of the parity-derived nodes (OpenEthereum),
+/// also used for unknown clients.
+pub const PARITY_REPLACEMENT_FEE_BUMP: f64 = 1.125;
+
+/// Ethereum clients whose transaction pools differ in the rules for replacement
+/// transactions: the minimum fee bump and the error returned for underpriced
+/// replacements.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NodeClient {
+    #[default]
+    Geth,
+    Erigon,
+    Besu,
+    Nethermind,
+
+    /// OpenEthereum and the older Parity nodes.
+    OpenEthereum,
+
+    /// Unknown clients get the strictest rules.
+    Other,
+}
+
+impl NodeClient {
+    /// Identifies the client from its `web3_clientVersion` (e.g.,
+    /// "Geth/v1.13.5-stable/linux-amd64/go1.21.4").
+    pub fn from_client_version(client_version: &str) -> NodeClient {
+        let name = client_version
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match name.as_str() {
+            "geth" => NodeClient::Geth,
+            "erigon" => NodeClient::Erigon,
+            "besu" => NodeClient::Besu,
+            "nethermind" => NodeClient::Nethermind,
+            "openethereum" | "parity-ethereum" | "parity" => NodeClient::OpenEthereum,
+            _ => NodeClient::Other,
+        }
+    }
+
+    /// Minimum factor by which the node requires the fees of a replacement
+    /// transaction to exceed the fees of the replaced one.
+    pub fn replacement_fee_bump(self) -> f64 {
+        match self {
+            NodeClient::Geth | NodeClient::Erigon | NodeClient::Besu | NodeClient::Nethermind => {
+                REPLACEMENT_FEE_BUMP
+            }
+            NodeClient::OpenEthereum | NodeClient::Other => PARITY_REPLACEMENT_FEE_BUMP,
+        }
+    }
+
+    /// Whether the node's error rejects an underpriced replacement transaction.
+    pub fn is_replacement_underpriced(self, error: &str) -> bool {
+        const GETH: &str = "replacement transaction underpriced";
+        const BESU: &str = "replacement_underpriced";
+        const NETHERMIND: &str = "replacementnotallowed";
+        const OPENETHEREUM: &str = "there is another transaction with same nonce in the queue";
+
+        let error = error.to_lowercase();
+        let messages: &[&str] = match self {
+            NodeClient::Geth | NodeClient::Erigon => &[GETH],
+            NodeClient::Besu => &[GETH, BESU],
+            NodeClient::Nethermind => &[GETH, NETHERMIND],
+            NodeClient::OpenEthereum => &[OPENETHEREUM],
+            NodeClient::Other => &[GETH, BESU, NETHERMIND, OPENETHEREUM],
+        };
+        messages.iter().any(|message| error.contains(message))
+    }
+
+    /// Whether the node's error rejects a replacement transaction because the
+    /// sender reached the node's limit of replacements per nonce. The clients
+    /// word it differently, so every known message is accepted.
+    pub fn is_replacement_limit_reached(self, error: &str) -> bool {
+        const MESSAGES: &[&str] = &[
+            "replacement limit reached",
+            "replacement limit exceeded",
+            "too many replacements",
+        ];
+
+        let error = error.to_lowercase();
+        MESSAGES.iter().any(|message| error.contains(message))
+    }
+}
+
+/// Which replacement rules the manager follows (see
+/// `Configuration::replacement_rules`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplacementRules {
+    /// The rules of the given client.
+    Client(NodeClient),
+
+    /// The rules of the provider's client, detected with `web3_clientVersion`
+    /// when the manager starts (the strictest ones, if it cannot be detected).
+    Detect,
+}
+
+impl Default for ReplacementRules {
+    fn default() -> Self {
+        ReplacementRules::Client(NodeClient::Geth)
+    }
+}
+
+/// Returns the fees of a transaction that replaces another (with the same
+/// nonce) in the transaction pool: the fees of the gas info, raised to at least
+/// `fee_bump` times the previous fees (which must be at least
+/// `REPLACEMENT_FEE_BUMP` for nodes to accept the replacement).
+///
+/// Previous and new fees may be of different types: a legacy gas price counts
+/// as both the max fee and the max priority fee of EIP1559 transactions, and
+/// must exceed both when replacing one. A missing max fee or max priority fee
+/// counts as zero.
+pub fn replacement_gas_info(previous: GasInfo, gas_info: GasInfo, fee_bump: f64) -> GasInfo {
+    let (previous_max_fee, previous_max_priority_fee) = match previous {
+        GasInfo::Legacy(legacy) => (legacy.gas_price, legacy.gas_price),
+        GasInfo::EIP1559(eip1559) => (
+            eip1559.max_fee.unwrap_or_default(),
+            eip1559.max_priority_fee.unwrap_or_default(),
+        ),
+    };
+    let bump = |fee: Wei| fee.multiply(fee_bump);
+
+    match gas_info {
+        GasInfo::Legacy(legacy) => GasInfo::Legacy(LegacyGasInfo {
+            gas_price: legacy.gas_price.max(bump(previous_max_fee)),
+        }),
+        GasInfo::EIP1559(eip1559) => {
+            let max_priority_fee = eip1559
+                .max_priority_fee
+                .unwrap_or_default()
+                .max(bump(previous_max_priority_fee));
+            let max_fee = eip1559
+                .max_fee
+                .unwrap_or_default()
+                .max(bump(previous_max_fee))
+                .max(max_priority_fee);
+            GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: Some(max_fee),
+                max_priority_fee: Some(max_priority_fee),
+            })
+        }
+    }
+}
+
+/// Returns the fees of the transaction (as returned by the provider), if any.
+pub fn transaction_gas_info(transaction: &Transaction) -> Option<GasInfo> {
+    match (transaction.max_fee_per_gas, transaction.gas_price) {
+        (Some(max_fee), _) => Some(GasInfo::EIP1559(EIP1559GasInfo {
+            max_fee: Some(Wei(max_fee)),
+            max_priority_fee: transaction.max_priority_fee_per_gas.map(Wei),
+        })),
+        (None, Some(gas_price)) => Some(GasInfo::Legacy(LegacyGasInfo {
+            gas_price: Wei(gas_price),
+        })),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::Transaction;
+
+    use super::{replacement_gas_info, transaction_gas_info, NodeClient, REPLACEMENT_FEE_BUMP};
+    use crate::gas_oracle::{EIP1559GasInfo, GasInfo, LegacyGasInfo};
+    use crate::units::Wei;
+
+    fn legacy(gas_price: u64) -> GasInfo {
+        GasInfo::Legacy(LegacyGasInfo {
+            gas_price: gas_price.into(),
+        })
+    }
+
+    fn eip1559(max_fee: u64, max_priority_fee: Option<u64>) -> GasInfo {
+        GasInfo::EIP1559(EIP1559GasInfo {
+            max_fee: Some(max_fee.into()),
+            max_priority_fee: max_priority_fee.map(Wei::from),
+        })
+    }
+
+    fn fees(gas_info: GasInfo) -> (Wei, Option<Wei>) {
+        match gas_info {
+            GasInfo::Legacy(legacy) => (legacy.gas_price, None),
+            GasInfo::EIP1559(eip1559) => (eip1559.max_fee.unwrap(), eip1559.max_priority_fee),
+        }
+    }
+
+    #[test]
+    fn test_replacement_gas_info() {
+        let bump = REPLACEMENT_FEE_BUMP;
+        let cases = [
+            // Legacy replacing legacy: bumped, or the oracle's if higher.
+            (legacy(1000), legacy(500), bump, legacy(1100)),
+            (legacy(1000), legacy(2000), bump, legacy(2000)),
+            // EIP1559 replacing EIP1559: each fee bumped independently.
+            (
+                eip1559(1000, Some(100)),
+                eip1559(500, Some(50)),
+                bump,
+                eip1559(1100, Some(110)),
+            ),
+            (
+                eip1559(1000, Some(100)),
+                eip1559(2000, Some(50)),
+                bump,
+                eip1559(2000, Some(110)),
+            ),
+            (
+                eip1559(1000, Some(100)),
+                eip1559(500, Some(500)),
+                bump,
+                eip1559(1100, Some(500)),
+            ),
+            // Missing max priority fees count as zero.
+            (
+                eip1559(1000, None),
+                eip1559(500, None),
+                bump,
+                eip1559(1100, Some(0)),
+            ),
+            // The max fee is never below the max priority fee.
+            (
+                eip1559(100, Some(100)),
+                eip1559(50, Some(5000)),
+                bump,
+                eip1559(5000, Some(5000)),
+            ),
+            // Legacy replacing EIP1559: the gas price exceeds the max fee.
+            (eip1559(1000, Some(100)), legacy(500), bump, legacy(1100)),
+            // EIP1559 replacing legacy: the gas price is both fees.
+            (
+                legacy(1000),
+                eip1559(500, Some(50)),
+                bump,
+                eip1559(1100, Some(1100)),
+            ),
+            // Larger factors (e.g., when speeding up).
+            (legacy(1000), legacy(500), 2., legacy(2000)),
+            (
+                eip1559(1000, Some(100)),
+                eip1559(500, Some(50)),
+                2.,
+                eip1559(2000, Some(200)),
+            ),
+        ];
+        for (previous, gas_info, fee_bump, expected) in cases {
+            assert_eq!(
+                fees(expected),
+                fees(replacement_gas_info(previous, gas_info, fee_bump)),
+                "previous = {:?}, gas info = {:?}, fee bump = {:?}",
+                previous,
+                gas_info,
+                fee_bump
+            );
+        }
+    }
+
+    #[test]
+    fn test_transaction_gas_info() {
+        let transaction = Transaction {
+            gas_price: Some(1000.into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            (1000.into(), None),
+            fees(transaction_gas_info(&transaction).unwrap())
+        );
+
+        let transaction = Transaction {
+            gas_price: Some(900.into()),
+            max_fee_per_gas: Some(1000.into()),
+            max_priority_fee_per_gas: Some(100.into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            (1000.into(), Some(100.into())),
+            fees(transaction_gas_info(&transaction).unwrap())
+        );
+
+        assert!(transaction_gas_info(&Transaction::default()).is_none());
+    }
+
+    #[test]
+    fn test_node_client() {
+        let cases = [
+            ("Geth/v1.13.5-stable/linux-amd64/go1.21.4", NodeClient::Geth),
+            ("erigon/2.55.1/linux-amd64/go1.21.5", NodeClient::Erigon),
+            (
+                "besu/v23.10.2/linux-x86_64/openjdk-java-17",
+                NodeClient::Besu,
+            ),
+            (
+                "Nethermind/v1.25.0+a4ab5ab4/linux-x64/dotnet8.0.0",
+                NodeClient::Nethermind,
+            ),
+            (
+                "OpenEthereum//v3.3.5-stable/x86_64-linux-musl/rustc1.59.0",
+                NodeClient::OpenEthereum,
+            ),
+            (
+                "Parity-Ethereum//v2.7.2-stable/x86_64-linux-gnu/rustc1.41.0",
+                NodeClient::OpenEthereum,
+            ),
+            ("anvil/v0.2.0", NodeClient::Other),
+            ("", NodeClient::Other),
+        ];
+        for (client_version, client) in cases {
+            assert_eq!(client, NodeClient::from_client_version(client_version));
+        }
+
+        assert_eq!(
+            REPLACEMENT_FEE_BUMP,
+            NodeClient::Geth.replacement_fee_bump()
+        );
+        assert_eq!(1.125, NodeClient::OpenEthereum.replacement_fee_bump());
+        assert_eq!(1.125, NodeClient::Other.replacement_fee_bump());
+
+        let geth = "(code: -32000, message: replacement transaction underpriced, data: None)";
+        let openethereum = "Transaction gas price is too low. There is another transaction \
+            with same nonce in the queue. Try increasing the gas price or incrementing the nonce.";
+        assert!(NodeClient::Geth.is_replacement_underpriced(geth));
+        assert!(!NodeClient::Geth.is_replacement_underpriced(openethereum));
+        assert!(NodeClient::OpenEthereum.is_replacement_underpriced(openethereum));
+        assert!(NodeClient::Besu.is_replacement_underpriced("REPLACEMENT_UNDERPRICED"));
+        assert!(NodeClient::Other.is_replacement_underpriced(openethereum));
+        assert!(!NodeClient::Other.is_replacement_underpriced("nonce too low"));
+    }
+}
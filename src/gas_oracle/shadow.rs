@@ -0,0 +1,241 @@
+use async_trait::async_trait;
+use core::time::Duration;
+use ethers::types::U256;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::{trace, warn};
+
+use crate::gas_oracle::{DynGasOracle, GasInfo, GasOracle, GasOracleInfo};
+use crate::transaction::Priority;
+use crate::units::Wei;
+
+/// Counters comparing the candidate oracle of a `ShadowGasOracle` with the
+/// primary one (see `ShadowGasOracle::telemetry`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShadowTelemetry {
+    pub queries: u64,
+    pub primary_failures: u64,
+    pub candidate_failures: u64,
+
+    /// Queries where both oracles gave a max fee (or a gas price, for legacy
+    /// gas info). Responses without one are not compared.
+    pub comparisons: u64,
+
+    /// Comparisons where the candidate's fee was above (or below) the
+    /// primary's.
+    pub candidate_higher: u64,
+    pub candidate_lower: u64,
+
+    /// Sums of the compared fees (see `ShadowTelemetry::cost_ratio`).
+    pub primary_fees: U256,
+    pub candidate_fees: U256,
+
+    pub last_primary: Option<GasInfo>,
+    pub last_candidate: Option<GasInfo>,
+    pub last_candidate_latency: Option<Duration>,
+    pub last_error: Option<String>,
+}
+
+impl ShadowTelemetry {
+    /// How much the candidate's transactions would have cost, relative to the
+    /// primary's, for the same gas (none before the first comparison).
+    pub fn cost_ratio(&self) -> Option<f64> {
+        (!self.primary_fees.is_zero())
+            .then(|| to_f64(self.candidate_fees) / to_f64(self.primary_fees))
+    }
+
+    /// What a transaction that cost `cost` with the primary's fees would have
+    /// cost with the candidate's (e.g., for the costs of `Event::Confirmed`).
+    pub fn hypothetical_cost(&self, cost: Wei) -> Option<Wei> {
+        self.cost_ratio().map(|ratio| cost.multiply(ratio))
+    }
+}
+
+/// Implementation that evaluates a candidate oracle in production without
+/// risking funds: it queries both the primary and the candidate oracles
+/// concurrently, answers with the primary's fees, and records how the
+/// candidate's compare (see `ShadowGasOracle::telemetry`).
+///
+/// The candidate's failures are logged and counted, never returned.
+#[derive(Clone, Debug)]
+pub struct ShadowGasOracle<GO> {
+    primary: GO,
+    candidate: Arc<dyn DynGasOracle>,
+    telemetry: Arc<Mutex<ShadowTelemetry>>,
+}
+
+impl<GO: GasOracle> ShadowGasOracle<GO> {
+    pub fn new(primary: GO, candidate: Arc<dyn DynGasOracle>) -> ShadowGasOracle<GO> {
+        ShadowGasOracle {
+            primary,
+            candidate,
+            telemetry: Arc::new(Mutex::new(ShadowTelemetry::default())),
+        }
+    }
+
+    pub fn primary(&self) -> &GO {
+        &self.primary
+    }
+
+    /// Returns the counters of the comparisons so far.
+    pub fn telemetry(&self) -> ShadowTelemetry {
+        self.telemetry.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl<GO: GasOracle + Send + Sync> GasOracle for ShadowGasOracle<GO> {
+    type Error = GO::Error;
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn get_info(&self, priority: Priority) -> Result<GasOracleInfo, Self::Error> {
+        let candidate = async {
+            let start = Instant::now();
+            let result = DynGasOracle::get_info(self.candidate.as_ref(), priority).await;
+            (result, start.elapsed())
+        };
+        let (primary, (candidate, latency)) =
+            futures::join!(self.primary.get_info(priority), candidate);
+
+        let mut telemetry = self.telemetry.lock().unwrap();
+        telemetry.queries += 1;
+        telemetry.last_candidate_latency = Some(latency);
+        match &primary {
+            Ok(info) => telemetry.last_primary = Some(info.gas_info),
+            Err(_) => telemetry.primary_failures += 1,
+        }
+        match &candidate {
+            Ok(info) => {
+                telemetry.last_candidate = Some(info.gas_info);
+                telemetry.last_error = None;
+            }
+            Err(err) => {
+                warn!(?latency, "Candidate gas oracle failed: {}", err);
+                telemetry.candidate_failures += 1;
+                telemetry.last_error = Some(err.to_string());
+            }
+        }
+
+        if let (Ok(primary), Ok(candidate)) = (&primary, &candidate) {
+            if let (Some(primary_fee), Some(candidate_fee)) = (fee(primary), fee(candidate)) {
+                trace!(
+                    ?latency,
+                    "Candidate fee: {} (primary: {}).",
+                    candidate_fee,
+                    primary_fee
+                );
+                telemetry.comparisons += 1;
+                match candidate_fee.cmp(&primary_fee) {
+                    Ordering::Greater => telemetry.candidate_higher += 1,
+                    Ordering::Less => telemetry.candidate_lower += 1,
+                    Ordering::Equal => {}
+                }
+                telemetry.primary_fees = telemetry.primary_fees.saturating_add(primary_fee);
+                telemetry.candidate_fees = telemetry.candidate_fees.saturating_add(candidate_fee);
+            }
+        }
+
+        primary
+    }
+
+    fn supports_chain(&self, chain_id: u64) -> bool {
+        self.primary.supports_chain(chain_id)
+    }
+}
+
+/// The gas price (for legacy gas info) or the max fee, if any.
+fn fee(info: &GasOracleInfo) -> Option<U256> {
+    match info.gas_info {
+        GasInfo::Legacy(legacy) => Some(legacy.gas_price.into()),
+        GasInfo::EIP1559(eip1559) => eip1559.max_fee.map(U256::from),
+    }
+}
+
+fn to_f64(value: U256) -> f64 {
+    value.min(U256::from(u128::MAX)).as_u128() as f64
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    use super::ShadowGasOracle;
+    use crate::gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo};
+    use crate::transaction::Priority;
+    use crate::units::Wei;
+
+    #[derive(Clone, Debug)]
+    struct FixedGasOracle(Option<u64>);
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("unavailable")]
+    struct Unavailable;
+
+    #[async_trait]
+    impl GasOracle for FixedGasOracle {
+        type Error = Unavailable;
+
+        async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
+            let max_fee = self.0.ok_or(Unavailable)?;
+            Ok(GasOracleInfo {
+                gas_info: GasInfo::EIP1559(EIP1559GasInfo {
+                    max_fee: Some(max_fee.into()),
+                    max_priority_fee: Some(1.into()),
+                }),
+                mining_time: None,
+                block_time: None,
+                timestamp: None,
+            })
+        }
+    }
+
+    fn max_fee(info: GasOracleInfo) -> Wei {
+        match info.gas_info {
+            GasInfo::EIP1559(info) => info.max_fee.unwrap(),
+            GasInfo::Legacy(_) => panic!("expected EIP1559 gas info"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shadow_gas_oracle() {
+        // Answers with the primary's fees, and compares the candidate's.
+        let oracle = ShadowGasOracle::new(
+            FixedGasOracle(Some(100)),
+            Arc::new(FixedGasOracle(Some(150))),
+        );
+        for _ in 0..2 {
+            let info = oracle.get_info(Priority::Normal).await.unwrap();
+            assert_eq!(Wei::from(100), max_fee(info));
+        }
+        let telemetry = oracle.telemetry();
+        assert_eq!(2, telemetry.queries);
+        assert_eq!(2, telemetry.comparisons);
+        assert_eq!(2, telemetry.candidate_higher);
+        assert_eq!(Some(1.5), telemetry.cost_ratio());
+        assert_eq!(
+            Some(Wei::from(3000)),
+            telemetry.hypothetical_cost(Wei::from(2000))
+        );
+
+        // The candidate's failures are not returned.
+        let oracle =
+            ShadowGasOracle::new(FixedGasOracle(Some(100)), Arc::new(FixedGasOracle(None)));
+        assert!(oracle.get_info(Priority::Normal).await.is_ok());
+        let telemetry = oracle.telemetry();
+        assert_eq!(1, telemetry.candidate_failures);
+        assert_eq!(0, telemetry.comparisons);
+        assert_eq!(None, telemetry.cost_ratio());
+        assert_eq!(Some("unavailable".to_string()), telemetry.last_error);
+
+        // The primary's are.
+        let oracle =
+            ShadowGasOracle::new(FixedGasOracle(None), Arc::new(FixedGasOracle(Some(100))));
+        assert!(oracle.get_info(Priority::Normal).await.is_err());
+        let telemetry = oracle.telemetry();
+        assert_eq!(1, telemetry.primary_failures);
+        assert_eq!(0, telemetry.candidate_failures);
+    }
+}
@@ -0,0 +1,80 @@
+use ethers::types::{transaction::eip2718::TypedTransaction, Signature, H256};
+use std::fmt::Debug;
+
+/// Derives the hashes of the transactions the manager signs, and verifies them
+/// against the hashes returned by the node when the transactions are sent.
+///
+/// The manager stores the derived hash before sending the transaction (to
+/// recover it after a crash), so chains that hash (or wrap) transactions
+/// differently need a strategy that accounts for it.
+pub trait HashStrategy: Debug + Send + Sync {
+    /// Derives the hash of the signed transaction.
+    fn derive(&self, transaction: &TypedTransaction, signature: &Signature) -> H256;
+
+    /// Verifies the hash returned by the node against the derived one. Returns
+    /// the hash the manager should track, or `None` if they are inconsistent.
+    fn verify(&self, derived: H256, returned: H256) -> Option<H256>;
+}
+
+/// Default strategy: the hash is the keccak256 of the signed transaction's RLP
+/// encoding, and the node must return the same hash.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalHash;
+
+impl HashStrategy for LocalHash {
+    fn derive(&self, transaction: &TypedTransaction, signature: &Signature) -> H256 {
+        transaction.hash(signature)
+    }
+
+    fn verify(&self, derived: H256, returned: H256) -> Option<H256> {
+        (derived == returned).then_some(derived)
+    }
+}
+
+/// Permissive strategy for chains with non-standard transaction hashing: the
+/// manager trusts (and tracks) the hash returned by the node.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodeHash;
+
+impl HashStrategy for NodeHash {
+    fn derive(&self, transaction: &TypedTransaction, signature: &Signature) -> H256 {
+        transaction.hash(signature)
+    }
+
+    fn verify(&self, _: H256, returned: H256) -> Option<H256> {
+        Some(returned)
+    }
+}
+
+/// What the manager does when the hash returned by the node is inconsistent
+/// with the derived one (see `HashStrategy::verify`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashMismatchPolicy {
+    /// Returns an `Error::HashMismatch`. The transaction was already sent, and
+    /// the manager recovers it from the database (by its derived hash) when
+    /// reinstantiated.
+    #[default]
+    Error,
+
+    /// Logs a warning and tracks both hashes.
+    Adopt,
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::H256;
+
+    use super::{HashStrategy, LocalHash, NodeHash};
+
+    #[test]
+    fn test_verify() {
+        let derived = H256::from_low_u64_be(1);
+        let returned = H256::from_low_u64_be(2);
+
+        assert_eq!(LocalHash.verify(derived, derived), Some(derived));
+        assert_eq!(LocalHash.verify(derived, returned), None);
+
+        assert_eq!(NodeHash.verify(derived, derived), Some(derived));
+        assert_eq!(NodeHash.verify(derived, returned), Some(returned));
+    }
+}
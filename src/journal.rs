@@ -0,0 +1,241 @@
+use ethers::{
+    types::{Address, Bytes, H256, U256, U64},
+    utils::keccak256,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// An entry of the journal, stored as one JSON object per line (see
+/// `schema/journal_entry.json`).
+///
+/// Entries are chained: each one holds the keccak256 of the previous line (or
+/// zero, for the first entry), so that edits and deletions are detectable (see
+/// `Journal::verify`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub sequence: u64,
+
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+
+    /// The account that signed the transaction.
+    pub sender: Address,
+
+    pub nonce: Option<U256>,
+
+    #[serde(flatten)]
+    pub transition: Transition,
+
+    pub previous: H256,
+}
+
+/// The state transitions recorded by the journal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Transition {
+    /// A transaction was submitted through the manager's provider.
+    Submitted {
+        hash: H256,
+        endpoint: String,
+        gas_limit: Option<U256>,
+        gas_price: Option<U256>,
+        max_fee: Option<U256>,
+        max_priority_fee: Option<U256>,
+
+        /// The signed transaction, RLP-encoded (see
+        /// `Configuration::include_raw_transactions`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        raw_transaction: Option<Bytes>,
+    },
+
+    /// A transaction was resubmitted through a fallback broadcaster.
+    Resubmitted {
+        hash: H256,
+
+        /// The name of the broadcaster (see
+        /// `Configuration::add_fallback_broadcaster`).
+        endpoint: String,
+    },
+
+    Confirmed {
+        hash: H256,
+        block_number: Option<U64>,
+        gas_used: Option<U256>,
+        effective_gas_price: Option<U256>,
+    },
+
+    Skipped,
+
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JournalError {
+    #[error("io: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("malformed entry at line {line}: {err}")]
+    Malformed { line: u64, err: serde_json::Error },
+
+    #[error("broken chain at line {line}")]
+    BrokenChain { line: u64 },
+}
+
+/// Append-only journal of the state transitions of the transactions sent by
+/// the manager, in the JSON lines format (see `Configuration::journal`).
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    endpoint: String,
+    tail: Mutex<Tail>,
+}
+
+#[derive(Debug)]
+struct Tail {
+    file: File,
+    sequence: u64,
+    previous: H256,
+}
+
+impl Journal {
+    /// Opens (or creates) the journal at the path, continuing its chain. The
+    /// endpoint identifies the manager's provider in the entries.
+    pub fn open(path: impl AsRef<Path>, endpoint: impl Into<String>) -> io::Result<Journal> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        let mut sequence = 0;
+        let mut previous = H256::zero();
+        for line in BufReader::new(File::open(&path)?).lines() {
+            previous = H256(keccak256(line?));
+            sequence += 1;
+        }
+
+        Ok(Journal {
+            path,
+            endpoint: endpoint.into(),
+            tail: Mutex::new(Tail {
+                file,
+                sequence,
+                previous,
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Appends an entry to the journal.
+    pub fn record(
+        &self,
+        sender: Address,
+        nonce: Option<U256>,
+        transition: Transition,
+    ) -> io::Result<JournalEntry> {
+        let mut tail = self.tail.lock().unwrap();
+        let entry = JournalEntry {
+            sequence: tail.sequence,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            sender,
+            nonce,
+            transition,
+            previous: tail.previous,
+        };
+
+        let line = serde_json::to_string(&entry)?;
+        tail.file.write_all(format!("{}\n", line).as_bytes())?;
+        tail.file.flush()?;
+        tail.sequence += 1;
+        tail.previous = H256(keccak256(line));
+        Ok(entry)
+    }
+
+    /// Verifies the chain of the journal at the path and returns its entries.
+    pub fn verify(path: impl AsRef<Path>) -> Result<Vec<JournalEntry>, JournalError> {
+        let mut entries = Vec::new();
+        let mut previous = H256::zero();
+        for (line_number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let line_number = line_number as u64 + 1;
+            let entry: JournalEntry =
+                serde_json::from_str(&line).map_err(|err| JournalError::Malformed {
+                    line: line_number,
+                    err,
+                })?;
+            if entry.sequence != line_number - 1 || entry.previous != previous {
+                return Err(JournalError::BrokenChain { line: line_number });
+            }
+            previous = H256(keccak256(line));
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::{Address, H256, U256};
+    use std::fs;
+
+    use super::{Journal, JournalError, Transition};
+
+    #[test]
+    fn test_journal() {
+        let path = std::env::temp_dir().join("tx_manager_test_journal.jsonl");
+        let _ = fs::remove_file(&path);
+        let sender = Address::from_low_u64_be(1);
+        let submitted = Transition::Submitted {
+            hash: H256::from_low_u64_be(2),
+            endpoint: "http://localhost:8545".to_string(),
+            gas_limit: Some(U256::from(21000)),
+            gas_price: None,
+            max_fee: Some(U256::from(2)),
+            max_priority_fee: Some(U256::from(1)),
+            raw_transaction: None,
+        };
+
+        // Continues the chain after being reopened.
+        {
+            let journal = Journal::open(&path, "http://localhost:8545").unwrap();
+            journal
+                .record(sender, Some(U256::zero()), submitted.clone())
+                .unwrap();
+        }
+        {
+            let journal = Journal::open(&path, "http://localhost:8545").unwrap();
+            let entry = journal
+                .record(sender, Some(U256::zero()), Transition::Skipped)
+                .unwrap();
+            assert_eq!(1, entry.sequence);
+            assert_ne!(H256::zero(), entry.previous);
+        }
+        let entries = Journal::verify(&path).unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!(submitted, entries[0].transition);
+        assert_eq!(Transition::Skipped, entries[1].transition);
+
+        // Detects tampering.
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replacen("0x5208", "0x5209", 1)).unwrap();
+        assert!(matches!(
+            Journal::verify(&path),
+            Err(JournalError::BrokenChain { line: 2 })
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,9 +1,42 @@
+pub mod broadcaster;
+#[cfg(feature = "cartesi")]
+pub mod cartesi;
 pub mod config;
 pub mod database;
+#[cfg(feature = "debug-rpc")]
+pub mod debug_rpc;
+pub mod dyn_manager;
+pub mod events;
+#[cfg(feature = "executor")]
+pub mod executor;
+pub mod failover;
+pub mod fee_model;
+pub mod formatting;
+pub mod funding;
 pub mod gas_oracle;
+pub mod hashing;
+pub mod journal;
 pub mod manager;
+pub mod price_feed;
+pub mod report;
+pub mod splitter;
+pub mod state_store;
+#[cfg(feature = "opentelemetry")]
+pub mod telemetry;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 pub mod time;
 pub mod transaction;
+pub mod units;
+pub mod watcher;
 
-pub use manager::{Chain, Error, Manager as TransactionManager};
-pub use transaction::{Priority, Transaction, Value};
+pub use dyn_manager::{BoxedError, DynError, DynManager};
+pub use manager::{
+    Chain, CheckSummary, ConfirmationEstimator, ConfirmationUpdate, Currency, Error, ErrorKind,
+    HandoffToken, Manager as TransactionManager, NonceManagement, Patience, PauseSwitch,
+    PendingResolution, ResubmissionMode, SendOptions, SendOutcome, SendTimings, SpeedUpSwitch,
+};
+pub use report::SendReport;
+pub use transaction::{Metadata, Precondition, Priority, Transaction, Value};
+pub use units::{GasLimit, Gwei, Wei};
+pub use watcher::PendingTxWatcher;
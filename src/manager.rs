@@ -1,25 +1,111 @@
 use async_recursion::async_recursion;
 use ethers::{
+    abi::{self, ParamType, RawLog, Token},
+    contract::EthEvent,
     providers::Middleware,
     types::{
         transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes,
-        NameOrAddress, TransactionReceipt, H256, U256,
+        NameOrAddress, TransactionReceipt, TransactionRequest, H256, U256, U64,
+    },
+    utils::{
+        get_contract_address, hex, keccak256, EIP1559_FEE_ESTIMATION_DEFAULT_PRIORITY_FEE,
+        EIP1559_FEE_ESTIMATION_PAST_BLOCKS,
     },
 };
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::{Future, Stream};
+use serde::{Deserialize, Serialize};
 
+use std::collections::BTreeMap;
 use std::default::Default;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tracing::{error, info, trace, warn};
+use tracing::{error, info, trace, trace_span, warn, Instrument, Span};
 
-use crate::gas_oracle::{GasInfo, GasOracle, GasOracleInfo, LegacyGasInfo};
+use crate::broadcaster::Broadcaster;
+use crate::database::{Database, DatabaseOperation, DatabaseStats};
+use crate::dyn_manager::DynError;
+use crate::events::{Event, EventListener};
+use crate::failover::Failover;
+use crate::fee_model::{EthereumFeeModel, FeeModel};
+use crate::formatting;
+use crate::gas_oracle::EIP1559GasInfo;
+use crate::gas_oracle::{
+    self, GasInfo, GasOracle, GasOracleInfo, LegacyGasInfo, NodeClient, ReplacementRules,
+    REPLACEMENT_FEE_BUMP,
+};
+use crate::hashing::{HashMismatchPolicy, HashStrategy, LocalHash};
+use crate::journal::{Journal, Transition};
+use crate::price_feed::{self, PriceFeed};
+use crate::report::SendReport;
+use crate::splitter::Splitter;
 use crate::time::{DefaultTime, Time};
-use crate::transaction::{PersistentState, Priority, StaticTxData, SubmittedTxs, Transaction};
-use crate::{database::Database, gas_oracle::EIP1559GasInfo};
+use crate::transaction::{
+    Attempt, BroadcastStatus, PersistentState, Precondition, Priority, StaticTxData, SubmittedTxs,
+    Transaction, Value,
+};
+use crate::units::{GasLimit, Wei};
+use crate::watcher::{PendingTxWatcher, WatchStatus};
+
+mod driven;
+
+pub use driven::{Action, ActionResult, SignedTransaction};
 
 // Default values.
 const TRANSACTION_MINING_TIME: Duration = Duration::from_secs(60);
 const BLOCK_TIME: Duration = Duration::from_secs(20);
+const MAX_FEE_HEADROOM: Option<f64> = None;
+const ESTIMATE_GAS_RETRIES: u32 = 3;
+const ESTIMATE_GAS_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many times the manager re-queries the gas oracle when its estimates are
+/// stale, before falling back to the provider's.
+const GAS_ORACLE_STALENESS_RETRIES: u32 = 2;
+
+/// How many times the manager resends a transaction whose max fee was rejected
+/// by the node for not covering the base fee, within the same send attempt.
+const BASE_FEE_REJECTION_RETRIES: u32 = 3;
+
+/// The maximum increase of the base fee from one block to the next (EIP-1559),
+/// used as the headroom of rejected max fees when none is configured.
+const BASE_FEE_MAX_INCREASE: f64 = 1.125;
+
+/// Multiplier of the base fee in the provider's fee estimates, when no
+/// `Configuration::base_fee_multiplier` is configured (the max fee covers the
+/// base fee doubling before the transaction is included).
+const PROVIDER_BASE_FEE_MULTIPLIER: f64 = 2.;
+
+/// Confirmations required by default on chains without a configured number
+/// (see `Chain::default_confirmations`).
+pub(crate) const DEFAULT_CONFIRMATIONS: usize = 12;
+
+/// Transaction mining time recommended for a chain, in blocks.
+const RECOMMENDED_MINING_BLOCKS: u32 = 3;
+
+/// Interval between the checks of whether a submitted transaction is visible
+/// in the transaction pool.
+const POOL_ACCEPTANCE_POLLING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Weight of the latest send in the observed mining time (an exponential
+/// moving average, see `Manager::observed_mining_time`).
+const OBSERVED_MINING_TIME_WEIGHT: f64 = 0.25;
+
+/// Weight of the latest sample in the observed block time (an exponential
+/// moving average, see `ConfirmationEstimator`).
+const OBSERVED_BLOCK_TIME_WEIGHT: f64 = 0.25;
+
+/// Multiplier of the wait time between resubmissions once the node refused
+/// further replacements of the transaction (see `Event::ReplacementLimitReached`).
+const REPLACEMENT_LIMIT_PATIENCE: u32 = 4;
+
+/// How many of the last errors of the node a `SendReport` keeps.
+const SEND_REPORT_RPC_ERRORS: usize = 5;
+
+/// Database operations that take longer than this are reported (by default).
+const SLOW_DATABASE_THRESHOLD: Duration = Duration::from_secs(1);
 
 // ------------------------------------------------------------------------------------------------
 // Error
@@ -50,6 +136,294 @@ pub enum Error<M: Middleware, GO: GasOracle, DB: Database> {
 
     #[error("internal error: incompatible gas oracle ({0})")]
     IncompatibleGasOracle(&'static str),
+
+    #[error("max fee below base fee (max fee: {max_fee}, base fee: {base_fee})")]
+    MaxFeeBelowBaseFee { max_fee: Wei, base_fee: Wei },
+
+    #[error("chain id mismatch (configured: {configured}, reported: {reported})")]
+    ChainIdMismatch { configured: u64, reported: U256 },
+
+    #[error("the gas oracle does not support the chain (chain id: {0})")]
+    UnsupportedGasOracleChain(u64),
+
+    #[error("signer mismatch (signer: {signer}, from: {from})")]
+    SignerMismatch { signer: Address, from: Address },
+
+    #[error("transaction reverted (reason: {0:?})")]
+    Reverted(Option<String>),
+
+    #[error("the transaction {transaction_hash:?} did not emit the expected `{event}` event")]
+    EventMissing {
+        event: String,
+        transaction_hash: H256,
+    },
+
+    /// The transaction was confirmed, but the post-condition of
+    /// `Manager::send_and_verify` did not hold.
+    #[error(
+        "the post-condition of the transaction {:?} failed: {reason}",
+        receipt.transaction_hash
+    )]
+    PostConditionFailed {
+        receipt: Box<TransactionReceipt>,
+        reason: String,
+    },
+
+    #[error("gas required exceeds the node's allowance (cap: {0:?})")]
+    GasAllowanceExceeded(Option<u64>),
+
+    #[error("intrinsic gas too low (gas limit: {0:?}), the gas limit must be raised")]
+    IntrinsicGasTooLow(Option<U256>),
+
+    #[error(
+        "gas required exceeds the block gas limit (gas limit: {gas_limit:?}, \
+        block gas limit: {block_gas_limit:?}), the operation must be split"
+    )]
+    ExceedsBlockGasLimit {
+        gas_limit: Option<U256>,
+        block_gas_limit: Option<U256>,
+    },
+
+    #[error("no pending transaction to replace")]
+    NoPendingTransaction,
+
+    #[error("the transaction {hash:?} cannot be adopted ({reason})")]
+    NotAdoptable { hash: H256, reason: &'static str },
+
+    /// The node does not know the transaction to wait for (see
+    /// `Manager::wait_for`).
+    #[error("the node does not know the transaction {0:?}")]
+    UnknownTransaction(H256),
+
+    /// The mined transaction is not the one the manager sent (see
+    /// `Configuration::validate_receipts`). A `nonce` mismatch means the nonce
+    /// was consumed by a different transaction.
+    #[error("the mined transaction {hash:?} does not match the one sent ({mismatch})")]
+    ReceiptMismatch { hash: H256, mismatch: &'static str },
+
+    /// The nonce was mined, but none of the transactions the manager sent
+    /// with it was (see `Configuration::validate_receipts`).
+    #[error("the nonce {nonce} was consumed by a transaction the manager did not send")]
+    NonceConsumed { nonce: U256 },
+
+    #[error("replacement sender mismatch (pending: {pending}, replacement: {replacement})")]
+    ReplacementSenderMismatch {
+        pending: Address,
+        replacement: Address,
+    },
+
+    #[error("hash mismatch (derived: {derived:?}, returned by the node: {returned:?})")]
+    HashMismatch { derived: H256, returned: H256 },
+
+    #[error(
+        "the provider did not assign a nonce to the transaction (see `NonceManagement::Provider`)"
+    )]
+    NonceNotAssigned,
+
+    #[error("could not resolve the ENS name `{name}`: {error}")]
+    UnresolvableName { name: String, error: M::Error },
+
+    #[error("broadcast: {0}")]
+    Broadcast(DynError),
+
+    #[error("fee model: {0}")]
+    FeeModel(DynError),
+
+    #[error("caller-driven mode: {0}")]
+    Driven(&'static str),
+
+    /// The state in the database was handed off with another token, or with
+    /// none (`found`), than the one the manager was instantiated with
+    /// (`expected`, none for `Manager::new`).
+    #[error("handoff token mismatch (expected: {expected:?}, found: {found:?})")]
+    HandoffMismatch {
+        expected: Option<HandoffToken>,
+        found: Option<HandoffToken>,
+    },
+
+    #[error("invalid configuration: {0}")]
+    Configuration(ConfigurationError),
+
+    /// The provider's latest block did not advance for longer than expected,
+    /// and there was no other endpoint to fail over to (see
+    /// `Configuration::stale_provider_blocks`).
+    #[error("the provider is stale (stuck at block {block_number} for {age:?})")]
+    ProviderStale { block_number: U64, age: Duration },
+
+    /// The send of a transaction (with a nonce assigned) failed with `error`;
+    /// `report` tells what the manager did until then.
+    #[error("{}", &**.error)]
+    Failed {
+        error: Box<Error<M, GO, DB>>,
+        report: Box<SendReport>,
+    },
+}
+
+impl<M: Middleware, GO: GasOracle, DB: Database> Error<M, GO, DB> {
+    /// The report of the failed send (see `Error::Failed`).
+    pub fn report(&self) -> Option<&SendReport> {
+        match self {
+            Error::Failed { report, .. } => Some(report),
+            _ => None,
+        }
+    }
+
+    /// The error of the failed send, without its report.
+    pub fn inner(&self) -> &Self {
+        match self {
+            Error::Failed { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// The error without the report of the failed send.
+    pub fn into_inner(self) -> Self {
+        match self {
+            Error::Failed { error, .. } => *error,
+            error => error,
+        }
+    }
+
+    /// The category of the error, for programmatic retry decisions (see
+    /// `ErrorKind`). The errors of the provider are categorized by their
+    /// messages.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Middleware(err) => ErrorKind::of_message(&err.to_string()),
+            Error::UnresolvableName { error, .. } => {
+                match ErrorKind::of_message(&error.to_string()) {
+                    ErrorKind::Transient => ErrorKind::Transient,
+                    _ => ErrorKind::Config,
+                }
+            }
+            Error::Broadcast(err) | Error::FeeModel(err) => ErrorKind::of_message(&err.to_string()),
+            Error::GasOracle(err1, err2) => {
+                if is_transient(&err1.to_string()) || is_transient(&err2.to_string()) {
+                    ErrorKind::Transient
+                } else {
+                    ErrorKind::Fatal
+                }
+            }
+
+            Error::LatestBlockIsNone
+            | Error::LatestBaseFeeIsNone
+            | Error::MaxFeeBelowBaseFee { .. }
+            | Error::ProviderStale { .. } => ErrorKind::Transient,
+
+            Error::NonceTooLow { .. }
+            | Error::NonceNotAssigned
+            | Error::ReceiptMismatch { .. }
+            | Error::NonceConsumed { .. } => ErrorKind::Nonce,
+
+            Error::Reverted(_) | Error::EventMissing { .. } | Error::PostConditionFailed { .. } => {
+                ErrorKind::Revert
+            }
+
+            Error::GasAllowanceExceeded(_)
+            | Error::IntrinsicGasTooLow(_)
+            | Error::ExceedsBlockGasLimit { .. } => ErrorKind::Gas,
+
+            Error::IncompatibleGasOracle(_)
+            | Error::ChainIdMismatch { .. }
+            | Error::UnsupportedGasOracleChain(_)
+            | Error::SignerMismatch { .. }
+            | Error::HashMismatch { .. }
+            | Error::Configuration(_) => ErrorKind::Config,
+
+            Error::Database(_) | Error::HandoffMismatch { .. } => ErrorKind::Storage,
+
+            Error::NoPendingTransaction
+            | Error::NotAdoptable { .. }
+            | Error::UnknownTransaction(_)
+            | Error::ReplacementSenderMismatch { .. }
+            | Error::Driven(_) => ErrorKind::Usage,
+
+            Error::Failed { error, .. } => error.kind(),
+        }
+    }
+}
+
+/// Stable categories of the manager's errors (see `Error::kind`), so that
+/// services can decide whether to retry without matching on error messages.
+/// Their codes (see `ErrorKind::code`) can be used in logs and metrics.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The provider or the gas oracle could not answer (e.g., timeouts, rate
+    /// limits, or a stale node), or the base fee was above the max fee cap.
+    /// Sending the transaction again later may succeed.
+    Transient,
+
+    /// The sender cannot pay for the transaction.
+    Funds,
+
+    /// The nonce was consumed by another transaction, or the provider did not
+    /// assign one.
+    Nonce,
+
+    /// The transaction was mined but reverted, or did not have the expected
+    /// effects (events or post-conditions).
+    Revert,
+
+    /// The gas limit of the transaction is invalid (e.g., above the block gas
+    /// limit): the transaction must be changed.
+    Gas,
+
+    /// The configuration does not match the chain, the signer, or the gas
+    /// oracle.
+    Config,
+
+    /// The database failed, or another writer changed the state.
+    Storage,
+
+    /// The function was called in a state that does not allow it (e.g.,
+    /// replacing a transaction when none is pending).
+    Usage,
+
+    /// Any other failure, which is not expected to go away by itself.
+    Fatal,
+}
+
+impl ErrorKind {
+    /// The stable code of the category (e.g., `"transient"`).
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Transient => "transient",
+            ErrorKind::Funds => "funds",
+            ErrorKind::Nonce => "nonce",
+            ErrorKind::Revert => "revert",
+            ErrorKind::Gas => "gas",
+            ErrorKind::Config => "config",
+            ErrorKind::Storage => "storage",
+            ErrorKind::Usage => "usage",
+            ErrorKind::Fatal => "fatal",
+        }
+    }
+
+    /// Whether sending the transaction again (as is) may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorKind::Transient)
+    }
+
+    /// Categorizes an error of the provider by its message.
+    fn of_message(message: &str) -> ErrorKind {
+        let lowercase = message.to_lowercase();
+        if is_transient(message) {
+            ErrorKind::Transient
+        } else if lowercase.contains("insufficient funds") {
+            ErrorKind::Funds
+        } else if lowercase.contains("nonce too low") || lowercase.contains("nonce too high") {
+            ErrorKind::Nonce
+        } else {
+            ErrorKind::Fatal
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -69,9 +443,214 @@ pub struct Configuration<T: Time> {
 
     /// Dependency that handles process sleeping and calculating elapsed time.
     pub time: T,
+
+    /// Multiplier applied to the latest base fee when the max fee provided by
+    /// the gas oracle does not cover it (e.g., right after a base fee spike).
+    /// If `None`, the manager returns an `Error::MaxFeeBelowBaseFee` instead.
+    ///
+    /// Also applied when the node rejects a transaction's max fee for not
+    /// covering the base fee, in which case the manager resends it with a
+    /// raised max fee (a 12.5% headroom is used if `None`).
+    pub max_fee_headroom: Option<f64>,
+
+    /// Multiplier applied to the latest base fee wherever the manager derives
+    /// the max fee from it: the provider's fee estimates (`2.0` if `None`),
+    /// gas oracles that only give the max priority fee, and max fees rejected
+    /// by the node (`max_fee_headroom`, or a 12.5% headroom, if `None`). For
+    /// example, `2.0` makes the max fee cover a doubling of the base fee
+    /// before the transaction is included.
+    pub base_fee_multiplier: Option<f64>,
+
+    /// Whose rules replacement transactions (same nonce) follow: the minimum
+    /// fee bump over the replaced transaction, and the error nodes return for
+    /// underpriced replacements. Geth's (a 10% bump) by default; with
+    /// `ReplacementRules::Detect`, the provider's client is detected when the
+    /// manager starts.
+    pub replacement_rules: ReplacementRules,
+
+    /// What the manager does when a transaction is not mined within the wait
+    /// time: resubmit it with bumped fees (the default), rebroadcast it as is,
+    /// or keep waiting (see `ResubmissionMode`).
+    pub resubmission_mode: ResubmissionMode,
+
+    /// The highest max fee (or gas price, for legacy transactions) the manager
+    /// offers. Higher fees are capped, and, while the base fee is above the
+    /// cap (the whole network is congested), the manager holds the
+    /// resubmissions, which could not be included anyway, until it comes back
+    /// within range (see `Event::WaitingForFeeDrop`).
+    pub max_fee_cap: Option<Wei>,
+
+    /// Maximum age of the gas oracle's estimates (see `GasOracleInfo::age`).
+    /// The manager re-queries the gas oracle when they are older, and falls
+    /// back to the provider's estimates if they stay stale. If `None`, the
+    /// estimates are used regardless of their age.
+    pub gas_oracle_staleness_window: Option<Duration>,
+
+    /// Fee history reward percentiles used to estimate the max priority fee of
+    /// EIP1559 transactions when the gas oracle defers to the provider.
+    pub priority_fee_percentiles: PriorityFeePercentiles,
+
+    /// Multipliers applied to the provider's gas price of legacy transactions
+    /// when the gas oracle defers to the provider.
+    pub gas_price_multipliers: GasPriceMultipliers,
+
+    /// Listeners notified of the lifecycle transitions of the transactions.
+    pub event_listeners: Vec<Arc<dyn EventListener>>,
+
+    /// If true, the manager adopts the chain id reported by the provider when
+    /// it differs from the configured one (instead of returning an
+    /// `Error::ChainIdMismatch`).
+    pub adopt_provider_chain_id: bool,
+
+    /// How long a transaction may stay in flight (since its first submission)
+    /// before the manager emits an `Event::Stalled`. The manager keeps trying
+    /// to confirm stalled transactions. If `None`, no stall alerts are emitted.
+    pub stall_threshold: Option<Duration>,
+
+    /// After how many block times without a new block the manager considers
+    /// its provider stale (stuck at an old block), emits an
+    /// `Event::ProviderStale`, and fails over to another endpoint through
+    /// `failover`, or, without one, fails with `Error::ProviderStale`. If
+    /// `None`, the provider's freshness is not checked.
+    pub stale_provider_blocks: Option<u32>,
+    pub failover: Option<Arc<dyn Failover>>,
+
+    /// Source of the ETH price in USD, used to convert cost estimates (see
+    /// `Manager::estimate_cost`). If `None`, estimates are only given in wei.
+    pub price_feed: Option<Arc<dyn PriceFeed>>,
+
+    /// How many times the manager retries estimating the gas of a transaction
+    /// when the provider fails for transient reasons (e.g., timeouts or rate
+    /// limits), and the interval before the first retry (doubled after each).
+    pub estimate_gas_retries: u32,
+    pub estimate_gas_retry_interval: Duration,
+
+    /// How the manager derives the hashes of the transactions and verifies
+    /// them against the ones returned by the node (see `HashStrategy`).
+    pub hash_strategy: Arc<dyn HashStrategy>,
+
+    /// What the manager does when the hashes are inconsistent.
+    pub hash_mismatch_policy: HashMismatchPolicy,
+
+    /// How the manager builds and encodes the transactions, for chains with
+    /// non-Ethereum fee mechanics (see `FeeModel`).
+    pub fee_model: Arc<dyn FeeModel>,
+
+    /// If set, the manager submits the signed transactions through this
+    /// endpoint (e.g., a private one) instead of its provider, which it still
+    /// uses for everything else (e.g., querying receipts through a cheaper
+    /// public endpoint). Each can fail over independently (e.g., a
+    /// `Provider<FailoverClient<_>>`).
+    pub broadcast_provider: Option<Arc<dyn Broadcaster>>,
+
+    /// If set, after submitting a transaction the manager waits (up to this
+    /// window) for it to be visible in the provider's transaction pool before
+    /// waiting for it to be mined. If it is not, the manager resubmits it
+    /// through the fallback broadcasters (in order) until it is.
+    pub pool_acceptance_window: Option<Duration>,
+
+    /// The fallback broadcasters, with the names that identify them in the
+    /// logs and the journal (instead of their configurations, which may hold
+    /// credentials, e.g., API keys in URLs).
+    pub fallback_broadcasters: Vec<(String, Arc<dyn Broadcaster>)>,
+
+    /// Append-only journal of the state transitions of the transactions (for
+    /// audits). Failures to write to it are logged, not returned.
+    pub journal: Option<Arc<Journal>>,
+
+    /// Pauses the submissions of the managers that share it (see
+    /// `PauseSwitch`).
+    pub pause_switch: PauseSwitch,
+
+    /// Resubmits the in-flight transactions of the managers that share it at
+    /// higher fees on demand (see `SpeedUpSwitch`).
+    pub speed_up_switch: SpeedUpSwitch,
+
+    /// If true, the manager sends legacy (type-0) transactions even on EIP1559
+    /// chains (for providers that mishandle type-2 transactions), converting
+    /// the EIP1559 fees of the gas oracle into an equivalent gas price. It can
+    /// be toggled between transactions with `Manager::update_configuration`.
+    pub force_legacy: bool,
+
+    /// If true, the logs of submissions and confirmations render the fees in
+    /// gwei, the values in the chain's native currency, and the hashes with
+    /// explorer links (see `Chain` and the `formatting` module).
+    pub human_logs: bool,
+
+    /// If true, the wait before resubmitting a transaction adapts to the
+    /// mining times observed in the previous sends (see
+    /// `Manager::observed_mining_time`), instead of using the configured
+    /// `transaction_mining_time`, unless the gas oracle estimates it.
+    pub adaptive_mining_time: bool,
+
+    /// If true, the `Submitted` events and journal entries include the signed
+    /// transactions (RLP-encoded, as broadcast), for setups that must archive
+    /// the exact payloads. Off by default, as they are large and sensitive.
+    pub include_raw_transactions: bool,
+
+    /// If true, before returning a receipt, the manager fetches the mined
+    /// transaction and checks that it is the one it sent (its sender, nonce,
+    /// recipient and call data), failing with `Error::ReceiptMismatch`
+    /// otherwise (e.g., a node serving someone else's transaction for a
+    /// non-standard hash). It costs a request per send. Before resubmitting,
+    /// it also checks whether the nonce was mined without any of the sent
+    /// transactions, failing with `Error::NonceConsumed`.
+    pub validate_receipts: bool,
+
+    /// Who assigns the nonces of the transactions.
+    pub nonce_management: NonceManagement,
+
+    /// If set, the wait before resubmitting a transaction that was not mined
+    /// grows with each resubmission, instead of staying fixed.
+    pub patience: Option<Patience>,
+
+    /// Database operations that take longer than this are logged as warnings
+    /// and reported with `SlowDatabase` events (see `Manager::database_stats`).
+    pub slow_database_threshold: Option<Duration>,
 }
 
 impl<T: Time> Configuration<T> {
+    pub fn new(time: T) -> Configuration<T> {
+        Self {
+            transaction_mining_time: TRANSACTION_MINING_TIME,
+            block_time: BLOCK_TIME,
+            time,
+            max_fee_headroom: MAX_FEE_HEADROOM,
+            base_fee_multiplier: None,
+            replacement_rules: ReplacementRules::default(),
+            resubmission_mode: ResubmissionMode::default(),
+            max_fee_cap: None,
+            gas_oracle_staleness_window: None,
+            priority_fee_percentiles: PriorityFeePercentiles::default(),
+            gas_price_multipliers: GasPriceMultipliers::default(),
+            event_listeners: Vec::new(),
+            adopt_provider_chain_id: false,
+            stall_threshold: None,
+            stale_provider_blocks: None,
+            failover: None,
+            price_feed: None,
+            estimate_gas_retries: ESTIMATE_GAS_RETRIES,
+            estimate_gas_retry_interval: ESTIMATE_GAS_RETRY_INTERVAL,
+            hash_strategy: Arc::new(LocalHash),
+            hash_mismatch_policy: HashMismatchPolicy::default(),
+            fee_model: Arc::new(EthereumFeeModel),
+            broadcast_provider: None,
+            pool_acceptance_window: None,
+            fallback_broadcasters: Vec::new(),
+            journal: None,
+            pause_switch: PauseSwitch::default(),
+            speed_up_switch: SpeedUpSwitch::default(),
+            force_legacy: false,
+            human_logs: false,
+            adaptive_mining_time: true,
+            include_raw_transactions: false,
+            validate_receipts: false,
+            nonce_management: NonceManagement::default(),
+            patience: None,
+            slow_database_threshold: Some(SLOW_DATABASE_THRESHOLD),
+        }
+    }
+
     pub fn set_transaction_mining_time(
         mut self,
         transaction_mining_time: Duration,
@@ -89,125 +668,1197 @@ impl<T: Time> Configuration<T> {
         self.time = time;
         self
     }
-}
 
-impl Default for Configuration<DefaultTime> {
-    fn default() -> Self {
-        Self {
-            transaction_mining_time: TRANSACTION_MINING_TIME,
-            block_time: BLOCK_TIME,
-            time: DefaultTime,
-        }
+    pub fn set_max_fee_headroom(mut self, max_fee_headroom: Option<f64>) -> Configuration<T> {
+        self.max_fee_headroom = max_fee_headroom;
+        self
     }
-}
 
-// ------------------------------------------------------------------------------------------------
-// Chain
-// ------------------------------------------------------------------------------------------------
+    pub fn set_replacement_rules(
+        mut self,
+        replacement_rules: ReplacementRules,
+    ) -> Configuration<T> {
+        self.replacement_rules = replacement_rules;
+        self
+    }
 
-#[derive(Copy, Clone, Debug)]
-pub struct Chain {
-    pub id: u64,
-    pub is_legacy: bool,
-}
+    pub fn set_resubmission_mode(
+        mut self,
+        resubmission_mode: ResubmissionMode,
+    ) -> Configuration<T> {
+        self.resubmission_mode = resubmission_mode;
+        self
+    }
 
-impl Chain {
-    /// For chains that implement the EIP1559.
-    pub fn new(id: u64) -> Chain {
-        Self {
-            id,
-            is_legacy: false,
-        }
+    pub fn set_base_fee_multiplier(mut self, base_fee_multiplier: Option<f64>) -> Configuration<T> {
+        self.base_fee_multiplier = base_fee_multiplier;
+        self
     }
 
-    /// For chains that do not implement the EIP1559.
-    pub fn legacy(id: u64) -> Chain {
-        Self {
-            id,
-            is_legacy: true,
-        }
+    pub fn set_gas_oracle_staleness_window(
+        mut self,
+        gas_oracle_staleness_window: Option<Duration>,
+    ) -> Configuration<T> {
+        self.gas_oracle_staleness_window = gas_oracle_staleness_window;
+        self
     }
-}
 
-// ------------------------------------------------------------------------------------------------
-// Manager
-// ------------------------------------------------------------------------------------------------
+    pub fn set_priority_fee_percentiles(
+        mut self,
+        priority_fee_percentiles: PriorityFeePercentiles,
+    ) -> Configuration<T> {
+        self.priority_fee_percentiles = priority_fee_percentiles;
+        self
+    }
 
-#[derive(Debug)]
-pub struct Manager<M: Middleware, GO: GasOracle, DB: Database, T: Time> {
-    provider: M,
-    gas_oracle: GO,
-    db: DB,
-    chain: Chain,
-    configuration: Configuration<T>,
-}
+    pub fn set_gas_price_multipliers(
+        mut self,
+        gas_price_multipliers: GasPriceMultipliers,
+    ) -> Configuration<T> {
+        self.gas_price_multipliers = gas_price_multipliers;
+        self
+    }
 
-/// Public functions.
-impl<M: Middleware, GO: GasOracle, DB: Database, T: Time> Manager<M, GO, DB, T>
-where
-    M: Send + Sync,
-    GO: Send + Sync,
-    DB: Send + Sync,
-    T: Send + Sync,
-{
-    /// Sends and confirms any pending transaction persisted in the database
-    /// before returning an instance of the transaction manager. In case a
-    /// pending transaction was mined, it's receipt is also returned.
-    #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn new(
-        provider: M,
-        gas_oracle: GO,
-        db: DB,
-        chain: Chain,
-        configuration: Configuration<T>,
-    ) -> Result<(Self, Option<TransactionReceipt>), Error<M, GO, DB>> {
-        let mut manager = Self {
-            provider,
-            gas_oracle,
-            db,
-            chain,
-            configuration,
-        };
+    pub fn add_event_listener(
+        mut self,
+        event_listener: Arc<dyn EventListener>,
+    ) -> Configuration<T> {
+        self.event_listeners.push(event_listener);
+        self
+    }
 
-        trace!("Instantiating a new transaction manager => {:#?}", manager);
+    pub fn set_adopt_provider_chain_id(
+        mut self,
+        adopt_provider_chain_id: bool,
+    ) -> Configuration<T> {
+        self.adopt_provider_chain_id = adopt_provider_chain_id;
+        self
+    }
 
-        let transaction_receipt = match manager.db.get_state().await.map_err(Error::Database)? {
-            Some(mut state) => {
-                warn!("Dealing with previous state => {:#?}", state);
+    pub fn set_stall_threshold(mut self, stall_threshold: Option<Duration>) -> Configuration<T> {
+        self.stall_threshold = stall_threshold;
+        self
+    }
 
-                {
-                    let current_nonce = manager.get_nonce(state.tx_data.transaction.from).await?;
-                    let expected_nonce = state.tx_data.nonce;
+    pub fn set_stale_provider_blocks(
+        mut self,
+        stale_provider_blocks: Option<u32>,
+    ) -> Configuration<T> {
+        self.stale_provider_blocks = stale_provider_blocks;
+        self
+    }
 
-                    if current_nonce > expected_nonce {
-                        error!(
-                            "Nonce too low! Current is `{}`, expected `{}`",
-                            current_nonce, expected_nonce
-                        );
+    pub fn set_failover(mut self, failover: Option<Arc<dyn Failover>>) -> Configuration<T> {
+        self.failover = failover;
+        self
+    }
 
-                        return Err(Error::NonceTooLow {
-                            current_nonce,
-                            expected_nonce,
-                        });
-                    }
-                }
+    pub fn set_price_feed(mut self, price_feed: Option<Arc<dyn PriceFeed>>) -> Configuration<T> {
+        self.price_feed = price_feed;
+        self
+    }
 
-                let wait_time = manager.get_wait_time(state.tx_data.confirmations, None);
-                let transaction_receipt = manager
-                    .confirm_transaction(&mut state, wait_time, false)
-                    .await?;
-                manager.db.clear_state().await.map_err(Error::Database)?;
-                Some(transaction_receipt)
-            }
+    pub fn set_estimate_gas_retries(mut self, estimate_gas_retries: u32) -> Configuration<T> {
+        self.estimate_gas_retries = estimate_gas_retries;
+        self
+    }
 
-            None => None,
-        };
+    pub fn set_estimate_gas_retry_interval(
+        mut self,
+        estimate_gas_retry_interval: Duration,
+    ) -> Configuration<T> {
+        self.estimate_gas_retry_interval = estimate_gas_retry_interval;
+        self
+    }
 
-        Ok((manager, transaction_receipt))
+    pub fn set_hash_strategy(mut self, hash_strategy: Arc<dyn HashStrategy>) -> Configuration<T> {
+        self.hash_strategy = hash_strategy;
+        self
     }
 
-    #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn force_new(
+    pub fn set_hash_mismatch_policy(
+        mut self,
+        hash_mismatch_policy: HashMismatchPolicy,
+    ) -> Configuration<T> {
+        self.hash_mismatch_policy = hash_mismatch_policy;
+        self
+    }
+
+    pub fn set_fee_model(mut self, fee_model: Arc<dyn FeeModel>) -> Configuration<T> {
+        self.fee_model = fee_model;
+        self
+    }
+
+    pub fn set_pool_acceptance_window(
+        mut self,
+        pool_acceptance_window: Option<Duration>,
+    ) -> Configuration<T> {
+        self.pool_acceptance_window = pool_acceptance_window;
+        self
+    }
+
+    pub fn set_broadcast_provider(
+        mut self,
+        broadcast_provider: Option<Arc<dyn Broadcaster>>,
+    ) -> Configuration<T> {
+        self.broadcast_provider = broadcast_provider;
+        self
+    }
+
+    pub fn add_fallback_broadcaster(
+        mut self,
+        name: impl Into<String>,
+        fallback_broadcaster: Arc<dyn Broadcaster>,
+    ) -> Configuration<T> {
+        self.fallback_broadcasters
+            .push((name.into(), fallback_broadcaster));
+        self
+    }
+
+    pub fn set_journal(mut self, journal: Option<Arc<Journal>>) -> Configuration<T> {
+        self.journal = journal;
+        self
+    }
+
+    pub fn set_pause_switch(mut self, pause_switch: PauseSwitch) -> Configuration<T> {
+        self.pause_switch = pause_switch;
+        self
+    }
+
+    pub fn set_speed_up_switch(mut self, speed_up_switch: SpeedUpSwitch) -> Configuration<T> {
+        self.speed_up_switch = speed_up_switch;
+        self
+    }
+
+    pub fn set_force_legacy(mut self, force_legacy: bool) -> Configuration<T> {
+        self.force_legacy = force_legacy;
+        self
+    }
+
+    pub fn set_human_logs(mut self, human_logs: bool) -> Configuration<T> {
+        self.human_logs = human_logs;
+        self
+    }
+
+    pub fn set_adaptive_mining_time(mut self, adaptive_mining_time: bool) -> Configuration<T> {
+        self.adaptive_mining_time = adaptive_mining_time;
+        self
+    }
+
+    pub fn set_validate_receipts(mut self, validate_receipts: bool) -> Configuration<T> {
+        self.validate_receipts = validate_receipts;
+        self
+    }
+
+    pub fn set_include_raw_transactions(
+        mut self,
+        include_raw_transactions: bool,
+    ) -> Configuration<T> {
+        self.include_raw_transactions = include_raw_transactions;
+        self
+    }
+
+    pub fn set_nonce_management(mut self, nonce_management: NonceManagement) -> Configuration<T> {
+        self.nonce_management = nonce_management;
+        self
+    }
+
+    pub fn set_patience(mut self, patience: Option<Patience>) -> Configuration<T> {
+        self.patience = patience;
+        self
+    }
+
+    pub fn set_max_fee_cap(mut self, max_fee_cap: Option<Wei>) -> Configuration<T> {
+        self.max_fee_cap = max_fee_cap;
+        self
+    }
+
+    pub fn set_slow_database_threshold(
+        mut self,
+        slow_database_threshold: Option<Duration>,
+    ) -> Configuration<T> {
+        self.slow_database_threshold = slow_database_threshold;
+        self
+    }
+
+    /// Sets the block time and the transaction mining time to the values
+    /// recommended for the chain (see `Chain::average_block_time`), if known.
+    pub fn set_recommended_values(mut self, chain: &Chain) -> Configuration<T> {
+        if let Some(block_time) = chain.average_block_time() {
+            self.block_time = block_time;
+            self.transaction_mining_time = block_time * RECOMMENDED_MINING_BLOCKS;
+        }
+        self
+    }
+
+    /// Checks the configuration for the chain. Returns an error for values
+    /// that break the manager, and warnings for values that are far from the
+    /// ones recommended for the chain.
+    ///
+    /// Zero block and mining times are only valid for development chains
+    /// (which mine transactions instantly, so the block time is not checked
+    /// against the chain's average).
+    pub fn validate(&self, chain: &Chain) -> Result<Vec<ConfigurationWarning>, ConfigurationError> {
+        if !chain.is_dev() {
+            if self.block_time.is_zero() {
+                return Err(ConfigurationError::ZeroBlockTime);
+            }
+            if self.transaction_mining_time.is_zero() {
+                return Err(ConfigurationError::ZeroTransactionMiningTime);
+            }
+        }
+        if let Some(max_fee_headroom) = self.max_fee_headroom {
+            if !(1. ..).contains(&max_fee_headroom) {
+                return Err(ConfigurationError::InvalidMaxFeeHeadroom(max_fee_headroom));
+            }
+        }
+        if let Some(base_fee_multiplier) = self.base_fee_multiplier {
+            if !(1. ..).contains(&base_fee_multiplier) {
+                return Err(ConfigurationError::InvalidBaseFeeMultiplier(
+                    base_fee_multiplier,
+                ));
+            }
+        }
+        let PriorityFeePercentiles {
+            low,
+            normal,
+            high,
+            asap,
+        } = self.priority_fee_percentiles;
+        for percentile in [low, normal, high, asap] {
+            if !(0. ..=100.).contains(&percentile) {
+                return Err(ConfigurationError::InvalidPriorityFeePercentile(percentile));
+            }
+        }
+        let GasPriceMultipliers {
+            low,
+            normal,
+            high,
+            asap,
+        } = self.gas_price_multipliers;
+        for multiplier in [low, normal, high, asap] {
+            if multiplier.is_nan() || multiplier <= 0. {
+                return Err(ConfigurationError::InvalidGasPriceMultiplier(multiplier));
+            }
+        }
+        if let Some(Patience { factor, .. }) = self.patience {
+            if !(1. ..).contains(&factor) {
+                return Err(ConfigurationError::InvalidPatienceFactor(factor));
+            }
+        }
+
+        let mut warnings = Vec::new();
+        if let Some(recommended) = chain.average_block_time().filter(|_| !chain.is_dev()) {
+            let configured = self.block_time;
+            if configured > recommended * 2 || configured * 2 < recommended {
+                warnings.push(ConfigurationWarning::BlockTimeMismatch {
+                    configured,
+                    recommended,
+                });
+            }
+        }
+        if self.transaction_mining_time < self.block_time {
+            warnings.push(ConfigurationWarning::MiningTimeBelowBlockTime {
+                transaction_mining_time: self.transaction_mining_time,
+                block_time: self.block_time,
+            });
+        }
+        Ok(warnings)
+    }
+}
+
+impl Default for Configuration<DefaultTime> {
+    fn default() -> Self {
+        Self::new(DefaultTime)
+    }
+}
+
+/// Who assigns the nonces of the transactions (see
+/// `Configuration::nonce_management`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonceManagement {
+    /// The manager uses the sender's pending transaction count.
+    #[default]
+    Internal,
+
+    /// The provider assigns them (e.g., an ethers `NonceManagerMiddleware`,
+    /// for nonces shared with transactions sent outside the manager): the
+    /// manager asks it to fill the nonce of each new transaction (with
+    /// `Middleware::fill_transaction`), and persists the nonce, which its
+    /// resubmissions and replacements reuse.
+    Provider,
+}
+
+/// What the manager does when a transaction is not mined within the wait time
+/// (see `Configuration::resubmission_mode`). Explicit speed-ups (see
+/// `SpeedUpSwitch`) bump the fees regardless.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResubmissionMode {
+    /// Resubmits it with the gas oracle's current fees, bumped over the last
+    /// submission's (replace-by-fee).
+    #[default]
+    BumpFees,
+
+    /// Rebroadcasts the last submission as is, for chains that do not need or
+    /// support replacements (e.g., some L2 sequencers). Transactions recovered
+    /// from the database are only waited for until they are resubmitted.
+    RebroadcastOnly,
+
+    /// Never resubmits it, only waits for it to be mined.
+    Disabled,
+}
+
+/// How the wait before resubmitting a transaction grows (see
+/// `Configuration::patience`). During sustained congestion, resubmitting at a
+/// fixed interval creates long chains of replacements that compete with each
+/// other.
+///
+/// The wait before the Nth resubmission is the usual wait (see
+/// `Configuration::transaction_mining_time`) multiplied by `factor` N - 1
+/// times, up to `max_wait`. The number of resubmissions is persisted with the
+/// transaction, so a recovered transaction continues the schedule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Patience {
+    /// The growth of the wait on each resubmission (at least 1).
+    pub factor: f64,
+
+    /// The longest wait before a resubmission (unless the usual wait is
+    /// longer).
+    pub max_wait: Duration,
+}
+
+impl Patience {
+    pub fn new(factor: f64, max_wait: Duration) -> Patience {
+        Patience { factor, max_wait }
+    }
+
+    /// The wait before the next resubmission, after the given number of them.
+    pub fn wait_time(&self, wait_time: Duration, resubmissions: u32) -> Duration {
+        let factor = self.factor.powi(resubmissions.min(i32::MAX as u32) as i32);
+        match Duration::try_from_secs_f64(wait_time.as_secs_f64() * factor) {
+            Ok(patient_wait_time) => patient_wait_time.min(self.max_wait).max(wait_time),
+            Err(_) => self.max_wait.max(wait_time),
+        }
+    }
+}
+
+/// Maps each priority level to a fee history reward percentile (from 0 to 100).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PriorityFeePercentiles {
+    pub low: f64,
+    pub normal: f64,
+    pub high: f64,
+    pub asap: f64,
+}
+
+impl PriorityFeePercentiles {
+    /// Custom priorities use their own percentile.
+    pub fn get(&self, priority: Priority) -> f64 {
+        match priority {
+            Priority::Low => self.low,
+            Priority::Normal => self.normal,
+            Priority::High => self.high,
+            Priority::ASAP => self.asap,
+            Priority::Custom(_) => priority.percentile().unwrap() as f64,
+        }
+    }
+}
+
+impl Default for PriorityFeePercentiles {
+    fn default() -> Self {
+        Self {
+            low: 10.,
+            normal: 25.,
+            high: 50.,
+            asap: 90.,
+        }
+    }
+}
+
+/// Maps each priority level to a multiplier of the provider's gas price. By
+/// default, no priority pays less than the gas price, as the transactions
+/// priced below it may never be mined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GasPriceMultipliers {
+    pub low: f64,
+    pub normal: f64,
+    pub high: f64,
+    pub asap: f64,
+}
+
+impl GasPriceMultipliers {
+    /// The multipliers of custom priorities are interpolated between the ones
+    /// of the fixed levels (see `Priority::LEVEL_PERCENTILES`).
+    pub fn get(&self, priority: Priority) -> f64 {
+        match priority {
+            Priority::Low => self.low,
+            Priority::Normal => self.normal,
+            Priority::High => self.high,
+            Priority::ASAP => self.asap,
+            Priority::Custom(_) => {
+                let percentile = priority.percentile().unwrap() as f64;
+                let points = Priority::LEVEL_PERCENTILES
+                    .map(|(level, level_percentile)| (level_percentile as f64, self.get(level)));
+                let (first, last) = (points[0], points[points.len() - 1]);
+                if percentile <= first.0 {
+                    return first.1;
+                }
+                if percentile >= last.0 {
+                    return last.1;
+                }
+                points
+                    .windows(2)
+                    .find(|window| percentile <= window[1].0)
+                    .map(|window| {
+                        let ((x0, y0), (x1, y1)) = (window[0], window[1]);
+                        y0 + (y1 - y0) * (percentile - x0) / (x1 - x0)
+                    })
+                    .unwrap()
+            }
+        }
+    }
+}
+
+impl Default for GasPriceMultipliers {
+    fn default() -> Self {
+        Self {
+            low: 1.,
+            normal: 1.,
+            high: 1.25,
+            asap: 1.5,
+        }
+    }
+}
+
+/// Configuration values that break the manager (see `Configuration::validate`).
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum ConfigurationError {
+    #[error("the block time is zero (the manager would busy-loop polling for receipts)")]
+    ZeroBlockTime,
+
+    #[error(
+        "the transaction mining time is zero (the manager would resubmit transactions immediately)"
+    )]
+    ZeroTransactionMiningTime,
+
+    #[error("invalid max fee headroom {0} (it must be at least 1)")]
+    InvalidMaxFeeHeadroom(f64),
+
+    #[error("invalid base fee multiplier {0} (it must be at least 1)")]
+    InvalidBaseFeeMultiplier(f64),
+
+    #[error("invalid priority fee percentile {0} (it must be between 0 and 100)")]
+    InvalidPriorityFeePercentile(f64),
+
+    #[error("invalid gas price multiplier {0} (it must be positive)")]
+    InvalidGasPriceMultiplier(f64),
+
+    #[error("invalid patience factor {0} (it must be at least 1)")]
+    InvalidPatienceFactor(f64),
+}
+
+/// Configuration values that work, but likely not as intended (see
+/// `Configuration::validate`).
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum ConfigurationWarning {
+    #[error("the block time ({configured:?}) is far from the chain's average ({recommended:?})")]
+    BlockTimeMismatch {
+        configured: Duration,
+        recommended: Duration,
+    },
+
+    #[error("the transaction mining time ({transaction_mining_time:?}) is shorter than the block time ({block_time:?}), so transactions are resubmitted before they can be mined")]
+    MiningTimeBelowBlockTime {
+        transaction_mining_time: Duration,
+        block_time: Duration,
+    },
+}
+
+// ------------------------------------------------------------------------------------------------
+// Chain
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Chain {
+    pub id: u64,
+    pub is_legacy: bool,
+
+    /// Human-friendly name of the chain (e.g., "Ethereum"), for messages.
+    pub name: Option<&'static str>,
+
+    /// The native currency, for rendering values (e.g., costs and balances).
+    pub currency: Option<Currency>,
+
+    /// Base URL of the chain's block explorer (e.g., "https://etherscan.io").
+    /// If `None`, the one in the ethers chain registry is used (if any).
+    pub explorer_url: Option<&'static str>,
+
+    /// Confirmations required by default for the chain's transactions (see
+    /// `Chain::default_confirmations`).
+    pub confirmations: Option<usize>,
+}
+
+/// The native currency of a chain.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Currency {
+    pub symbol: &'static str,
+    pub decimals: u8,
+}
+
+impl Chain {
+    /// For chains that implement the EIP1559.
+    pub const fn new(id: u64) -> Chain {
+        Self {
+            id,
+            is_legacy: false,
+            name: None,
+            currency: None,
+            explorer_url: None,
+            confirmations: None,
+        }
+    }
+
+    /// For chains that do not implement the EIP1559.
+    pub const fn legacy(id: u64) -> Chain {
+        Self {
+            is_legacy: true,
+            ..Chain::new(id)
+        }
+    }
+
+    pub const fn with_name(mut self, name: &'static str) -> Chain {
+        self.name = Some(name);
+        self
+    }
+
+    pub const fn with_currency(mut self, symbol: &'static str, decimals: u8) -> Chain {
+        self.currency = Some(Currency { symbol, decimals });
+        self
+    }
+
+    pub const fn with_explorer_url(mut self, explorer_url: &'static str) -> Chain {
+        self.explorer_url = Some(explorer_url);
+        self
+    }
+
+    pub const fn with_confirmations(mut self, confirmations: usize) -> Chain {
+        self.confirmations = Some(confirmations);
+        self
+    }
+
+    /// Confirmations required for the transactions sent without an explicit
+    /// number (see `SendOptions`): the chain's configured number, if any; none
+    /// for development chains, which only mine blocks with transactions; and
+    /// `DEFAULT_CONFIRMATIONS` (12) for the other chains.
+    pub fn default_confirmations(&self) -> usize {
+        match self.confirmations {
+            Some(confirmations) => confirmations,
+            None if self.is_dev() => 0,
+            None => DEFAULT_CONFIRMATIONS,
+        }
+    }
+
+    /// Link to the transaction in the chain's block explorer, if it is known.
+    pub fn transaction_url(&self, hash: H256) -> Option<String> {
+        let explorer_url = self.explorer_url.or_else(|| {
+            ethers::types::Chain::try_from(self.id)
+                .ok()
+                .and_then(|chain| chain.etherscan_urls())
+                .map(|(_, explorer_url)| explorer_url)
+        })?;
+        Some(format!(
+            "{}/tx/{:?}",
+            explorer_url.trim_end_matches('/'),
+            hash
+        ))
+    }
+
+    /// Renders the value in the native currency (e.g., "0.5 ETH"), or in wei
+    /// if the currency is unknown.
+    pub fn format_value(&self, value: impl Into<Wei>) -> String {
+        formatting::value(self, value.into())
+    }
+
+    /// Average block time of the chain, if it is in the ethers chain registry.
+    pub fn average_block_time(&self) -> Option<Duration> {
+        ethers::types::Chain::try_from(self.id)
+            .ok()
+            .and_then(|chain| chain.average_blocktime_hint())
+    }
+
+    /// Whether the chain is a local development chain (geth's dev mode, anvil,
+    /// or hardhat).
+    pub fn is_dev(&self) -> bool {
+        matches!(
+            ethers::types::Chain::try_from(self.id),
+            Ok(ethers::types::Chain::Dev | ethers::types::Chain::AnvilHardhat)
+        )
+    }
+}
+
+/// The chain's name and id (e.g., "Ethereum (1)"), or just its id.
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "{} ({})", name, self.id),
+            None => write!(f, "chain {}", self.id),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// PauseSwitch
+// ------------------------------------------------------------------------------------------------
+
+/// Shared switch that pauses and resumes managers (e.g., during maintenance
+/// windows of the provider or gas price spikes).
+///
+/// While paused, a manager does not submit (or resubmit, escalating the fees)
+/// transactions, but keeps watching whether the submitted ones get mined.
+#[derive(Clone, Debug, Default)]
+pub struct PauseSwitch(Arc<AtomicBool>);
+
+impl PauseSwitch {
+    pub fn new() -> PauseSwitch {
+        PauseSwitch::default()
+    }
+
+    pub fn pause(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// SpeedUpSwitch
+// ------------------------------------------------------------------------------------------------
+
+/// Shared switch that makes managers resubmit their in-flight transactions
+/// right away at higher fees, without waiting for the transaction mining time
+/// (e.g., in urgent situations).
+///
+/// The fees of the resubmission are the gas oracle's or the last submission's
+/// multiplied by the requested multiplier, whichever are higher. Multipliers
+/// below the minimum bump nodes require for replacements (10%, or the bump of
+/// the node's client; see `Configuration::replacement_rules`) are raised to
+/// it. Requests made while paused are served once the manager resumes.
+#[derive(Clone, Debug, Default)]
+pub struct SpeedUpSwitch(Arc<Mutex<Option<f64>>>);
+
+impl SpeedUpSwitch {
+    pub fn new() -> SpeedUpSwitch {
+        SpeedUpSwitch::default()
+    }
+
+    /// Requests a speed-up (replacing any request not yet served).
+    pub fn speed_up(&self, multiplier: f64) {
+        *self.0.lock().unwrap() = Some(multiplier.max(REPLACEMENT_FEE_BUMP));
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+
+    fn take(&self, replacement_fee_bump: f64) -> Option<f64> {
+        self.0
+            .lock()
+            .unwrap()
+            .take()
+            .map(|multiplier| multiplier.max(replacement_fee_bump))
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// SendOptions
+// ------------------------------------------------------------------------------------------------
+
+/// Options of a transaction send (see `Manager::send`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SendOptions {
+    /// How many blocks must be mined on top of the transaction's block for it
+    /// to be confirmed. If `None`, the chain's default is used (see
+    /// `Chain::default_confirmations`).
+    pub confirmations: Option<usize>,
+
+    pub priority: Priority,
+}
+
+impl SendOptions {
+    pub fn new() -> SendOptions {
+        SendOptions::default()
+    }
+
+    pub fn set_confirmations(mut self, confirmations: usize) -> SendOptions {
+        self.confirmations = Some(confirmations);
+        self
+    }
+
+    pub fn set_priority(mut self, priority: Priority) -> SendOptions {
+        self.priority = priority;
+        self
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// ConfirmationUpdate
+// ------------------------------------------------------------------------------------------------
+
+/// Progress of a mined transaction towards its required confirmations (see
+/// `Manager::confirmation_updates`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfirmationUpdate {
+    pub nonce: U256,
+    pub hash: H256,
+
+    /// The block the transaction was mined in.
+    pub block_number: U64,
+
+    /// The latest block seen by the manager.
+    pub latest_block: U64,
+
+    /// How many blocks were mined on top of the transaction's block so far
+    /// (may go back down after a reorg).
+    pub depth: usize,
+
+    /// How many confirmations the transaction requires.
+    pub confirmations: usize,
+
+    /// Estimated time until the transaction is confirmed (see
+    /// `ConfirmationEstimator`).
+    pub eta: Duration,
+}
+
+impl ConfirmationUpdate {
+    pub fn is_confirmed(&self) -> bool {
+        self.depth >= self.confirmations
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// ConfirmationEstimator
+// ------------------------------------------------------------------------------------------------
+
+/// Estimates how long a transaction will take to be confirmed, from its
+/// expected mining time (the gas oracle's estimate, the observed one, or the
+/// configured one) and the block times the manager observes as blocks arrive.
+///
+/// The estimates are exposed in the `Submitted` events and in the
+/// confirmation updates (see `Manager::confirmation_updates`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConfirmationEstimator {
+    observed_block_time: Option<Duration>,
+    last_block: Option<(U64, Instant)>,
+}
+
+impl ConfirmationEstimator {
+    /// Records the latest block, refining the observed block time with the
+    /// time elapsed since the previous block was recorded.
+    pub fn observe_block<T: Time>(&mut self, block: U64, time: &T) {
+        match self.last_block {
+            Some((last_block, _)) if block <= last_block => {}
+            Some((last_block, at)) => {
+                let blocks = (block - last_block).low_u32().max(1);
+                let sample = time.elapsed(at) / blocks;
+                self.observed_block_time = Some(match self.observed_block_time {
+                    Some(observed_block_time) => {
+                        observed_block_time.mul_f64(1. - OBSERVED_BLOCK_TIME_WEIGHT)
+                            + sample.mul_f64(OBSERVED_BLOCK_TIME_WEIGHT)
+                    }
+                    None => sample,
+                });
+                self.last_block = Some((block, Instant::now()));
+            }
+            None => self.last_block = Some((block, Instant::now())),
+        }
+    }
+
+    /// Moving average of the block times observed so far, if any.
+    pub fn observed_block_time(&self) -> Option<Duration> {
+        self.observed_block_time
+    }
+
+    /// Estimated time until the transaction has the given confirmations. A
+    /// pending transaction (without a `depth`) is expected to be mined after
+    /// what is left of its mining time (at least a block); a mined one, after
+    /// the missing confirmations. The block time defaults to `block_time`
+    /// until one is observed.
+    pub fn eta(
+        &self,
+        block_time: Duration,
+        mining_time: Duration,
+        waited: Duration,
+        depth: Option<usize>,
+        confirmations: usize,
+    ) -> Duration {
+        let block_time = self.observed_block_time.unwrap_or(block_time);
+        match depth {
+            Some(depth) => block_time * confirmations.saturating_sub(depth) as u32,
+            None => {
+                mining_time.saturating_sub(waited).max(block_time)
+                    + block_time * confirmations as u32
+            }
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// SendOutcome
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SendOutcome {
+    /// The transaction was mined and has enough confirmations.
+    Confirmed(TransactionReceipt),
+
+    /// The transaction's precondition did not hold. If the transaction had
+    /// already been submitted, contains the receipt of its cancellation.
+    Skipped(Option<TransactionReceipt>),
+}
+
+impl SendOutcome {
+    pub fn receipt(self) -> Option<TransactionReceipt> {
+        match self {
+            SendOutcome::Confirmed(receipt) => Some(receipt),
+            SendOutcome::Skipped(receipt) => receipt,
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// SendTimings
+// ------------------------------------------------------------------------------------------------
+
+/// How long a transaction took to be mined and confirmed, as observed by the
+/// manager (see `Manager::last_timings` and `Event::Confirmed`). The times
+/// are measured with the configuration's `Time`, so they are rounded up to the
+/// polling interval (the block time).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SendTimings {
+    /// From the first submission to the first check that saw it mined. For
+    /// transactions recovered from the database, counts from the recovery.
+    /// None if the manager did not see it being mined.
+    pub mining_time: Option<Duration>,
+
+    /// From the first check that saw the transaction mined to the check that
+    /// saw it with enough confirmations.
+    pub confirmation_time: Option<Duration>,
+
+    /// The round-trips to the database while sending the transaction (until
+    /// it was confirmed).
+    pub database: DatabaseStats,
+}
+
+// ------------------------------------------------------------------------------------------------
+// PendingResolution
+// ------------------------------------------------------------------------------------------------
+
+/// How `Manager::new` resolved the transaction left pending in the database.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PendingResolution {
+    /// There was no pending transaction.
+    None,
+
+    /// The manager waited for the pending transaction (and resent it, if
+    /// needed) until it was confirmed.
+    ConfirmedNow(TransactionReceipt),
+
+    /// The pending transaction had already been mined, with enough
+    /// confirmations, before the manager started.
+    AlreadyMined(TransactionReceipt),
+
+    /// The pending transaction's precondition did not hold (see
+    /// `SendOutcome::Skipped`).
+    Skipped(Option<TransactionReceipt>),
+
+    /// The pending transaction's nonce was consumed by a transaction the
+    /// manager did not send, so the pending transaction (whose latest hash is
+    /// given) was abandoned.
+    RecoveredExternally { hash: Option<H256> },
+}
+
+impl PendingResolution {
+    pub fn receipt(self) -> Option<TransactionReceipt> {
+        match self {
+            PendingResolution::ConfirmedNow(receipt) | PendingResolution::AlreadyMined(receipt) => {
+                Some(receipt)
+            }
+            PendingResolution::Skipped(receipt) => receipt,
+            PendingResolution::None | PendingResolution::RecoveredExternally { .. } => None,
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// HandoffToken
+// ------------------------------------------------------------------------------------------------
+
+/// Proof that a manager handed the transaction in flight over to another
+/// instance (see `Manager::hand_off` and `Manager::take_over`). It is stored
+/// with the state in the database until the new instance takes over.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandoffToken(String);
+
+impl HandoffToken {
+    /// A token unique to the state and the moment of the handoff.
+    fn new(state: &PersistentState) -> HandoffToken {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let mut preimage = serde_json::to_vec(state).expect("the state is serializable");
+        preimage.extend(nanos.to_be_bytes());
+        preimage.extend(std::process::id().to_be_bytes());
+        HandoffToken(hex::encode(keccak256(preimage)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for HandoffToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for HandoffToken {
+    fn from(token: String) -> HandoffToken {
+        HandoffToken(token)
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// CheckSummary
+// ------------------------------------------------------------------------------------------------
+
+/// The state of the pending transaction in the database, as seen by a single
+/// check (see `Manager::check_once`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckSummary {
+    /// There is no pending transaction.
+    Idle,
+
+    /// None of the transactions sent for the pending transaction was mined.
+    Pending {
+        nonce: U256,
+
+        /// How many transactions were sent (submitted, cancellation, and
+        /// replaced ones).
+        submitted: usize,
+
+        /// Whether the manager would resubmit the transaction (it waited too
+        /// much, or a speed-up was requested).
+        resubmission_due: bool,
+    },
+
+    /// One of the transactions was mined, and is confirmed if its depth
+    /// reached the required confirmations.
+    Mined {
+        receipt: Box<TransactionReceipt>,
+        update: ConfirmationUpdate,
+    },
+}
+
+impl CheckSummary {
+    pub fn is_confirmed(&self) -> bool {
+        matches!(self, CheckSummary::Mined { update, .. } if update.is_confirmed())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// CostEstimate
+// ------------------------------------------------------------------------------------------------
+
+/// Estimated cost of sending a transaction (see `Manager::estimate_cost`).
+#[derive(Clone, Copy, Debug)]
+pub struct CostEstimate {
+    pub gas_limit: GasLimit,
+    pub gas_info: GasInfo,
+
+    /// Upper bound of the total cost (value included), assuming all the gas is
+    /// used at the max fee (or gas price, for legacy transactions).
+    pub max_cost: Wei,
+
+    /// The max cost in USD, if the configuration has a price feed.
+    pub max_cost_usd: Option<f64>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Manager
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub struct Manager<M: Middleware, GO: GasOracle, DB: Database, T: Time> {
+    provider: M,
+    gas_oracle: GO,
+    db: DB,
+    chain: Chain,
+    configuration: Configuration<T>,
+    in_flight: InFlight,
+
+    /// Version of the state in the database (see
+    /// `Database::set_state_if_version`).
+    db_version: u64,
+
+    /// Receivers of the confirmation updates (see
+    /// `Manager::confirmation_updates`).
+    confirmation_watchers: Vec<UnboundedSender<ConfirmationUpdate>>,
+
+    /// The transaction being driven by the caller (see `Manager::drive`).
+    driven: Option<driven::Driven>,
+
+    /// Moving average of the mining times of the previous sends (see
+    /// `Manager::observed_mining_time`).
+    observed_mining_time: Option<Duration>,
+
+    /// The timings of the last confirmed send.
+    last_timings: Option<SendTimings>,
+
+    /// Whether `Manager::shutdown` was called (see the `Drop` implementation).
+    shut_down: bool,
+
+    /// The round-trips to the database since the manager was created (see
+    /// `Manager::database_stats`).
+    database_stats: Mutex<DatabaseStats>,
+
+    /// Estimates the confirmation times (see `Manager::confirmation_estimator`).
+    confirmation_estimator: ConfirmationEstimator,
+
+    /// The client whose replacement rules the manager follows (see
+    /// `Configuration::replacement_rules`).
+    node_client: NodeClient,
+}
+
+/// In-memory information about the transaction being currently processed.
+#[derive(Debug, Default)]
+struct InFlight {
+    first_submission: Option<Instant>,
+    stalled: bool,
+
+    /// How many times the manager checked whether the transaction was mined.
+    polls: usize,
+
+    /// The transaction hash and block hash of the last receipt (to detect
+    /// reorgs).
+    mined: Option<(H256, Option<H256>)>,
+
+    /// The hashes of the last anomaly of several mined transactions (to flag
+    /// it once).
+    multiple_receipts: Option<Vec<H256>>,
+
+    /// Whether the transaction was recovered from the database and not resent
+    /// yet (its fees continue from the last persisted attempt).
+    recovered: bool,
+
+    /// When the manager first saw the transaction mined (in its current
+    /// block), and how long after the first submission.
+    mined_at: Option<(Instant, Option<Duration>)>,
+
+    /// The block time estimated by the gas oracle for this send, which
+    /// overrides the configured one (see `Manager::block_time`).
+    block_time: Option<Duration>,
+
+    /// The mining time estimated by the gas oracle for this send.
+    mining_time: Option<Duration>,
+
+    /// The round-trips to the database while sending the transaction.
+    database: DatabaseStats,
+
+    /// A broadcast transaction whose next resubmission is sent anyway (its
+    /// last one was skipped, or it was dropped from the chain by a reorg; see
+    /// `Manager::skip_broadcast`).
+    broadcast_due: Option<H256>,
+
+    /// Whether the node refused further replacements of the transaction (see
+    /// `Event::ReplacementLimitReached`).
+    replacement_limit_reached: bool,
+
+    /// The last errors of the node to the submissions (see `SendReport`).
+    rpc_errors: Vec<String>,
+
+    /// The last submission the node accepted (rebroadcast as is with
+    /// `ResubmissionMode::RebroadcastOnly`).
+    last_broadcast: Option<SignedTransaction>,
+
+    /// The provider's latest block, and since when (see
+    /// `Manager::check_provider_freshness`).
+    latest_block: Option<(U64, Instant)>,
+
+    /// Whether the manager only watches the transaction, which it did not
+    /// send (see `Manager::wait_for`): it never resubmits nor journals it.
+    watching: bool,
+}
+
+impl InFlight {
+    /// Keeps the last `SEND_REPORT_RPC_ERRORS` errors of the node.
+    fn record_rpc_error(&mut self, err: &impl fmt::Display) {
+        self.rpc_errors.push(err.to_string());
+        if self.rpc_errors.len() > SEND_REPORT_RPC_ERRORS {
+            self.rpc_errors.remove(0);
+        }
+    }
+}
+
+/// Public functions.
+impl<M: Middleware, GO: GasOracle, DB: Database, T: Time> Manager<M, GO, DB, T>
+where
+    M: Send + Sync,
+    GO: Send + Sync,
+    DB: Send + Sync,
+    T: Send + Sync,
+{
+    /// Sends and confirms any pending transaction persisted in the database
+    /// before returning an instance of the transaction manager. In case a
+    /// pending transaction was mined, it's receipt is also returned.
+    ///
+    /// Fails with `Error::HandoffMismatch` if the pending transaction was
+    /// handed off to another instance (see `Manager::take_over`).
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn new(
+        provider: M,
+        gas_oracle: GO,
+        db: DB,
+        chain: Chain,
+        configuration: Configuration<T>,
+    ) -> Result<(Self, PendingResolution), Error<M, GO, DB>> {
+        Self::start(provider, gas_oracle, db, chain, configuration, None).await
+    }
+
+    /// Takes over the transaction handed off by another instance (see
+    /// `Manager::hand_off`), resuming it like `Manager::new` does.
+    ///
+    /// The token is consumed with a versioned write (see
+    /// `Database::set_state_if_version`), after which the old instance can no
+    /// longer write the state. With databases whose versioned writes are atomic
+    /// (like the `FileSystemDatabase`), only one instance takes over, and the
+    /// others fail with a version conflict (`Error::Database`). With databases
+    /// that do not track versions, this is only best-effort. Fails with
+    /// `Error::HandoffMismatch` if the state was not handed off with the token
+    /// (e.g., another instance took over). If the database has no state, there
+    /// is nothing to resume.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn take_over(
+        provider: M,
+        gas_oracle: GO,
+        db: DB,
+        chain: Chain,
+        configuration: Configuration<T>,
+        token: HandoffToken,
+    ) -> Result<(Self, PendingResolution), Error<M, GO, DB>> {
+        Self::start(provider, gas_oracle, db, chain, configuration, Some(token)).await
+    }
+
+    /// Instantiates the manager, checking its configuration, its chain, and
+    /// its provider.
+    async fn init(
         provider: M,
         gas_oracle: GO,
         db: DB,
@@ -220,327 +1871,2746 @@ where
             db,
             chain,
             configuration,
+            in_flight: InFlight::default(),
+            db_version: 0,
+            confirmation_watchers: Vec::new(),
+            driven: None,
+            observed_mining_time: None,
+            last_timings: None,
+            shut_down: false,
+            database_stats: Mutex::default(),
+            confirmation_estimator: ConfirmationEstimator::default(),
+            node_client: NodeClient::default(),
+        };
+
+        manager.validate_configuration()?;
+        manager.check_chain_id().await?;
+        manager.check_gas_oracle_chain()?;
+        manager.detect_node_client().await;
+        Ok(manager)
+    }
+
+    async fn start(
+        provider: M,
+        gas_oracle: GO,
+        db: DB,
+        chain: Chain,
+        configuration: Configuration<T>,
+        handoff: Option<HandoffToken>,
+    ) -> Result<(Self, PendingResolution), Error<M, GO, DB>> {
+        let mut manager = Self::init(provider, gas_oracle, db, chain, configuration).await?;
+
+        trace!("Instantiating a new transaction manager => {:#?}", manager);
+
+        let start = Instant::now();
+        let result = manager
+            .db
+            .get_versioned_state()
+            .instrument(trace_span!("get_versioned_state"))
+            .await;
+        manager.observe_database(DatabaseOperation::Get, start);
+        let mut state = match result.map_err(Error::Database)? {
+            Some((state, version)) => {
+                manager.db_version = version;
+                state
+            }
+            None => return Ok((manager, PendingResolution::None)),
+        };
+
+        if state.handoff != handoff {
+            return Err(Error::HandoffMismatch {
+                expected: handoff,
+                found: state.handoff,
+            });
+        }
+        if let Some(token) = state.handoff.take() {
+            info!(
+                "Taking over the handed off transaction (token = {}).",
+                token
+            );
+            manager.set_state(&state).await?;
+        }
+
+        warn!("Dealing with previous state => {:#?}", state);
+        manager.check_signer(state.tx_data.transaction.from)?;
+
+        let mut rebroadcast = false;
+        {
+            let current_nonce = manager.get_nonce(state.tx_data.transaction.from).await?;
+            let expected_nonce = state.tx_data.nonce;
+
+            // Was the nonce consumed by one of the tracked transactions?
+            if current_nonce > expected_nonce
+                && manager.get_mined_transaction(&mut state).await?.is_none()
+            {
+                let mined_nonce = manager
+                    .get_mined_nonce(state.tx_data.transaction.from)
+                    .await?;
+                if mined_nonce > expected_nonce {
+                    let hash = state.txs_hashes().last().copied();
+                    warn!(
+                        "The nonce `{}` of the pending transaction (hash = {:?}) was consumed \
+                        by a transaction the manager did not send. Abandoning it.",
+                        expected_nonce, hash
+                    );
+                    manager.clear_state().await?;
+                    return Ok((manager, PendingResolution::RecoveredExternally { hash }));
+                }
+
+                error!(
+                    "Nonce too low! Current is `{}`, expected `{}`",
+                    current_nonce, expected_nonce
+                );
+
+                return Err(Error::NonceTooLow {
+                    current_nonce,
+                    expected_nonce,
+                });
+            }
+
+            // None of the tracked transactions was mined yet.
+            if current_nonce <= expected_nonce {
+                rebroadcast = manager.rebroadcast_prepared_txs(&mut state).await?;
+            }
+        }
+
+        // The actual first submission happened before the recovery.
+        manager.in_flight.first_submission = Some(first_submission(&state));
+        manager.in_flight.recovered = true;
+        let submitted = state.txs_hashes().count();
+        let wait_time = manager.get_wait_time(&state, None);
+        let outcome = manager
+            .confirm_transaction(&mut state, wait_time, false)
+            .await?;
+        let resolution = match outcome {
+            SendOutcome::Confirmed(transaction_receipt) => {
+                manager.emit_confirmed(&state, &transaction_receipt);
+                // Confirmed by the first check, without resending.
+                if !rebroadcast
+                    && manager.in_flight.polls == 1
+                    && state.txs_hashes().count() == submitted
+                {
+                    PendingResolution::AlreadyMined(transaction_receipt)
+                } else {
+                    PendingResolution::ConfirmedNow(transaction_receipt)
+                }
+            }
+            SendOutcome::Skipped(transaction_receipt) => {
+                PendingResolution::Skipped(transaction_receipt)
+            }
+        };
+        manager.clear_state().await?;
+        info!("Resolved the pending transaction => {:?}", resolution);
+
+        Ok((manager, resolution))
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn force_new(
+        provider: M,
+        gas_oracle: GO,
+        db: DB,
+        chain: Chain,
+        configuration: Configuration<T>,
+    ) -> Result<Self, Error<M, GO, DB>> {
+        let mut manager = Self::init(provider, gas_oracle, db, chain, configuration).await?;
+
+        trace!(
+            "Forcing the instantiation of a new transaction manager => {:#?}",
+            manager
+        );
+
+        trace!("Clearing DB state");
+        manager.clear_state().await?;
+
+        Ok(manager)
+    }
+
+    /// Instantiates a new transaction manager that, instead of recovering the
+    /// transaction left pending in the database (like `new` does), replaces
+    /// its content with the given transaction.
+    ///
+    /// The replacement reuses the pending transaction's nonce, confirmations,
+    /// and priority, and it is sent with higher fees than the pending
+    /// transaction, so nodes accept it into their transaction pools. The
+    /// manager keeps tracking the replaced transactions until either one of
+    /// them or the replacement is confirmed. Returns the receipt of the confirmed one.
+    #[tracing::instrument(level = "trace", skip_all, fields(nonce, transaction_hash))]
+    pub async fn replace_pending(
+        provider: M,
+        gas_oracle: GO,
+        db: DB,
+        chain: Chain,
+        configuration: Configuration<T>,
+        transaction: Transaction,
+    ) -> Result<(Self, TransactionReceipt), Error<M, GO, DB>> {
+        let mut manager = Self::init(provider, gas_oracle, db, chain, configuration).await?;
+
+        trace!(
+            "Instantiating a new transaction manager to replace the pending transaction => {:#?}",
+            manager
+        );
+
+        let start = Instant::now();
+        let result = manager
+            .db
+            .get_versioned_state()
+            .instrument(trace_span!("get_versioned_state"))
+            .await;
+        manager.observe_database(DatabaseOperation::Get, start);
+        let (state, version) = result
+            .map_err(Error::Database)?
+            .ok_or(Error::NoPendingTransaction)?;
+        manager.db_version = version;
+        warn!("Replacing the pending transaction => {:#?}", state);
+        Span::current().record("nonce", tracing::field::debug(state.tx_data.nonce));
+
+        let transaction = manager.resolve_recipient(transaction).await?;
+        if transaction.from != state.tx_data.transaction.from {
+            return Err(Error::ReplacementSenderMismatch {
+                pending: state.tx_data.transaction.from,
+                replacement: transaction.from,
+            });
+        }
+        manager.check_signer(transaction.from)?;
+
+        {
+            let current_nonce = manager.get_nonce(transaction.from).await?;
+            let expected_nonce = state.tx_data.nonce;
+
+            if current_nonce > expected_nonce {
+                error!(
+                    "Nonce too low! Current is `{}`, expected `{}`",
+                    current_nonce, expected_nonce
+                );
+
+                return Err(Error::NonceTooLow {
+                    current_nonce,
+                    expected_nonce,
+                });
+            }
+        }
+
+        // The replaced transactions (including cancellations) are still
+        // pending, so the manager keeps tracking them.
+        // The most recent hash goes last (see `bump_gas_info`).
+        let cancellation_txs = state.cancellation_txs.iter().flatten();
+        let replaced_txs = state
+            .replaced_txs
+            .iter()
+            .chain(&state.submitted_txs)
+            .chain(cancellation_txs)
+            .copied()
+            .collect();
+
+        // The replacement is sent unconditionally.
+        let mut state = PersistentState {
+            tx_data: StaticTxData {
+                transaction,
+                precondition: None,
+                ..state.tx_data
+            },
+            submitted_txs: SubmittedTxs::new(),
+            cancellation_txs: None,
+            replaced_txs,
+            broadcast_statuses: BTreeMap::new(),
+            attempts: state.attempts,
+            resubmissions: state.resubmissions,
+            handoff: None,
+            first_submitted_at: None,
+        };
+        let start = Instant::now();
+        let result = manager
+            .db
+            .set_state(&state)
+            .instrument(trace_span!("set_state"))
+            .await;
+        manager.observe_database(DatabaseOperation::Set, start);
+        result.map_err(Error::Database)?;
+
+        match manager.process(&mut state).await? {
+            SendOutcome::Confirmed(receipt) => Ok((manager, receipt)),
+            SendOutcome::Skipped(_) => {
+                unreachable!("transactions without preconditions are never skipped")
+            }
+        }
+    }
+
+    /// Sends a transaction and returns the receipt.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn send(
+        self,
+        transaction: Transaction,
+        options: SendOptions,
+    ) -> Result<(Self, TransactionReceipt), Error<M, GO, DB>> {
+        let (manager, outcome) = self.send_with(transaction, None, options).await?;
+        match outcome {
+            SendOutcome::Confirmed(receipt) => Ok((manager, receipt)),
+            SendOutcome::Skipped(_) => {
+                unreachable!("transactions without preconditions are never skipped")
+            }
+        }
+    }
+
+    /// Sends a transaction only while the precondition holds. The precondition
+    /// is checked right before each (re)submission. If it stops holding after
+    /// the transaction was submitted, the manager cancels the transaction by
+    /// replacing it with a no-op (an empty transfer to the sender itself).
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn send_if(
+        self,
+        transaction: Transaction,
+        precondition: Precondition,
+        options: SendOptions,
+    ) -> Result<(Self, SendOutcome), Error<M, GO, DB>> {
+        self.send_with(transaction, Some(precondition), options)
+            .await
+    }
+
+    /// Sends a transaction, splitting it with the splitter while it does not
+    /// fit in a block, and returns the receipts of the transactions sent (in
+    /// order). Before each send, the manager checks the transaction's gas
+    /// estimate against the latest block's gas limit. Transactions that
+    /// exceed it (or the node's gas allowance) are split, and the parts are
+    /// checked (and split) in turn.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn send_split(
+        mut self,
+        transaction: Transaction,
+        options: SendOptions,
+        splitter: &dyn Splitter,
+    ) -> Result<(Self, Vec<TransactionReceipt>), Error<M, GO, DB>> {
+        let mut receipts = Vec::new();
+        let mut transactions = vec![transaction];
+        while let Some(transaction) = transactions.pop() {
+            let transaction = self.resolve_recipient(transaction).await?;
+            if let Err(err) = self
+                .check_block_gas_limit(&transaction, options.priority)
+                .await
+            {
+                if !matches!(
+                    err,
+                    Error::ExceedsBlockGasLimit { .. } | Error::GasAllowanceExceeded(_)
+                ) {
+                    return Err(err);
+                }
+                match splitter.split(&transaction) {
+                    Some(parts) if parts.len() > 1 => {
+                        info!("Splitting the transaction into {} parts.", parts.len());
+                        transactions.extend(parts.into_iter().rev());
+                        continue;
+                    }
+                    _ => {
+                        error!("The transaction does not fit in a block and cannot be split.");
+                        return Err(err);
+                    }
+                }
+            }
+
+            let (manager, receipt) = self.send(transaction, options).await?;
+            self = manager;
+            receipts.push(receipt);
+        }
+        Ok((self, receipts))
+    }
+
+    /// Sends the transactions in order (with consecutive nonces), and returns
+    /// the result of each one, so that a failing transaction does not prevent
+    /// the ones after it from being sent. Each transaction is persisted (and
+    /// cleared) independently, as with `Manager::send`.
+    ///
+    /// A transaction that reverts while its nonce is taken (because it was
+    /// already submitted, or because the provider assigned it, see
+    /// `NonceManagement::Provider`) is cancelled with a no-op (an empty
+    /// transfer to the sender itself), so that the later nonces can be mined.
+    /// Its result is the `Error::Reverted`, unless one of its submissions was
+    /// mined before the cancellation.
+    ///
+    /// Other failures of a submitted transaction stop the batch, as its nonce
+    /// remains pending (and persisted, to be recovered by the next manager),
+    /// so the results of the transactions after it are missing. The batch
+    /// fails as a whole only if a cancellation fails.
+    #[allow(clippy::type_complexity)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn send_batch(
+        mut self,
+        transactions: Vec<Transaction>,
+        options: SendOptions,
+    ) -> Result<(Self, Vec<Result<SendOutcome, Error<M, GO, DB>>>), Error<M, GO, DB>> {
+        let mut results = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            let mut state = match self.new_state(transaction, None, options).await {
+                Ok(state) => state,
+                Err(err) => {
+                    results.push(Err(err));
+                    continue;
+                }
+            };
+            let err = match self.process(&mut state).await {
+                Ok(outcome) => {
+                    results.push(Ok(outcome));
+                    continue;
+                }
+                Err(err) => err,
+            };
+
+            let nonce_taken = !state.submitted_txs.is_empty()
+                || self.configuration.nonce_management == NonceManagement::Provider;
+            match err.inner() {
+                Error::Reverted(_) if nonce_taken => {
+                    warn!(
+                        "The transaction with nonce {:?} reverted, cancelling it.",
+                        state.tx_data.nonce
+                    );
+                    // The submissions are replaced (see `replace_pending`), so
+                    // that the cancellation outbids them.
+                    let submitted_txs = std::mem::take(&mut state.submitted_txs);
+                    state.replaced_txs = state
+                        .replaced_txs
+                        .iter()
+                        .chain(&submitted_txs)
+                        .copied()
+                        .collect();
+                    state.cancellation_txs = Some(SubmittedTxs::new());
+                    match self.process(&mut state).await? {
+                        outcome @ SendOutcome::Confirmed(_) => results.push(Ok(outcome)),
+                        SendOutcome::Skipped(_) => results.push(Err(err)),
+                    }
+                }
+                _ if !state.submitted_txs.is_empty() => {
+                    error!(
+                        "The transaction with nonce {:?} is still pending, stopping the batch.",
+                        state.tx_data.nonce
+                    );
+                    results.push(Err(err));
+                    break;
+                }
+                _ => results.push(Err(err)),
+            }
+        }
+        Ok((self, results))
+    }
+
+    /// Adopts a pending transaction sent outside the manager (e.g., manually,
+    /// with a wallet): fetches it by hash, persists it as if the manager had
+    /// sent it, and confirms it as usual, resubmitting it (with fees escalated
+    /// from its own) if it is not mined in time. The resubmissions are signed
+    /// by the provider, so it must hold the sender's key.
+    #[tracing::instrument(level = "trace", skip_all, fields(nonce, transaction_hash))]
+    pub async fn adopt(
+        mut self,
+        hash: H256,
+        options: SendOptions,
+    ) -> Result<(Self, TransactionReceipt), Error<M, GO, DB>> {
+        let adopted = self
+            .provider
+            .get_transaction(hash)
+            .instrument(trace_span!("get_transaction"))
+            .await
+            .map_err(Error::Middleware)?
+            .ok_or(Error::NotAdoptable {
+                hash,
+                reason: "not found",
+            })?;
+        let to = adopted.to.ok_or(Error::NotAdoptable {
+            hash,
+            reason: "it creates a contract",
+        })?;
+        self.check_signer(adopted.from)?;
+        Span::current().record("nonce", tracing::field::debug(adopted.nonce));
+
+        let transaction = external_transaction(&adopted, to);
+        let confirmations = options
+            .confirmations
+            .unwrap_or_else(|| self.chain.default_confirmations());
+        let mut state = PersistentState::new(StaticTxData::new(
+            transaction,
+            adopted.nonce,
+            confirmations,
+            options.priority,
+        ));
+        state.submitted_txs.add(hash);
+        if let Some(gas_info) = gas_oracle::transaction_gas_info(&adopted) {
+            state.attempts.push(Attempt { hash, gas_info });
+        }
+        info!(
+            "Adopting the transaction {:?} (nonce = {:?}).",
+            hash, adopted.nonce
+        );
+        self.set_state(&state).await?;
+
+        // As with a recovered transaction, the first submission happened
+        // before, and the resubmissions continue from its fees.
+        self.in_flight = InFlight {
+            first_submission: Some(Instant::now()),
+            recovered: true,
+            ..InFlight::default()
+        };
+        let wait_time = self.get_wait_time(&state, None);
+        let result = self.confirm_transaction(&mut state, wait_time, false).await;
+        match self.conclude(&state, result).await? {
+            SendOutcome::Confirmed(receipt) => Ok((self, receipt)),
+            SendOutcome::Skipped(_) => {
+                unreachable!("transactions without preconditions are never skipped")
+            }
+        }
+    }
+
+    /// Waits for a transaction the manager did not send (e.g., a deposit or a
+    /// third party's transaction) to be mined with the number of
+    /// confirmations of the options (or `Chain::default_confirmations`),
+    /// with the manager's confirmation logic: it picks the
+    /// canonical receipt, follows reorgs, and emits the confirmation updates
+    /// and the `Stalled`, `Reorged`, and `Confirmed` events.
+    ///
+    /// The transaction is neither persisted, nor journaled, nor resubmitted,
+    /// so one that is dropped from the pool is waited for indefinitely (bound
+    /// the wait with a timeout, if needed). Fails with
+    /// `Error::UnknownTransaction` if the node does not know the transaction.
+    #[tracing::instrument(level = "trace", skip_all, fields(nonce, transaction_hash))]
+    pub async fn wait_for(
+        mut self,
+        hash: H256,
+        options: SendOptions,
+    ) -> Result<(Self, TransactionReceipt), Error<M, GO, DB>> {
+        let watched = self
+            .provider
+            .get_transaction(hash)
+            .instrument(trace_span!("get_transaction"))
+            .await
+            .map_err(Error::Middleware)?
+            .ok_or(Error::UnknownTransaction(hash))?;
+        Span::current().record("nonce", tracing::field::debug(watched.nonce));
+        Span::current().record("transaction_hash", tracing::field::debug(hash));
+
+        // Contract creations are viewed as sent to the created contract.
+        let to = watched
+            .to
+            .unwrap_or_else(|| get_contract_address(watched.from, watched.nonce));
+        let transaction = external_transaction(&watched, to);
+        let confirmations = options
+            .confirmations
+            .unwrap_or_else(|| self.chain.default_confirmations());
+        let mut state = PersistentState::new(StaticTxData::new(
+            transaction,
+            watched.nonce,
+            confirmations,
+            options.priority,
+        ));
+        state.submitted_txs.add(hash);
+        info!(
+            "Waiting for the transaction {:?} (nonce = {:?}).",
+            hash, watched.nonce
+        );
+
+        self.in_flight = InFlight {
+            first_submission: Some(Instant::now()),
+            watching: true,
+            ..InFlight::default()
+        };
+        let result = self
+            .confirm_transaction(&mut state, Duration::MAX, false)
+            .await;
+        self.in_flight.watching = false;
+        match result? {
+            SendOutcome::Confirmed(receipt) => {
+                self.emit_confirmed(&state, &receipt);
+                Ok((self, receipt))
+            }
+            SendOutcome::Skipped(_) => {
+                unreachable!("watched transactions have no cancellations")
+            }
+        }
+    }
+
+    /// Sends a transaction (usually a contract call) and, once it is
+    /// confirmed, looks for the event `E` accepted by the filter (e.g., with
+    /// the expected parameters) among the receipt's logs, as the event is the
+    /// actual success criteria of many contract interactions. Returns the
+    /// first matching event, decoded.
+    ///
+    /// Fails with `Error::Reverted` if the transaction was mined but failed,
+    /// and with `Error::EventMissing` if it emitted no matching event (from
+    /// any contract).
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn send_and_wait_for_event<E: EthEvent>(
+        self,
+        transaction: Transaction,
+        options: SendOptions,
+        filter: impl Fn(&E) -> bool,
+    ) -> Result<(Self, E), Error<M, GO, DB>> {
+        let (manager, receipt) = self.send(transaction, options).await?;
+        if receipt.status == Some(U64::zero()) {
+            error!(
+                "The transaction {:?} was mined, but it failed.",
+                receipt.transaction_hash
+            );
+            return Err(Error::Reverted(None));
+        }
+
+        let event = receipt
+            .logs
+            .iter()
+            .filter(|log| E::is_anonymous() || log.topics.first() == Some(&E::signature()))
+            .filter_map(|log| {
+                let raw_log = RawLog::from((log.topics.clone(), log.data.to_vec()));
+                <E as EthEvent>::decode_log(&raw_log).ok()
+            })
+            .find(|event| filter(event));
+        match event {
+            Some(event) => Ok((manager, event)),
+            None => {
+                error!(
+                    "The transaction {:?} did not emit the expected `{}` event.",
+                    receipt.transaction_hash,
+                    E::name()
+                );
+                Err(Error::EventMissing {
+                    event: E::name().into_owned(),
+                    transaction_hash: receipt.transaction_hash,
+                })
+            }
+        }
+    }
+
+    /// Sends a transaction and, once it is confirmed, runs the post-condition
+    /// on its receipt (e.g., checking that the contract's storage changed, or
+    /// that a balance moved) before declaring success, for end-to-end
+    /// verification.
+    ///
+    /// Fails with `Error::PostConditionFailed` (with the receipt) if the
+    /// post-condition returns an error.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn send_and_verify<F, Fut, E>(
+        self,
+        transaction: Transaction,
+        options: SendOptions,
+        postcondition: F,
+    ) -> Result<(Self, TransactionReceipt), Error<M, GO, DB>>
+    where
+        F: FnOnce(TransactionReceipt) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: fmt::Display,
+    {
+        let (manager, receipt) = self.send(transaction, options).await?;
+        match postcondition(receipt.clone()).await {
+            Ok(()) => Ok((manager, receipt)),
+            Err(reason) => {
+                error!(
+                    "The post-condition of the transaction {:?} failed: {}.",
+                    receipt.transaction_hash, reason
+                );
+                Err(Error::PostConditionFailed {
+                    receipt: Box::new(receipt),
+                    reason: reason.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Sends a transaction with the given confirmations and priority.
+    #[deprecated(note = "use `Manager::send` with `SendOptions`")]
+    pub async fn send_transaction(
+        self,
+        transaction: Transaction,
+        confirmations: usize,
+        priority: Priority,
+    ) -> Result<(Self, TransactionReceipt), Error<M, GO, DB>> {
+        let options = SendOptions::new()
+            .set_confirmations(confirmations)
+            .set_priority(priority);
+        self.send(transaction, options).await
+    }
+
+    /// Sends a transaction with the given confirmations and priority only while
+    /// the precondition holds.
+    #[deprecated(note = "use `Manager::send_if` with `SendOptions`")]
+    pub async fn send_transaction_if(
+        self,
+        transaction: Transaction,
+        precondition: Precondition,
+        confirmations: usize,
+        priority: Priority,
+    ) -> Result<(Self, SendOutcome), Error<M, GO, DB>> {
+        let options = SendOptions::new()
+            .set_confirmations(confirmations)
+            .set_priority(priority);
+        self.send_if(transaction, precondition, options).await
+    }
+
+    /// Estimates the cost of sending the transaction with the given priority,
+    /// without sending it, so callers can decide whether it is worth sending
+    /// at current prices.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn estimate_cost(
+        &self,
+        transaction: &Transaction,
+        priority: Priority,
+    ) -> Result<CostEstimate, Error<M, GO, DB>> {
+        let transaction = self.resolve_recipient(transaction.clone()).await?;
+        let nonce = self.get_nonce(transaction.from).await?;
+        let gas_info = self.get_gas_oracle_info(priority).await?.gas_info;
+
+        let tx_data = StaticTxData::new(transaction.clone(), nonce, 0, priority);
+        let gas_limit = GasLimit(
+            *self
+                .typed_transaction(&tx_data, gas_info)
+                .await?
+                .gas()
+                .unwrap(),
+        );
+
+        let max_fee = match gas_info {
+            GasInfo::Legacy(legacy_gas_info) => legacy_gas_info.gas_price,
+            // max_fee must be set (guaranteed by get_gas_oracle_info)
+            GasInfo::EIP1559(eip1559_gas_info) => eip1559_gas_info.max_fee.unwrap(),
+        };
+        let max_cost = gas_limit * max_fee + Wei::from(transaction.value);
+
+        let max_cost_usd = self
+            .configuration
+            .price_feed
+            .as_ref()
+            .and_then(|price_feed| price_feed::to_usd(price_feed.as_ref(), max_cost.into()));
+
+        let cost_estimate = CostEstimate {
+            gas_limit,
+            gas_info,
+            max_cost,
+            max_cost_usd,
+        };
+        trace!("Cost estimate: {:?}.", cost_estimate);
+        Ok(cost_estimate)
+    }
+
+    /// Returns the manager's current configuration.
+    pub fn configuration(&self) -> &Configuration<T> {
+        &self.configuration
+    }
+
+    /// Pauses the manager (see `PauseSwitch`). As the sending functions take
+    /// ownership of the manager, use `pause_switch` to pause it while it sends.
+    pub fn pause(&self) {
+        self.configuration.pause_switch.pause();
+    }
+
+    pub fn resume(&self) {
+        self.configuration.pause_switch.resume();
+    }
+
+    /// Handle that pauses and resumes the manager (and the ones that share it).
+    pub fn pause_switch(&self) -> PauseSwitch {
+        self.configuration.pause_switch.clone()
+    }
+
+    /// Requests the manager to resubmit the in-flight transaction right away
+    /// with its fees multiplied by `multiplier` (see `SpeedUpSwitch`). As the
+    /// sending functions take ownership of the manager, use `speed_up_switch`
+    /// to speed it up while it sends.
+    pub fn speed_up(&self, multiplier: f64) {
+        self.configuration.speed_up_switch.speed_up(multiplier);
+    }
+
+    /// Handle that speeds up the transactions of the manager (and of the ones
+    /// that share it).
+    pub fn speed_up_switch(&self) -> SpeedUpSwitch {
+        self.configuration.speed_up_switch.clone()
+    }
+
+    /// How long the last transaction confirmed by `send` (or in the
+    /// caller-driven mode) took to be mined and confirmed.
+    pub fn last_timings(&self) -> Option<SendTimings> {
+        self.last_timings
+    }
+
+    /// The round-trips to the database since the manager was created (see
+    /// `SendTimings::database` for those of a single send).
+    pub fn database_stats(&self) -> DatabaseStats {
+        *self.database_stats.lock().unwrap()
+    }
+
+    /// Moving average of the mining times observed in the previous sends,
+    /// which replaces the configured `transaction_mining_time` when waiting to
+    /// resubmit (if `Configuration::adaptive_mining_time`). None before the
+    /// first send is mined.
+    pub fn observed_mining_time(&self) -> Option<Duration> {
+        self.observed_mining_time
+    }
+
+    /// The estimator of the confirmation times, with the block times observed
+    /// so far.
+    pub fn confirmation_estimator(&self) -> ConfirmationEstimator {
+        self.confirmation_estimator
+    }
+
+    /// Returns a stream of the confirmation depth of the transactions the
+    /// manager sends from now on, updated whenever it sees a new block on top
+    /// of them (e.g., to show the progress of transactions requiring many
+    /// confirmations). The stream ends when the manager is dropped.
+    ///
+    /// Since sending consumes the manager, the stream must be polled
+    /// concurrently with the send.
+    pub fn confirmation_updates(
+        &mut self,
+    ) -> impl Stream<Item = ConfirmationUpdate> + Send + Unpin {
+        let (sender, receiver) = mpsc::unbounded();
+        self.confirmation_watchers.push(sender);
+        receiver
+    }
+
+    /// Updates the manager's configuration in place, allowing long-running
+    /// services to change it without recreating the manager. The updated
+    /// configuration is validated (see `Configuration::validate`), and the
+    /// previous one is kept if it is invalid.
+    pub fn update_configuration(
+        &mut self,
+        f: impl FnOnce(&mut Configuration<T>),
+    ) -> Result<(), ConfigurationError> {
+        let previous = self.configuration.clone();
+        f(&mut self.configuration);
+        match self.configuration.validate(&self.chain) {
+            Ok(warnings) => {
+                for warning in warnings {
+                    warn!("Configuration: {}.", warning);
+                }
+                trace!("Updated the configuration => {:#?}", self.configuration);
+                Ok(())
+            }
+            Err(err) => {
+                self.configuration = previous;
+                Err(err)
+            }
+        }
+    }
+
+    /// Hashes of the transactions sent for the pending transaction in the
+    /// database (submitted, cancellation, and replaced ones), any of which may
+    /// be mined. Empty if there is no pending transaction.
+    ///
+    /// The database is updated as the transactions are sent, so other
+    /// processes can follow a send in progress by reading the same database
+    /// (e.g., a `FileSystemDatabase` with the same path).
+    pub async fn get_submitted_hashes(&self) -> Result<Vec<H256>, Error<M, GO, DB>> {
+        let start = Instant::now();
+        let result = self
+            .db
+            .get_state()
+            .instrument(trace_span!("get_state"))
+            .await;
+        self.observe_database(DatabaseOperation::Get, start);
+        let state = result.map_err(Error::Database)?;
+        Ok(state
+            .map(|state| state.txs_hashes().copied().collect())
+            .unwrap_or_default())
+    }
+
+    /// Performs a single pass over the pending transaction in the database:
+    /// checks whether any of its transactions was mined (and how deep), or
+    /// whether it is due for resubmission. Does not sleep, send, or change
+    /// the database, so callers can drive the manager from their own loop
+    /// (e.g., a cron-style scheduler or a `select!`).
+    ///
+    /// The wait before a resubmission is due counts from the manager's first
+    /// submission of the transaction, or from its first check of it.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn check_once(&mut self) -> Result<CheckSummary, Error<M, GO, DB>> {
+        let start = Instant::now();
+        let result = self
+            .db
+            .get_state()
+            .instrument(trace_span!("get_state"))
+            .await;
+        self.observe_database(DatabaseOperation::Get, start);
+        let mut state = match result.map_err(Error::Database)? {
+            Some(state) => state,
+            None => return Ok(CheckSummary::Idle),
+        };
+
+        let receipt = self
+            .get_mined_transaction(&mut state)
+            .await?
+            .filter(|receipt| receipt.block_number.is_some());
+        self.check_reorg(&state, receipt.as_ref());
+
+        let summary = match receipt {
+            Some(receipt) => {
+                let WatchStatus {
+                    receipt,
+                    latest_block,
+                    depth,
+                    ..
+                } = self
+                    .watcher(&state)
+                    .status(&self.provider, receipt)
+                    .await
+                    .map_err(Error::Middleware)?;
+                self.confirmation_estimator
+                    .observe_block(latest_block, &self.configuration.time);
+                let update = ConfirmationUpdate {
+                    nonce: state.tx_data.nonce,
+                    hash: receipt.transaction_hash,
+                    block_number: receipt.block_number.unwrap(),
+                    latest_block,
+                    depth,
+                    confirmations: state.tx_data.confirmations,
+                    eta: self.confirmation_eta(&state, Some(depth)),
+                };
+                CheckSummary::Mined {
+                    receipt: Box::new(receipt),
+                    update,
+                }
+            }
+            None => {
+                let first_submission = *self
+                    .in_flight
+                    .first_submission
+                    .get_or_insert_with(|| first_submission(&state));
+                let wait_time = self.get_wait_time(&state, None);
+                let resubmission_due = self.configuration.speed_up_switch.is_requested()
+                    || self.configuration.time.elapsed(first_submission) > wait_time;
+                CheckSummary::Pending {
+                    nonce: state.tx_data.nonce,
+                    submitted: state.txs_hashes().count(),
+                    resubmission_due,
+                }
+            }
+        };
+        trace!("Checked the pending transaction => {:?}", summary);
+        Ok(summary)
+    }
+
+    /// Shuts the manager down (e.g., before a rolling deployment replaces the
+    /// process): flushes the latest state of the transaction being driven (see
+    /// `Manager::drive`) to the database, so that the next manager recovers
+    /// it, and closes the database (see `Database::close`).
+    ///
+    /// The sending functions persist the state as they go, so aborting a send
+    /// loses nothing but the in-memory progress since its last write. Managers
+    /// dropped while driving a transaction, without being shut down, log a
+    /// warning, as its latest state may not have been flushed.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn shutdown(mut self) -> Result<(), Error<M, GO, DB>> {
+        self.close().await?;
+        info!("The manager was shut down.");
+        Ok(())
+    }
+
+    /// Hands the transaction in flight over to another instance (e.g., in a
+    /// blue/green deployment): like `Manager::shutdown`, but it also stores a
+    /// handoff token with the state, which the new instance must present to
+    /// resume the transaction (see `Manager::take_over`). Until it does, other
+    /// instances fail to start with the state, preventing double submissions.
+    ///
+    /// Returns `None` if there is no transaction in flight.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn hand_off(mut self) -> Result<Option<HandoffToken>, Error<M, GO, DB>> {
+        self.flush_driven().await?;
+
+        let start = Instant::now();
+        let result = self
+            .db
+            .get_versioned_state()
+            .instrument(trace_span!("get_versioned_state"))
+            .await;
+        self.observe_database(DatabaseOperation::Get, start);
+        let token = match result.map_err(Error::Database)? {
+            Some((mut state, version)) => {
+                let token = HandoffToken::new(&state);
+                state.handoff = Some(token.clone());
+                self.db_version = version;
+                self.set_state(&state).await?;
+                Some(token)
+            }
+            None => None,
+        };
+
+        self.close().await?;
+        info!("The manager was handed off (token = {:?}).", token);
+        Ok(token)
+    }
+}
+
+/// Best-effort: the state cannot be flushed without an async context, so
+/// dropping a manager that was not shut down only warns about it.
+impl<M: Middleware, GO: GasOracle, DB: Database, T: Time> Drop for Manager<M, GO, DB, T> {
+    fn drop(&mut self) {
+        if self.shut_down || std::thread::panicking() {
+            return;
+        }
+        if self.driven.is_some() {
+            warn!(
+                "The manager was dropped while driving a transaction, without being shut down. \
+                Its latest state may not have been flushed to the database."
+            );
+        }
+    }
+}
+
+impl<M: Middleware, GO: GasOracle, DB: Database, T: Time> Manager<M, GO, DB, T>
+where
+    M: Send + Sync,
+    GO: Send + Sync,
+    DB: Send + Sync,
+    T: Send + Sync,
+{
+    /// The transaction's span records its nonce and the hash of the mined
+    /// transaction. With the `opentelemetry` feature, the span is a child of
+    /// the trace propagated through the transaction's `traceparent` metadata.
+    #[tracing::instrument(level = "trace", skip_all, fields(nonce, transaction_hash))]
+    async fn send_with(
+        mut self,
+        transaction: Transaction,
+        precondition: Option<Precondition>,
+        options: SendOptions,
+    ) -> Result<(Self, SendOutcome), Error<M, GO, DB>> {
+        let mut state = self.new_state(transaction, precondition, options).await?;
+        let outcome = self.process(&mut state).await?;
+        Ok((self, outcome))
+    }
+
+    /// The initial state of a transaction to send (with its nonce assigned).
+    async fn new_state(
+        &mut self,
+        transaction: Transaction,
+        precondition: Option<Precondition>,
+        options: SendOptions,
+    ) -> Result<PersistentState, Error<M, GO, DB>> {
+        trace!("Sending the transaction.");
+        let confirmations = options
+            .confirmations
+            .unwrap_or_else(|| self.chain.default_confirmations());
+        let priority = options.priority;
+
+        #[cfg(feature = "opentelemetry")]
+        crate::telemetry::set_parent(&Span::current(), &transaction.metadata);
+
+        self.check_signer(transaction.from)
+            .map_err(|err| self.emit_failed(None, &transaction, err))?;
+        let transaction = match self.resolve_recipient(transaction.clone()).await {
+            Ok(transaction) => transaction,
+            Err(err) => return Err(self.emit_failed(None, &transaction, err)),
+        };
+
+        let nonce = self
+            .assign_nonce(&transaction)
+            .await
+            .map_err(|err| self.emit_failed(None, &transaction, err))?;
+        Span::current().record("nonce", tracing::field::debug(nonce));
+
+        let tx_data = StaticTxData {
+            transaction,
+            nonce,
+            confirmations,
+            priority,
+            precondition,
+        };
+        Ok(PersistentState::new(tx_data))
+    }
+
+    /// Sends (or resends) the transaction until it is confirmed or skipped,
+    /// then clears its state from the database.
+    async fn process(
+        &mut self,
+        state: &mut PersistentState,
+    ) -> Result<SendOutcome, Error<M, GO, DB>> {
+        self.in_flight = InFlight::default();
+        let result = self.send_then_confirm_transaction(state).await;
+        self.conclude(state, result).await
+    }
+
+    /// Reports the outcome of sending the transaction, then clears its state
+    /// from the database.
+    async fn conclude(
+        &mut self,
+        state: &PersistentState,
+        result: Result<SendOutcome, Error<M, GO, DB>>,
+    ) -> Result<SendOutcome, Error<M, GO, DB>> {
+        let outcome = result.map_err(|err| {
+            let error =
+                self.emit_failed(Some(state.tx_data.nonce), &state.tx_data.transaction, err);
+            Error::Failed {
+                error: Box::new(error),
+                report: Box::new(self.send_report(state)),
+            }
+        })?;
+
+        match &outcome {
+            SendOutcome::Confirmed(receipt) => {
+                self.emit_confirmed(state, receipt);
+                self.observe_timings();
+                Span::current().record(
+                    "transaction_hash",
+                    tracing::field::debug(receipt.transaction_hash),
+                );
+
+                if self.configuration.human_logs {
+                    info!(
+                        "{}",
+                        formatting::confirmation(&self.chain, &state.tx_data, receipt)
+                    );
+                }
+                info!(
+                    "Transaction with nonce {:?} was sent. Transaction hash = {:?}. Metadata = {:?}.",
+                    state.tx_data.nonce, receipt.transaction_hash, state.tx_data.transaction.metadata
+                );
+            }
+            SendOutcome::Skipped(_) => {
+                self.emit(Event::Skipped {
+                    nonce: state.tx_data.nonce,
+                    metadata: state.tx_data.transaction.metadata.clone(),
+                });
+                self.journal(
+                    state.tx_data.transaction.from,
+                    Some(state.tx_data.nonce),
+                    || Transition::Skipped,
+                );
+
+                info!(
+                    "Transaction with nonce {:?} was skipped. Metadata = {:?}.",
+                    state.tx_data.nonce, state.tx_data.transaction.metadata
+                );
+            }
+        }
+
+        // Clearing information about the transaction in the database.
+        self.clear_state().await?;
+
+        Ok(outcome)
+    }
+
+    #[async_recursion]
+    #[tracing::instrument(
+        level = "trace",
+        skip_all,
+        fields(attempt = state.submitted_txs.len() + 1)
+    )]
+    async fn send_then_confirm_transaction(
+        &mut self,
+        state: &mut PersistentState,
+    ) -> Result<SendOutcome, Error<M, GO, DB>> {
+        trace!("(Re)sending the transaction.");
+
+        // Holding the submission while paused.
+        if self.wait_while_paused(state).await? {
+            let wait_time = self.get_wait_time(state, None);
+            return self.confirm_transaction(state, wait_time, false).await;
+        }
+
+        // Holding the resubmissions while the base fee is above the max fee cap.
+        if self.wait_for_fee_drop(state).await? {
+            let wait_time = self.get_wait_time(state, None);
+            return self.confirm_transaction(state, wait_time, false).await;
+        }
+
+        // Checking the precondition (unless already cancelling).
+        if state.cancellation_txs.is_none() && !self.check_precondition(&state.tx_data).await? {
+            if state.submitted_txs.is_empty() {
+                info!("The precondition does not hold, skipping the transaction.");
+                return Ok(SendOutcome::Skipped(None));
+            }
+
+            warn!("The precondition no longer holds, cancelling the transaction.");
+            state.cancellation_txs = Some(SubmittedTxs::new());
+        }
+        let tx_data = match state.cancellation_txs {
+            Some(_) => state.tx_data.cancellation(),
+            None => state.tx_data.clone(),
+        };
+
+        // Estimating gas prices.
+        let mut gas_oracle_info = self.get_gas_oracle_info(state.tx_data.priority).await?;
+
+        // Bumping the fees when first sending the replacement of a pending transaction.
+        if state.submitted_txs.is_empty() {
+            if let Some(&replaced_hash) = state.replaced_txs.txs_hashes.last() {
+                gas_oracle_info.gas_info = self
+                    .bump_gas_info(
+                        replaced_hash,
+                        gas_oracle_info.gas_info,
+                        self.node_client.replacement_fee_bump(),
+                    )
+                    .await?;
+            }
+        }
+
+        // Continuing the escalation from the last attempt (instead of from the
+        // oracle's fees) when resending a recovered transaction.
+        if std::mem::take(&mut self.in_flight.recovered) {
+            if let Some(last_attempt) = state.attempts.last() {
+                gas_oracle_info.gas_info = gas_oracle::replacement_gas_info(
+                    last_attempt.gas_info,
+                    gas_oracle_info.gas_info,
+                    1.,
+                );
+                trace!(
+                    "Resuming from attempt {} => {:?}.",
+                    state.attempts.len(),
+                    gas_oracle_info.gas_info
+                );
+            }
+        }
+
+        // Speeding up the transaction on demand.
+        if let Some(multiplier) = self
+            .configuration
+            .speed_up_switch
+            .take(self.node_client.replacement_fee_bump())
+        {
+            info!(
+                "Speeding up the transaction with nonce {:?} (multiplier = {:?}).",
+                state.tx_data.nonce, multiplier
+            );
+            let last_hash = match &state.cancellation_txs {
+                Some(cancellation_txs) => cancellation_txs.txs_hashes.last(),
+                None => state.submitted_txs.txs_hashes.last(),
+            };
+            gas_oracle_info.gas_info = match last_hash {
+                Some(&last_hash) => {
+                    self.bump_gas_info(last_hash, gas_oracle_info.gas_info, multiplier)
+                        .await?
+                }
+                None => multiply_gas_info(gas_oracle_info.gas_info, multiplier),
+            };
+        }
+
+        gas_oracle_info.gas_info = self.cap_gas_info(gas_oracle_info.gas_info);
+
+        // Overriding the configured block time (for this send only) and
+        // calculating the wait time.
+        if let Some(block_time) = gas_oracle_info.block_time {
+            self.in_flight.block_time = Some(block_time);
+        }
+        if let Some(mining_time) = gas_oracle_info.mining_time {
+            self.in_flight.mining_time = Some(mining_time);
+        }
+        let wait_time = self.get_wait_time(state, gas_oracle_info.mining_time);
+
+        // Resending with a raised max fee when the node rejects it for not
+        // covering the base fee.
+        let mut base_fee_rejections = 0;
+        loop {
+            // Creating the transaction request.
+            // FIXME: "insufficient funds for transfer" is detected here (when
+            // estimating the gas limit)!
+            let typed_transaction = self
+                .typed_transaction(&tx_data, gas_oracle_info.gas_info)
+                .await?;
+
+            {
+                // Calculating the transaction hash.
+                let (transaction_hash, raw_transaction) =
+                    self.raw_transaction(&typed_transaction).await?;
+
+                // Checking for the "already known" transactions.
+                let submitted_txs = match &mut state.cancellation_txs {
+                    Some(cancellation_txs) => cancellation_txs,
+                    None => &mut state.submitted_txs,
+                };
+                if !submitted_txs.contains(transaction_hash)
+                    && self.in_flight.replacement_limit_reached
+                {
+                    info!(
+                        "The node refused further replacements, waiting for the transaction \
+                        with nonce {:?} to be mined.",
+                        tx_data.nonce
+                    );
+                    break;
+                }
+                if !submitted_txs.contains(transaction_hash) {
+                    // Storing the pending transaction in the database (as
+                    // prepared, so that it can be rebroadcast after a crash),
+                    // together with its fees.
+                    submitted_txs.add(transaction_hash);
+                    state.broadcast_statuses.insert(
+                        transaction_hash,
+                        BroadcastStatus::PreparedNotBroadcast(raw_transaction.clone()),
+                    );
+                    state.attempts.push(Attempt {
+                        hash: transaction_hash,
+                        gas_info: gas_oracle_info.gas_info,
+                    });
+                    self.set_state(state).await?;
+                }
+
+                // Skipping the round-trip when the node already accepted the
+                // transaction (the fees did not change since it was sent, so
+                // neither did the hash).
+                if self.skip_broadcast(state, transaction_hash) {
+                    info!(
+                        "The transaction with hash {:?} was already broadcast, skipping the \
+                        resubmission.",
+                        transaction_hash
+                    );
+                    break;
+                }
+
+                // Sending the transaction.
+                let mut max_fee_rejected = false;
+                let mut replacement_limit_reached = false;
+                let returned_hash = {
+                    match self.send_raw_transaction(raw_transaction.clone()).await {
+                        Ok(returned_hash) => Some(returned_hash),
+                        Err(err) => {
+                            self.in_flight.record_rpc_error(&err);
+                            if self.is_replacement_underpriced(&err) {
+                                assert!(!state.submitted_txs.is_empty());
+                                warn!("Tried to send an underpriced transaction.");
+                                /* goes back to confirm_transaction */
+                            } else if self.is_replacement_limit_reached(&err) {
+                                assert!(!state.submitted_txs.is_empty());
+                                replacement_limit_reached = true;
+                                /* goes back to confirm_transaction */
+                            } else if is_error(&err, "max fee per gas less than block base fee")
+                                && matches!(gas_oracle_info.gas_info, GasInfo::EIP1559(_))
+                            {
+                                warn!(
+                                    "The node rejected the max fee for not covering the base fee."
+                                );
+                                max_fee_rejected = true;
+                            } else if is_error(&err, "intrinsic gas too low") {
+                                error!("The transaction's gas limit is below its intrinsic gas.");
+                                return Err(Error::IntrinsicGasTooLow(
+                                    typed_transaction.gas().copied(),
+                                ));
+                            } else if is_error(&err, "exceeds block gas limit") {
+                                error!("The transaction's gas limit exceeds the block gas limit.");
+                                return Err(Error::ExceedsBlockGasLimit {
+                                    gas_limit: typed_transaction.gas().copied(),
+                                    block_gas_limit: None,
+                                });
+                            } else if is_error(&err, "already known") {
+                                assert!(!state.submitted_txs.is_empty());
+                                warn!("Tried to send an already known transaction.");
+                                /* goes back to confirm_transaction */
+                            } else {
+                                error!("Error while submitting transaction: {:?}", err);
+                                return Err(err);
+                            }
+                            None
+                        }
+                    }
+                };
+
+                if replacement_limit_reached {
+                    self.replacement_limit_reached(state);
+                }
+
+                if max_fee_rejected {
+                    let eip1559_gas_info = match gas_oracle_info.gas_info {
+                        GasInfo::EIP1559(eip1559_gas_info) => eip1559_gas_info,
+                        GasInfo::Legacy(_) => unreachable!(),
+                    };
+                    let base_fee = self.get_latest_base_fee().await?;
+                    if base_fee_rejections == BASE_FEE_REJECTION_RETRIES {
+                        error!("The node keeps rejecting the max fee.");
+                        return Err(Error::MaxFeeBelowBaseFee {
+                            max_fee: eip1559_gas_info.max_fee.unwrap_or_default(),
+                            base_fee,
+                        });
+                    }
+                    base_fee_rejections += 1;
+                    gas_oracle_info.gas_info =
+                        GasInfo::EIP1559(self.raise_rejected_max_fee(eip1559_gas_info, base_fee));
+                    continue;
+                }
+
+                if let Some(returned_hash) = returned_hash {
+                    self.in_flight
+                        .first_submission
+                        .get_or_insert_with(Instant::now);
+                    state.first_submitted_at.get_or_insert_with(unix_millis);
+                    self.in_flight.last_broadcast = Some(SignedTransaction {
+                        hash: transaction_hash,
+                        raw_transaction: raw_transaction.clone(),
+                    });
+                    state
+                        .broadcast_statuses
+                        .insert(transaction_hash, BroadcastStatus::Broadcast);
+                    let transaction_hash = match self
+                        .configuration
+                        .hash_strategy
+                        .verify(transaction_hash, returned_hash)
+                    {
+                        Some(transaction_hash) => transaction_hash,
+                        None => match self.configuration.hash_mismatch_policy {
+                            HashMismatchPolicy::Error => {
+                                error!(
+                                    "Hash mismatch! Derived is `{:?}`, returned by the node is `{:?}`.",
+                                    transaction_hash, returned_hash
+                                );
+                                return Err(Error::HashMismatch {
+                                    derived: transaction_hash,
+                                    returned: returned_hash,
+                                });
+                            }
+                            HashMismatchPolicy::Adopt => {
+                                warn!(
+                                    "Hash mismatch! Derived is `{:?}`, returned by the node is `{:?}`. \
+                                    Tracking both.",
+                                    transaction_hash, returned_hash
+                                );
+                                returned_hash
+                            }
+                        },
+                    };
+                    let submitted_txs = match &mut state.cancellation_txs {
+                        Some(cancellation_txs) => cancellation_txs,
+                        None => &mut state.submitted_txs,
+                    };
+                    if !submitted_txs.contains(transaction_hash) {
+                        // Tracking the hash returned by the node.
+                        submitted_txs.add(transaction_hash);
+                        state
+                            .broadcast_statuses
+                            .insert(transaction_hash, BroadcastStatus::Broadcast);
+                    }
+                    self.set_state(state).await?;
+                    info!(
+                        "The manager has submitted transaction with hash {:?} \
+                        to the transaction pool, for a total of {:?} submitted \
+                        transaction(s).",
+                        transaction_hash,
+                        state.submitted_txs.len()
+                    );
+                    if self.configuration.human_logs {
+                        info!(
+                            "{}",
+                            formatting::submission(
+                                &self.chain,
+                                &tx_data,
+                                transaction_hash,
+                                &gas_oracle_info.gas_info
+                            )
+                        );
+                    }
+                    let archived_transaction = self
+                        .configuration
+                        .include_raw_transactions
+                        .then(|| raw_transaction.clone());
+                    self.emit(Event::Submitted {
+                        nonce: state.tx_data.nonce,
+                        hash: transaction_hash,
+                        raw_transaction: archived_transaction.clone(),
+                        eta: self.confirmation_eta(state, None),
+                        metadata: state.tx_data.transaction.metadata.clone(),
+                    });
+                    self.journal_submission(
+                        state,
+                        transaction_hash,
+                        gas_oracle_info.gas_info,
+                        typed_transaction.gas().copied(),
+                        archived_transaction,
+                    );
+
+                    if let Some(window) = self.configuration.pool_acceptance_window {
+                        self.await_pool_acceptance(
+                            state,
+                            transaction_hash,
+                            raw_transaction,
+                            window,
+                        )
+                        .await?;
+                    }
+                }
+            }
+
+            break;
+        }
+
+        // Confirming the transaction.
+        self.confirm_transaction(state, wait_time, true).await
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn confirm_transaction(
+        &mut self,
+        state: &mut PersistentState,
+        wait_time: Duration,
+        sleep_first: bool,
+    ) -> Result<SendOutcome, Error<M, GO, DB>> {
+        trace!(
+            "Confirming transaction (nonce = {:?}).",
+            state.tx_data.nonce
+        );
+
+        let mut start_time = Instant::now();
+        let mut last_update = None;
+        let mut sleep_time = if sleep_first {
+            self.block_time()
+        } else {
+            Duration::ZERO
+        };
+
+        loop {
+            // Sleeping.
+            self.configuration.time.sleep(sleep_time).await;
+
+            // Has the transaction stalled? Or the provider?
+            self.check_stall(state);
+            self.check_provider_freshness().await?;
+            self.in_flight.polls += 1;
+
+            // Were any of the transactions mined?
+            trace!("Were any of the transactions mined?");
+            let receipt = self
+                .get_mined_transaction(state)
+                .await?
+                // Some nodes return receipts (without blocks) for pending transactions.
+                .filter(|receipt| receipt.block_number.is_some());
+            self.check_reorg(state, receipt.as_ref());
+
+            match receipt {
+                Some(receipt) => {
+                    let WatchStatus {
+                        receipt,
+                        latest_block,
+                        depth,
+                        confirmed,
+                    } = self
+                        .watcher(state)
+                        .status(&self.provider, receipt)
+                        .await
+                        .map_err(Error::Middleware)?;
+
+                    self.confirmation_estimator
+                        .observe_block(latest_block, &self.configuration.time);
+                    let update = ConfirmationUpdate {
+                        nonce: state.tx_data.nonce,
+                        hash: receipt.transaction_hash,
+                        block_number: receipt.block_number.unwrap(),
+                        latest_block,
+                        depth,
+                        confirmations: state.tx_data.confirmations,
+                        eta: self.confirmation_eta(state, Some(depth)),
+                    };
+                    if last_update != Some((update.hash, update.depth)) {
+                        last_update = Some((update.hash, update.depth));
+                        self.notify_confirmation_watchers(update);
+                    }
+
+                    // Are there enough confirmations?
+                    trace!(
+                        "{:?} more confirmation(s) required.",
+                        state.tx_data.confirmations.saturating_sub(depth)
+                    );
+                    if confirmed {
+                        let is_cancellation = state
+                            .cancellation_txs
+                            .as_ref()
+                            .is_some_and(|txs| txs.txs_hashes.contains(&receipt.transaction_hash));
+                        if is_cancellation {
+                            return Ok(SendOutcome::Skipped(Some(receipt)));
+                        }
+                        if self.configuration.validate_receipts && !self.in_flight.watching {
+                            self.validate_receipt(state, &receipt).await?;
+                        }
+                        return Ok(SendOutcome::Confirmed(receipt));
+                    }
+                }
+                None => {
+                    trace!("No transaction mined.");
+
+                    // Was a speed-up requested? (Watched transactions are
+                    // never resubmitted.)
+                    if self.configuration.speed_up_switch.is_requested() && !self.in_flight.watching
+                    {
+                        return self.send_then_confirm_transaction(state).await;
+                    }
+
+                    // Have I waited too much?
+                    let elapsed_time = self.configuration.time.elapsed(start_time);
+                    if elapsed_time > wait_time {
+                        trace!(
+                            "I have waited too much! (elapsed = {:?}, max = {:?})",
+                            elapsed_time,
+                            wait_time
+                        );
+                        self.check_nonce_consumed(state).await?;
+                        match self.configuration.resubmission_mode {
+                            ResubmissionMode::BumpFees => {
+                                state.resubmissions += 1;
+                                return self.send_then_confirm_transaction(state).await;
+                            }
+                            ResubmissionMode::RebroadcastOnly => {
+                                state.resubmissions += 1;
+                                self.rebroadcast().await;
+                            }
+                            ResubmissionMode::Disabled => {
+                                trace!("Resubmissions are disabled, waiting for the transaction.");
+                            }
+                        }
+                        start_time = Instant::now();
+                    }
+                }
+            }
+
+            sleep_time = self.block_time();
+        }
+    }
+
+    /// Submits the signed transaction through the broadcast provider, if any
+    /// (see `Configuration::broadcast_provider`), or the provider, and returns
+    /// the hash returned by the node.
+    async fn send_raw_transaction(&self, raw_transaction: Bytes) -> Result<H256, Error<M, GO, DB>> {
+        match &self.configuration.broadcast_provider {
+            Some(broadcast_provider) => broadcast_provider
+                .send_raw_transaction(raw_transaction)
+                .instrument(trace_span!("send_raw_transaction"))
+                .await
+                .map_err(Error::Broadcast),
+            None => self
+                .provider
+                .send_raw_transaction(raw_transaction)
+                .instrument(trace_span!("send_raw_transaction"))
+                .await
+                .map(|pending_transaction| H256(*pending_transaction.as_fixed_bytes()))
+                .map_err(Error::Middleware),
+        }
+    }
+
+    /// Rebroadcasts the last submission the node accepted, as is (see
+    /// `ResubmissionMode::RebroadcastOnly`). The node's errors are only logged:
+    /// the transaction may have been mined in the meantime.
+    async fn rebroadcast(&mut self) {
+        let submission = match &self.in_flight.last_broadcast {
+            Some(submission) => submission.clone(),
+            None => {
+                trace!("There is no submission to rebroadcast, waiting for the transaction.");
+                return;
+            }
+        };
+
+        info!(
+            "Rebroadcasting the transaction with hash {:?}.",
+            submission.hash
+        );
+        match self.send_raw_transaction(submission.raw_transaction).await {
+            Ok(_) => {}
+            Err(err) if is_error(&err, "already known") => {
+                trace!("The node already knows the transaction.");
+            }
+            Err(err) => {
+                warn!("The node did not accept the rebroadcast: {}", err);
+                self.in_flight.record_rpc_error(&err);
+            }
+        }
+    }
+
+    /// Checks that the mined transaction is the one the manager sent (see
+    /// `Configuration::validate_receipts`). Only the sender and the nonce of
+    /// the transactions replaced with `Manager::replace_pending` are checked,
+    /// as their content differs from the persisted one.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn validate_receipt(
+        &self,
+        state: &PersistentState,
+        receipt: &TransactionReceipt,
+    ) -> Result<(), Error<M, GO, DB>> {
+        let hash = receipt.transaction_hash;
+        let mismatch = |mismatch| {
+            error!(
+                "The mined transaction {:?} does not match the one sent ({}).",
+                hash, mismatch
+            );
+            Error::ReceiptMismatch { hash, mismatch }
+        };
+
+        let transaction = self
+            .provider
+            .get_transaction(hash)
+            .instrument(trace_span!("get_transaction"))
+            .await
+            .map_err(Error::Middleware)?
+            .ok_or_else(|| mismatch("not found"))?;
+        let tx_data = &state.tx_data;
+        if transaction.from != tx_data.transaction.from {
+            return Err(mismatch("sender"));
+        }
+        if transaction.nonce != tx_data.nonce {
+            return Err(mismatch("nonce"));
+        }
+        if state.replaced_txs.contains(hash) {
+            return Ok(());
+        }
+        if let NameOrAddress::Address(to) = tx_data.transaction.to {
+            if transaction.to != Some(to) {
+                return Err(mismatch("recipient"));
+            }
+        }
+        let call_data = tx_data.transaction.call_data.clone().unwrap_or_default();
+        if transaction.input != call_data {
+            return Err(mismatch("call data"));
+        }
+        trace!("The mined transaction {:?} matches the one sent.", hash);
+        Ok(())
+    }
+
+    /// Fails with `Error::NonceConsumed` when the nonce of the transaction was
+    /// mined, but none of the submitted transactions was (e.g., another
+    /// process sent a transaction with the same nonce). Only with
+    /// `Configuration::validate_receipts`.
+    async fn check_nonce_consumed(
+        &mut self,
+        state: &mut PersistentState,
+    ) -> Result<(), Error<M, GO, DB>> {
+        if !self.configuration.validate_receipts
+            || self.in_flight.watching
+            || state.txs_hashes().next().is_none()
+        {
+            return Ok(());
+        }
+        let nonce = state.tx_data.nonce;
+        if self.get_mined_nonce(state.tx_data.transaction.from).await? <= nonce {
+            return Ok(());
+        }
+        // One of the transactions may have been mined since its receipt was
+        // queried.
+        if self.get_mined_transaction(state).await?.is_some() {
+            return Ok(());
+        }
+        error!(
+            "The nonce {:?} was consumed by a transaction the manager did not send.",
+            nonce
+        );
+        Err(Error::NonceConsumed { nonce })
+    }
+
+    /// Retrieves the gas_price (legacy) or max_fee and max_priority_fee
+    /// (EIP1559) from the provider and packs it inside GasOracleInfo.
+    ///
+    /// For legacy chains (or when forcing legacy transactions), the gas_price is
+    /// scaled by the multiplier configured for the given priority. For EIP1559
+    /// chains, the max_priority_fee is the median of the fee history rewards at
+    /// the percentile configured for the given priority.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn get_provider_gas_oracle_info(
+        &self,
+        priority: Priority,
+    ) -> Result<GasOracleInfo, M::Error> {
+        let gas_info = if self.chain.is_legacy || self.configuration.force_legacy {
+            let multiplier = self.configuration.gas_price_multipliers.get(priority);
+            trace!(
+                "Calculating legacy gas price using the provider (multiplier = {:?}).",
+                multiplier
+            );
+            let gas_price = Wei(self
+                .provider
+                .get_gas_price()
+                .instrument(trace_span!("get_gas_price"))
+                .await?)
+            .multiply(multiplier);
+            trace!("(gas_price = {})", gas_price);
+            GasInfo::Legacy(LegacyGasInfo { gas_price })
+        } else {
+            let percentile = self.configuration.priority_fee_percentiles.get(priority);
+            trace!(
+                "Estimating EIP1559 fees with the provider (reward percentile = {:?}).",
+                percentile
+            );
+            let fee_history = self
+                .provider
+                .fee_history(
+                    EIP1559_FEE_ESTIMATION_PAST_BLOCKS,
+                    BlockNumber::Latest,
+                    &[percentile],
+                )
+                .instrument(trace_span!("fee_history"))
+                .await?;
+            // The last element is the base fee of the next block.
+            let base_fee = Wei(fee_history
+                .base_fee_per_gas
+                .last()
+                .copied()
+                .unwrap_or_default());
+            let max_priority_fee = Wei(estimate_max_priority_fee(&fee_history.reward));
+            let max_fee = base_fee.multiply(self.base_fee_multiplier(PROVIDER_BASE_FEE_MULTIPLIER))
+                + max_priority_fee;
+            trace!(
+                "(max_fee = {:?}, max_priority_fee = {:?})",
+                max_fee,
+                max_priority_fee
+            );
+            GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: Some(max_fee),
+                max_priority_fee: Some(max_priority_fee),
+            })
+        };
+        Ok(GasOracleInfo {
+            gas_info,
+            mining_time: None,
+            block_time: None,
+            timestamp: None,
+        })
+    }
+
+    /// Uses the provider to calculate the max_priority_fee given the max_fee.
+    /// If the max_fee does not cover the latest base fee, it gets raised
+    /// according to the configured headroom (or an error is returned).
+    async fn get_max_priority_fee(&self, max_fee: Wei) -> Result<EIP1559GasInfo, Error<M, GO, DB>> {
+        let base_fee = self.get_latest_base_fee().await?;
+
+        let mut eip1559_gas_info = EIP1559GasInfo {
+            max_fee: Some(max_fee),
+            max_priority_fee: None,
         };
+        if max_fee <= base_fee {
+            warn!(
+                "The gas oracle's max fee ({:?}) does not cover the base fee ({:?}).",
+                max_fee, base_fee
+            );
+
+            let raised_max_fee = self
+                .configuration
+                .max_fee_headroom
+                .map(|headroom| base_fee.multiply(headroom))
+                .filter(|raised_max_fee| *raised_max_fee > base_fee)
+                .ok_or(Error::MaxFeeBelowBaseFee { max_fee, base_fee })?;
+
+            warn!("Raising the max fee to {:?}.", raised_max_fee);
+            eip1559_gas_info.max_fee = Some(raised_max_fee);
+        }
+
+        eip1559_gas_info.max_priority_fee =
+            eip1559_gas_info.max_fee.map(|max_fee| max_fee - base_fee);
+        Ok(eip1559_gas_info)
+    }
 
+    /// Uses the provider to calculate the max_fee given the max_priority_fee:
+    /// the latest base fee times the base fee multiplier (see
+    /// `derived_base_fee_multiplier`), plus the max_priority_fee.
+    async fn get_max_fee(&self, max_priority_fee: Wei) -> Result<EIP1559GasInfo, Error<M, GO, DB>> {
+        let base_fee = self.get_latest_base_fee().await?;
+        let multiplier = self.derived_base_fee_multiplier();
+        let max_fee = base_fee.multiply(multiplier) + max_priority_fee;
         trace!(
-            "Forcing the instantiation of a new transaction manager => {:#?}",
-            manager
+            "Calculated the max fee from the gas oracle's max priority fee \
+            (max_fee = {:?}, base_fee = {:?}).",
+            max_fee,
+            base_fee
         );
+        Ok(EIP1559GasInfo {
+            max_fee: Some(max_fee),
+            max_priority_fee: Some(max_priority_fee),
+        })
+    }
 
-        trace!("Clearing DB state");
-        manager.db.clear_state().await.map_err(Error::Database)?;
+    /// Converts the EIP1559 fees into the gas price of an equivalent legacy
+    /// transaction: the latest base fee plus the max priority fee, capped at
+    /// the max fee.
+    async fn get_equivalent_gas_price(
+        &self,
+        eip1559_gas_info: EIP1559GasInfo,
+    ) -> Result<LegacyGasInfo, Error<M, GO, DB>> {
+        let base_fee = self.get_latest_base_fee().await?;
+        // the fees must be set (guaranteed by get_gas_oracle_info)
+        let max_priority_fee = eip1559_gas_info.max_priority_fee.unwrap();
+        let gas_price = eip1559_gas_info
+            .max_fee
+            .unwrap()
+            .min(base_fee.saturating_add(max_priority_fee));
+        trace!("Forcing a legacy transaction (gas_price = {}).", gas_price);
+        Ok(LegacyGasInfo { gas_price })
+    }
 
-        Ok(manager)
+    /// The configured base fee multiplier, or `default` if there is none.
+    fn base_fee_multiplier(&self, default: f64) -> f64 {
+        self.configuration.base_fee_multiplier.unwrap_or(default)
     }
 
-    /// Sends a transaction and returns the receipt.
-    #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn send_transaction(
-        mut self,
-        transaction: Transaction,
-        confirmations: usize,
-        priority: Priority,
-    ) -> Result<(Self, TransactionReceipt), Error<M, GO, DB>> {
-        trace!("Sending the transaction.");
+    /// The base fee multiplier of max fees derived after the fact (missing or
+    /// rejected by the node): the configured multiplier, or the max fee
+    /// headroom, or the maximum base fee increase per block.
+    fn derived_base_fee_multiplier(&self) -> f64 {
+        let default = self
+            .configuration
+            .max_fee_headroom
+            .unwrap_or(BASE_FEE_MAX_INCREASE);
+        self.base_fee_multiplier(default)
+    }
 
-        let mut state = {
-            let nonce = self.get_nonce(transaction.from).await?;
+    /// Raises the max fee rejected by the node to the latest base fee (or to
+    /// the rejected max fee, if higher) times the base fee multiplier (see
+    /// `derived_base_fee_multiplier`).
+    fn raise_rejected_max_fee(
+        &self,
+        mut eip1559_gas_info: EIP1559GasInfo,
+        base_fee: Wei,
+    ) -> EIP1559GasInfo {
+        let max_fee = base_fee
+            .max(eip1559_gas_info.max_fee.unwrap_or_default())
+            .multiply(self.derived_base_fee_multiplier());
+        warn!("Raising the max fee to {:?}.", max_fee);
+        eip1559_gas_info.max_fee = Some(max_fee);
+        eip1559_gas_info.max_priority_fee = eip1559_gas_info
+            .max_priority_fee
+            .map(|max_priority_fee| max_priority_fee.min(max_fee));
+        eip1559_gas_info
+    }
 
-            let tx_data = StaticTxData {
-                transaction,
-                nonce,
-                confirmations,
-                priority,
-            };
+    /// Resolves the ENS name of the transaction's recipient (if it has one)
+    /// with the provider, so that the state only holds addresses. Names that
+    /// are hex addresses (e.g., parsed with `NameOrAddress::from_str`) are
+    /// not resolved.
+    async fn resolve_recipient(
+        &self,
+        mut transaction: Transaction,
+    ) -> Result<Transaction, Error<M, GO, DB>> {
+        if let NameOrAddress::Name(name) = &transaction.to {
+            if let Ok(address) = Address::from_str(name) {
+                transaction.to = NameOrAddress::Address(address);
+                return Ok(transaction);
+            }
+            let address = self
+                .provider
+                .resolve_name(name)
+                .instrument(trace_span!("resolve_name"))
+                .await
+                .map_err(|error| Error::UnresolvableName {
+                    name: name.clone(),
+                    error,
+                })?;
+            trace!("Resolved the ENS name `{}` => {:?}", name, address);
+            transaction.to = NameOrAddress::Address(address);
+        }
+        Ok(transaction)
+    }
+
+    /// Checks whether the transaction's gas estimate fits in the latest
+    /// block's gas limit.
+    async fn check_block_gas_limit(
+        &self,
+        transaction: &Transaction,
+        priority: Priority,
+    ) -> Result<(), Error<M, GO, DB>> {
+        let GasLimit(gas_limit) = self.estimate_cost(transaction, priority).await?.gas_limit;
+        let block_gas_limit = self
+            .provider
+            .get_block(BlockId::Number(BlockNumber::Latest))
+            .instrument(trace_span!("get_block"))
+            .await
+            .map_err(Error::Middleware)?
+            .ok_or(Error::LatestBlockIsNone)?
+            .gas_limit;
+        if gas_limit > block_gas_limit {
+            warn!(
+                "The transaction requires more gas ({}) than the block gas limit ({}).",
+                gas_limit, block_gas_limit
+            );
+            return Err(Error::ExceedsBlockGasLimit {
+                gas_limit: Some(gas_limit),
+                block_gas_limit: Some(block_gas_limit),
+            });
+        }
+        Ok(())
+    }
 
-            let submitted_txs = SubmittedTxs::new();
+    async fn get_latest_base_fee(&self) -> Result<Wei, Error<M, GO, DB>> {
+        self.provider
+            .get_block(BlockId::Number(BlockNumber::Latest))
+            .instrument(trace_span!("get_block"))
+            .await
+            .map_err(Error::Middleware)?
+            .ok_or(Error::LatestBlockIsNone)?
+            .base_fee_per_gas
+            .map(Wei)
+            .ok_or(Error::LatestBaseFeeIsNone)
+    }
 
-            PersistentState {
-                tx_data,
-                submitted_txs,
-            }
-        };
+    /// Retrieves the gas_oracle_info from the gas oracle if there is one, or
+    /// from the provider otherwise.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn get_gas_oracle_info(
+        &self,
+        priority: Priority,
+    ) -> Result<GasOracleInfo, Error<M, GO, DB>> {
+        let mut result = self.gas_oracle.get_info(priority).await;
 
-        let receipt = self.send_then_confirm_transaction(&mut state).await?;
+        // Re-querying the gas oracle while its estimates are stale.
+        if let Some(staleness_window) = self.configuration.gas_oracle_staleness_window {
+            let mut retries = GAS_ORACLE_STALENESS_RETRIES;
+            while let Ok(gas_oracle_info) = &result {
+                let age = match gas_oracle_info.age() {
+                    Some(age) if age > staleness_window => age,
+                    _ => break,
+                };
+                if retries == 0 {
+                    warn!(
+                        "The gas oracle's estimates are stale (age = {:?}), \
+                        falling back to the provider's.",
+                        age
+                    );
+                    return self
+                        .get_provider_gas_oracle_info(priority)
+                        .await
+                        .map_err(Error::Middleware);
+                }
+                warn!(
+                    "The gas oracle's estimates are stale (age = {:?}), re-querying it.",
+                    age
+                );
+                retries -= 1;
+                result = self.gas_oracle.get_info(priority).await;
+            }
+        }
 
-        info!(
-            "Transaction with nonce {:?} was sent. Transaction hash = {:?}.",
-            state.tx_data.nonce, receipt.transaction_hash
-        );
+        match result {
+            Ok(mut gas_oracle_info) => {
+                assert_eq!(gas_oracle_info.gas_info.is_legacy(), self.chain.is_legacy);
 
-        // Clearing information about the transaction in the database.
-        self.db.clear_state().await.map_err(Error::Database)?;
+                if let GasInfo::EIP1559(mut eip1559_gas_info) = gas_oracle_info.gas_info {
+                    match (eip1559_gas_info.max_fee, eip1559_gas_info.max_priority_fee) {
+                        (Some(_), Some(_)) => {}
+                        (Some(max_fee), None) => {
+                            eip1559_gas_info = self.get_max_priority_fee(max_fee).await?;
+                        }
+                        (None, Some(max_priority_fee)) => {
+                            eip1559_gas_info = self.get_max_fee(max_priority_fee).await?;
+                        }
+                        (None, None) => {
+                            return Err(Error::IncompatibleGasOracle("no EIP1559 fees"));
+                        }
+                    }
+                    gas_oracle_info.gas_info = if self.configuration.force_legacy {
+                        GasInfo::Legacy(self.get_equivalent_gas_price(eip1559_gas_info).await?)
+                    } else {
+                        GasInfo::EIP1559(eip1559_gas_info)
+                    };
+                }
 
-        Ok((self, receipt))
+                Ok(gas_oracle_info)
+            }
+            Err(err1) => {
+                trace!(
+                    "Gas oracle has failed and/or is defaulting to the provider ({}).",
+                    err1.to_string()
+                );
+                self.get_provider_gas_oracle_info(priority)
+                    .await
+                    .map_err(|err2| Error::GasOracle(err1, err2))
+            }
+        }
     }
-}
 
-impl<M: Middleware, GO: GasOracle, DB: Database, T: Time> Manager<M, GO, DB, T>
-where
-    M: Send + Sync,
-    GO: Send + Sync,
-    DB: Send + Sync,
-    T: Send + Sync,
-{
-    #[async_recursion]
+    /// Returns the receipt of the tracked transaction that was mined (see
+    /// `PendingTxWatcher::receipts`), and flags the anomaly with an
+    /// `Event::MultipleReceipts` when more than one of them appears mined.
     #[tracing::instrument(level = "trace", skip_all)]
-    async fn send_then_confirm_transaction(
+    async fn get_mined_transaction(
         &mut self,
         state: &mut PersistentState,
-    ) -> Result<TransactionReceipt, Error<M, GO, DB>> {
-        trace!("(Re)sending the transaction.");
-
-        // Estimating gas prices.
-        let gas_oracle_info = self.get_gas_oracle_info(state.tx_data.priority).await?;
+    ) -> Result<Option<TransactionReceipt>, Error<M, GO, DB>> {
+        let receipts = self
+            .watcher(state)
+            .receipts(&self.provider)
+            .await
+            .map_err(Error::Middleware)?;
+        if receipts.conflicting.is_empty() {
+            return Ok(receipts.mined.or(receipts.pending));
+        }
 
-        // Overwriting the default block time and calculating the wait time.
-        if let Some(block_time) = gas_oracle_info.block_time {
-            self.configuration.block_time = block_time;
-        }
-        let wait_time =
-            self.get_wait_time(state.tx_data.confirmations, gas_oracle_info.mining_time);
-
-        // Creating the transaction request.
-        let typed_transaction: TypedTransaction = {
-            let mut typed_transaction = state
-                .tx_data
-                .to_typed_transaction(&self.chain, gas_oracle_info.gas_info);
-
-            // Estimating the gas limit of the transaction.
-            // FIXME: "insufficient funds for transfer" is detected here!
-            typed_transaction.set_gas(
-                self.provider
-                    .estimate_gas(&typed_transaction, None)
-                    .await
-                    .map_err(Error::Middleware)?,
+        let hashes = receipts.conflicting;
+        let canonical = receipts.mined;
+        if self.in_flight.multiple_receipts.as_ref() != Some(&hashes) {
+            warn!(
+                "Several transactions with nonce {:?} appear mined ({:?})! Picking the \
+                canonical one ({:?}).",
+                state.tx_data.nonce,
+                hashes,
+                canonical.as_ref().map(|receipt| receipt.transaction_hash)
             );
+            self.emit(Event::MultipleReceipts {
+                nonce: state.tx_data.nonce,
+                hashes: hashes.clone(),
+                canonical: canonical.as_ref().map(|receipt| receipt.transaction_hash),
+                metadata: state.tx_data.transaction.metadata.clone(),
+            });
+            self.in_flight.multiple_receipts = Some(hashes);
+        }
+        Ok(canonical)
+    }
 
-            typed_transaction
-        };
+    /// Watches the tracked transactions of the state.
+    fn watcher(&self, state: &PersistentState) -> PendingTxWatcher {
+        PendingTxWatcher::new(state.txs_hashes().copied(), state.tx_data.confirmations)
+    }
 
-        {
-            // Calculating the transaction hash.
-            let (transaction_hash, raw_transaction) =
-                self.raw_transaction(&typed_transaction).await?;
+    /// Raises the fees of the gas info to at least `factor` times the fees of
+    /// the replaced transaction, if it is still in the transaction pool (see
+    /// `gas_oracle::replacement_gas_info`).
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn bump_gas_info(
+        &self,
+        replaced_hash: H256,
+        gas_info: GasInfo,
+        factor: f64,
+    ) -> Result<GasInfo, Error<M, GO, DB>> {
+        let replaced = self
+            .provider
+            .get_transaction(replaced_hash)
+            .instrument(trace_span!("get_transaction"))
+            .await
+            .map_err(Error::Middleware)?;
+        let previous = match replaced.as_ref().and_then(gas_oracle::transaction_gas_info) {
+            Some(previous) => previous,
+            None => return Ok(gas_info),
+        };
 
-            // Checking for the "already known" transactions.
-            if !state.submitted_txs.contains(transaction_hash) {
-                // Storing information about the pending transaction in the database.
-                state.submitted_txs.add(transaction_hash);
-                self.db.set_state(state).await.map_err(Error::Database)?;
-            }
+        let gas_info = gas_oracle::replacement_gas_info(previous, gas_info, factor);
+        trace!("Bumped gas info: {:?}.", gas_info);
+        Ok(gas_info)
+    }
 
-            // Sending the transaction.
-            let result = self
+    /// Estimates the gas limit of the transaction. Retries (with exponential
+    /// backoff) when the provider fails for transient reasons, and classifies
+    /// reverts and gas allowance errors.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn estimate_gas(
+        &self,
+        typed_transaction: &TypedTransaction,
+    ) -> Result<U256, Error<M, GO, DB>> {
+        let mut retry_interval = self.configuration.estimate_gas_retry_interval;
+        let mut attempt = 0;
+        loop {
+            let err = match self
                 .provider
-                .send_raw_transaction(raw_transaction)
+                .estimate_gas(typed_transaction, None)
+                .instrument(trace_span!("estimate_gas"))
                 .await
-                .map_err(Error::Middleware);
+            {
+                Ok(gas_limit) => return Ok(gas_limit),
+                Err(err) => err,
+            };
 
-            match result {
-                Ok(pending_transaction) => {
-                    assert_eq!(
-                        transaction_hash,
-                        H256(*pending_transaction.as_fixed_bytes()),
-                        "stored hash is different from the pending transaction's hash"
-                    );
-                    info!(
-                        "The manager has submitted transaction with hash {:?} \
-                        to the transaction pool, for a total of {:?} submitted \
-                        transaction(s).",
-                        transaction_hash,
-                        state.submitted_txs.len()
-                    );
-                }
+            let message = format!("{:?}", err);
+            if message.contains("execution reverted") {
+                let reason = revert_reason(&message);
+                error!("The transaction reverted (reason: {:?}).", reason);
+                return Err(Error::Reverted(reason));
+            } else if message.contains("gas required exceeds allowance") {
+                let cap = gas_allowance(&message);
+                error!(
+                    "The transaction requires more gas than the node allows ({:?}).",
+                    cap
+                );
+                return Err(Error::GasAllowanceExceeded(cap));
+            } else if message.contains("intrinsic gas too low") {
+                error!("The transaction's gas limit is below its intrinsic gas.");
+                return Err(Error::IntrinsicGasTooLow(typed_transaction.gas().copied()));
+            } else if message.contains("exceeds block gas limit") {
+                error!("The transaction requires more gas than the block gas limit.");
+                return Err(Error::ExceedsBlockGasLimit {
+                    gas_limit: typed_transaction.gas().copied(),
+                    block_gas_limit: None,
+                });
+            } else if !is_transient(&message) || attempt >= self.configuration.estimate_gas_retries
+            {
+                return Err(Error::Middleware(err));
+            }
+
+            attempt += 1;
+            warn!(
+                "Transient error while estimating gas (attempt {}): {:?}",
+                attempt, err
+            );
+            self.configuration.time.sleep(retry_interval).await;
+            retry_interval *= 2;
+        }
+    }
+
+    /// Checks whether the provider signs transactions for the `from` address.
+    /// Providers without a default sender are trusted to sign for any address.
+    fn check_signer(&self, from: Address) -> Result<(), Error<M, GO, DB>> {
+        match self.provider.default_sender() {
+            Some(signer) if signer != from => {
+                error!(
+                    "Signer mismatch! Signer is `{:?}`, transaction is from `{:?}`.",
+                    signer, from
+                );
+                Err(Error::SignerMismatch { signer, from })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Rebroadcasts the transactions that were stored before being sent, as
+    /// the manager may have stopped before sending them. Returns whether there
+    /// were any.
+    async fn rebroadcast_prepared_txs(
+        &mut self,
+        state: &mut PersistentState,
+    ) -> Result<bool, Error<M, GO, DB>> {
+        let unbroadcast_txs: Vec<_> = state
+            .unbroadcast_txs()
+            .map(|(&hash, raw_transaction)| (hash, raw_transaction.clone()))
+            .collect();
+        if unbroadcast_txs.is_empty() {
+            return Ok(false);
+        }
+
+        for (hash, raw_transaction) in unbroadcast_txs {
+            warn!(
+                "Rebroadcasting transaction {:?}, which may not have been sent.",
+                hash
+            );
+            let broadcast = match self.send_raw_transaction(raw_transaction).await {
+                Ok(_) => true,
+                Err(err) if is_error(&err, "already known") => true,
                 Err(err) => {
-                    if is_error(&err, "replacement transaction underpriced") {
-                        assert!(!state.submitted_txs.is_empty());
-                        warn!("Tried to send an underpriced transaction.");
-                        /* goes back to confirm_transaction */
-                    } else if is_error(&err, "already known") {
-                        assert!(!state.submitted_txs.is_empty());
-                        warn!("Tried to send an already known transaction.");
-                        /* goes back to confirm_transaction */
-                    } else {
-                        error!("Error while submitting transaction: {:?}", err);
-                        return Err(err);
-                    }
+                    warn!("Could not rebroadcast transaction {:?}: {:?}", hash, err);
+                    false
                 }
             };
-        };
-
-        // Confirming the transaction.
-        self.confirm_transaction(state, wait_time, true).await
+            if broadcast {
+                state
+                    .broadcast_statuses
+                    .insert(hash, BroadcastStatus::Broadcast);
+            }
+        }
+        self.set_state(state).await?;
+        Ok(true)
     }
 
-    #[tracing::instrument(level = "trace", skip_all)]
-    async fn confirm_transaction(
+    /// Waits while the manager is paused, watching whether one of the submitted
+    /// transactions gets mined in the meantime (returns true if one does).
+    async fn wait_while_paused(
         &mut self,
         state: &mut PersistentState,
-        wait_time: Duration,
-        sleep_first: bool,
-    ) -> Result<TransactionReceipt, Error<M, GO, DB>> {
-        trace!(
-            "Confirming transaction (nonce = {:?}).",
+    ) -> Result<bool, Error<M, GO, DB>> {
+        if !self.configuration.pause_switch.is_paused() {
+            return Ok(false);
+        }
+
+        info!(
+            "The manager is paused, holding the transaction with nonce {:?}.",
             state.tx_data.nonce
         );
+        while self.configuration.pause_switch.is_paused() {
+            self.check_stall(state);
+            if state.txs_hashes().next().is_some()
+                && self.get_mined_transaction(state).await?.is_some()
+            {
+                info!("A submitted transaction was mined while the manager was paused.");
+                return Ok(true);
+            }
+            self.configuration.time.sleep(self.block_time()).await;
+        }
+        info!("The manager was resumed.");
+        Ok(false)
+    }
 
-        let start_time = Instant::now();
-        let mut sleep_time = if sleep_first {
-            self.configuration.block_time
-        } else {
-            Duration::ZERO
+    /// Holds the resubmissions of the transaction while the base fee is above
+    /// the max fee cap (see `Configuration::max_fee_cap`), as they could not
+    /// be included. Returns true if a submitted transaction was mined in the
+    /// meantime.
+    async fn wait_for_fee_drop(
+        &mut self,
+        state: &mut PersistentState,
+    ) -> Result<bool, Error<M, GO, DB>> {
+        let (mut base_fee, max_fee_cap) = match self.base_fee_above_cap(state).await? {
+            Some(fees) => fees,
+            None => return Ok(false),
         };
 
-        loop {
-            // Sleeping.
-            self.configuration.time.sleep(sleep_time).await;
-
-            // Were any of the transactions mined?
-            trace!("Were any of the transactions mined?");
-            let receipt = self.get_mined_transaction(state).await?;
+        self.hold_for_fee_drop(state, base_fee, max_fee_cap);
+        while base_fee > max_fee_cap {
+            self.check_stall(state);
+            if self.get_mined_transaction(state).await?.is_some() {
+                info!("A submitted transaction was mined while waiting for the base fee to drop.");
+                return Ok(true);
+            }
+            self.configuration.time.sleep(self.block_time()).await;
+            base_fee = self.get_latest_base_fee().await?;
+        }
+        info!(
+            "The base fee ({:?}) is back within the max fee cap, resuming.",
+            base_fee
+        );
+        Ok(false)
+    }
 
-            match receipt {
-                Some(receipt) => {
-                    let transaction_block = receipt.block_number.unwrap().as_usize();
-                    let current_block = self
-                        .provider
-                        .get_block_number()
-                        .await
-                        .map_err(Error::Middleware)?
-                        .as_usize();
+    /// The base fee and the max fee cap, if the base fee is above the cap and
+    /// the transaction was already submitted (see `wait_for_fee_drop`).
+    async fn base_fee_above_cap(
+        &self,
+        state: &PersistentState,
+    ) -> Result<Option<(Wei, Wei)>, Error<M, GO, DB>> {
+        let max_fee_cap = match self.configuration.max_fee_cap {
+            Some(max_fee_cap) => max_fee_cap,
+            None => return Ok(None),
+        };
+        if state.submitted_txs.is_empty() || self.chain.is_legacy || self.configuration.force_legacy
+        {
+            return Ok(None);
+        }
+        let base_fee = self.get_latest_base_fee().await?;
+        Ok((base_fee > max_fee_cap).then_some((base_fee, max_fee_cap)))
+    }
 
-                    trace!("Mined transaction block: {:?}.", transaction_block);
-                    trace!("Current block: {:?}.", current_block);
+    fn hold_for_fee_drop(&self, state: &PersistentState, base_fee: Wei, max_fee_cap: Wei) {
+        warn!(
+            "The base fee ({:?}) is above the max fee cap ({:?}), holding the transaction with nonce {:?}.",
+            base_fee, max_fee_cap, state.tx_data.nonce
+        );
+        self.emit(Event::WaitingForFeeDrop {
+            nonce: state.tx_data.nonce,
+            base_fee: base_fee.0,
+            max_fee_cap: max_fee_cap.0,
+            metadata: state.tx_data.transaction.metadata.clone(),
+        });
+    }
 
-                    // Are there enough confirmations?
-                    assert!(current_block >= transaction_block);
-                    let mut delta = (current_block - transaction_block) as i32;
-                    delta = (state.tx_data.confirmations as i32) - delta;
-                    trace!("{:?} more confirmation(s) required.", delta);
-                    if delta <= 0 {
-                        return Ok(receipt);
-                    }
-                }
-                None => {
-                    trace!("No transaction mined.");
+    /// Caps the fees at the max fee cap (see `Configuration::max_fee_cap`).
+    fn cap_gas_info(&self, gas_info: GasInfo) -> GasInfo {
+        let max_fee_cap = match self.configuration.max_fee_cap {
+            Some(max_fee_cap) => max_fee_cap,
+            None => return gas_info,
+        };
+        match gas_info {
+            GasInfo::EIP1559(eip1559_gas_info) if matches!(eip1559_gas_info.max_fee, Some(max_fee) if max_fee > max_fee_cap) =>
+            {
+                warn!(
+                    "Capping the max fee ({:?}) at {:?}.",
+                    eip1559_gas_info.max_fee, max_fee_cap
+                );
+                GasInfo::EIP1559(EIP1559GasInfo {
+                    max_fee: Some(max_fee_cap),
+                    max_priority_fee: eip1559_gas_info
+                        .max_priority_fee
+                        .map(|max_priority_fee| max_priority_fee.min(max_fee_cap)),
+                })
+            }
+            GasInfo::Legacy(legacy_gas_info) if legacy_gas_info.gas_price > max_fee_cap => {
+                warn!(
+                    "Capping the gas price ({:?}) at {:?}.",
+                    legacy_gas_info.gas_price, max_fee_cap
+                );
+                GasInfo::Legacy(LegacyGasInfo {
+                    gas_price: max_fee_cap,
+                })
+            }
+            gas_info => gas_info,
+        }
+    }
 
-                    // Have I waited too much?
-                    let elapsed_time = self.configuration.time.elapsed(start_time);
-                    if elapsed_time > wait_time {
-                        trace!(
-                            "I have waited too much! (elapsed = {:?}, max = {:?})",
-                            elapsed_time,
-                            wait_time
-                        );
-                        return self.send_then_confirm_transaction(state).await;
+    /// Emits an `Event::Reorged` if the last receipt was dropped or moved to
+    /// another block (by a reorg) since the previous check. Confirmations are
+    /// always counted from the latest receipt, so the manager just keeps
+    /// confirming (or resends the transaction, if it is not mined again).
+    fn check_reorg(&mut self, state: &PersistentState, receipt: Option<&TransactionReceipt>) {
+        let mined = receipt.map(|receipt| (receipt.transaction_hash, receipt.block_hash));
+        if let Some((hash, block_hash)) = self.in_flight.mined {
+            if mined != self.in_flight.mined {
+                warn!(
+                    "Reorg! Transaction {:?} (block hash = {:?}) was {}.",
+                    hash,
+                    block_hash,
+                    if mined.is_some() {
+                        "moved to another block"
+                    } else {
+                        "dropped from the chain"
                     }
-                }
+                );
+                self.emit(Event::Reorged {
+                    nonce: state.tx_data.nonce,
+                    hash,
+                    block_hash,
+                    metadata: state.tx_data.transaction.metadata.clone(),
+                });
+            }
+        }
+        if mined.is_none() {
+            self.in_flight.mined_at = None;
+            // Nodes may not return the transactions of the dropped blocks to
+            // their pools.
+            if let Some((hash, _)) = self.in_flight.mined {
+                self.in_flight.broadcast_due = Some(hash);
             }
+        } else if mined != self.in_flight.mined {
+            let mining_time = self
+                .in_flight
+                .first_submission
+                .map(|first_submission| self.configuration.time.elapsed(first_submission));
+            self.in_flight.mined_at = Some((Instant::now(), mining_time));
+        }
+        self.in_flight.mined = mined;
+    }
+
+    /// Flushes the state and closes the database (see `Manager::shutdown`).
+    async fn close(&mut self) -> Result<(), Error<M, GO, DB>> {
+        self.flush_driven().await?;
+        self.db.close().await.map_err(Error::Database)?;
+        self.shut_down = true;
+        Ok(())
+    }
+
+    /// The block time of the current send: the gas oracle's estimate, if it
+    /// gave one, or the configured one.
+    fn block_time(&self) -> Duration {
+        self.in_flight
+            .block_time
+            .unwrap_or(self.configuration.block_time)
+    }
 
-            sleep_time = self.configuration.block_time;
+    /// The timings of the transaction in flight (see `SendTimings`).
+    fn timings(&self) -> SendTimings {
+        match self.in_flight.mined_at {
+            Some((mined_at, mining_time)) => SendTimings {
+                mining_time,
+                confirmation_time: Some(self.configuration.time.elapsed(mined_at)),
+                database: self.in_flight.database,
+            },
+            None => SendTimings {
+                database: self.in_flight.database,
+                ..SendTimings::default()
+            },
         }
     }
 
-    /// Retrieves the gas_price (legacy) or max_fee and max_priority_fee
-    /// (EIP1559) from the provider and packs it inside GasOracleInfo.
-    #[tracing::instrument(level = "trace", skip_all)]
-    async fn get_provider_gas_oracle_info(&self) -> Result<GasOracleInfo, M::Error> {
-        let gas_info = if self.chain.is_legacy {
-            trace!("Calculating legacy gas price using the provider.");
-            let gas_price = self.provider.get_gas_price().await?;
-            trace!("(gas_price = {:?} wei)", gas_price);
-            GasInfo::Legacy(LegacyGasInfo { gas_price })
-        } else {
-            trace!("Estimating EIP1559 fees with the provider.");
-            let (max_fee, max_priority_fee) = self.provider.estimate_eip1559_fees(None).await?;
+    /// Records the timings of the confirmed transaction, and feeds its mining
+    /// time into the observed mining time.
+    fn observe_timings(&mut self) {
+        let timings = self.timings();
+        if let Some(mining_time) = timings.mining_time {
+            let observed_mining_time = match self.observed_mining_time {
+                Some(observed_mining_time) => {
+                    observed_mining_time.mul_f64(1. - OBSERVED_MINING_TIME_WEIGHT)
+                        + mining_time.mul_f64(OBSERVED_MINING_TIME_WEIGHT)
+                }
+                None => mining_time,
+            };
             trace!(
-                "(max_fee = {:?}, max_priority_fee = {:?})",
-                max_fee,
-                max_priority_fee
+                "Observed mining time: {:?} (last = {:?}).",
+                observed_mining_time,
+                mining_time
             );
-            GasInfo::EIP1559(EIP1559GasInfo {
-                max_fee,
-                max_priority_fee: Some(max_priority_fee),
-            })
-        };
-        Ok(GasOracleInfo {
-            gas_info,
-            mining_time: None,
-            block_time: None,
-        })
+            self.observed_mining_time = Some(observed_mining_time);
+        }
+        self.last_timings = Some(timings);
     }
 
-    /// Uses the provider to calculate the max_priority_fee given the max_fee.
-    async fn get_max_priority_fee(&self, max_fee: U256) -> Result<U256, Error<M, GO, DB>> {
-        let base_fee = self
+    /// Checks that the provider's latest block advanced within the configured
+    /// number of block times (see `Configuration::stale_provider_blocks`).
+    /// Otherwise, emits an `Event::ProviderStale` and fails over, or fails if
+    /// there is no other endpoint.
+    async fn check_provider_freshness(&mut self) -> Result<(), Error<M, GO, DB>> {
+        let stale_provider_blocks = match self.configuration.stale_provider_blocks {
+            Some(stale_provider_blocks) => stale_provider_blocks,
+            None => return Ok(()),
+        };
+        let block_number = self
             .provider
-            .get_block(BlockId::Number(BlockNumber::Latest))
+            .get_block_number()
+            .instrument(trace_span!("get_block_number"))
             .await
-            .map_err(Error::Middleware)?
-            .ok_or(Error::LatestBlockIsNone)?
-            .base_fee_per_gas
-            .ok_or(Error::LatestBaseFeeIsNone)?;
+            .map_err(Error::Middleware)?;
+        let since = match self.in_flight.latest_block {
+            Some((latest_block, since)) if block_number <= latest_block => since,
+            _ => {
+                self.in_flight.latest_block = Some((block_number, Instant::now()));
+                return Ok(());
+            }
+        };
 
-        assert!(
-            max_fee > base_fee,
-            "max_fee({:?}) <= base_fee({:?})",
-            max_fee,
-            base_fee
+        let age = self.configuration.time.elapsed(since);
+        if age <= self.block_time() * stale_provider_blocks {
+            return Ok(());
+        }
+        warn!(
+            "The provider is stuck at block {} (for {:?}).",
+            block_number, age
         );
+        self.emit(Event::ProviderStale { block_number, age });
+        let failed_over = match &self.configuration.failover {
+            Some(failover) => failover.fail_over(),
+            None => false,
+        };
+        if !failed_over {
+            error!("There is no other endpoint to fail over to.");
+            return Err(Error::ProviderStale { block_number, age });
+        }
+        // Giving the next endpoint as long to advance.
+        self.in_flight.latest_block = Some((block_number, Instant::now()));
+        Ok(())
+    }
+
+    /// Emits an `Event::Stalled` (once) if the transaction has been in flight
+    /// for longer than the configured stall threshold.
+    fn check_stall(&mut self, state: &PersistentState) {
+        let (stall_threshold, first_submission) = match (
+            self.configuration.stall_threshold,
+            self.in_flight.first_submission,
+        ) {
+            (Some(stall_threshold), Some(first_submission)) => (stall_threshold, first_submission),
+            _ => return,
+        };
+
+        let in_flight = self.configuration.time.elapsed(first_submission);
+        if self.in_flight.stalled || in_flight <= stall_threshold {
+            return;
+        }
 
-        Ok(max_fee - base_fee)
+        warn!(
+            "Transaction with nonce {:?} has stalled (in flight for {:?}).",
+            state.tx_data.nonce, in_flight
+        );
+        self.in_flight.stalled = true;
+        self.emit(Event::Stalled {
+            nonce: state.tx_data.nonce,
+            in_flight,
+            metadata: state.tx_data.transaction.metadata.clone(),
+        });
     }
 
-    /// Retrieves the gas_oracle_info from the gas oracle if there is one, or
-    /// from the provider otherwise.
+    /// Waits for the submitted transaction to be visible in the provider's
+    /// transaction pool, resubmitting it through the fallback broadcasters
+    /// while it is not. If the transaction was (silently) dropped, it only
+    /// warns, as the manager resubmits it when the mining wait times out.
     #[tracing::instrument(level = "trace", skip_all)]
-    async fn get_gas_oracle_info(
+    async fn await_pool_acceptance(
         &self,
-        priority: Priority,
-    ) -> Result<GasOracleInfo, Error<M, GO, DB>> {
-        match self.gas_oracle.get_info(priority).await {
-            Ok(mut gas_oracle_info) => {
-                assert_eq!(gas_oracle_info.gas_info.is_legacy(), self.chain.is_legacy);
-
-                if let GasInfo::EIP1559(mut eip1559_gas_info) = gas_oracle_info.gas_info {
-                    if eip1559_gas_info.max_priority_fee.is_none() {
-                        eip1559_gas_info.max_priority_fee =
-                            Some(self.get_max_priority_fee(eip1559_gas_info.max_fee).await?);
-                        gas_oracle_info.gas_info = GasInfo::EIP1559(eip1559_gas_info);
-                    };
-                }
+        state: &PersistentState,
+        transaction_hash: H256,
+        raw_transaction: Bytes,
+        window: Duration,
+    ) -> Result<(), Error<M, GO, DB>> {
+        if self.is_visible(transaction_hash, window).await? {
+            trace!(
+                "Transaction {:?} was accepted into the pool.",
+                transaction_hash
+            );
+            return Ok(());
+        }
 
-                Ok(gas_oracle_info)
+        for (name, broadcaster) in &self.configuration.fallback_broadcasters {
+            warn!(
+                "Transaction {:?} is not visible in the pool, resubmitting it through {}.",
+                transaction_hash, name
+            );
+            if let Err(err) = broadcaster
+                .send_raw_transaction(raw_transaction.clone())
+                .instrument(trace_span!("send_raw_transaction"))
+                .await
+            {
+                warn!("Could not resubmit the transaction: {}", err);
+                continue;
             }
-            Err(err1) => {
-                trace!(
-                    "Gas oracle has failed and/or is defaulting to the provider ({}).",
-                    err1.to_string()
+            self.journal(
+                state.tx_data.transaction.from,
+                Some(state.tx_data.nonce),
+                || Transition::Resubmitted {
+                    hash: transaction_hash,
+                    endpoint: name.clone(),
+                },
+            );
+            if self.is_visible(transaction_hash, window).await? {
+                info!(
+                    "Transaction {:?} was accepted into the pool after being resubmitted.",
+                    transaction_hash
                 );
-                self.get_provider_gas_oracle_info()
-                    .await
-                    .map_err(|err2| Error::GasOracle(err1, err2))
+                return Ok(());
             }
         }
+
+        warn!(
+            "Transaction {:?} was dropped (not visible in the pool after {:?}); \
+            it is resubmitted when the mining wait times out.",
+            transaction_hash, window
+        );
+        Ok(())
     }
 
-    #[tracing::instrument(level = "trace", skip_all)]
-    async fn get_mined_transaction(
+    /// Polls the provider (for up to the window) for the transaction.
+    async fn is_visible(
         &self,
-        state: &mut PersistentState,
-    ) -> Result<Option<TransactionReceipt>, Error<M, GO, DB>> {
-        for &hash in &state.submitted_txs {
-            if let Some(receipt) = self
+        transaction_hash: H256,
+        window: Duration,
+    ) -> Result<bool, Error<M, GO, DB>> {
+        let mut waited = Duration::ZERO;
+        loop {
+            let transaction = self
                 .provider
-                .get_transaction_receipt(hash)
+                .get_transaction(transaction_hash)
+                .instrument(trace_span!("get_transaction"))
                 .await
-                .map_err(Error::Middleware)?
-            {
-                return Ok(Some(receipt));
+                .map_err(Error::Middleware)?;
+            if transaction.is_some() {
+                return Ok(true);
+            }
+            if waited >= window {
+                return Ok(false);
+            }
+            self.configuration
+                .time
+                .sleep(POOL_ACCEPTANCE_POLLING_INTERVAL)
+                .await;
+            waited += POOL_ACCEPTANCE_POLLING_INTERVAL;
+        }
+    }
+
+    /// Validates the configuration for the chain (see `Configuration::validate`),
+    /// logging the warnings.
+    fn validate_configuration(&self) -> Result<(), Error<M, GO, DB>> {
+        let warnings = self
+            .configuration
+            .validate(&self.chain)
+            .map_err(Error::Configuration)?;
+        for warning in warnings {
+            warn!("Configuration: {}.", warning);
+        }
+        Ok(())
+    }
+
+    /// Compares the configured chain id with the one reported by the provider.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn check_chain_id(&mut self) -> Result<(), Error<M, GO, DB>> {
+        let reported = self
+            .provider
+            .get_chainid()
+            .instrument(trace_span!("get_chainid"))
+            .await
+            .map_err(Error::Middleware)?;
+        let configured = self.chain.id;
+        if reported == U256::from(configured) {
+            return Ok(());
+        }
+
+        if self.configuration.adopt_provider_chain_id && reported <= U256::from(u64::MAX) {
+            warn!(
+                "Chain id mismatch! Adopting the provider's `{}` instead of `{}`.",
+                reported, configured
+            );
+            self.chain.id = reported.as_u64();
+            Ok(())
+        } else {
+            error!(
+                "Chain id mismatch! Configured is `{}`, reported is `{}`.",
+                configured, reported
+            );
+            Err(Error::ChainIdMismatch {
+                configured,
+                reported,
+            })
+        }
+    }
+
+    /// Sets the client whose replacement rules the manager follows, detecting
+    /// it with `web3_clientVersion` if so configured.
+    async fn detect_node_client(&mut self) {
+        self.node_client = match self.configuration.replacement_rules {
+            ReplacementRules::Client(node_client) => node_client,
+            ReplacementRules::Detect => {
+                match self
+                    .provider
+                    .client_version()
+                    .instrument(trace_span!("client_version"))
+                    .await
+                {
+                    Ok(client_version) => {
+                        let node_client = NodeClient::from_client_version(&client_version);
+                        info!(
+                            "Following the replacement rules of {:?} (client version = {}).",
+                            node_client, client_version
+                        );
+                        node_client
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Could not detect the node's client ({:?}), following the \
+                            strictest replacement rules.",
+                            err
+                        );
+                        NodeClient::Other
+                    }
+                }
             }
+        };
+    }
+
+    /// Whether to skip resubmitting a transaction that was already accepted by
+    /// a node (resubmitting it would only get an "already known" back). Every
+    /// other resubmission of the same transaction is sent anyway, in case it
+    /// was evicted from the transaction pool.
+    fn skip_broadcast(&mut self, state: &PersistentState, hash: H256) -> bool {
+        let broadcast = matches!(
+            state.broadcast_statuses.get(&hash),
+            Some(BroadcastStatus::Broadcast)
+        );
+        let due = self.in_flight.broadcast_due.take() == Some(hash);
+        if broadcast && !due {
+            self.in_flight.broadcast_due = Some(hash);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether the node rejected the transaction as an underpriced replacement
+    /// (according to the rules of its client).
+    fn is_replacement_underpriced<E: Debug>(&self, err: &E) -> bool {
+        self.node_client
+            .is_replacement_underpriced(&format!("{:?}", err))
+    }
+
+    fn is_replacement_limit_reached<E: Debug>(&self, err: &E) -> bool {
+        self.node_client
+            .is_replacement_limit_reached(&format!("{:?}", err))
+    }
+
+    /// Stops replacing the transaction after the node refused further
+    /// replacements, flagging it once.
+    fn replacement_limit_reached(&mut self, state: &PersistentState) {
+        if std::mem::replace(&mut self.in_flight.replacement_limit_reached, true) {
+            return;
+        }
+        warn!(
+            "The node refused further replacements of the transaction with nonce {:?} \
+            ({} submissions).",
+            state.tx_data.nonce,
+            state.submitted_txs.len()
+        );
+        self.emit(Event::ReplacementLimitReached {
+            nonce: state.tx_data.nonce,
+            submissions: state.submitted_txs.len(),
+            metadata: state.tx_data.transaction.metadata.clone(),
+        });
+    }
+
+    /// Checks that the gas oracle has fees for the chain (after the chain id was
+    /// checked, since it may be adopted from the provider).
+    fn check_gas_oracle_chain(&self) -> Result<(), Error<M, GO, DB>> {
+        if self.gas_oracle.supports_chain(self.chain.id) {
+            Ok(())
+        } else {
+            error!(
+                "The gas oracle does not support the chain `{}`.",
+                self.chain.id
+            );
+            Err(Error::UnsupportedGasOracleChain(self.chain.id))
         }
-        Ok(None)
+    }
+
+    /// Checks whether the transaction's precondition (if any) holds.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn check_precondition(&self, tx_data: &StaticTxData) -> Result<bool, Error<M, GO, DB>> {
+        let precondition = match &tx_data.precondition {
+            Some(precondition) => precondition,
+            None => return Ok(true),
+        };
+
+        let call = TypedTransaction::Legacy(
+            TransactionRequest::new()
+                .from(tx_data.transaction.from)
+                .to(precondition.to)
+                .data(precondition.call_data.clone()),
+        );
+        let output = self
+            .provider
+            .call(&call, None)
+            .instrument(trace_span!("call"))
+            .await
+            .map_err(Error::Middleware)?;
+        trace!("Precondition call output: {:?}.", output);
+        Ok(output == precondition.expected_output)
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
@@ -550,10 +4620,71 @@ where
                 NameOrAddress::Address(address),
                 Some(BlockId::Number(BlockNumber::Pending)),
             )
+            .instrument(trace_span!("get_transaction_count"))
+            .await
+            .map_err(Error::Middleware)
+    }
+
+    /// The nonce of a new transaction (see `NonceManagement`).
+    async fn assign_nonce(&self, transaction: &Transaction) -> Result<U256, Error<M, GO, DB>> {
+        if self.configuration.nonce_management == NonceManagement::Internal {
+            return self.get_nonce(transaction.from).await;
+        }
+
+        // The placeholders keep the provider from estimating the fees and the
+        // gas of a request that is only used for its nonce.
+        let mut typed_transaction = TypedTransaction::Legacy(TransactionRequest {
+            from: Some(transaction.from),
+            to: Some(transaction.to.clone()),
+            gas: Some(U256::zero()),
+            gas_price: Some(U256::zero()),
+            ..Default::default()
+        });
+        self.provider
+            .fill_transaction(&mut typed_transaction, None)
+            .instrument(trace_span!("fill_transaction"))
+            .await
+            .map_err(Error::Middleware)?;
+        let nonce = typed_transaction
+            .nonce()
+            .copied()
+            .ok_or(Error::NonceNotAssigned)?;
+        trace!("The provider assigned the nonce {}.", nonce);
+        Ok(nonce)
+    }
+
+    /// Returns the number of mined transactions sent from the address.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn get_mined_nonce(&self, address: Address) -> Result<U256, Error<M, GO, DB>> {
+        self.provider
+            .get_transaction_count(
+                NameOrAddress::Address(address),
+                Some(BlockId::Number(BlockNumber::Latest)),
+            )
+            .instrument(trace_span!("get_transaction_count"))
             .await
             .map_err(Error::Middleware)
     }
 
+    /// Builds the transaction request with the fee model, and sets its gas
+    /// limit (estimated by the provider) if the fee model did not.
+    async fn typed_transaction(
+        &self,
+        tx_data: &StaticTxData,
+        gas_info: GasInfo,
+    ) -> Result<TypedTransaction, Error<M, GO, DB>> {
+        let mut typed_transaction = self
+            .configuration
+            .fee_model
+            .build(tx_data, &self.chain, gas_info)
+            .await
+            .map_err(Error::FeeModel)?;
+        if typed_transaction.gas().is_none() {
+            typed_transaction.set_gas(self.estimate_gas(&typed_transaction).await?);
+        }
+        Ok(typed_transaction)
+    }
+
     /// Returns the transaction hash and the raw transaction.
     #[tracing::instrument(level = "trace", skip_all)]
     async fn raw_transaction(
@@ -564,29 +4695,400 @@ where
         let signature = self
             .provider
             .sign_transaction(typed_transaction, from)
+            .instrument(trace_span!("sign_transaction"))
             .await
             .map_err(Error::Middleware)?;
-        let hash = typed_transaction.hash(&signature);
-        let rlp_data = typed_transaction.rlp_signed(&signature);
+        let hash = self
+            .configuration
+            .hash_strategy
+            .derive(typed_transaction, &signature);
+        let rlp_data = self
+            .configuration
+            .fee_model
+            .encode(typed_transaction, &signature);
         Ok((hash, rlp_data))
     }
 
+    /// Persists the state, failing if another writer changed it since the
+    /// manager last read or wrote it.
+    async fn set_state(&mut self, state: &PersistentState) -> Result<(), Error<M, GO, DB>> {
+        let start = Instant::now();
+        let result = self
+            .db
+            .set_state_if_version(state, self.db_version)
+            .instrument(trace_span!("set_state"))
+            .await;
+        let time = self.observe_database(DatabaseOperation::Set, start);
+        self.in_flight.database.record(DatabaseOperation::Set, time);
+        self.db_version = result.map_err(Error::Database)?;
+        Ok(())
+    }
+
+    async fn clear_state(&mut self) -> Result<(), Error<M, GO, DB>> {
+        let start = Instant::now();
+        let result = self
+            .db
+            .clear_state()
+            .instrument(trace_span!("clear_state"))
+            .await;
+        let time = self.observe_database(DatabaseOperation::Clear, start);
+        self.in_flight
+            .database
+            .record(DatabaseOperation::Clear, time);
+        result.map_err(Error::Database)?;
+        self.db_version = 0;
+        Ok(())
+    }
+
+    /// Records how long the database operation that started at `start` took,
+    /// reporting it if slow (see `Configuration::slow_database_threshold`).
+    fn observe_database(&self, operation: DatabaseOperation, start: Instant) -> Duration {
+        let time = self.configuration.time.elapsed(start);
+        trace!("Database operation {:?} took {:?}.", operation, time);
+        self.database_stats.lock().unwrap().record(operation, time);
+        if matches!(self.configuration.slow_database_threshold, Some(threshold) if time > threshold)
+        {
+            warn!(
+                "The database is slow! (operation = {:?}, time = {:?})",
+                operation, time
+            );
+            self.emit(Event::SlowDatabase {
+                operation,
+                duration: time,
+            });
+        }
+        time
+    }
+
+    fn emit(&self, event: Event) {
+        for event_listener in &self.configuration.event_listeners {
+            event_listener.notify(&event);
+        }
+    }
+
+    /// Sends the update to the confirmation watchers, forgetting the ones
+    /// whose streams were dropped.
+    fn notify_confirmation_watchers(&mut self, update: ConfirmationUpdate) {
+        self.confirmation_watchers
+            .retain(|watcher| watcher.unbounded_send(update).is_ok());
+    }
+
+    /// Emits a failure event and returns the error.
+    fn emit_failed(
+        &self,
+        nonce: Option<U256>,
+        transaction: &Transaction,
+        err: Error<M, GO, DB>,
+    ) -> Error<M, GO, DB> {
+        self.emit(Event::Failed {
+            nonce,
+            error: err.to_string(),
+            metadata: transaction.metadata.clone(),
+        });
+        self.journal(transaction.from, nonce, || Transition::Failed {
+            error: err.to_string(),
+        });
+        err
+    }
+
+    fn send_report(&self, state: &PersistentState) -> SendReport {
+        let timings = self.timings();
+        SendReport {
+            nonce: state.tx_data.nonce,
+            attempts: state.attempts.clone(),
+            resubmissions: state.resubmissions,
+            in_flight: self
+                .in_flight
+                .first_submission
+                .map(|first_submission| self.configuration.time.elapsed(first_submission)),
+            mining_time: timings.mining_time,
+            confirmation_time: timings.confirmation_time,
+            database: timings.database.total_time(),
+            rpc_errors: self.in_flight.rpc_errors.clone(),
+        }
+    }
+
+    fn emit_confirmed(&self, state: &PersistentState, receipt: &TransactionReceipt) {
+        let cost = receipt
+            .gas_used
+            .zip(receipt.effective_gas_price)
+            .map(|(gas_used, effective_gas_price)| gas_used * effective_gas_price);
+        let cost_usd = cost
+            .zip(self.configuration.price_feed.as_ref())
+            .and_then(|(cost, price_feed)| price_feed::to_usd(price_feed.as_ref(), cost));
+        let timings = self.timings();
+        self.emit(Event::Confirmed {
+            nonce: state.tx_data.nonce,
+            hash: receipt.transaction_hash,
+            block_number: receipt.block_number,
+            cost,
+            cost_usd,
+            mining_time: timings.mining_time,
+            confirmation_time: timings.confirmation_time,
+            metadata: state.tx_data.transaction.metadata.clone(),
+        });
+        self.journal(
+            state.tx_data.transaction.from,
+            Some(state.tx_data.nonce),
+            || Transition::Confirmed {
+                hash: receipt.transaction_hash,
+                block_number: receipt.block_number,
+                gas_used: receipt.gas_used,
+                effective_gas_price: receipt.effective_gas_price,
+            },
+        );
+    }
+
+    fn journal_submission(
+        &self,
+        state: &PersistentState,
+        hash: H256,
+        gas_info: GasInfo,
+        gas_limit: Option<U256>,
+        raw_transaction: Option<Bytes>,
+    ) {
+        self.journal(
+            state.tx_data.transaction.from,
+            Some(state.tx_data.nonce),
+            || {
+                let (gas_price, max_fee, max_priority_fee) = match gas_info {
+                    GasInfo::Legacy(legacy) => (Some(legacy.gas_price), None, None),
+                    GasInfo::EIP1559(eip1559) => (None, eip1559.max_fee, eip1559.max_priority_fee),
+                };
+                Transition::Submitted {
+                    hash,
+                    endpoint: self
+                        .configuration
+                        .journal
+                        .as_ref()
+                        .unwrap()
+                        .endpoint()
+                        .into(),
+                    gas_limit,
+                    gas_price: gas_price.map(Into::into),
+                    max_fee: max_fee.map(Into::into),
+                    max_priority_fee: max_priority_fee.map(Into::into),
+                    raw_transaction,
+                }
+            },
+        );
+    }
+
+    /// Records a state transition in the journal, if the configuration has one.
+    fn journal(
+        &self,
+        sender: Address,
+        nonce: Option<U256>,
+        transition: impl FnOnce() -> Transition,
+    ) {
+        if self.in_flight.watching {
+            return;
+        }
+        if let Some(journal) = &self.configuration.journal {
+            if let Err(err) = journal.record(sender, nonce, transition()) {
+                error!(
+                    "Could not write to the journal at {:?}: {}",
+                    journal.path(),
+                    err
+                );
+            }
+        }
+    }
+
     /// TODO: docs.
     #[tracing::instrument(level = "trace", skip_all)]
     fn get_wait_time(
         &self,
-        confirmations: usize,
+        state: &PersistentState,
         transaction_mining_time: Option<Duration>,
     ) -> Duration {
-        let transaction_mining_time =
-            transaction_mining_time.unwrap_or(self.configuration.transaction_mining_time);
+        let transaction_mining_time = self.transaction_mining_time(transaction_mining_time);
+        let confirmations = state.tx_data.confirmations;
         let confirmation_time = if confirmations > 0 {
             confirmations as u32
         } else {
             1
-        } * self.configuration.block_time;
-        transaction_mining_time + confirmation_time
+        } * self.block_time();
+        let wait_time = transaction_mining_time + confirmation_time;
+        let wait_time = match self.configuration.patience {
+            Some(patience) => patience.wait_time(wait_time, state.resubmissions),
+            None => wait_time,
+        };
+        if self.in_flight.replacement_limit_reached {
+            wait_time * REPLACEMENT_LIMIT_PATIENCE
+        } else {
+            wait_time
+        }
     }
+
+    /// The expected mining time: the gas oracle's estimate, if it gave one,
+    /// the observed one (if `Configuration::adaptive_mining_time`), or the
+    /// configured one.
+    fn transaction_mining_time(&self, transaction_mining_time: Option<Duration>) -> Duration {
+        let observed_mining_time = self
+            .observed_mining_time
+            .filter(|_| self.configuration.adaptive_mining_time)
+            // Never resubmitting before a block could include the transaction.
+            .map(|observed_mining_time| observed_mining_time.max(self.block_time()));
+        transaction_mining_time
+            .or(observed_mining_time)
+            .unwrap_or(self.configuration.transaction_mining_time)
+    }
+
+    /// Estimated time until the transaction in flight is confirmed (see
+    /// `ConfirmationEstimator::eta`).
+    fn confirmation_eta(&self, state: &PersistentState, depth: Option<usize>) -> Duration {
+        let waited = self
+            .in_flight
+            .first_submission
+            .map(|first_submission| self.configuration.time.elapsed(first_submission))
+            .unwrap_or_default();
+        self.confirmation_estimator.eta(
+            self.block_time(),
+            self.transaction_mining_time(self.in_flight.mining_time),
+            waited,
+            depth,
+            state.tx_data.confirmations,
+        )
+    }
+}
+
+/// The manager's view of a transaction sent outside it (see `Manager::adopt`
+/// and `Manager::wait_for`).
+fn external_transaction(transaction: &ethers::types::Transaction, to: Address) -> Transaction {
+    Transaction {
+        from: transaction.from,
+        to: to.into(),
+        value: if transaction.value.is_zero() {
+            Value::Nothing
+        } else {
+            Value::Number(Wei(transaction.value))
+        },
+        call_data: Some(transaction.input.clone()).filter(|input| !input.is_empty()),
+        metadata: Default::default(),
+    }
+}
+
+/// Returns the median of the non-zero fee history rewards (or a default value if
+/// all blocks were empty).
+fn estimate_max_priority_fee(rewards: &[Vec<U256>]) -> U256 {
+    let mut rewards: Vec<U256> = rewards
+        .iter()
+        .filter_map(|reward| reward.first().copied())
+        .filter(|reward| !reward.is_zero())
+        .collect();
+    if rewards.is_empty() {
+        return U256::from(EIP1559_FEE_ESTIMATION_DEFAULT_PRIORITY_FEE);
+    }
+    rewards.sort();
+    rewards[rewards.len() / 2]
+}
+
+/// Multiplies a U256 value by a floating point factor (with three decimal
+/// places of precision).
+pub(crate) fn multiply(value: U256, factor: f64) -> U256 {
+    value * U256::from((factor * 1000.) as u64) / 1000
+}
+
+fn multiply_gas_info(gas_info: GasInfo, factor: f64) -> GasInfo {
+    match gas_info {
+        GasInfo::Legacy(legacy) => GasInfo::Legacy(LegacyGasInfo {
+            gas_price: legacy.gas_price.multiply(factor),
+        }),
+        GasInfo::EIP1559(eip1559) => GasInfo::EIP1559(EIP1559GasInfo {
+            max_fee: eip1559.max_fee.map(|max_fee| max_fee.multiply(factor)),
+            max_priority_fee: eip1559
+                .max_priority_fee
+                .map(|max_priority_fee| max_priority_fee.multiply(factor)),
+        }),
+    }
+}
+
+/// Decodes the revert reason from the error's `Error(string)` data (or, as a
+/// fallback, from its message).
+fn revert_reason(message: &str) -> Option<String> {
+    const ERROR_SELECTOR: &str = "08c379a0";
+
+    let data = message.find(ERROR_SELECTOR).and_then(|i| {
+        let data: String = message[i + ERROR_SELECTOR.len()..]
+            .chars()
+            .take_while(|c| c.is_ascii_hexdigit())
+            .collect();
+        let data = hex::decode(data).ok()?;
+        match abi::decode(&[ParamType::String], &data).ok()?.pop()? {
+            Token::String(reason) => Some(reason),
+            _ => None,
+        }
+    });
+
+    data.or_else(|| {
+        let (_, reason) = message.split_once("execution reverted: ")?;
+        let reason = reason.split('"').next()?;
+        Some(reason.to_string())
+    })
+}
+
+/// Parses the node's gas cap from a "gas required exceeds allowance (cap)"
+/// error message.
+fn gas_allowance(message: &str) -> Option<u64> {
+    let (_, cap) = message.split_once("gas required exceeds allowance (")?;
+    cap.split(')').next()?.parse().ok()
+}
+
+/// Whether the error is likely caused by the node being (temporarily) unable
+/// to answer, in which case the request may be retried.
+pub(crate) fn is_transient(message: &str) -> bool {
+    const TRANSIENT_ERRORS: [&str; 8] = [
+        "timed out",
+        "timeout",
+        "connection",
+        "too many requests",
+        "rate limit",
+        "bad gateway",
+        "service unavailable",
+        "header not found",
+    ];
+    const TRANSIENT_STATUSES: [&str; 3] = ["429", "502", "503"];
+
+    let message = message.to_lowercase();
+    TRANSIENT_ERRORS.iter().any(|s| message.contains(s))
+        || TRANSIENT_STATUSES
+            .iter()
+            .any(|status| has_status(&message, status))
+}
+
+/// Whether the (lower-cased) message has the HTTP status or JSON-RPC error
+/// code as a token after "status", "http", or "code" (e.g., "status(429)",
+/// "http 503", "code: 429"), and not only its digits (e.g., in call data).
+fn has_status(message: &str, status: &str) -> bool {
+    message.match_indices(status).any(|(i, _)| {
+        let bounded = !message[i + status.len()..].starts_with(|c: char| c.is_ascii_alphanumeric());
+        let before = message[..i].trim_end_matches([' ', ':', '(', '=', '"']);
+        bounded
+            && ["status", "http", "code"]
+                .iter()
+                .any(|s| before.ends_with(s))
+    })
+}
+
+/// When the transaction was first broadcast, according to its state (see
+/// `PersistentState::first_submitted_at`), or now, if unknown.
+fn first_submission(state: &PersistentState) -> Instant {
+    let in_flight = state
+        .first_submitted_at
+        .map(|first_submitted_at| unix_millis().saturating_sub(first_submitted_at))
+        .unwrap_or_default();
+    Instant::now()
+        .checked_sub(Duration::from_millis(in_flight))
+        .unwrap_or_else(Instant::now)
+}
+
+/// Milliseconds since the Unix epoch.
+fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
 }
 
 fn is_error<E>(err: &E, s: &str) -> bool
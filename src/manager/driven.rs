@@ -0,0 +1,541 @@
+//! Caller-driven mode: instead of sleeping and sending on its own, the manager
+//! tells the caller what to do next (`Manager::next_action`), and the caller
+//! reports the result (`Manager::apply`), so that embedders can interleave the
+//! manager's state machine with their own scheduling (e.g., a node's event
+//! loop).
+//!
+//! ```ignore
+//! manager.drive(transaction, SendOptions::new()).await?;
+//! let receipt = loop {
+//!     match manager.next_action().await? {
+//!         Action::Sleep(duration) => {
+//!             tokio::time::sleep(duration).await;
+//!             manager.apply(ActionResult::Slept).await?;
+//!         }
+//!         Action::Submit(signed) => {
+//!             let result = broadcaster.send_raw_transaction(signed.raw_transaction).await;
+//!             manager.apply(ActionResult::Submitted(result)).await?;
+//!         }
+//!         Action::Done(receipt) => break receipt,
+//!     }
+//! };
+//! ```
+//!
+//! The driven transaction is held (with `Action::Sleep`) while the manager is
+//! paused or the base fee is above the max fee cap, and resent with a raised
+//! max fee when the node rejects it for not covering the base fee, as the ones
+//! sent with `send`. Its receipt is validated (see
+//! `Configuration::validate_receipts`), and its transitions are journaled.
+
+use ethers::{
+    providers::Middleware,
+    types::{Bytes, TransactionReceipt, H256, U256},
+};
+use std::time::{Duration, Instant};
+use tracing::{error, info, trace, warn};
+
+use super::{
+    is_error, multiply_gas_info, unix_millis, Error, InFlight, Manager, ResubmissionMode,
+    SendOptions, BASE_FEE_REJECTION_RETRIES,
+};
+use crate::database::Database;
+use crate::dyn_manager::DynError;
+use crate::events::Event;
+use crate::gas_oracle::{GasInfo, GasOracle};
+use crate::hashing::HashMismatchPolicy;
+use crate::time::Time;
+use crate::transaction::{Attempt, BroadcastStatus, PersistentState, StaticTxData, Transaction};
+use crate::watcher::WatchStatus;
+
+/// What the caller should do next for the driven transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Wait for the duration, then report `ActionResult::Slept`.
+    Sleep(Duration),
+
+    /// Broadcast the signed transaction, then report the result with
+    /// `ActionResult::Submitted`.
+    Submit(SignedTransaction),
+
+    /// The transaction was confirmed, and the manager is done with it.
+    Done(Box<TransactionReceipt>),
+}
+
+/// A signed transaction, ready to be broadcast.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedTransaction {
+    pub hash: H256,
+    pub raw_transaction: Bytes,
+}
+
+/// The result of an action performed by the caller.
+#[derive(Debug)]
+pub enum ActionResult {
+    Slept,
+
+    /// The hash returned by the node, or the error of the broadcast.
+    Submitted(Result<H256, DynError>),
+}
+
+/// The transaction being driven.
+#[derive(Debug)]
+pub(super) struct Driven {
+    state: PersistentState,
+
+    /// The submission the caller was asked to perform (and report).
+    submission: Option<SignedTransaction>,
+
+    /// When the transaction was last broadcast.
+    last_submission: Option<Instant>,
+
+    /// Whether the caller must sleep before the manager checks the
+    /// transaction (after a submission).
+    awaiting_sleep: bool,
+
+    /// The gas limit of the submission.
+    gas_limit: Option<U256>,
+
+    /// Whether the transaction is held while the base fee is above the max
+    /// fee cap.
+    waiting_for_fee_drop: bool,
+
+    /// The fees of the resubmission after the node rejected the max fee (see
+    /// `Manager::raise_rejected_max_fee`), and how many times it did.
+    raised_gas_info: Option<GasInfo>,
+    base_fee_rejections: u32,
+}
+
+impl<M: Middleware, GO: GasOracle, DB: Database, T: Time> Manager<M, GO, DB, T>
+where
+    M: Send + Sync,
+    GO: Send + Sync,
+    DB: Send + Sync,
+    T: Send + Sync,
+{
+    /// Starts driving the transaction in the caller-driven mode (see the
+    /// module documentation). The transaction is persisted in the database as
+    /// the ones sent with `send`.
+    pub async fn drive(
+        &mut self,
+        transaction: Transaction,
+        options: SendOptions,
+    ) -> Result<(), Error<M, GO, DB>> {
+        if self.driven.is_some() {
+            return Err(Error::Driven("a transaction is already being driven"));
+        }
+
+        self.check_signer(transaction.from)?;
+        let transaction = self.resolve_recipient(transaction).await?;
+        let nonce = self.assign_nonce(&transaction).await?;
+        let confirmations = options
+            .confirmations
+            .unwrap_or_else(|| self.chain.default_confirmations());
+        let tx_data = StaticTxData::new(transaction, nonce, confirmations, options.priority);
+        trace!("Driving the transaction => {:?}", tx_data);
+
+        self.in_flight = InFlight::default();
+        self.driven = Some(Driven {
+            state: PersistentState::new(tx_data),
+            submission: None,
+            last_submission: None,
+            awaiting_sleep: false,
+            gas_limit: None,
+            waiting_for_fee_drop: false,
+            raised_gas_info: None,
+            base_fee_rejections: 0,
+        });
+        Ok(())
+    }
+
+    /// Checks the driven transaction and returns what the caller should do
+    /// next. Asks again for the same submission until its result is applied.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn next_action(&mut self) -> Result<Action, Error<M, GO, DB>> {
+        let mut driven = self
+            .driven
+            .take()
+            .ok_or(Error::Driven("no transaction is being driven"))?;
+        let result = self
+            .next_driven_action(&mut driven)
+            .await
+            .map_err(|err| self.driven_failed(&driven, err));
+        if !matches!(result, Ok(Action::Done(_))) {
+            self.driven = Some(driven);
+        }
+        result
+    }
+
+    /// Applies the result of the action performed by the caller.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn apply(&mut self, result: ActionResult) -> Result<(), Error<M, GO, DB>> {
+        let mut driven = self
+            .driven
+            .take()
+            .ok_or(Error::Driven("no transaction is being driven"))?;
+        let result = self
+            .apply_driven(&mut driven, result)
+            .await
+            .map_err(|err| self.driven_failed(&driven, err));
+        self.driven = Some(driven);
+        result
+    }
+}
+
+impl<M: Middleware, GO: GasOracle, DB: Database, T: Time> Manager<M, GO, DB, T>
+where
+    M: Send + Sync,
+    GO: Send + Sync,
+    DB: Send + Sync,
+    T: Send + Sync,
+{
+    async fn next_driven_action(
+        &mut self,
+        driven: &mut Driven,
+    ) -> Result<Action, Error<M, GO, DB>> {
+        if let Some(submission) = &driven.submission {
+            return Ok(Action::Submit(submission.clone()));
+        }
+        if driven.awaiting_sleep {
+            return Ok(Action::Sleep(self.block_time()));
+        }
+
+        // Were any of the transactions mined?
+        self.check_stall(&driven.state);
+        self.check_provider_freshness().await?;
+        self.in_flight.polls += 1;
+        let receipt = self
+            .get_mined_transaction(&mut driven.state)
+            .await?
+            .filter(|receipt| receipt.block_number.is_some());
+        self.check_reorg(&driven.state, receipt.as_ref());
+
+        if let Some(receipt) = receipt {
+            let WatchStatus {
+                receipt,
+                depth,
+                confirmed,
+                ..
+            } = self
+                .watcher(&driven.state)
+                .status(&self.provider, receipt)
+                .await
+                .map_err(Error::Middleware)?;
+            trace!("The transaction was mined (depth = {}).", depth);
+            if !confirmed {
+                return Ok(Action::Sleep(self.block_time()));
+            }
+            if self.configuration.validate_receipts {
+                self.validate_receipt(&driven.state, &receipt).await?;
+            }
+
+            self.emit_confirmed(&driven.state, &receipt);
+            self.observe_timings();
+            self.clear_state().await?;
+            info!(
+                "Transaction with nonce {:?} was sent. Transaction hash = {:?}.",
+                driven.state.tx_data.nonce, receipt.transaction_hash
+            );
+            return Ok(Action::Done(Box::new(receipt)));
+        }
+
+        // Holding the submission while paused.
+        if self.configuration.pause_switch.is_paused() {
+            trace!(
+                "The manager is paused, holding the transaction with nonce {:?}.",
+                driven.state.tx_data.nonce
+            );
+            return Ok(Action::Sleep(self.block_time()));
+        }
+
+        // Holding the resubmissions while the base fee is above the max fee cap.
+        match self.base_fee_above_cap(&driven.state).await? {
+            Some((base_fee, max_fee_cap)) => {
+                if !driven.waiting_for_fee_drop {
+                    driven.waiting_for_fee_drop = true;
+                    self.hold_for_fee_drop(&driven.state, base_fee, max_fee_cap);
+                }
+                return Ok(Action::Sleep(self.block_time()));
+            }
+            None if driven.waiting_for_fee_drop => {
+                driven.waiting_for_fee_drop = false;
+                info!("The base fee is back within the max fee cap, resuming.");
+            }
+            None => {}
+        }
+
+        // Is a (re)submission due?
+        let wait_time = self.get_wait_time(&driven.state, None);
+        let speed_up = self.configuration.speed_up_switch.is_requested();
+        let raised = driven.raised_gas_info.is_some();
+        let submission_due = match driven.last_submission {
+            Some(_) if speed_up || raised => true,
+            Some(last_submission) => {
+                let waited_too_much = self.configuration.time.elapsed(last_submission) > wait_time;
+                if waited_too_much {
+                    self.check_nonce_consumed(&mut driven.state).await?;
+                    driven.state.resubmissions += 1;
+                }
+                waited_too_much
+            }
+            None => true,
+        };
+        if !submission_due {
+            return Ok(Action::Sleep(self.block_time()));
+        }
+
+        // Resubmitting without bumping the fees (see `ResubmissionMode`).
+        let resubmission_mode = self.configuration.resubmission_mode;
+        if driven.last_submission.is_some()
+            && !speed_up
+            && !raised
+            && resubmission_mode != ResubmissionMode::BumpFees
+        {
+            let last_broadcast = self
+                .in_flight
+                .last_broadcast
+                .clone()
+                .filter(|_| resubmission_mode == ResubmissionMode::RebroadcastOnly);
+            return match last_broadcast {
+                Some(submission) => {
+                    driven.submission = Some(submission.clone());
+                    Ok(Action::Submit(submission))
+                }
+                None => {
+                    driven.last_submission = Some(Instant::now());
+                    Ok(Action::Sleep(self.block_time()))
+                }
+            };
+        }
+
+        let raised_gas_info = driven.raised_gas_info.take();
+        let (submission, gas_limit) = match self
+            .prepare_submission(&mut driven.state, raised_gas_info)
+            .await?
+        {
+            Some(submission) => submission,
+            None => {
+                trace!("The node refused further replacements, skipping the resubmission.");
+                driven.last_submission = Some(Instant::now());
+                return Ok(Action::Sleep(self.block_time()));
+            }
+        };
+        if self.skip_broadcast(&driven.state, submission.hash) {
+            trace!(
+                "The transaction with hash {:?} was already broadcast, skipping the \
+                resubmission.",
+                submission.hash
+            );
+            driven.last_submission = Some(Instant::now());
+            return Ok(Action::Sleep(self.block_time()));
+        }
+        driven.submission = Some(submission.clone());
+        driven.gas_limit = gas_limit;
+        Ok(Action::Submit(submission))
+    }
+
+    /// Reports the failure of the driven transaction (see `Manager::emit_failed`).
+    fn driven_failed(&self, driven: &Driven, err: Error<M, GO, DB>) -> Error<M, GO, DB> {
+        let tx_data = &driven.state.tx_data;
+        self.emit_failed(Some(tx_data.nonce), &tx_data.transaction, err)
+    }
+
+    /// Writes the state of the driven transaction to the database (see
+    /// `Manager::shutdown`).
+    pub(super) async fn flush_driven(&mut self) -> Result<(), Error<M, GO, DB>> {
+        if let Some(driven) = self.driven.take() {
+            trace!("Flushing the driven transaction => {:?}", driven.state);
+            self.set_state(&driven.state).await?;
+        }
+        Ok(())
+    }
+
+    /// Signs the transaction with the current fees (or with the raised ones,
+    /// if any), and stores it as prepared. Returns `None` when it would be a
+    /// new replacement after the node refused further ones.
+    async fn prepare_submission(
+        &mut self,
+        state: &mut PersistentState,
+        raised_gas_info: Option<GasInfo>,
+    ) -> Result<Option<(SignedTransaction, Option<U256>)>, Error<M, GO, DB>> {
+        let gas_info = match raised_gas_info {
+            Some(gas_info) => gas_info,
+            None => self.current_gas_info(state).await?,
+        };
+        let typed_transaction = self.typed_transaction(&state.tx_data, gas_info).await?;
+        let (hash, raw_transaction) = self.raw_transaction(&typed_transaction).await?;
+
+        if !state.submitted_txs.contains(hash) && self.in_flight.replacement_limit_reached {
+            return Ok(None);
+        }
+        if !state.submitted_txs.contains(hash) {
+            state.submitted_txs.add(hash);
+            state.broadcast_statuses.insert(
+                hash,
+                BroadcastStatus::PreparedNotBroadcast(raw_transaction.clone()),
+            );
+            state.attempts.push(Attempt { hash, gas_info });
+            self.set_state(state).await?;
+        }
+        let submission = SignedTransaction {
+            hash,
+            raw_transaction,
+        };
+        Ok(Some((submission, typed_transaction.gas().copied())))
+    }
+
+    /// The oracle's fees, sped up on demand and capped.
+    async fn current_gas_info(
+        &mut self,
+        state: &PersistentState,
+    ) -> Result<GasInfo, Error<M, GO, DB>> {
+        let mut gas_info = self
+            .get_gas_oracle_info(state.tx_data.priority)
+            .await?
+            .gas_info;
+        if let Some(multiplier) = self
+            .configuration
+            .speed_up_switch
+            .take(self.node_client.replacement_fee_bump())
+        {
+            gas_info = match state.submitted_txs.txs_hashes.last() {
+                Some(&last_hash) => self.bump_gas_info(last_hash, gas_info, multiplier).await?,
+                None => multiply_gas_info(gas_info, multiplier),
+            };
+        }
+
+        Ok(self.cap_gas_info(gas_info))
+    }
+
+    async fn apply_driven(
+        &mut self,
+        driven: &mut Driven,
+        result: ActionResult,
+    ) -> Result<(), Error<M, GO, DB>> {
+        let (submission, result) = match result {
+            ActionResult::Slept => {
+                driven.awaiting_sleep = false;
+                return Ok(());
+            }
+            ActionResult::Submitted(result) => match driven.submission.take() {
+                Some(submission) => (submission, result),
+                None => return Err(Error::Driven("there is no submission to apply")),
+            },
+        };
+
+        let returned_hash = match result {
+            Ok(returned_hash) => returned_hash,
+            Err(err)
+                if is_error(&err, "already known") || self.is_replacement_underpriced(&err) =>
+            {
+                warn!("The node did not accept the submission: {}", err);
+                driven.last_submission = Some(Instant::now());
+                driven.awaiting_sleep = true;
+                return Ok(());
+            }
+            Err(err) if self.is_replacement_limit_reached(&err) => {
+                self.replacement_limit_reached(&driven.state);
+                driven.last_submission = Some(Instant::now());
+                driven.awaiting_sleep = true;
+                return Ok(());
+            }
+            Err(err) if is_error(&err, "max fee per gas less than block base fee") => {
+                let gas_info = driven
+                    .state
+                    .attempts
+                    .iter()
+                    .rfind(|attempt| attempt.hash == submission.hash)
+                    .map(|attempt| attempt.gas_info);
+                let eip1559_gas_info = match gas_info {
+                    Some(GasInfo::EIP1559(eip1559_gas_info)) => eip1559_gas_info,
+                    _ => {
+                        error!("Error while submitting transaction: {}", err);
+                        return Err(Error::Broadcast(err));
+                    }
+                };
+                warn!("The node rejected the max fee for not covering the base fee.");
+                let base_fee = self.get_latest_base_fee().await?;
+                if driven.base_fee_rejections == BASE_FEE_REJECTION_RETRIES {
+                    error!("The node keeps rejecting the max fee.");
+                    return Err(Error::MaxFeeBelowBaseFee {
+                        max_fee: eip1559_gas_info.max_fee.unwrap_or_default(),
+                        base_fee,
+                    });
+                }
+                driven.base_fee_rejections += 1;
+                driven.raised_gas_info = Some(GasInfo::EIP1559(
+                    self.raise_rejected_max_fee(eip1559_gas_info, base_fee),
+                ));
+                return Ok(());
+            }
+            Err(err) => {
+                error!("Error while submitting transaction: {}", err);
+                return Err(Error::Broadcast(err));
+            }
+        };
+
+        let hash = match self
+            .configuration
+            .hash_strategy
+            .verify(submission.hash, returned_hash)
+        {
+            Some(hash) => hash,
+            None => match self.configuration.hash_mismatch_policy {
+                HashMismatchPolicy::Error => {
+                    return Err(Error::HashMismatch {
+                        derived: submission.hash,
+                        returned: returned_hash,
+                    })
+                }
+                HashMismatchPolicy::Adopt => returned_hash,
+            },
+        };
+
+        let state = &mut driven.state;
+        if !state.submitted_txs.contains(hash) {
+            state.submitted_txs.add(hash);
+        }
+        for hash in [submission.hash, hash] {
+            state
+                .broadcast_statuses
+                .insert(hash, BroadcastStatus::Broadcast);
+        }
+        state.first_submitted_at.get_or_insert_with(unix_millis);
+        self.set_state(state).await?;
+
+        self.in_flight
+            .first_submission
+            .get_or_insert_with(Instant::now);
+        self.in_flight.last_broadcast = Some(submission.clone());
+        driven.last_submission = Some(Instant::now());
+        driven.awaiting_sleep = true;
+        driven.base_fee_rejections = 0;
+        info!("The caller has submitted transaction with hash {:?}.", hash);
+        let raw_transaction = self
+            .configuration
+            .include_raw_transactions
+            .then_some(submission.raw_transaction);
+        self.emit(Event::Submitted {
+            nonce: driven.state.tx_data.nonce,
+            hash,
+            raw_transaction: raw_transaction.clone(),
+            eta: self.confirmation_eta(&driven.state, None),
+            metadata: driven.state.tx_data.transaction.metadata.clone(),
+        });
+        let gas_info = driven
+            .state
+            .attempts
+            .iter()
+            .rfind(|attempt| attempt.hash == submission.hash)
+            .map(|attempt| attempt.gas_info);
+        if let Some(gas_info) = gas_info {
+            self.journal_submission(
+                &driven.state,
+                hash,
+                gas_info,
+                driven.gas_limit,
+                raw_transaction,
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+use tracing::{trace, warn};
+
+use crate::price_feed::PriceFeed;
+
+const URL: &str = "https://api.coingecko.com/api/v3/simple/price?ids=ethereum&vs_currencies=usd";
+
+/// Implementation that uses the Coingecko API.
+
+#[derive(Debug, thiserror::Error)]
+pub enum CoingeckoError {
+    #[error("GET request error: {0}")]
+    Request(reqwest::Error),
+
+    #[error("invalid status code: {0}")]
+    StatusCode(reqwest::StatusCode),
+
+    #[error("could not parse the request's response: {0}")]
+    ParseResponse(serde_json::Error),
+}
+
+/// Price feed that caches the ETH price fetched from Coingecko, refreshing it
+/// in the background (requires a tokio runtime) until the feed is dropped.
+#[derive(Debug)]
+pub struct CoingeckoPriceFeed {
+    price: Arc<RwLock<Option<f64>>>,
+}
+
+impl CoingeckoPriceFeed {
+    pub fn new(refresh_interval: Duration) -> CoingeckoPriceFeed {
+        let price = Arc::new(RwLock::new(None));
+        tokio::spawn(refresh(Arc::downgrade(&price), refresh_interval));
+        CoingeckoPriceFeed { price }
+    }
+}
+
+impl PriceFeed for CoingeckoPriceFeed {
+    fn eth_price_usd(&self) -> Option<f64> {
+        *self.price.read().unwrap()
+    }
+}
+
+async fn refresh(price: Weak<RwLock<Option<f64>>>, refresh_interval: Duration) {
+    loop {
+        let result = fetch().await;
+        let price = match price.upgrade() {
+            Some(price) => price,
+            None => return, // the feed was dropped
+        };
+        match result {
+            Ok(eth_price_usd) => {
+                trace!("ETH price: {} USD", eth_price_usd);
+                *price.write().unwrap() = Some(eth_price_usd);
+            }
+            Err(err) => warn!("Could not fetch the ETH price: {}", err),
+        }
+        drop(price);
+        tokio::time::sleep(refresh_interval).await;
+    }
+}
+
+async fn fetch() -> Result<f64, CoingeckoError> {
+    let res = reqwest::get(URL).await.map_err(CoingeckoError::Request)?;
+    if res.status() != StatusCode::OK {
+        return Err(CoingeckoError::StatusCode(res.status()));
+    }
+
+    let bytes = &res.bytes().await.map_err(CoingeckoError::Request)?;
+    parse(bytes)
+}
+
+fn parse(bytes: &[u8]) -> Result<f64, CoingeckoError> {
+    let response: CoingeckoResponse =
+        serde_json::from_slice(bytes).map_err(CoingeckoError::ParseResponse)?;
+    Ok(response.ethereum.usd)
+}
+
+#[derive(Debug, Deserialize)]
+struct CoingeckoResponse {
+    ethereum: CoingeckoPrice,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoingeckoPrice {
+    usd: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, CoingeckoError};
+
+    #[test]
+    fn test_coingecko_parse() {
+        let result = parse(br#"{"ethereum":{"usd":1234.5}}"#);
+        assert!(matches!(result, Ok(price) if price == 1234.5));
+
+        let result = parse(br#"{"error":"rate limited"}"#);
+        assert!(matches!(result, Err(CoingeckoError::ParseResponse(_))));
+    }
+}
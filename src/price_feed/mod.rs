@@ -0,0 +1,22 @@
+use ethers::{types::U256, utils::format_units};
+use std::fmt::Debug;
+
+#[cfg(feature = "coingecko")]
+mod coingecko;
+
+#[cfg(feature = "coingecko")]
+pub use coingecko::{CoingeckoError, CoingeckoPriceFeed};
+
+/// Provides the current price of ETH in USD, used to annotate costs in fiat
+/// (see `Manager::estimate_cost` and `Event::Confirmed`). Implementations must
+/// not block, as they are called from within the manager's control flow.
+pub trait PriceFeed: Debug + Send + Sync {
+    fn eth_price_usd(&self) -> Option<f64>;
+}
+
+/// Converts an amount in wei to USD, if the price feed has a price.
+pub fn to_usd(price_feed: &dyn PriceFeed, wei: U256) -> Option<f64> {
+    let eth_price_usd = price_feed.eth_price_usd()?;
+    let eth: f64 = format_units(wei, "ether").ok()?.parse().ok()?;
+    Some(eth * eth_price_usd)
+}
@@ -0,0 +1,97 @@
+use ethers::types::{H256, U256};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::time::Duration;
+
+use crate::formatting;
+use crate::transaction::Attempt;
+
+/// What the manager did while sending a transaction that failed, for bug
+/// reports and incident reviews (see `Error::Failed`). It prints as a compact
+/// table (with both `Display` and `Debug`) and serializes to JSON, with the
+/// times in milliseconds.
+#[derive(Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SendReport {
+    pub nonce: U256,
+
+    /// The submissions of the transaction (and of its cancellation, if any),
+    /// with their fees, in order.
+    pub attempts: Vec<Attempt>,
+
+    pub resubmissions: u32,
+
+    /// From the first submission to the failure (none if it was never
+    /// submitted).
+    #[serde(rename = "in_flight_ms", serialize_with = "millis")]
+    pub in_flight: Option<Duration>,
+
+    /// See `SendTimings`.
+    #[serde(rename = "mining_time_ms", serialize_with = "millis")]
+    pub mining_time: Option<Duration>,
+    #[serde(rename = "confirmation_time_ms", serialize_with = "millis")]
+    pub confirmation_time: Option<Duration>,
+
+    /// The time spent in the database.
+    #[serde(rename = "database_ms", serialize_with = "total_millis")]
+    pub database: Duration,
+
+    /// The last errors the node returned to the submissions, oldest first.
+    pub rpc_errors: Vec<String>,
+}
+
+impl SendReport {
+    /// The hashes of the submitted transactions, in order.
+    pub fn hashes(&self) -> impl Iterator<Item = H256> + '_ {
+        self.attempts.iter().map(|attempt| attempt.hash)
+    }
+}
+
+impl fmt::Display for SendReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let duration = |duration: Option<Duration>| match duration {
+            Some(duration) => format!("{:?}", duration),
+            None => "-".to_string(),
+        };
+        writeln!(
+            f,
+            "nonce {} | {} attempts ({} resubmissions) | in flight {} | mining {} | \
+            confirmation {} | database {}",
+            self.nonce,
+            self.attempts.len(),
+            self.resubmissions,
+            duration(self.in_flight),
+            duration(self.mining_time),
+            duration(self.confirmation_time),
+            duration(Some(self.database)),
+        )?;
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3} | {:?} | {}",
+                i + 1,
+                attempt.hash,
+                formatting::fees(&attempt.gas_info)
+            )?;
+        }
+        for error in &self.rpc_errors {
+            writeln!(f, "  ! | {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SendReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+fn millis<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    duration
+        .map(|duration| duration.as_millis() as u64)
+        .serialize(serializer)
+}
+
+fn total_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    millis(&Some(*duration), serializer)
+}
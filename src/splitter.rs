@@ -0,0 +1,22 @@
+use crate::transaction::Transaction;
+
+/// Splits operations that do not fit in a block into smaller ones (see
+/// `Manager::send_split`).
+///
+/// Closures of type `Fn(&Transaction) -> Option<Vec<Transaction>>` are
+/// splitters.
+pub trait Splitter: Send + Sync {
+    /// Splits the transaction into transactions that perform the same
+    /// operation when sent in order (e.g., a batch into two halves), or
+    /// returns `None` if it cannot be split any further.
+    fn split(&self, transaction: &Transaction) -> Option<Vec<Transaction>>;
+}
+
+impl<F> Splitter for F
+where
+    F: Fn(&Transaction) -> Option<Vec<Transaction>> + Send + Sync,
+{
+    fn split(&self, transaction: &Transaction) -> Option<Vec<Transaction>> {
+        self(transaction)
+    }
+}
@@ -0,0 +1,266 @@
+//! A directory of state files, one per sender and chain, for processes that run
+//! many managers (see `StateStore`).
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockId, BlockNumber, NameOrAddress, U256},
+};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use tokio::fs;
+use tracing::{info, warn};
+
+use crate::database::{Database, FileSystemDatabase};
+use crate::dyn_manager::DynError;
+
+const EXTENSION: &str = "json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum StateStoreError {
+    #[error("could not read the directory: {0}")]
+    ReadDirectory(std::io::Error),
+
+    #[error("could not delete file: {0}")]
+    DeleteFile(std::io::Error),
+
+    #[error("provider error: {0}")]
+    Middleware(DynError),
+}
+
+/// A state file of the store.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateFile {
+    pub chain_id: u64,
+    pub sender: Address,
+    pub path: PathBuf,
+
+    /// When the file was last written.
+    pub modified: Option<SystemTime>,
+}
+
+/// What a state file holds (see `StateStore::summary`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateSummary {
+    pub file: StateFile,
+
+    /// The nonce of the pending transaction (none if the file is unreadable).
+    pub nonce: Option<U256>,
+
+    /// How many transactions were submitted with the nonce.
+    pub submitted: usize,
+
+    /// Why the file could not be read, if it could not.
+    pub error: Option<String>,
+}
+
+/// Manages a directory of state files, named after the chain and the sender
+/// (`<chain id>-<sender>.json`), so that processes running many managers do not
+/// need their own naming scheme.
+///
+/// The managers clear their state files once their transactions are confirmed.
+/// Files left behind (e.g., by processes that crashed, and were not restarted
+/// for that sender) can be pruned with `StateStore::prune`.
+#[derive(Clone, Debug)]
+pub struct StateStore {
+    directory: PathBuf,
+}
+
+impl StateStore {
+    pub fn new(directory: impl Into<PathBuf>) -> StateStore {
+        StateStore {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// The path of the state file of the sender on the chain.
+    pub fn path(&self, chain_id: u64, sender: Address) -> PathBuf {
+        self.directory
+            .join(format!("{}-{:?}.{}", chain_id, sender, EXTENSION))
+    }
+
+    /// The database of the sender on the chain (for its manager).
+    pub fn database(&self, chain_id: u64, sender: Address) -> FileSystemDatabase {
+        FileSystemDatabase::new(self.path(chain_id, sender).to_string_lossy().into_owned())
+    }
+
+    /// The state files in the directory, ordered by chain and sender. Other
+    /// files are ignored, and a missing directory has no files.
+    pub async fn list(&self) -> Result<Vec<StateFile>, StateStoreError> {
+        let mut entries = match fs::read_dir(&self.directory).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(StateStoreError::ReadDirectory(err)),
+        };
+
+        let mut files = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(StateStoreError::ReadDirectory)?
+        {
+            let path = entry.path();
+            let (chain_id, sender) = match parse_file_name(&path) {
+                Some(parsed) => parsed,
+                None => continue,
+            };
+            let modified = entry
+                .metadata()
+                .await
+                .ok()
+                .and_then(|metadata| metadata.modified().ok());
+            files.push(StateFile {
+                chain_id,
+                sender,
+                path,
+                modified,
+            });
+        }
+        files.sort_by_key(|file| (file.chain_id, file.sender));
+        Ok(files)
+    }
+
+    /// Summarizes the state files (e.g., for dashboards).
+    pub async fn summary(&self) -> Result<Vec<StateSummary>, StateStoreError> {
+        let mut summaries = Vec::new();
+        for file in self.list().await? {
+            let database = self.database(file.chain_id, file.sender);
+            let (nonce, submitted, error) = match database.get_state().await {
+                Ok(Some(state)) => (Some(state.tx_data.nonce), state.txs_hashes().count(), None),
+                Ok(None) => (None, 0, None),
+                Err(err) => (None, 0, Some(err.to_string())),
+            };
+            summaries.push(StateSummary {
+                file,
+                nonce,
+                submitted,
+                error,
+            });
+        }
+        Ok(summaries)
+    }
+
+    /// Deletes the state files of the chain that were last written more than
+    /// `older_than` ago and whose transactions were confirmed (their nonces
+    /// were consumed, according to the provider). Returns the deleted files.
+    ///
+    /// Must not run concurrently with the managers of the senders it prunes.
+    pub async fn prune<M: Middleware>(
+        &self,
+        provider: &M,
+        chain_id: u64,
+        older_than: Duration,
+    ) -> Result<Vec<StateFile>, StateStoreError>
+    where
+        M::Error: 'static,
+    {
+        let mut pruned = Vec::new();
+        for file in self.list().await? {
+            let age = file
+                .modified
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+            if file.chain_id != chain_id || !matches!(age, Some(age) if age > older_than) {
+                continue;
+            }
+
+            let state = match self.database(chain_id, file.sender).get_state().await {
+                Ok(Some(state)) => state,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!("Skipping the state file {:?}: {}", file.path, err);
+                    continue;
+                }
+            };
+            let mined_nonce = provider
+                .get_transaction_count(
+                    NameOrAddress::Address(file.sender),
+                    Some(BlockId::Number(BlockNumber::Latest)),
+                )
+                .await
+                .map_err(|err| StateStoreError::Middleware(Box::new(err)))?;
+            if mined_nonce <= state.tx_data.nonce {
+                continue;
+            }
+
+            info!(
+                "Pruning the state file {:?} (nonce {} was consumed).",
+                file.path, state.tx_data.nonce
+            );
+            fs::remove_file(&file.path)
+                .await
+                .map_err(StateStoreError::DeleteFile)?;
+            pruned.push(file);
+        }
+        Ok(pruned)
+    }
+}
+
+/// Parses `<chain id>-<sender>.json` file names.
+fn parse_file_name(path: &Path) -> Option<(u64, Address)> {
+    if path.extension()? != EXTENSION {
+        return None;
+    }
+    let (chain_id, sender) = path.file_stem()?.to_str()?.split_once('-')?;
+    Some((chain_id.parse().ok()?, Address::from_str(sender).ok()?))
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::{Address, H160, U256};
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use super::StateStore;
+    use crate::database::Database;
+    use crate::transaction::{PersistentState, Priority, StaticTxData, Transaction, Value};
+
+    #[tokio::test]
+    async fn test_state_store_summary() {
+        let directory = std::env::temp_dir().join("tx_manager_state_store_test");
+        let _ = remove_dir_all(&directory);
+        let store = StateStore::new(&directory);
+        assert!(store.list().await.unwrap().is_empty());
+        create_dir_all(&directory).unwrap();
+
+        let sender = H160::from_low_u64_be(1);
+        let path = store.path(5, sender);
+        assert_eq!(
+            "5-0x0000000000000000000000000000000000000001.json",
+            path.file_name().unwrap()
+        );
+
+        let transaction = Transaction {
+            from: sender,
+            to: H160::from_low_u64_be(2).into(),
+            value: Value::Nothing,
+            call_data: None,
+            metadata: Default::default(),
+        };
+        let tx_data = StaticTxData::new(transaction, 7u64.into(), 1, Priority::Normal);
+        let mut database = store.database(5, sender);
+        database
+            .set_state(&PersistentState::new(tx_data))
+            .await
+            .unwrap();
+        write(store.path(1, Address::zero()), "this is not a JSON!").unwrap();
+        write(directory.join("notes.txt"), "ignored").unwrap();
+
+        let summaries = store.summary().await.unwrap();
+        assert_eq!(2, summaries.len());
+        assert_eq!(1, summaries[0].file.chain_id);
+        assert!(summaries[0].error.is_some());
+        assert_eq!(
+            (5, sender),
+            (summaries[1].file.chain_id, summaries[1].file.sender)
+        );
+        assert_eq!(Some(U256::from(7)), summaries[1].nonce);
+        assert_eq!(0, summaries[1].submitted);
+        assert!(summaries[1].file.modified.is_some());
+
+        remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+use opentelemetry::{
+    propagation::{Extractor, TextMapPropagator},
+    Context,
+};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::transaction::Metadata;
+
+/// Metadata key of the W3C trace context (e.g.,
+/// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`) under which
+/// callers propagate their traces into the transaction manager.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Extracts the caller's trace context from the transaction's metadata.
+pub fn extract_context(metadata: &Metadata) -> Context {
+    TraceContextPropagator::new().extract(&MetadataExtractor(metadata))
+}
+
+/// Makes the caller's trace (if any) the parent of the given span.
+pub(crate) fn set_parent(span: &Span, metadata: &Metadata) {
+    if metadata.contains_key(TRACEPARENT) {
+        span.set_parent(extract_context(metadata));
+    }
+}
+
+struct MetadataExtractor<'a>(&'a Metadata);
+
+impl<'a> Extractor for MetadataExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(&key.to_lowercase()).map(|value| value.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{TraceContextExt, TraceId};
+
+    use crate::telemetry::{extract_context, TRACEPARENT};
+    use crate::transaction::Metadata;
+
+    #[test]
+    fn test_extract_context() {
+        let mut metadata = Metadata::new();
+        metadata.insert(
+            TRACEPARENT.to_string(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string(),
+        );
+        let context = extract_context(&metadata);
+        let span_context = context.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            span_context.trace_id()
+        );
+
+        // no traceparent => invalid span context
+        let context = extract_context(&Metadata::new());
+        assert!(!context.span().span_context().is_valid());
+    }
+}
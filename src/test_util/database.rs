@@ -1,26 +1,35 @@
 use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
 
-use eth_tx_manager::transaction;
+use crate::transaction;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct MockDatabase {
     pub set_state_output: Option<()>,
     pub get_state_output: Option<Option<transaction::PersistentState>>,
     pub clear_state_output: Option<()>,
+    counters: Arc<Mutex<Counters>>, // shared by the clones
 }
 
 impl MockDatabase {
     pub fn new() -> Self {
-        Global::setup();
         Self {
             set_state_output: None,
             get_state_output: None,
             clear_state_output: None,
+            counters: Arc::new(Mutex::new(Counters::default())),
         }
     }
 
-    pub fn global() -> &'static Global {
-        unsafe { &GLOBAL }
+    /// How many times each function was called (by this mock and its clones).
+    pub fn counters(&self) -> Counters {
+        self.counters.lock().unwrap().clone()
+    }
+}
+
+impl Default for MockDatabase {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -37,48 +46,39 @@ pub enum DatabaseStateError {
 }
 
 #[async_trait]
-impl eth_tx_manager::database::Database for MockDatabase {
+impl crate::database::Database for MockDatabase {
     type Error = DatabaseStateError;
 
     async fn set_state(&mut self, _: &transaction::PersistentState) -> Result<(), Self::Error> {
-        unsafe { GLOBAL.set_state_n += 1 };
+        self.counters.lock().unwrap().set_state_n += 1;
         self.set_state_output.ok_or(DatabaseStateError::Set)
     }
 
     async fn get_state(&self) -> Result<Option<transaction::PersistentState>, Self::Error> {
-        unsafe { GLOBAL.get_state_n += 1 };
+        self.counters.lock().unwrap().get_state_n += 1;
         self.get_state_output
             .as_ref()
             .ok_or(DatabaseStateError::Get)
-            .map(|x| x.clone())
+            .cloned()
     }
 
     async fn clear_state(&mut self) -> Result<(), Self::Error> {
-        unsafe { GLOBAL.clear_state_n += 1 };
+        self.counters.lock().unwrap().clear_state_n += 1;
         self.clear_state_output.ok_or(DatabaseStateError::Clear)
     }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        self.counters.lock().unwrap().close_n += 1;
+        Ok(())
+    }
 }
 
-pub struct Global {
+/// How many times each function of a `MockDatabase` was called (see
+/// `MockDatabase::counters`).
+#[derive(Clone, Debug, Default)]
+pub struct Counters {
     pub set_state_n: i32,
     pub get_state_n: i32,
     pub clear_state_n: i32,
-}
-
-static mut GLOBAL: Global = Global::default();
-
-impl Global {
-    const fn default() -> Global {
-        Global {
-            set_state_n: 0,
-            get_state_n: 0,
-            clear_state_n: 0,
-        }
-    }
-
-    fn setup() {
-        unsafe {
-            GLOBAL = Global::default();
-        }
-    }
+    pub close_n: i32,
 }
@@ -0,0 +1,25 @@
+use std::sync::{Arc, Mutex};
+
+use crate::events::{Event, EventListener};
+
+/// Records every event it is notified of.
+#[derive(Clone, Debug, Default)]
+pub struct MockEventListener {
+    pub events: Arc<Mutex<Vec<Event>>>,
+}
+
+impl MockEventListener {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl EventListener for MockEventListener {
+    fn notify(&self, event: &Event) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
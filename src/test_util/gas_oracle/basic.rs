@@ -0,0 +1,68 @@
+use crate::{
+    gas_oracle::{GasOracle, GasOracleInfo},
+    transaction::Priority,
+};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug)]
+pub struct MockGasOracle {
+    pub gas_oracle_info_output: Option<GasOracleInfo>,
+
+    /// The chains the oracle supports (all of them if `None`).
+    pub supported_chains: Option<Vec<u64>>,
+
+    counters: Arc<Mutex<MockGasOracleCounters>>, // shared by the clones
+}
+
+impl MockGasOracle {
+    pub fn new() -> Self {
+        Self {
+            gas_oracle_info_output: None,
+            supported_chains: None,
+            counters: Arc::new(Mutex::new(MockGasOracleCounters::default())),
+        }
+    }
+
+    /// How many times each function was called (by this mock and its clones).
+    pub fn counters(&self) -> MockGasOracleCounters {
+        self.counters.lock().unwrap().clone()
+    }
+}
+
+impl Default for MockGasOracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MockGasOracleError {
+    #[error("gas oracle mock error: gas info")]
+    GasInfo,
+}
+
+#[async_trait]
+impl GasOracle for MockGasOracle {
+    type Error = MockGasOracleError;
+
+    async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
+        self.counters.lock().unwrap().gas_info_n += 1;
+        self.gas_oracle_info_output
+            .ok_or(MockGasOracleError::GasInfo)
+    }
+
+    fn supports_chain(&self, chain_id: u64) -> bool {
+        match &self.supported_chains {
+            Some(chains) => chains.contains(&chain_id),
+            None => true,
+        }
+    }
+}
+
+/// How many times each function of a `MockGasOracle` was called (see
+/// `MockGasOracle::counters`).
+#[derive(Clone, Debug, Default)]
+pub struct MockGasOracleCounters {
+    pub gas_info_n: i32,
+}
@@ -1,9 +1,9 @@
-use async_trait::async_trait;
-use eth_tx_manager::{
+use crate::{
     gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo},
     transaction::Priority,
+    units::Gwei,
 };
-use ethers::types::U256;
+use async_trait::async_trait;
 
 /// Always returns a 2 gwei max fee and 1 gwei max priority fee.
 #[derive(Clone, Debug)]
@@ -15,6 +15,12 @@ impl ConstantGasOracle {
     }
 }
 
+impl Default for ConstantGasOracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConstantGasOracleError {}
 
@@ -25,11 +31,12 @@ impl GasOracle for ConstantGasOracle {
     async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
         Ok(GasOracleInfo {
             gas_info: GasInfo::EIP1559(EIP1559GasInfo {
-                max_fee: U256::from(2e9 as u32),
-                max_priority_fee: Some(U256::from(1e9 as u32)),
+                max_fee: Some(Gwei::from(2).into()),
+                max_priority_fee: Some(Gwei::from(1).into()),
             }),
             mining_time: None,
             block_time: None,
+            timestamp: None,
         })
     }
 }
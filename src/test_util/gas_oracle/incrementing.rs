@@ -0,0 +1,53 @@
+use crate::{
+    gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo},
+    transaction::Priority,
+    units::Wei,
+};
+use async_trait::async_trait;
+use ethers::types::U256;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub struct IncrementingGasOracle {
+    n: Arc<AtomicU32>, // shared by the clones
+}
+
+impl IncrementingGasOracle {
+    pub fn new() -> Self {
+        Self {
+            n: Arc::new(AtomicU32::new(100)),
+        }
+    }
+}
+
+impl Default for IncrementingGasOracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IncrementingGasOracleError {}
+
+#[async_trait]
+impl GasOracle for IncrementingGasOracle {
+    type Error = IncrementingGasOracleError;
+
+    async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
+        // Doubles the increment every time.
+        let n = self
+            .n
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| Some(n + n))
+            .unwrap();
+        Ok(GasOracleInfo {
+            gas_info: GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: Some(Wei(U256::from(2_000_000_000 + n))),
+                max_priority_fee: Some(Wei::from(100_000)),
+            }),
+            mining_time: None,
+            block_time: None,
+            timestamp: None,
+        })
+    }
+}
@@ -3,7 +3,7 @@ mod constant;
 mod incrementing;
 mod underpriced;
 
-pub use basic::{MockGasOracle, MockGasOracleError};
+pub use basic::{MockGasOracle, MockGasOracleCounters, MockGasOracleError};
 pub use constant::ConstantGasOracle;
 pub use incrementing::{IncrementingGasOracle, IncrementingGasOracleError};
 pub use underpriced::UnderpricedGasOracle;
@@ -1,19 +1,30 @@
-use async_trait::async_trait;
-use eth_tx_manager::{
+use crate::{
     gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo},
     transaction::Priority,
+    units::Gwei,
 };
-use ethers::types::U256;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 /// Guarantees that from the second transaction onward the max fee will be
 /// underpriced.
 #[derive(Clone, Debug)]
-pub struct UnderpricedGasOracle {}
+pub struct UnderpricedGasOracle {
+    first: Arc<AtomicBool>, // shared by the clones
+}
 
 impl UnderpricedGasOracle {
     pub fn new() -> Self {
-        Global::setup();
-        Self {}
+        Self {
+            first: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+impl Default for UnderpricedGasOracle {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -27,34 +38,16 @@ impl GasOracle for UnderpricedGasOracle {
     async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
         // The first transaction has a max_fee of 2 gwei.
         // Other transactions have a max_fee of 1 gwei.
-        let max_fee = U256::from(if unsafe { GLOBAL.flag } { 2e9 } else { 1e9 } as u32);
-        let result = Ok(GasOracleInfo {
+        let first = self.first.swap(false, Ordering::SeqCst);
+        let max_fee = Gwei::from(if first { 2 } else { 1 }).into();
+        Ok(GasOracleInfo {
             gas_info: GasInfo::EIP1559(EIP1559GasInfo {
-                max_fee,
+                max_fee: Some(max_fee),
                 max_priority_fee: None,
             }),
             mining_time: None,
             block_time: None,
-        });
-        unsafe { GLOBAL.flag = false };
-        result
-    }
-}
-
-pub struct Global {
-    pub flag: bool,
-}
-
-static mut GLOBAL: Global = Global::default();
-
-impl Global {
-    const fn default() -> Global {
-        Global { flag: true }
-    }
-
-    fn setup() {
-        unsafe {
-            GLOBAL = Global::default();
-        }
+            timestamp: None,
+        })
     }
 }
@@ -0,0 +1,539 @@
+use async_trait::async_trait;
+use ethers::{
+    providers::{FromErr, Middleware, MockProvider, PendingTransaction, Provider},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
+        FeeHistory, Log, NameOrAddress, Signature, Transaction, TransactionReceipt, TxHash, U256,
+        U64,
+    },
+    utils::keccak256,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Middleware mock.
+
+#[derive(Debug, thiserror::Error)]
+pub enum MockMiddlewareError {
+    #[error("mock middleware error: call")]
+    Call,
+
+    #[error("mock middleware error: get chain id")]
+    GetChainId,
+
+    #[error("mock middleware error: estimate gas")]
+    EstimateGas,
+
+    #[error("mock middleware error: get block number")]
+    GetBlockNumber,
+
+    #[error("mock middleware error: get balance")]
+    GetBalance,
+
+    #[error("mock middleware error: get block")]
+    GetBlock,
+
+    #[error("mock middleware error: estimate EIP1559 fees")]
+    EstimateEIP1559Fees,
+
+    #[error("mock middleware error: fee history")]
+    FeeHistory,
+
+    #[error("mock middleware error: get gas price")]
+    GetGasPrice,
+
+    #[error("mock middleware error: get transaction")]
+    GetTransaction,
+
+    #[error("mock middleware error: get transaction count")]
+    GetTransactionCount,
+
+    #[error("mock middleware error: get transaction receipt ($0)")]
+    GetTransactionReceipt(i32),
+
+    #[error("mock middleware error: send transaction")]
+    SendTransaction,
+
+    #[error("mock middleware error: sign transaction")]
+    SignTransaction,
+
+    #[error("mock middleware error: resolve name")]
+    ResolveName,
+
+    #[error("mock middleware error: client version")]
+    ClientVersion,
+
+    #[error("mock middleware error: {0}")]
+    Message(String),
+
+    #[error("mock middleware error: {0}")]
+    Rpc(RpcError),
+}
+
+/// A JSON-RPC error returned by the node, with the code and message of the
+/// geth errors the manager handles (see `MockMiddleware::send_errors`).
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("(code: {code}, message: {message})")]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: &str) -> RpcError {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn already_known() -> RpcError {
+        RpcError::new(-32000, "already known")
+    }
+
+    pub fn underpriced() -> RpcError {
+        RpcError::new(-32000, "replacement transaction underpriced")
+    }
+
+    pub fn max_fee_below_base_fee() -> RpcError {
+        RpcError::new(-32000, "max fee per gas less than block base fee")
+    }
+
+    pub fn intrinsic_gas_too_low() -> RpcError {
+        RpcError::new(-32000, "intrinsic gas too low")
+    }
+
+    pub fn nonce_too_low() -> RpcError {
+        RpcError::new(-32000, "nonce too low")
+    }
+
+    pub fn replacement_limit_reached() -> RpcError {
+        RpcError::new(-32000, "replacement limit reached for sender")
+    }
+
+    /// The underpriced replacement error of the parity-derived nodes.
+    pub fn openethereum_underpriced() -> RpcError {
+        RpcError::new(
+            -32010,
+            "Transaction gas price is too low. There is another transaction with same nonce in \
+            the queue. Try increasing the gas price or incrementing the nonce.",
+        )
+    }
+}
+
+impl FromErr<MockMiddlewareError> for MockMiddlewareError {
+    fn from(err: MockMiddlewareError) -> MockMiddlewareError {
+        err
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MockMiddleware {
+    provider: (Provider<MockProvider>, MockProvider),
+    pub call: Vec<bool>, // precondition results
+    pub get_chainid: Option<u64>,
+    pub default_sender: Option<Address>,
+    pub estimate_gas: Option<U256>,
+    pub estimate_gas_errors: Vec<String>, // returned (in order) before estimate_gas
+    pub get_block_number: Vec<u32>,
+    pub get_block: Vec<u32>,   // base fees
+    pub get_balance: Vec<u32>, // balances (in order)
+    pub estimate_eip1559_fees: Option<(u32, u32)>,
+    pub fee_history: Option<(u32, Vec<u32>)>, // (next base fee, rewards)
+    pub get_gas_price: Option<u32>,
+    pub get_transaction: Option<Option<(u32, u32)>>, // (max fee, max priority fee)
+    pub get_transaction_count: Option<()>,
+    pub get_transaction_receipt: Vec<bool>,
+    pub receipt_status: Option<u32>, // status of the receipts
+    pub receipt_logs: Vec<Log>,      // logs of the receipts
+    pub send_transaction: Option<()>,
+    pub send_errors: HashMap<i32, RpcError>, // returned by the Nth send (from 0)
+    pub sign_transaction: Option<()>,
+    pub wrap_transaction_hashes: bool, // returns non-standard transaction hashes
+    pub external_transactions: u32,    // sent from the account outside the manager
+    pub resolve_name: HashMap<String, Address>, // ENS names
+    pub fill_transaction: Option<u32>, // nonce assigned by the provider
+    pub client_version: Option<String>, // web3_clientVersion
+    state: Arc<Mutex<State>>,          // shared by the clones
+}
+
+impl MockMiddleware {
+    pub fn new() -> Self {
+        Self {
+            provider: Provider::mocked(),
+            call: Vec::new(),
+            get_chainid: None,
+            default_sender: None,
+            estimate_gas: None,
+            estimate_gas_errors: Vec::new(),
+            get_block_number: Vec::new(),
+            get_block: Vec::new(),
+            get_balance: Vec::new(),
+            estimate_eip1559_fees: None,
+            fee_history: None,
+            get_gas_price: None,
+            get_transaction: None,
+            get_transaction_count: None,
+            get_transaction_receipt: Vec::new(),
+            receipt_status: None,
+            receipt_logs: Vec::new(),
+            send_transaction: None,
+            send_errors: HashMap::new(),
+            sign_transaction: None,
+            wrap_transaction_hashes: false,
+            external_transactions: 0,
+            resolve_name: HashMap::new(),
+            fill_transaction: None,
+            client_version: None,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// How many times each function was called (by this mock and its clones).
+    pub fn counters(&self) -> Counters {
+        self.state().counters.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Default for MockMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Middleware for MockMiddleware {
+    type Error = MockMiddlewareError;
+    type Provider = MockProvider;
+    type Inner = Self;
+
+    fn inner(&self) -> &Self::Inner {
+        unreachable!()
+    }
+
+    fn provider(&self) -> &Provider<Self::Provider> {
+        &self.provider.0
+    }
+
+    async fn call(&self, _: &TypedTransaction, _: Option<BlockId>) -> Result<Bytes, Self::Error> {
+        let i = self.state().counters.next(|counters| &mut counters.call_n) as usize;
+        let output = self.call.get(i).ok_or(MockMiddlewareError::Call)?;
+        Ok(Bytes::from(vec![*output as u8]))
+    }
+
+    fn default_sender(&self) -> Option<Address> {
+        self.default_sender
+    }
+
+    async fn client_version(&self) -> Result<String, Self::Error> {
+        self.state().counters.client_version_n += 1;
+        self.client_version
+            .clone()
+            .ok_or(MockMiddlewareError::ClientVersion)
+    }
+
+    async fn resolve_name(&self, ens_name: &str) -> Result<Address, Self::Error> {
+        self.resolve_name
+            .get(ens_name)
+            .copied()
+            .ok_or(MockMiddlewareError::ResolveName)
+    }
+
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        _: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        if let Some(nonce) = self.fill_transaction {
+            tx.set_nonce(nonce);
+        }
+        Ok(())
+    }
+
+    async fn get_chainid(&self) -> Result<U256, Self::Error> {
+        self.state().counters.get_chainid_n += 1;
+        self.get_chainid
+            .map(U256::from)
+            .ok_or(MockMiddlewareError::GetChainId)
+    }
+
+    async fn estimate_gas(
+        &self,
+        _: &TypedTransaction,
+        _: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        let i = self
+            .state()
+            .counters
+            .next(|counters| &mut counters.estimate_gas_n) as usize;
+        if let Some(message) = self.estimate_gas_errors.get(i) {
+            return Err(MockMiddlewareError::Message(message.clone()));
+        }
+        self.estimate_gas.ok_or(MockMiddlewareError::EstimateGas)
+    }
+
+    async fn get_block_number(&self) -> Result<U64, Self::Error> {
+        let i = self
+            .state()
+            .counters
+            .next(|counters| &mut counters.get_block_number_n) as usize;
+        if i >= self.get_block_number.len() {
+            Err(MockMiddlewareError::GetBlockNumber)
+        } else {
+            Ok(u64(self.get_block_number[i]))
+        }
+    }
+
+    async fn get_balance<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        _: T,
+        _: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        let i = self
+            .state()
+            .counters
+            .next(|counters| &mut counters.get_balance_n) as usize;
+        let balance = self
+            .get_balance
+            .get(i)
+            .ok_or(MockMiddlewareError::GetBalance)?;
+        Ok(u256(*balance))
+    }
+
+    async fn get_block<T: Into<BlockId> + Send + Sync>(
+        &self,
+        _: T,
+    ) -> Result<Option<Block<TxHash>>, Self::Error> {
+        let i = self
+            .state()
+            .counters
+            .next(|counters| &mut counters.get_block_n) as usize;
+        let base_fee = self.get_block.get(i).ok_or(MockMiddlewareError::GetBlock)?;
+        Ok(Some(Block {
+            base_fee_per_gas: Some(u256(*base_fee)),
+            ..Default::default()
+        }))
+    }
+
+    async fn estimate_eip1559_fees(
+        &self,
+        _: Option<fn(U256, Vec<Vec<U256>>) -> (U256, U256)>,
+    ) -> Result<(U256, U256), Self::Error> {
+        self.state().counters.estimate_eip1559_fees_n += 1;
+        self.estimate_eip1559_fees
+            .map(|(x, y)| (U256::from(x), U256::from(y)))
+            .ok_or(MockMiddlewareError::EstimateEIP1559Fees)
+    }
+
+    async fn fee_history<T: Into<U256> + serde::Serialize + Send + Sync>(
+        &self,
+        _: T,
+        _: BlockNumber,
+        reward_percentiles: &[f64],
+    ) -> Result<FeeHistory, Self::Error> {
+        {
+            let mut state = self.state();
+            state.counters.fee_history_n += 1;
+            state.counters.reward_percentiles = reward_percentiles.to_vec();
+        }
+        let (base_fee, rewards) = self
+            .fee_history
+            .clone()
+            .ok_or(MockMiddlewareError::FeeHistory)?;
+        Ok(FeeHistory {
+            base_fee_per_gas: vec![u256(base_fee)],
+            gas_used_ratio: vec![],
+            oldest_block: U256::zero(),
+            reward: rewards
+                .into_iter()
+                .map(|reward| vec![u256(reward)])
+                .collect(),
+        })
+    }
+
+    async fn get_gas_price(&self) -> Result<U256, Self::Error> {
+        self.state().counters.get_gas_price_n += 1;
+        self.get_gas_price
+            .map(u256)
+            .ok_or(MockMiddlewareError::GetGasPrice)
+    }
+
+    async fn get_transaction<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<Transaction>, Self::Error> {
+        self.state().counters.get_transaction_n += 1;
+        let transaction = self
+            .get_transaction
+            .ok_or(MockMiddlewareError::GetTransaction)?;
+        Ok(transaction.map(|(max_fee, max_priority_fee)| Transaction {
+            hash: transaction_hash.into(),
+            max_fee_per_gas: Some(u256(max_fee)),
+            max_priority_fee_per_gas: Some(u256(max_priority_fee)),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_transaction_count<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        _: T,
+        _: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        self.state().counters.get_transaction_count_n += 1;
+        self.get_transaction_count
+            .ok_or(MockMiddlewareError::GetTransactionCount)?;
+        Ok(u256(self.state().nonce + self.external_transactions))
+    }
+
+    #[tracing::instrument(skip(self, transaction_hash))]
+    async fn get_transaction_receipt<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<TransactionReceipt>, Self::Error> {
+        let i = self
+            .state()
+            .counters
+            .next(|counters| &mut counters.get_transaction_receipt_n) as usize;
+        if i >= self.get_transaction_receipt.len() {
+            return Err(MockMiddlewareError::GetTransactionReceipt(i as i32));
+        }
+
+        if !self.get_transaction_receipt[i] {
+            Ok(None)
+        } else {
+            let transaction_hash = transaction_hash.into();
+
+            let block_number = self
+                .state()
+                .sent_transactions
+                .get(&transaction_hash)
+                .copied()
+                .unwrap_or_default();
+
+            let receipt = TransactionReceipt {
+                block_number: Some(u64(block_number.try_into().unwrap())),
+                transaction_hash,
+                gas_used: Some(u256(21000)),
+                effective_gas_price: Some(u256(1_000_000_000)),
+                status: self.receipt_status.map(u64),
+                logs: self.receipt_logs.clone(),
+                ..Default::default()
+            };
+
+            Ok(Some(receipt))
+        }
+    }
+
+    async fn send_raw_transaction<'a>(
+        &'a self,
+        tx: Bytes,
+    ) -> Result<PendingTransaction<'a, Self::Provider>, Self::Error> {
+        let i = self
+            .state()
+            .counters
+            .next(|counters| &mut counters.send_raw_transaction_n);
+        if let Some(err) = self.send_errors.get(&i) {
+            return Err(MockMiddlewareError::Rpc(err.clone()));
+        }
+
+        let mut hash = self
+            .send_transaction
+            .map(|_| TxHash(keccak256(tx)))
+            .ok_or(MockMiddlewareError::SendTransaction)?;
+        if self.wrap_transaction_hashes {
+            hash = TxHash(keccak256(hash));
+        }
+
+        let pending_transaction = PendingTransaction::new(hash, self.provider());
+
+        {
+            let mut state = self.state();
+            let current_block = state.counters.get_block_number_n;
+            state.insert_transaction(*pending_transaction, current_block);
+        }
+
+        Ok(pending_transaction)
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &TypedTransaction,
+        _: Address,
+    ) -> Result<Signature, Self::Error> {
+        {
+            let mut state = self.state();
+            state.counters.sign_transaction_n += 1;
+            state.counters.signed_transaction = Some(tx.clone());
+        }
+        let signer: LocalWallet =
+            "380eb0f3d505f087e438eca80bc4df9a7faa24f868e69fc0440261a0fc0567dc"
+                .parse()
+                .unwrap();
+        let signature = signer.sign_transaction(tx).await.unwrap();
+        self.sign_transaction
+            .ok_or(MockMiddlewareError::SignTransaction)
+            .map(|_| signature)
+    }
+}
+
+fn u64(n: u32) -> U64 {
+    U64::from_dec_str(&n.to_string()).unwrap()
+}
+
+fn u256(n: u32) -> U256 {
+    U256::from_dec_str(&n.to_string()).unwrap()
+}
+
+// State used to simulate the blockchain.
+
+#[derive(Debug, Default)]
+struct State {
+    nonce: u32,
+    sent_transactions: HashMap<TxHash, i32>, // hash to block
+    counters: Counters,
+}
+
+impl State {
+    fn insert_transaction(&mut self, hash: TxHash, block_number: i32) {
+        self.sent_transactions.insert(hash, block_number);
+        self.nonce += 1;
+    }
+}
+
+/// How many times each function of a `MockMiddleware` was called (see
+/// `MockMiddleware::counters`).
+#[derive(Clone, Debug, Default)]
+pub struct Counters {
+    pub reward_percentiles: Vec<f64>, // last requested percentiles
+    pub signed_transaction: Option<TypedTransaction>, // last signed transaction
+
+    pub call_n: i32,
+    pub get_chainid_n: i32,
+    pub estimate_gas_n: i32,
+    pub get_block_number_n: i32,
+    pub get_balance_n: i32,
+    pub get_block_n: i32,
+    pub estimate_eip1559_fees_n: i32,
+    pub fee_history_n: i32,
+    pub get_gas_price_n: i32,
+    pub get_transaction_n: i32,
+    pub get_transaction_count_n: i32,
+    pub get_transaction_receipt_n: i32,
+    pub send_raw_transaction_n: i32,
+    pub sign_transaction_n: i32,
+    pub client_version_n: i32,
+}
+
+impl Counters {
+    /// Increments the counter, returning its previous value.
+    fn next(&mut self, counter: impl FnOnce(&mut Counters) -> &mut i32) -> i32 {
+        let counter = counter(self);
+        *counter += 1;
+        *counter - 1
+    }
+}
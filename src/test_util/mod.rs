@@ -0,0 +1,19 @@
+//! Mocks of the manager's dependencies, for testing code that uses the
+//! transaction manager (enabled by the `test-util` feature).
+//!
+//! The mocks script the results of their functions through public fields and
+//! count how many times each function was called (see their `counters()`
+//! functions). The counters belong to each mock and are shared by its clones,
+//! so keep a clone of a mock to read them after moving it into the manager.
+//!
+//! The `scenario` module drives the manager through scripted chain events
+//! instead (see `Scenario`), and the `replay` module replays the sends
+//! recorded in a journal against scenarios (see `Replay`).
+
+pub mod database;
+pub mod events;
+pub mod gas_oracle;
+pub mod middleware;
+pub mod replay;
+pub mod scenario;
+pub mod time;
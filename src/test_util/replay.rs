@@ -0,0 +1,342 @@
+//! Replays of the sends recorded in a journal (see `Journal`).
+//!
+//! A `Replay` turns the state transitions of a recorded send into a scripted
+//! `Scenario` and re-executes the send against it, with a gas oracle that
+//! serves the recorded fees, to reproduce production incidents
+//! deterministically:
+//!
+//! ```ignore
+//! let replay = Replay::from_journal("journal.jsonl", sender, nonce)?;
+//! let report = replay.run(Chain::new(chain_id), |configuration| configuration).await;
+//! assert_eq!(None, report.divergence());
+//! ```
+//!
+//! The script reproduces the recorded submissions (one tick each, with the
+//! transaction kept out of the blocks by the base fee), the failures (as errors
+//! of the first submission or, after submissions, of the next query of the
+//! receipt), and the confirmation. It does not reproduce the
+//! exact block timings, the resubmissions through fallback broadcasters, or
+//! the skips of conditional transactions. The script and the fees are public,
+//! so they can be edited (e.g., to inject RPC errors) before running.
+
+use async_trait::async_trait;
+use ethers::{
+    types::{Address, Bytes, NameOrAddress, TransactionReceipt, U256},
+    utils::rlp,
+};
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::scenario::{Call, ChainEvent, Scenario, ScenarioTime};
+use crate::database::EphemeralDatabase;
+use crate::gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo, LegacyGasInfo};
+use crate::journal::{Journal, JournalEntry, JournalError, Transition};
+use crate::manager::{Chain, Configuration, Manager, SendOptions};
+use crate::transaction::{Metadata, Priority, Transaction, Value};
+use crate::units::Wei;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("journal error: {0}")]
+    Journal(#[from] JournalError),
+
+    #[error("no entries for the send of {sender:?} with nonce {nonce}")]
+    NoEntries { sender: Address, nonce: U256 },
+
+    #[error("the recorded send has no submissions nor failures")]
+    NothingToReplay,
+}
+
+/// A recorded send, ready to be replayed (see the module documentation).
+#[derive(Clone, Debug)]
+pub struct Replay {
+    /// The transitions recorded for the send, in order.
+    pub recorded: Vec<Transition>,
+
+    /// The transaction to send (from the scenario's signer). It is decoded
+    /// from the recorded raw transactions, if any (see
+    /// `Configuration::include_raw_transactions`), or else is an empty
+    /// transaction to the recorded sender.
+    pub transaction: Transaction,
+
+    /// The fees of the gas oracle for each submission, in order (the last
+    /// ones are repeated).
+    pub fees: Vec<GasInfo>,
+
+    /// The events applied before the send, and the ticks of the script.
+    pub now: Vec<ChainEvent>,
+    pub ticks: Vec<Vec<ChainEvent>>,
+}
+
+/// The result of a replay (see `Replay::run`).
+#[derive(Debug)]
+pub struct ReplayReport {
+    pub recorded: Vec<Transition>,
+    pub replayed: Vec<Transition>,
+    pub result: Result<TransactionReceipt, String>,
+
+    /// The scenario the send was replayed against (e.g., for its calls).
+    pub scenario: Scenario,
+}
+
+impl ReplayReport {
+    /// The position (in the recorded transitions) of the first transition
+    /// the replay did not reproduce, if any. Only the kinds of the transitions
+    /// are compared, and resubmissions through broadcasters are ignored.
+    pub fn divergence(&self) -> Option<usize> {
+        let kinds = |transitions: &[Transition]| {
+            transitions
+                .iter()
+                .filter(|transition| !matches!(transition, Transition::Resubmitted { .. }))
+                .map(std::mem::discriminant)
+                .collect::<Vec<_>>()
+        };
+        let (recorded, replayed) = (kinds(&self.recorded), kinds(&self.replayed));
+        recorded
+            .iter()
+            .zip(&replayed)
+            .position(|(recorded, replayed)| recorded != replayed)
+            .or_else(|| {
+                (recorded.len() != replayed.len()).then(|| recorded.len().min(replayed.len()))
+            })
+    }
+}
+
+impl Replay {
+    /// Reads the send of the sender with the nonce from the journal at the
+    /// path (verifying its chain).
+    pub fn from_journal(
+        path: impl AsRef<Path>,
+        sender: Address,
+        nonce: U256,
+    ) -> Result<Replay, ReplayError> {
+        Replay::from_entries(&Journal::verify(path)?, sender, nonce)
+    }
+
+    pub fn from_entries(
+        entries: &[JournalEntry],
+        sender: Address,
+        nonce: U256,
+    ) -> Result<Replay, ReplayError> {
+        let recorded: Vec<Transition> = entries
+            .iter()
+            .filter(|entry| entry.sender == sender && entry.nonce == Some(nonce))
+            .map(|entry| entry.transition.clone())
+            .collect();
+        if recorded.is_empty() {
+            return Err(ReplayError::NoEntries { sender, nonce });
+        }
+
+        let mut transaction = Transaction {
+            from: Scenario::signer(),
+            to: NameOrAddress::Address(sender),
+            value: Value::Nothing,
+            call_data: None,
+            metadata: Metadata::new(),
+        };
+        let mut fees = Vec::new();
+        let mut now = Vec::new();
+        let mut ticks: Vec<Vec<ChainEvent>> = Vec::new();
+        let mut last_fee = None;
+        for transition in &recorded {
+            match transition {
+                Transition::Submitted {
+                    gas_price,
+                    max_fee,
+                    max_priority_fee,
+                    raw_transaction,
+                    ..
+                } => {
+                    let gas_info = match gas_price {
+                        Some(gas_price) => GasInfo::Legacy(LegacyGasInfo {
+                            gas_price: Wei(*gas_price),
+                        }),
+                        None => GasInfo::EIP1559(EIP1559GasInfo {
+                            max_fee: max_fee.map(Wei),
+                            max_priority_fee: max_priority_fee.map(Wei),
+                        }),
+                    };
+                    let fee = gas_price.or(*max_fee).unwrap_or_default();
+                    if fees.is_empty() {
+                        now.push(ChainEvent::SetBaseFee(fee.low_u64()));
+                    }
+                    if let Some(raw_transaction) = raw_transaction {
+                        decode(raw_transaction, &mut transaction);
+                    }
+                    fees.push(gas_info);
+                    last_fee = Some((fee, max_priority_fee.unwrap_or_default()));
+
+                    // Keeps the transaction out of the next block.
+                    ticks.push(vec![
+                        ChainEvent::SetBaseFee(fee.low_u64().saturating_add(1)),
+                        ChainEvent::MineBlock,
+                    ]);
+                }
+                Transition::Confirmed {
+                    effective_gas_price,
+                    ..
+                } => {
+                    // Lets the transaction into the next block, at the recorded
+                    // price (if possible).
+                    let (fee, max_priority_fee) = last_fee.unwrap_or_default();
+                    let base_fee = effective_gas_price
+                        .map_or(fee, |price| price.saturating_sub(max_priority_fee))
+                        .min(fee);
+                    if let Some(tick) = ticks.last_mut() {
+                        *tick = vec![
+                            ChainEvent::SetBaseFee(base_fee.low_u64()),
+                            ChainEvent::MineBlock,
+                        ];
+                    }
+                }
+                Transition::Failed { error } => match ticks.last_mut() {
+                    Some(tick) => {
+                        tick.push(ChainEvent::Fail(Call::GetTransactionReceipt, error.clone()))
+                    }
+                    None => now.push(ChainEvent::Fail(Call::SendRawTransaction, error.clone())),
+                },
+                Transition::Resubmitted { .. } | Transition::Skipped => {}
+            }
+        }
+        if fees.is_empty() && now.is_empty() {
+            return Err(ReplayError::NothingToReplay);
+        }
+
+        // Once the script is over, the transaction is mined.
+        if let Some((fee, _)) = last_fee {
+            ticks.push(vec![
+                ChainEvent::SetBaseFee(fee.low_u64()),
+                ChainEvent::MineBlock,
+            ]);
+        }
+
+        Ok(Replay {
+            recorded,
+            transaction,
+            fees,
+            now,
+            ticks,
+        })
+    }
+
+    /// The scenario of the replay.
+    pub fn scenario(&self, chain_id: u64) -> Scenario {
+        let mut scenario = Scenario::new(chain_id).now(self.now.clone());
+        for tick in &self.ticks {
+            scenario = scenario.tick(tick.clone());
+        }
+        scenario
+    }
+
+    /// Replays the send on the chain, with the configuration (without mining
+    /// and block times, by default), and compares its transitions with the
+    /// recorded ones.
+    pub async fn run(
+        &self,
+        chain: Chain,
+        configure: impl FnOnce(Configuration<ScenarioTime>) -> Configuration<ScenarioTime>,
+    ) -> ReplayReport {
+        let scenario = self.scenario(chain.id);
+        let path = journal_path();
+        let replayed = self.send(&scenario, chain, configure, &path).await;
+        let journal = Journal::verify(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let replayed_transitions = journal
+            .map(|entries| entries.into_iter().map(|entry| entry.transition).collect())
+            .unwrap_or_default();
+        ReplayReport {
+            recorded: self.recorded.clone(),
+            replayed: replayed_transitions,
+            result: replayed,
+            scenario,
+        }
+    }
+
+    async fn send(
+        &self,
+        scenario: &Scenario,
+        chain: Chain,
+        configure: impl FnOnce(Configuration<ScenarioTime>) -> Configuration<ScenarioTime>,
+        path: &Path,
+    ) -> Result<TransactionReceipt, String> {
+        let journal = Journal::open(path, "replay").map_err(|err| err.to_string())?;
+        let gas_oracle = RecordedGasOracle {
+            scenario: scenario.clone(),
+            fees: self.fees.clone(),
+        };
+        let configuration = Configuration::new(scenario.time())
+            .set_transaction_mining_time(Duration::ZERO)
+            .set_block_time(Duration::ZERO);
+        let configuration = configure(configuration).set_journal(Some(Arc::new(journal)));
+        let (manager, _) = Manager::new(
+            scenario.middleware(),
+            gas_oracle,
+            EphemeralDatabase::new(),
+            chain,
+            configuration,
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+        manager
+            .send(self.transaction.clone(), SendOptions::new())
+            .await
+            .map(|(_, receipt)| receipt)
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Takes the recipient, value, and call data of the recorded transaction.
+fn decode(raw_transaction: &Bytes, transaction: &mut Transaction) {
+    if let Ok(recorded) = rlp::decode::<ethers::types::Transaction>(raw_transaction) {
+        if let Some(to) = recorded.to {
+            transaction.to = NameOrAddress::Address(to);
+        }
+        transaction.value = Value::Number(Wei(recorded.value));
+        transaction.call_data = (!recorded.input.is_empty()).then_some(recorded.input);
+    }
+}
+
+/// A fresh path for the journal of a replay.
+fn journal_path() -> PathBuf {
+    static REPLAYS: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "tx_manager_replay_{}_{}.jsonl",
+        std::process::id(),
+        REPLAYS.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Serves the recorded fees of the next submission.
+#[derive(Debug)]
+struct RecordedGasOracle {
+    scenario: Scenario,
+    fees: Vec<GasInfo>,
+}
+
+#[async_trait]
+impl GasOracle for RecordedGasOracle {
+    type Error = Infallible;
+
+    async fn get_info(&self, _: Priority) -> Result<GasOracleInfo, Self::Error> {
+        let submissions = self.scenario.calls(Call::SendRawTransaction);
+        let gas_info = self
+            .fees
+            .get(submissions)
+            .or(self.fees.last())
+            .copied()
+            .unwrap_or(GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: None,
+                max_priority_fee: None,
+            }));
+        Ok(GasOracleInfo {
+            gas_info,
+            mining_time: None,
+            block_time: None,
+            timestamp: None,
+        })
+    }
+}
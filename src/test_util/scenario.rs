@@ -0,0 +1,630 @@
+//! Scripted scenarios that drive the manager deterministically.
+//!
+//! A `Scenario` simulates a chain (blocks, a transaction pool, and reorgs)
+//! behind a `ScenarioMiddleware`, and scripts what happens on it: every time
+//! the manager sleeps (through the `ScenarioTime`), the next tick of the script
+//! is applied. Once the script is over, every tick mines a block.
+//!
+//! ```ignore
+//! let scenario = Scenario::new(chain_id)
+//!     .tick([ChainEvent::MineBlock])
+//!     .tick([ChainEvent::Reorg { depth: 1 }, ChainEvent::MineEmptyBlock]);
+//! let configuration = Configuration::new(scenario.time());
+//! let manager = Manager::new(scenario.middleware(), gas_oracle, db, chain, configuration);
+//! ```
+//!
+//! Unlike the other mocks, scenarios keep their state (and call counters) per
+//! instance.
+
+use async_trait::async_trait;
+use ethers::{
+    providers::{Middleware, MockProvider, PendingTransaction, Provider},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
+        FeeHistory, NameOrAddress, Signature, Transaction, TransactionReceipt, TxHash, H256, U256,
+        U64,
+    },
+    utils::{keccak256, rlp},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::middleware::MockMiddlewareError;
+use crate::time::Time;
+
+const GAS_LIMIT: u64 = 21000;
+const BLOCK_GAS_LIMIT: u64 = 30_000_000;
+const BASE_FEE: u64 = 1_000_000_000;
+const PRIORITY_FEE: u64 = 1_000_000;
+const SIGNER: &str = "380eb0f3d505f087e438eca80bc4df9a7faa24f868e69fc0440261a0fc0567dc";
+
+/// The middleware calls the manager makes (for scripting errors and counting
+/// calls).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Call {
+    Call,
+    GetChainId,
+    EstimateGas,
+    GetBlockNumber,
+    GetBlock,
+    FeeHistory,
+    GetGasPrice,
+    GetTransaction,
+    GetTransactionCount,
+    GetTransactionReceipt,
+    SendRawTransaction,
+    SignTransaction,
+}
+
+/// Something that happens on the simulated chain.
+#[derive(Clone, Debug)]
+pub enum ChainEvent {
+    /// Mines a block with the transactions in the pool (the last submitted
+    /// one for each nonce, in nonce order, while their fees cover the base
+    /// fee). Their receipts appear.
+    MineBlock,
+
+    /// Mines a block without transactions.
+    MineEmptyBlock,
+
+    /// Mines a block with a transaction from the signer, with the next nonce,
+    /// that the manager did not send (e.g., sent by another process with the
+    /// same key).
+    MineForeignTransaction,
+
+    /// Removes the last `depth` blocks, returning their transactions to the
+    /// pool. Their receipts disappear (until they are mined again, in a block
+    /// with another hash).
+    Reorg { depth: usize },
+
+    /// Makes the node keep serving the receipts of the transactions in reorged
+    /// blocks (as lagging nodes do).
+    ServeStaleReceipts,
+
+    /// Evicts all the transactions from the pool.
+    ClearPool,
+
+    /// Makes the next call fail with the message (e.g., "connection reset" or
+    /// "nonce too low").
+    Fail(Call, String),
+
+    /// Sets the base fee of the next blocks.
+    SetBaseFee(u64),
+
+    /// Sets the output of `eth_call` (e.g., for preconditions).
+    SetCallOutput(Bytes),
+}
+
+/// A scripted scenario (see the module documentation).
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    chain: Arc<Mutex<Chain>>,
+    provider: Provider<MockProvider>,
+}
+
+impl Scenario {
+    pub fn new(chain_id: u64) -> Scenario {
+        Scenario {
+            chain: Arc::new(Mutex::new(Chain::new(chain_id))),
+            provider: Provider::mocked().0,
+        }
+    }
+
+    /// Applies the events right away (e.g., to script errors of the first
+    /// calls).
+    pub fn now(self, events: impl IntoIterator<Item = ChainEvent>) -> Scenario {
+        {
+            let mut chain = self.chain.lock().unwrap();
+            for event in events {
+                chain.apply(event);
+            }
+        }
+        self
+    }
+
+    /// Appends a tick to the script: its events are applied the next time the
+    /// manager sleeps after the previous ticks.
+    pub fn tick(self, events: impl IntoIterator<Item = ChainEvent>) -> Scenario {
+        self.chain
+            .lock()
+            .unwrap()
+            .script
+            .push_back(events.into_iter().collect());
+        self
+    }
+
+    /// Appends `n` ticks without events to the script.
+    pub fn idle(mut self, n: usize) -> Scenario {
+        for _ in 0..n {
+            self = self.tick([]);
+        }
+        self
+    }
+
+    pub fn middleware(&self) -> ScenarioMiddleware {
+        ScenarioMiddleware(self.clone())
+    }
+
+    pub fn time(&self) -> ScenarioTime {
+        ScenarioTime(self.clone())
+    }
+
+    /// The signer of the middleware (the `from` of the transactions).
+    pub fn signer() -> Address {
+        SIGNER.parse::<LocalWallet>().unwrap().address()
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.chain.lock().unwrap().block_number()
+    }
+
+    /// The block the transaction was mined in, if any.
+    pub fn mined_in(&self, hash: H256) -> Option<u64> {
+        self.chain.lock().unwrap().mined_in(hash)
+    }
+
+    /// The hashes of the transactions sent to the pool, in order.
+    pub fn submitted(&self) -> Vec<H256> {
+        self.chain.lock().unwrap().submitted.clone()
+    }
+
+    /// The transaction with the hash, as sent to the pool.
+    pub fn transaction(&self, hash: H256) -> Option<Transaction> {
+        self.chain.lock().unwrap().transactions.get(&hash).cloned()
+    }
+
+    /// How many times the manager made the call.
+    pub fn calls(&self, call: Call) -> usize {
+        self.chain
+            .lock()
+            .unwrap()
+            .calls
+            .get(&call)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// How many ticks were applied so far.
+    pub fn ticks(&self) -> usize {
+        self.chain.lock().unwrap().ticks
+    }
+
+    /// Counts the call and returns its scripted error, if any.
+    fn call(&self, call: Call) -> Result<(), MockMiddlewareError> {
+        let mut chain = self.chain.lock().unwrap();
+        *chain.calls.entry(call).or_default() += 1;
+        match chain.errors.get_mut(&call).and_then(VecDeque::pop_front) {
+            Some(message) => Err(MockMiddlewareError::Message(message)),
+            None => Ok(()),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// Chain
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+struct SimulatedBlock {
+    hash: H256,
+    base_fee: U256,
+    transactions: Vec<H256>,
+}
+
+/// The simulated chain (with a single sender).
+#[derive(Debug)]
+struct Chain {
+    chain_id: u64,
+    blocks: Vec<SimulatedBlock>,
+    pool: Vec<H256>,
+    transactions: HashMap<H256, Transaction>,
+
+    /// The blocks removed by reorgs (with their numbers), and whether their
+    /// receipts are still served.
+    orphaned: Vec<(u64, SimulatedBlock)>,
+    stale_receipts: bool,
+
+    submitted: Vec<H256>,
+    base_fee: U256,
+    call_output: Bytes,
+
+    /// Incremented by reorgs, so that re-mined blocks have other hashes.
+    fork: u64,
+
+    script: VecDeque<Vec<ChainEvent>>,
+    ticks: usize,
+    errors: HashMap<Call, VecDeque<String>>,
+    calls: HashMap<Call, usize>,
+}
+
+impl Chain {
+    fn new(chain_id: u64) -> Chain {
+        let mut chain = Chain {
+            chain_id,
+            blocks: Vec::new(),
+            pool: Vec::new(),
+            transactions: HashMap::new(),
+            orphaned: Vec::new(),
+            stale_receipts: false,
+            submitted: Vec::new(),
+            base_fee: BASE_FEE.into(),
+            call_output: Bytes::from(vec![1]),
+            fork: 0,
+            script: VecDeque::new(),
+            ticks: 0,
+            errors: HashMap::new(),
+            calls: HashMap::new(),
+        };
+        chain.mine(Vec::new()); // genesis
+        chain
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+        match self.script.pop_front() {
+            Some(events) => {
+                for event in events {
+                    self.apply(event);
+                }
+            }
+            None => self.apply(ChainEvent::MineBlock),
+        }
+    }
+
+    fn apply(&mut self, event: ChainEvent) {
+        match event {
+            ChainEvent::MineBlock => {
+                let mut included = Vec::new();
+                let mut nonce = self.mined_nonce();
+                while let Some(hash) = self.pending(nonce) {
+                    if fee(&self.transactions[&hash]) < self.base_fee {
+                        break;
+                    }
+                    included.push(hash);
+                    self.pool
+                        .retain(|other| self.transactions[other].nonce != nonce);
+                    nonce += U256::one();
+                }
+                self.mine(included);
+            }
+            ChainEvent::MineEmptyBlock => self.mine(Vec::new()),
+            ChainEvent::MineForeignTransaction => {
+                let nonce = self.mined_nonce();
+                let hash = H256(keccak256(
+                    [b"foreign".as_slice(), &self.block_number().to_be_bytes()].concat(),
+                ));
+                self.transactions.insert(
+                    hash,
+                    Transaction {
+                        hash,
+                        nonce,
+                        from: Scenario::signer(),
+                        ..Default::default()
+                    },
+                );
+                self.pool
+                    .retain(|other| self.transactions[other].nonce != nonce);
+                self.mine(vec![hash]);
+            }
+            ChainEvent::Reorg { depth } => {
+                assert!(depth < self.blocks.len(), "cannot reorg the genesis block");
+                self.fork += 1;
+                for _ in 0..depth {
+                    let block = self.blocks.pop().unwrap();
+                    self.pool.extend(block.transactions.iter().copied());
+                    self.orphaned.push((self.blocks.len() as u64, block));
+                }
+            }
+            ChainEvent::ServeStaleReceipts => self.stale_receipts = true,
+            ChainEvent::ClearPool => self.pool.clear(),
+            ChainEvent::Fail(call, message) => {
+                self.errors.entry(call).or_default().push_back(message)
+            }
+            ChainEvent::SetBaseFee(base_fee) => self.base_fee = base_fee.into(),
+            ChainEvent::SetCallOutput(call_output) => self.call_output = call_output,
+        }
+    }
+
+    fn mine(&mut self, transactions: Vec<H256>) {
+        let number = self.blocks.len() as u64;
+        let hash = H256(keccak256(
+            [number.to_be_bytes(), self.fork.to_be_bytes()].concat(),
+        ));
+        self.blocks.push(SimulatedBlock {
+            hash,
+            base_fee: self.base_fee,
+            transactions,
+        });
+    }
+
+    fn block_number(&self) -> u64 {
+        self.blocks.len() as u64 - 1
+    }
+
+    fn mined_in(&self, hash: H256) -> Option<u64> {
+        self.blocks
+            .iter()
+            .position(|block| block.transactions.contains(&hash))
+            .map(|number| number as u64)
+    }
+
+    fn mined_nonce(&self) -> U256 {
+        let mined = self
+            .blocks
+            .iter()
+            .map(|block| block.transactions.len())
+            .sum::<usize>();
+        mined.into()
+    }
+
+    /// The last transaction submitted to the pool with the nonce.
+    fn pending(&self, nonce: U256) -> Option<H256> {
+        self.pool
+            .iter()
+            .rev()
+            .find(|hash| self.transactions[*hash].nonce == nonce)
+            .copied()
+    }
+
+    fn pending_nonce(&self) -> U256 {
+        let mut nonce = self.mined_nonce();
+        while self.pending(nonce).is_some() {
+            nonce += U256::one();
+        }
+        nonce
+    }
+
+    fn receipt(&self, hash: H256) -> Option<TransactionReceipt> {
+        let (number, block) = match self.mined_in(hash) {
+            Some(number) => (number, &self.blocks[number as usize]),
+            None if self.stale_receipts => self
+                .orphaned
+                .iter()
+                .rev()
+                .find(|(_, block)| block.transactions.contains(&hash))
+                .map(|(number, block)| (*number, block))?,
+            None => return None,
+        };
+        let transaction = &self.transactions[&hash];
+        let priority_fee = transaction
+            .max_priority_fee_per_gas
+            .unwrap_or_default()
+            .min(fee(transaction).saturating_sub(block.base_fee));
+        let effective_gas_price = match transaction.max_fee_per_gas {
+            Some(_) => block.base_fee + priority_fee,
+            None => transaction.gas_price.unwrap_or_default(),
+        };
+        Some(TransactionReceipt {
+            transaction_hash: hash,
+            block_hash: Some(block.hash),
+            block_number: Some(number.into()),
+            from: transaction.from,
+            to: transaction.to,
+            gas_used: Some(GAS_LIMIT.into()),
+            effective_gas_price: Some(effective_gas_price),
+            status: Some(1u64.into()),
+            ..Default::default()
+        })
+    }
+
+    fn send(&mut self, raw_transaction: &Bytes) -> Result<H256, MockMiddlewareError> {
+        let hash = H256(keccak256(raw_transaction));
+        let mut transaction: Transaction = rlp::decode(raw_transaction)
+            .map_err(|err| MockMiddlewareError::Message(err.to_string()))?;
+        transaction.hash = hash;
+        transaction.from = Scenario::signer();
+
+        if transaction.nonce < self.mined_nonce() {
+            return Err(MockMiddlewareError::Message("nonce too low".into()));
+        }
+        if self.pool.contains(&hash) {
+            return Err(MockMiddlewareError::Message("already known".into()));
+        }
+        if let Some(replaced) = self.pending(transaction.nonce) {
+            if fee(&transaction) * 10 < fee(&self.transactions[&replaced]) * 11 {
+                return Err(MockMiddlewareError::Message(
+                    "replacement transaction underpriced".into(),
+                ));
+            }
+        }
+
+        self.transactions.insert(hash, transaction);
+        self.pool.push(hash);
+        self.submitted.push(hash);
+        Ok(hash)
+    }
+}
+
+/// The max fee (or gas price, for legacy transactions) of the transaction.
+fn fee(transaction: &Transaction) -> U256 {
+    transaction
+        .max_fee_per_gas
+        .or(transaction.gas_price)
+        .unwrap_or_default()
+}
+
+// ------------------------------------------------------------------------------------------------
+// ScenarioMiddleware
+// ------------------------------------------------------------------------------------------------
+
+/// The middleware of a scenario (see `Scenario::middleware`).
+#[derive(Clone, Debug)]
+pub struct ScenarioMiddleware(Scenario);
+
+#[async_trait]
+impl Middleware for ScenarioMiddleware {
+    type Error = MockMiddlewareError;
+    type Provider = MockProvider;
+    type Inner = Self;
+
+    fn inner(&self) -> &Self::Inner {
+        unreachable!()
+    }
+
+    fn provider(&self) -> &Provider<Self::Provider> {
+        &self.0.provider
+    }
+
+    fn default_sender(&self) -> Option<Address> {
+        Some(Scenario::signer())
+    }
+
+    async fn call(&self, _: &TypedTransaction, _: Option<BlockId>) -> Result<Bytes, Self::Error> {
+        self.0.call(Call::Call)?;
+        Ok(self.0.chain.lock().unwrap().call_output.clone())
+    }
+
+    async fn get_chainid(&self) -> Result<U256, Self::Error> {
+        self.0.call(Call::GetChainId)?;
+        Ok(self.0.chain.lock().unwrap().chain_id.into())
+    }
+
+    async fn estimate_gas(
+        &self,
+        _: &TypedTransaction,
+        _: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        self.0.call(Call::EstimateGas)?;
+        Ok(GAS_LIMIT.into())
+    }
+
+    async fn get_block_number(&self) -> Result<U64, Self::Error> {
+        self.0.call(Call::GetBlockNumber)?;
+        Ok(self.0.block_number().into())
+    }
+
+    async fn get_block<T: Into<BlockId> + Send + Sync>(
+        &self,
+        block_id: T,
+    ) -> Result<Option<Block<TxHash>>, Self::Error> {
+        self.0.call(Call::GetBlock)?;
+        let chain = self.0.chain.lock().unwrap();
+        let number = match block_id.into() {
+            BlockId::Number(BlockNumber::Number(number)) => number.as_u64(),
+            BlockId::Hash(hash) => match chain.blocks.iter().position(|block| block.hash == hash) {
+                Some(number) => number as u64,
+                None => return Ok(None),
+            },
+            BlockId::Number(_) => chain.block_number(),
+        };
+        let block = match chain.blocks.get(number as usize) {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        Ok(Some(Block {
+            hash: Some(block.hash),
+            number: Some(number.into()),
+            base_fee_per_gas: Some(block.base_fee),
+            gas_limit: BLOCK_GAS_LIMIT.into(),
+            transactions: block.transactions.clone(),
+            ..Default::default()
+        }))
+    }
+
+    async fn fee_history<T: Into<U256> + serde::Serialize + Send + Sync>(
+        &self,
+        _: T,
+        _: BlockNumber,
+        reward_percentiles: &[f64],
+    ) -> Result<FeeHistory, Self::Error> {
+        self.0.call(Call::FeeHistory)?;
+        Ok(FeeHistory {
+            base_fee_per_gas: vec![self.0.chain.lock().unwrap().base_fee],
+            gas_used_ratio: vec![],
+            oldest_block: U256::zero(),
+            reward: vec![vec![PRIORITY_FEE.into(); reward_percentiles.len()]],
+        })
+    }
+
+    async fn get_gas_price(&self) -> Result<U256, Self::Error> {
+        self.0.call(Call::GetGasPrice)?;
+        Ok(self.0.chain.lock().unwrap().base_fee + PRIORITY_FEE)
+    }
+
+    async fn get_transaction<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<Transaction>, Self::Error> {
+        self.0.call(Call::GetTransaction)?;
+        let hash = transaction_hash.into();
+        let chain = self.0.chain.lock().unwrap();
+        if !chain.pool.contains(&hash) && chain.mined_in(hash).is_none() {
+            return Ok(None);
+        }
+        Ok(chain.transactions.get(&hash).cloned())
+    }
+
+    async fn get_transaction_count<T: Into<NameOrAddress> + Send + Sync>(
+        &self,
+        _: T,
+        block: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        self.0.call(Call::GetTransactionCount)?;
+        let chain = self.0.chain.lock().unwrap();
+        Ok(match block {
+            Some(BlockId::Number(BlockNumber::Pending)) => chain.pending_nonce(),
+            _ => chain.mined_nonce(),
+        })
+    }
+
+    async fn get_transaction_receipt<T: Send + Sync + Into<TxHash>>(
+        &self,
+        transaction_hash: T,
+    ) -> Result<Option<TransactionReceipt>, Self::Error> {
+        self.0.call(Call::GetTransactionReceipt)?;
+        Ok(self
+            .0
+            .chain
+            .lock()
+            .unwrap()
+            .receipt(transaction_hash.into()))
+    }
+
+    async fn send_raw_transaction<'a>(
+        &'a self,
+        raw_transaction: Bytes,
+    ) -> Result<PendingTransaction<'a, Self::Provider>, Self::Error> {
+        self.0.call(Call::SendRawTransaction)?;
+        let hash = self.0.chain.lock().unwrap().send(&raw_transaction)?;
+        Ok(PendingTransaction::new(hash, self.provider()))
+    }
+
+    async fn sign_transaction(
+        &self,
+        transaction: &TypedTransaction,
+        _: Address,
+    ) -> Result<Signature, Self::Error> {
+        self.0.call(Call::SignTransaction)?;
+        let signer: LocalWallet = SIGNER.parse().unwrap();
+        let chain_id = self.0.chain.lock().unwrap().chain_id;
+        Ok(signer
+            .with_chain_id(chain_id)
+            .sign_transaction(transaction)
+            .await
+            .unwrap())
+    }
+}
+
+// ------------------------------------------------------------------------------------------------
+// ScenarioTime
+// ------------------------------------------------------------------------------------------------
+
+/// The time of a scenario (see `Scenario::time`): sleeping applies the next
+/// tick of the script, and every call to `elapsed` returns one second.
+#[derive(Clone, Debug)]
+pub struct ScenarioTime(Scenario);
+
+#[async_trait]
+impl Time for ScenarioTime {
+    async fn sleep(&self, _: Duration) {
+        self.0.chain.lock().unwrap().tick();
+    }
+
+    fn elapsed(&self, _: Instant) -> Duration {
+        Duration::from_secs(1)
+    }
+}
@@ -1,5 +1,5 @@
+use crate::time::Time;
 use async_trait::async_trait;
-use eth_tx_manager::time::Time;
 use std::{
     fmt::Debug,
     time::{Duration, Instant},
@@ -5,42 +5,151 @@ use ethers::types::{
     TransactionRequest, H256, U256,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
 use crate::gas_oracle::GasInfo;
+use crate::manager::HandoffToken;
+use crate::units::Wei;
 use crate::Chain;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Priority {
     Low,
+    #[default]
     Normal,
     High,
     ASAP,
+
+    /// A percentile of the urgency scale, from 0 to 100 (higher values are
+    /// treated as 100). Gas oracles that only support the fixed levels use the
+    /// closest one (see `Priority::level`).
+    Custom(u8),
+}
+
+impl Priority {
+    /// Percentiles that correspond to the fixed levels (the same as the default
+    /// `PriorityFeePercentiles`).
+    pub const LEVEL_PERCENTILES: [(Priority, u8); 4] = [
+        (Priority::Low, 10),
+        (Priority::Normal, 25),
+        (Priority::High, 50),
+        (Priority::ASAP, 90),
+    ];
+
+    /// The percentile of a custom priority (capped at 100).
+    pub fn percentile(&self) -> Option<u8> {
+        match self {
+            Priority::Custom(percentile) => Some((*percentile).min(100)),
+            _ => None,
+        }
+    }
+
+    /// The fixed level closest to the priority.
+    pub fn level(&self) -> Priority {
+        match self.percentile() {
+            Some(percentile) => Priority::LEVEL_PERCENTILES
+                .iter()
+                .min_by_key(|(_, level)| level.abs_diff(percentile))
+                .map(|(priority, _)| *priority)
+                .unwrap(),
+            None => *self,
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Priority::Low => write!(f, "low"),
+            Priority::Normal => write!(f, "normal"),
+            Priority::High => write!(f, "high"),
+            Priority::ASAP => write!(f, "asap"),
+            Priority::Custom(percentile) => write!(f, "{}", percentile),
+        }
+    }
 }
 
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("invalid priority `{0}` (expected low, normal, high, asap, or a percentile)")]
+pub struct ParsePriorityError(String);
+
+/// Parses the fixed levels (case insensitive) or a percentile (custom).
+impl FromStr for Priority {
+    type Err = ParsePriorityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "normal" => Ok(Priority::Normal),
+            "high" => Ok(Priority::High),
+            "asap" => Ok(Priority::ASAP),
+            other => other
+                .parse()
+                .map(Priority::Custom)
+                .map_err(|_| ParsePriorityError(s.to_string())),
+        }
+    }
+}
+
+/// Key-value pairs that identify what a transaction is about (e.g., an epoch
+/// number or a claim id). They are persisted with the transaction's state.
+pub type Metadata = BTreeMap<String, String>;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Transaction {
     pub from: Address,
-    pub to: Address,
+
+    /// The recipient, or its ENS name, which the manager resolves once when
+    /// sending (the state only holds addresses).
+    pub to: NameOrAddress,
     pub value: Value,
     pub call_data: Option<Bytes>, // smart contract payload
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+impl Transaction {
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Value {
-    Number(U256),
+    Number(Wei),
     Nothing,
     // All,
 }
 
-impl From<Value> for U256 {
+impl From<Value> for Wei {
     fn from(value: Value) -> Self {
         match value {
             Value::Number(v) => v,
-            Value::Nothing => 0.into(),
+            Value::Nothing => Wei::zero(),
         }
     }
 }
 
+impl From<Value> for U256 {
+    fn from(value: Value) -> Self {
+        Wei::from(value).into()
+    }
+}
+
+/// An on-chain condition, evaluated through an `eth_call`, that must hold for a
+/// conditional transaction to be (re)submitted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Precondition {
+    pub to: Address,
+    pub call_data: Bytes,
+
+    /// The condition holds if the call returns exactly these bytes.
+    pub expected_output: Bytes,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct StaticTxData {
     /// Nonce of the current transaction.
@@ -51,12 +160,48 @@ pub struct StaticTxData {
 
     pub confirmations: usize,
     pub priority: Priority,
+
+    /// Condition checked before each (re)submission of the transaction.
+    #[serde(default)]
+    pub precondition: Option<Precondition>,
 }
 
 impl StaticTxData {
+    pub fn new(
+        transaction: Transaction,
+        nonce: U256,
+        confirmations: usize,
+        priority: Priority,
+    ) -> StaticTxData {
+        StaticTxData {
+            nonce,
+            transaction,
+            confirmations,
+            priority,
+            precondition: None,
+        }
+    }
+
+    /// Returns a no-op transaction (an empty transfer from the sender to
+    /// itself) that replaces the current one, using the same nonce.
+    pub fn cancellation(&self) -> StaticTxData {
+        let from = self.transaction.from;
+        StaticTxData {
+            transaction: Transaction {
+                from,
+                to: from.into(),
+                value: Value::Nothing,
+                call_data: None,
+                metadata: self.transaction.metadata.clone(),
+            },
+            precondition: None,
+            ..self.clone()
+        }
+    }
+
     pub fn to_typed_transaction(&self, chain: &Chain, gas_info: GasInfo) -> TypedTransaction {
         let from = Some(self.transaction.from);
-        let to = Some(NameOrAddress::Address(self.transaction.to));
+        let to = Some(self.transaction.to.clone());
         let value = Some(self.transaction.value.into());
         let data = self.transaction.call_data.clone();
         let nonce = Some(self.nonce);
@@ -68,7 +213,7 @@ impl StaticTxData {
                     from,
                     to,
                     gas: None, // must be set after
-                    gas_price: Some(legacy_gas_info.gas_price),
+                    gas_price: Some(legacy_gas_info.gas_price.into()),
                     value,
                     data,
                     nonce,
@@ -85,8 +230,10 @@ impl StaticTxData {
                     nonce,
                     access_list: AccessList::default(),
                     // max_priority_fee must be set (guaranteed by get_gas_oracle_info)
-                    max_priority_fee_per_gas: Some(eip1559_gas_info.max_priority_fee.unwrap()),
-                    max_fee_per_gas: Some(eip1559_gas_info.max_fee),
+                    max_priority_fee_per_gas: Some(
+                        eip1559_gas_info.max_priority_fee.unwrap().into(),
+                    ),
+                    max_fee_per_gas: eip1559_gas_info.max_fee.map(Into::into),
                     chain_id,
                 })
             }
@@ -100,6 +247,23 @@ pub struct SubmittedTxs {
     pub txs_hashes: Vec<H256>,
 }
 
+impl IntoIterator for SubmittedTxs {
+    type Item = H256;
+    type IntoIter = std::vec::IntoIter<H256>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.txs_hashes.into_iter()
+    }
+}
+
+impl FromIterator<H256> for SubmittedTxs {
+    fn from_iter<I: IntoIterator<Item = H256>>(iter: I) -> Self {
+        Self {
+            txs_hashes: iter.into_iter().collect(),
+        }
+    }
+}
+
 impl<'a> IntoIterator for &'a SubmittedTxs {
     type Item = &'a H256;
     type IntoIter = std::slice::Iter<'a, H256>;
@@ -116,7 +280,11 @@ impl SubmittedTxs {
         }
     }
 
-    pub fn contains(&mut self, hash: H256) -> bool {
+    pub fn iter(&self) -> std::slice::Iter<'_, H256> {
+        self.txs_hashes.iter()
+    }
+
+    pub fn contains(&self, hash: H256) -> bool {
         self.txs_hashes.contains(&hash)
     }
 
@@ -133,6 +301,26 @@ impl SubmittedTxs {
     }
 }
 
+/// Whether a submitted transaction reached a node (see
+/// `PersistentState::broadcast_statuses`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BroadcastStatus {
+    /// Stored before being sent, and possibly never sent (e.g., if the
+    /// manager stopped in between). Keeps the signed transaction, so that it
+    /// can be rebroadcast.
+    PreparedNotBroadcast(Bytes),
+
+    /// Accepted by a node.
+    Broadcast,
+}
+
+/// A submission of the transaction, with the fees it was signed with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attempt {
+    pub hash: H256,
+    pub gas_info: GasInfo,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PersistentState {
     /// Information about the transaction being currently processed.
@@ -140,4 +328,243 @@ pub struct PersistentState {
 
     /// Hashes of the pending transactions sent to the transaction pool.
     pub submitted_txs: SubmittedTxs,
+
+    /// Hashes of the transactions sent to cancel the pending transactions
+    /// (when the precondition stops holding), if there are any.
+    #[serde(default)]
+    pub cancellation_txs: Option<SubmittedTxs>,
+
+    /// Hashes of the pending transactions whose content was replaced (with
+    /// `Manager::replace_pending`), which may still be mined.
+    #[serde(default)]
+    pub replaced_txs: SubmittedTxs,
+
+    /// Broadcast status of the submitted (and cancellation) transactions, by
+    /// hash. The manager stores each transaction as prepared, together with
+    /// its hash, before sending it, and marks it as broadcast once a node
+    /// accepts it. Transactions without a status were broadcast.
+    #[serde(default)]
+    pub broadcast_statuses: BTreeMap<H256, BroadcastStatus>,
+
+    /// The submissions so far (including cancellations), in order, with the
+    /// fees they were signed with, so that a recovered transaction continues
+    /// escalating its fees from the last attempt.
+    #[serde(default)]
+    pub attempts: Vec<Attempt>,
+
+    /// How many times the transaction was resubmitted for not being mined in
+    /// time, which grows the wait before the next resubmission (see
+    /// `Configuration::patience`).
+    #[serde(default)]
+    pub resubmissions: u32,
+
+    /// Set when the transaction was handed off to another manager instance
+    /// (see `Manager::hand_off`), until it takes over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffToken>,
+
+    /// When the transaction was first broadcast (in milliseconds since the
+    /// Unix epoch), so that its time in flight (e.g., for
+    /// `Configuration::stall_threshold`) survives restarts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_submitted_at: Option<u64>,
+}
+
+impl PersistentState {
+    /// The state of a transaction that was not submitted yet.
+    pub fn new(tx_data: StaticTxData) -> PersistentState {
+        PersistentState {
+            tx_data,
+            submitted_txs: SubmittedTxs::new(),
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
+        }
+    }
+
+    /// Hashes of all the transactions sent with the current nonce (submitted,
+    /// cancellation, and replaced ones), any of which may be mined.
+    pub fn txs_hashes(&self) -> impl Iterator<Item = &H256> {
+        self.submitted_txs
+            .iter()
+            .chain(self.cancellation_txs.iter().flatten())
+            .chain(&self.replaced_txs)
+    }
+
+    /// Signed transactions that were prepared but possibly not broadcast, by
+    /// hash.
+    pub fn unbroadcast_txs(&self) -> impl Iterator<Item = (&H256, &Bytes)> {
+        self.broadcast_statuses
+            .iter()
+            .filter_map(|(hash, status)| match status {
+                BroadcastStatus::PreparedNotBroadcast(raw_transaction) => {
+                    Some((hash, raw_transaction))
+                }
+                BroadcastStatus::Broadcast => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::{Bytes, H160, H256};
+
+    use crate::gas_oracle::{EIP1559GasInfo, GasInfo};
+    use crate::transaction::{
+        Attempt, BroadcastStatus, PersistentState, Precondition, Priority, StaticTxData,
+        SubmittedTxs, Transaction, Value,
+    };
+    use crate::units::Wei;
+
+    /// The JSON format of the persistent state is stable: external tools
+    /// consume it (see `schema/persistent_state.json`).
+    const STATE_JSON: &str = r#"{
+  "tx_data": {
+    "nonce": "0x2",
+    "transaction": {
+      "from": "0x0000000000000000000000000000000000000001",
+      "to": "0x0000000000000000000000000000000000000002",
+      "value": {
+        "Number": "0x1388"
+      },
+      "call_data": "0x1234",
+      "metadata": {
+        "epoch": "42"
+      }
+    },
+    "confirmations": 1,
+    "priority": "High",
+    "precondition": {
+      "to": "0x0000000000000000000000000000000000000003",
+      "call_data": "0x5678",
+      "expected_output": "0x01"
+    }
+  },
+  "submitted_txs": {
+    "txs_hashes": [
+      "0x0000000000000000000000000000000000000000000000000000000000000001"
+    ]
+  },
+  "cancellation_txs": null,
+  "replaced_txs": {
+    "txs_hashes": []
+  },
+  "broadcast_statuses": {
+    "0x0000000000000000000000000000000000000000000000000000000000000001": "Broadcast"
+  },
+  "attempts": [
+    {
+      "hash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+      "gas_info": {
+        "EIP1559": {
+          "max_fee": "0x77359400",
+          "max_priority_fee": "0x5f5e100"
+        }
+      }
+    }
+  ],
+  "resubmissions": 1
+}"#;
+
+    fn state() -> PersistentState {
+        let transaction = Transaction {
+            from: H160::from_low_u64_be(1),
+            to: H160::from_low_u64_be(2).into(),
+            value: Value::Number(Wei::from(5000)),
+            call_data: Some(Bytes::from(vec![0x12, 0x34])),
+            metadata: Default::default(),
+        }
+        .with_metadata("epoch", "42");
+        let mut tx_data = StaticTxData::new(transaction, 2u64.into(), 1, Priority::High);
+        tx_data.precondition = Some(Precondition {
+            to: H160::from_low_u64_be(3),
+            call_data: Bytes::from(vec![0x56, 0x78]),
+            expected_output: Bytes::from(vec![1]),
+        });
+        let mut state = PersistentState::new(tx_data);
+        state.submitted_txs = [H256::from_low_u64_be(1)].into_iter().collect();
+        state
+            .broadcast_statuses
+            .insert(H256::from_low_u64_be(1), BroadcastStatus::Broadcast);
+        state.attempts.push(Attempt {
+            hash: H256::from_low_u64_be(1),
+            gas_info: GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: Some(Wei::from(2_000_000_000)),
+                max_priority_fee: Some(Wei::from(100_000_000)),
+            }),
+        });
+        state.resubmissions = 1;
+        state
+    }
+
+    #[test]
+    fn test_persistent_state_json_format() {
+        assert_eq!(STATE_JSON, serde_json::to_string_pretty(&state()).unwrap());
+    }
+
+    #[test]
+    fn test_persistent_state_json_round_trip() {
+        let state = state();
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(state, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn test_persistent_state_txs_hashes() {
+        let mut state = state();
+        state.cancellation_txs = Some(SubmittedTxs::from_iter([H256::from_low_u64_be(2)]));
+        state.replaced_txs.add(H256::from_low_u64_be(3));
+        let hashes: Vec<_> = state.txs_hashes().copied().collect();
+        assert_eq!(
+            vec![
+                H256::from_low_u64_be(1),
+                H256::from_low_u64_be(2),
+                H256::from_low_u64_be(3)
+            ],
+            hashes
+        );
+    }
+
+    #[test]
+    fn test_priority_from_str_and_display() {
+        for priority in [
+            Priority::Low,
+            Priority::Normal,
+            Priority::High,
+            Priority::ASAP,
+            Priority::Custom(40),
+        ] {
+            assert_eq!(Ok(priority), priority.to_string().parse());
+        }
+        assert_eq!(Ok(Priority::ASAP), "ASAP".parse());
+        assert_eq!(Ok(Priority::Custom(0)), " 0 ".parse());
+        assert!("urgent".parse::<Priority>().is_err());
+        assert!("256".parse::<Priority>().is_err());
+    }
+
+    #[test]
+    fn test_priority_serde() {
+        assert_eq!(r#""ASAP""#, serde_json::to_string(&Priority::ASAP).unwrap());
+        assert_eq!(
+            r#"{"Custom":40}"#,
+            serde_json::to_string(&Priority::Custom(40)).unwrap()
+        );
+        assert_eq!(
+            Priority::Custom(40),
+            serde_json::from_str(r#"{"Custom":40}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_priority_level() {
+        assert_eq!(Priority::High, Priority::High.level());
+        assert_eq!(Priority::Low, Priority::Custom(0).level());
+        assert_eq!(Priority::Normal, Priority::Custom(30).level());
+        assert_eq!(Priority::High, Priority::Custom(60).level());
+        assert_eq!(Priority::ASAP, Priority::Custom(200).level());
+    }
 }
@@ -0,0 +1,166 @@
+//! Typed amounts, so that fees, values, and gas limits are not mixed up (or
+//! off by a factor of 10^9).
+//!
+//! The types wrap a `U256` (the public field), serialize as one, and convert
+//! from and into it.
+
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+use crate::manager::multiply;
+
+const WEI_PER_GWEI: u64 = 1_000_000_000;
+
+/// An amount of wei (e.g., a fee per gas unit or a value).
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Wei(pub U256);
+
+impl Wei {
+    pub fn zero() -> Wei {
+        Wei(U256::zero())
+    }
+
+    /// Multiplies the amount by a factor (with three decimal places).
+    pub fn multiply(self, factor: f64) -> Wei {
+        Wei(multiply(self.0, factor))
+    }
+
+    /// The amount in gwei, rounded down.
+    pub fn to_gwei(self) -> Gwei {
+        Gwei(self.0 / WEI_PER_GWEI)
+    }
+}
+
+/// An amount of gwei (10^9 wei), the unit fees are usually quoted in.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Gwei(pub U256);
+
+/// An amount of gas units.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct GasLimit(pub U256);
+
+macro_rules! impl_conversions {
+    ($unit:ident) => {
+        impl From<U256> for $unit {
+            fn from(value: U256) -> Self {
+                $unit(value)
+            }
+        }
+
+        impl From<u64> for $unit {
+            fn from(value: u64) -> Self {
+                $unit(value.into())
+            }
+        }
+
+        impl $unit {
+            pub fn saturating_add(self, other: $unit) -> $unit {
+                $unit(self.0.saturating_add(other.0))
+            }
+        }
+
+        impl From<$unit> for U256 {
+            fn from(value: $unit) -> Self {
+                value.0
+            }
+        }
+
+        impl Add for $unit {
+            type Output = $unit;
+
+            fn add(self, other: $unit) -> $unit {
+                $unit(self.0 + other.0)
+            }
+        }
+
+        impl Sub for $unit {
+            type Output = $unit;
+
+            fn sub(self, other: $unit) -> $unit {
+                $unit(self.0 - other.0)
+            }
+        }
+    };
+}
+
+impl_conversions!(Wei);
+impl_conversions!(Gwei);
+impl_conversions!(GasLimit);
+
+impl From<Gwei> for Wei {
+    fn from(gwei: Gwei) -> Self {
+        Wei(gwei.0 * WEI_PER_GWEI)
+    }
+}
+
+/// Fee per gas unit times gas units.
+impl Mul<GasLimit> for Wei {
+    type Output = Wei;
+
+    fn mul(self, gas: GasLimit) -> Wei {
+        Wei(self.0 * gas.0)
+    }
+}
+
+impl Mul<Wei> for GasLimit {
+    type Output = Wei;
+
+    fn mul(self, fee: Wei) -> Wei {
+        fee * self
+    }
+}
+
+impl fmt::Display for Wei {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} wei", self.0)
+    }
+}
+
+impl fmt::Display for Gwei {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} gwei", self.0)
+    }
+}
+
+impl fmt::Display for GasLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} gas", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::U256;
+
+    use super::{GasLimit, Gwei, Wei};
+
+    #[test]
+    fn test_units() {
+        let fee = Wei::from(Gwei::from(2));
+        assert_eq!(Wei::from(2_000_000_000), fee);
+        assert_eq!(Gwei::from(2), fee.to_gwei());
+        assert_eq!(Gwei::from(1), Wei::from(1_999_999_999).to_gwei());
+        assert_eq!(Wei::from(3_000_000_000), fee.multiply(1.5));
+
+        let cost = fee * GasLimit::from(21000) + Wei::from(5);
+        assert_eq!(U256::from(42_000_000_000_005u64), U256::from(cost));
+
+        assert_eq!("2000000000 wei", fee.to_string());
+        assert_eq!("2 gwei", Gwei::from(2).to_string());
+        assert_eq!("21000 gas", GasLimit::from(21000).to_string());
+
+        assert_eq!("\"0x77359400\"", serde_json::to_string(&fee).unwrap());
+        assert_eq!(fee, serde_json::from_str("\"0x77359400\"").unwrap());
+    }
+}
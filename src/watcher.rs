@@ -0,0 +1,232 @@
+use ethers::{
+    providers::Middleware,
+    types::{TransactionReceipt, H256, U64},
+};
+use std::time::{Duration, Instant};
+use tracing::{trace, trace_span, Instrument};
+
+use crate::time::Time;
+
+/// Watches a set of transactions (e.g., the submissions of the same nonce)
+/// until one of them is mined with enough confirmations, without sending
+/// anything. The manager confirms its transactions with it, and components
+/// that only need to wait for transactions sent elsewhere can use it on its
+/// own (see `PendingTxWatcher::watch`).
+///
+/// The watcher holds no provider: its methods take one, so that it can be
+/// rebuilt for every poll as the set of hashes changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingTxWatcher {
+    hashes: Vec<H256>,
+    confirmations: usize,
+    policy: WatchPolicy,
+}
+
+/// How `PendingTxWatcher::watch` polls the provider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchPolicy {
+    /// Time between polls (usually the block time).
+    pub poll_interval: Duration,
+
+    /// How long to watch for before giving up with `WatchError::Timeout`. If
+    /// `None`, watches forever.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for WatchPolicy {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(20),
+            timeout: None,
+        }
+    }
+}
+
+/// The receipts of the watched transactions (see `PendingTxWatcher::receipts`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Receipts {
+    /// The receipt of the mined transaction. When several of them appear
+    /// mined, the one on the canonical chain (the deepest, if several).
+    pub mined: Option<TransactionReceipt>,
+
+    /// The hashes of the transactions that appear mined, when there are
+    /// several (replacements racing with a reorg, and nodes still serving the
+    /// receipts of the reorged blocks). Empty otherwise.
+    pub conflicting: Vec<H256>,
+
+    /// A receipt without a block, which some nodes return for pending
+    /// transactions (only when none of them is mined).
+    pub pending: Option<TransactionReceipt>,
+}
+
+/// A mined transaction and its confirmations (see `PendingTxWatcher::status`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchStatus {
+    pub receipt: TransactionReceipt,
+    pub latest_block: U64,
+
+    /// How many blocks were mined on top of the transaction's block.
+    pub depth: usize,
+
+    /// Whether the depth reached the required confirmations.
+    pub confirmed: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WatchError<E> {
+    #[error("middleware: {0}")]
+    Middleware(E),
+
+    #[error("no transaction was confirmed after {0:?}")]
+    Timeout(Duration),
+}
+
+impl PendingTxWatcher {
+    pub fn new(hashes: impl IntoIterator<Item = H256>, confirmations: usize) -> PendingTxWatcher {
+        PendingTxWatcher {
+            hashes: hashes.into_iter().collect(),
+            confirmations,
+            policy: WatchPolicy::default(),
+        }
+    }
+
+    pub fn set_policy(mut self, policy: WatchPolicy) -> PendingTxWatcher {
+        self.policy = policy;
+        self
+    }
+
+    pub fn hashes(&self) -> &[H256] {
+        &self.hashes
+    }
+
+    pub fn confirmations(&self) -> usize {
+        self.confirmations
+    }
+
+    /// Queries the receipts of the watched transactions.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn receipts<M: Middleware>(&self, provider: &M) -> Result<Receipts, M::Error> {
+        let mut receipts = Vec::new();
+        for &hash in &self.hashes {
+            if let Some(receipt) = provider
+                .get_transaction_receipt(hash)
+                .instrument(trace_span!("get_transaction_receipt"))
+                .await?
+            {
+                receipts.push(receipt);
+            }
+        }
+
+        let (mut mined, pending): (Vec<_>, Vec<_>) = receipts
+            .into_iter()
+            .partition(|receipt| receipt.block_number.is_some());
+        if mined.len() <= 1 {
+            let pending = if mined.is_empty() {
+                pending.into_iter().next()
+            } else {
+                None
+            };
+            return Ok(Receipts {
+                mined: mined.pop(),
+                conflicting: Vec::new(),
+                pending,
+            });
+        }
+
+        let conflicting = mined
+            .iter()
+            .map(|receipt| receipt.transaction_hash)
+            .collect();
+        let mut canonical: Option<TransactionReceipt> = None;
+        for receipt in mined {
+            if !is_canonical(provider, &receipt).await? {
+                continue;
+            }
+            let deeper = match &canonical {
+                Some(other) => receipt.block_number < other.block_number,
+                None => true,
+            };
+            if deeper {
+                canonical = Some(receipt);
+            }
+        }
+        Ok(Receipts {
+            mined: canonical,
+            conflicting,
+            pending: None,
+        })
+    }
+
+    /// Counts the confirmations of the mined transaction's receipt. (The
+    /// provider may lag behind the block of the receipt, e.g., when it
+    /// balances the load between nodes, in which case the depth is zero.)
+    pub async fn status<M: Middleware>(
+        &self,
+        provider: &M,
+        receipt: TransactionReceipt,
+    ) -> Result<WatchStatus, M::Error> {
+        let transaction_block = receipt.block_number.unwrap_or_default();
+        let latest_block = provider
+            .get_block_number()
+            .instrument(trace_span!("get_block_number"))
+            .await?;
+        trace!("Mined transaction block: {:?}.", transaction_block);
+        trace!("Current block: {:?}.", latest_block);
+
+        let depth = latest_block.saturating_sub(transaction_block).as_usize();
+        Ok(WatchStatus {
+            receipt,
+            latest_block,
+            depth,
+            confirmed: depth >= self.confirmations,
+        })
+    }
+
+    /// Queries the receipts of the watched transactions and, if one of them
+    /// was mined, its confirmations.
+    pub async fn poll<M: Middleware>(&self, provider: &M) -> Result<Option<WatchStatus>, M::Error> {
+        match self.receipts(provider).await?.mined {
+            Some(receipt) => Ok(Some(self.status(provider, receipt).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Polls the provider (see `WatchPolicy`) until one of the transactions is
+    /// mined with enough confirmations, and returns its receipt.
+    pub async fn watch<M: Middleware, T: Time>(
+        &self,
+        provider: &M,
+        time: &T,
+    ) -> Result<TransactionReceipt, WatchError<M::Error>> {
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.poll(provider).await.map_err(WatchError::Middleware)? {
+                if status.confirmed {
+                    return Ok(status.receipt);
+                }
+            }
+
+            let elapsed = time.elapsed(start);
+            if matches!(self.policy.timeout, Some(timeout) if elapsed > timeout) {
+                return Err(WatchError::Timeout(elapsed));
+            }
+            time.sleep(self.policy.poll_interval).await;
+        }
+    }
+}
+
+/// Whether the receipt's block is on the canonical chain.
+async fn is_canonical<M: Middleware>(
+    provider: &M,
+    receipt: &TransactionReceipt,
+) -> Result<bool, M::Error> {
+    let block_number = match receipt.block_number {
+        Some(block_number) => block_number,
+        None => return Ok(false),
+    };
+    let block = provider
+        .get_block(block_number)
+        .instrument(trace_span!("get_block"))
+        .await?;
+    Ok(matches!(block, Some(block) if block.hash.is_some() && block.hash == receipt.block_hash))
+}
@@ -0,0 +1,80 @@
+//! The full send flow against an anvil fork of a live chain (requires `anvil`
+//! in the PATH and the URL of the chain to fork in the `FORK_URL` environment
+//! variable).
+//!
+//! Run with `FORK_URL=<rpc url> cargo test --features expensive-tests --test fork_test`.
+#![cfg(feature = "expensive-tests")]
+
+use ethers::{providers::Middleware, types::Address};
+use std::time::Duration;
+
+use eth_tx_manager::{
+    database::EphemeralDatabase,
+    gas_oracle::NoGasOracle,
+    manager::{Configuration, Manager, SendOptions},
+    transaction::{Metadata, Transaction, Value},
+    Gwei, Wei,
+};
+
+use utilities::{Account, Fork, FORK_URL};
+
+const CONFIRMATIONS: usize = 2;
+const AMOUNT: u64 = 1000;
+
+#[tokio::test]
+async fn test_fork_send() {
+    utilities::setup_tracing();
+    let fork = match Fork::from_env(1).await {
+        Some(fork) => fork,
+        None => {
+            println!("[TEST LOG] Skipping: {} is not set.", FORK_URL);
+            return;
+        }
+    };
+
+    // Funding a fresh sender, as one would do with a production account.
+    let sender = Account::random();
+    let from: Address = sender.clone().into();
+    fork.set_balance(from, Gwei::from(1_000_000_000).into())
+        .await;
+    let provider = fork.provider_for(&sender);
+
+    let to: Address = Account::random().into();
+    let initial_balance = provider.inner.get_balance(to, None).await.unwrap();
+
+    let configuration = Configuration::default()
+        .set_block_time(Duration::from_secs(1))
+        .set_transaction_mining_time(Duration::from_secs(5));
+    let (manager, _) = Manager::new(
+        provider.inner.clone(),
+        NoGasOracle,
+        EphemeralDatabase::new(),
+        fork.chain(),
+        configuration,
+    )
+    .await
+    .unwrap();
+
+    let transaction = Transaction {
+        from,
+        to: to.into(),
+        value: Value::Number(Wei::from(AMOUNT)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let (_, receipt) = manager
+        .send(
+            transaction,
+            SendOptions::new().set_confirmations(CONFIRMATIONS),
+        )
+        .await
+        .unwrap();
+
+    // Mined on the fork (after the forked block), with enough confirmations.
+    let block_number = receipt.block_number.unwrap().as_usize();
+    let current_block = provider.inner.get_block_number().await.unwrap().as_usize();
+    assert!(current_block - block_number >= CONFIRMATIONS);
+
+    let balance = provider.inner.get_balance(to, None).await.unwrap();
+    assert_eq!(initial_balance + AMOUNT, balance);
+}
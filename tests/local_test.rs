@@ -9,10 +9,10 @@ use std::{fs::remove_file, sync::Arc, time::Duration};
 use eth_tx_manager::{
     database::FileSystemDatabase,
     gas_oracle::{DefaultGasOracle, GasOracle},
-    manager::{Configuration, Manager},
+    manager::{Configuration, Manager, SendOptions},
     time::DefaultTime,
-    transaction::{Priority, Transaction, Value},
-    Chain,
+    transaction::{Metadata, Priority, Transaction, Value},
+    Chain, Gwei,
 };
 
 use utilities::{
@@ -21,10 +21,7 @@ use utilities::{
     Account, Geth,
 };
 
-const CHAIN: Chain = Chain {
-    id: 1337,
-    is_legacy: false,
-};
+const CHAIN: Chain = Chain::new(1337);
 const FUNDS: u64 = 1e9 as u64;
 const DATABASE_PATH: &str = "./test_database.json";
 
@@ -53,12 +50,13 @@ async fn test_ok() {
         let transaction = Transaction {
             from: account1.clone().into(),
             to: account2.clone().into(),
-            value: Value::Number(utilities::gwei_to_wei(amount1)),
+            value: Value::Number(Gwei::from(amount1).into()),
             call_data: None,
+            metadata: Metadata::new(),
         };
 
         let result = manager
-            .send_transaction(transaction, 3, Priority::Normal)
+            .send(transaction, SendOptions::new().set_confirmations(3))
             .await;
 
         assert_ok!(result);
@@ -82,12 +80,18 @@ async fn test_ok() {
         let transaction = Transaction {
             from: account1.clone().into(),
             to: account2.clone().into(),
-            value: Value::Number(utilities::gwei_to_wei(amount2)),
+            value: Value::Number(Gwei::from(amount2).into()),
             call_data: None,
+            metadata: Metadata::new(),
         };
 
         let result = manager
-            .send_transaction(transaction, 1, Priority::ASAP)
+            .send(
+                transaction,
+                SendOptions::new()
+                    .set_confirmations(1)
+                    .set_priority(Priority::ASAP),
+            )
             .await;
 
         assert_ok!(result);
@@ -134,27 +138,41 @@ async fn test_smart_contract() {
     };
 
     // Sending the transaction
-    // Calling the <increment> function from the smart contract.
+    // Calling the <increment> function from the smart contract, and checking
+    // that the contract was updated.
     {
+        let before = contract.i().call().await.unwrap();
         let data = contract.increment().tx.data().unwrap().clone();
         println!("data: {}", data);
         let transaction = Transaction {
             from: account1.clone().into(),
-            to: contract_address,
+            to: contract_address.into(),
             value: Value::Nothing,
             call_data: Some(data),
+            metadata: Metadata::new(),
         };
 
+        let contract = &contract;
         let result = manager
-            .send_transaction(transaction, 0, Priority::ASAP)
+            .send_and_verify(
+                transaction,
+                SendOptions::new()
+                    .set_confirmations(0)
+                    .set_priority(Priority::ASAP),
+                |_| async move {
+                    let after = contract.i().call().await.map_err(|err| err.to_string())?;
+                    match after == before + 1 {
+                        true => Ok(()),
+                        false => Err(format!("i is {} (was {})", after, before)),
+                    }
+                },
+            )
             .await;
 
         assert_ok!(result);
         let (manager, _) = result.unwrap();
         manager
     };
-
-    // TODO: check if the contract was updated.
 }
 
 /// If you send a transaction that is exactly equal (same hash) to a transaction
@@ -184,12 +202,13 @@ async fn test_error_already_known() {
     let transaction = Transaction {
         from: account1.clone().into(),
         to: account2.clone().into(),
-        value: Value::Number(utilities::gwei_to_wei(amount)),
+        value: Value::Number(Gwei::from(amount).into()),
         call_data: None,
+        metadata: Metadata::new(),
     };
 
     let result = manager
-        .send_transaction(transaction, 0, Priority::Normal)
+        .send(transaction, SendOptions::new().set_confirmations(0))
         .await;
     assert_ok!(result);
 
@@ -227,12 +246,13 @@ async fn test_error_transaction_underpriced() {
     let transaction = Transaction {
         from: account1.clone().into(),
         to: account2.clone().into(),
-        value: Value::Number(utilities::gwei_to_wei(amount)),
+        value: Value::Number(Gwei::from(amount).into()),
         call_data: None,
+        metadata: Metadata::new(),
     };
 
     let result = manager
-        .send_transaction(transaction, 0, Priority::Normal)
+        .send(transaction, SendOptions::new().set_confirmations(0))
         .await;
     assert_ok!(result);
 
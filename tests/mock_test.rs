@@ -1,20 +1,65 @@
-use ethers::types::{TransactionReceipt, U256};
+use async_trait::async_trait;
+use ethers::{
+    abi::{self, Token},
+    contract::EthEvent,
+    providers::Middleware,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Bytes, Eip1559TransactionRequest, Log,
+        NameOrAddress, TransactionReceipt, TransactionRequest, H256, U256, U64,
+    },
+};
+use futures::StreamExt;
 use serial_test::serial;
-use std::time::Duration;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use eth_tx_manager::{
-    gas_oracle::{EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo},
-    manager::{Configuration, Manager},
-    transaction::{PersistentState, Priority, StaticTxData, SubmittedTxs, Transaction, Value},
-    Chain,
+    database::{DatabaseOperation, DatabaseStats, DynDatabase, FileSystemDatabase, OperationStats},
+    events::Event,
+    failover::Failover,
+    fee_model::FeeModel,
+    funding::{FundingMonitor, TopUp},
+    gas_oracle::{
+        EIP1559GasInfo, GasInfo, GasOracle, GasOracleInfo, LegacyGasInfo, LegacyProviderGasOracle,
+        NoGasOracle, NodeClient, ReplacementRules,
+    },
+    hashing::{HashMismatchPolicy, NodeHash},
+    journal::{Journal, JournalEntry, Transition},
+    manager::{
+        Action, ActionResult, Configuration, ConfigurationError, ConfigurationWarning,
+        GasPriceMultipliers, Manager, Patience, PauseSwitch, PriorityFeePercentiles,
+        ResubmissionMode, SendOptions, SendOutcome, SendTimings, SpeedUpSwitch,
+    },
+    price_feed::PriceFeed,
+    time::{DefaultTime, Time},
+    transaction::{
+        Metadata, PersistentState, Precondition, Priority, StaticTxData, SubmittedTxs, Transaction,
+        Value,
+    },
+    watcher::{WatchError, WatchPolicy},
+    Chain, CheckSummary, ConfirmationEstimator, DynError, DynManager, ErrorKind, GasLimit,
+    HandoffToken, NonceManagement, PendingResolution, PendingTxWatcher, Wei,
 };
 
 use utilities::{
     assert_err, assert_ok,
     mocks::{
-        database::{DatabaseStateError, MockDatabase},
-        gas_oracle::{IncrementingGasOracle, MockGasOracle, MockGasOracleError},
-        middleware::{MockMiddleware, MockMiddlewareError},
+        database::{self, DatabaseStateError, MockDatabase},
+        events::MockEventListener,
+        gas_oracle::{
+            ConstantGasOracle, IncrementingGasOracle, MockGasOracle, MockGasOracleCounters,
+            MockGasOracleError,
+        },
+        middleware::{self, MockMiddleware, MockMiddlewareError, RpcError},
+        replay::Replay,
+        scenario::{Call, ChainEvent, Scenario, ScenarioMiddleware, ScenarioTime},
         time::MockTime,
     },
     Account,
@@ -22,11 +67,9 @@ use utilities::{
 
 type MockManagerError = eth_tx_manager::Error<MockMiddleware, MockGasOracle, MockDatabase>;
 type MockManagerError2<GO> = eth_tx_manager::Error<MockMiddleware, GO, MockDatabase>;
+type MockManagerError3 = eth_tx_manager::Error<ScenarioMiddleware, MockGasOracle, MockDatabase>;
 
-const CHAIN: Chain = Chain {
-    id: 1337,
-    is_legacy: false,
-};
+const CHAIN: Chain = Chain::new(1337);
 
 #[tokio::test]
 #[serial]
@@ -39,8 +82,9 @@ async fn test_manager_new() {
     let transaction = Transaction {
         from: account1.into(),
         to: account2.into(),
-        value: Value::Number(U256::from(5u64)),
+        value: Value::Number(Wei::from(5)),
         call_data: None,
+        metadata: Metadata::new(),
     };
 
     // Instantiating a new transaction manager that has no pending transactions.
@@ -50,8 +94,27 @@ async fn test_manager_new() {
         let result =
             Manager::new(middleware, gas_oracle, db, CHAIN, Configuration::default()).await;
         assert_ok!(result);
-        let (_, transaction_receipt) = result.unwrap();
-        assert_eq!(transaction_receipt, None);
+        let (_, resolution) = result.unwrap();
+        assert_eq!(resolution, PendingResolution::None);
+    }
+
+    // Trying to instantiate a new transaction manager with an invalid
+    // configuration for the chain.
+    {
+        let (middleware, gas_oracle, mut db) = setup_dependencies();
+        db.get_state_output = Some(None);
+        let result = Manager::new(
+            middleware,
+            gas_oracle,
+            db,
+            Chain::new(1),
+            setup_configuration(),
+        )
+        .await;
+        let expected_err: MockManagerError =
+            eth_tx_manager::Error::Configuration(ConfigurationError::ZeroBlockTime);
+        assert_err!(result, expected_err);
+        assert_eq!(0, middleware_counters().get_chainid_n);
     }
 
     // Trying to instantiate new transaction manager without being able to check if
@@ -79,10 +142,18 @@ async fn test_manager_new() {
                 transaction: transaction.clone(),
                 priority: Priority::Normal,
                 confirmations: 1,
+                precondition: None,
             },
             submitted_txs: SubmittedTxs {
                 txs_hashes: vec![TRANSACTION_HASH1.parse().unwrap()],
             },
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
         }));
         db.clear_state_output = Some(());
         let result = Manager::new(
@@ -90,16 +161,119 @@ async fn test_manager_new() {
             gas_oracle,
             db,
             CHAIN,
-            Configuration {
-                transaction_mining_time: Duration::ZERO,
-                block_time: Duration::ZERO,
-                time: MockTime,
-            },
+            Configuration::new(MockTime)
+                .set_transaction_mining_time(Duration::ZERO)
+                .set_block_time(Duration::ZERO),
         )
         .await;
         assert_ok!(result);
-        let (_, transaction_receipt) = result.unwrap();
-        assert!(transaction_receipt.is_some());
+        let (_, resolution) = result.unwrap();
+        assert!(matches!(resolution, PendingResolution::AlreadyMined(_)));
+    };
+
+    // The time in flight of a recovered transaction counts from its first
+    // submission, before the restart.
+    {
+        let (mut middleware, gas_oracle, mut db) = setup_dependencies();
+        middleware.get_block_number = vec![1];
+        middleware.get_transaction_receipt = vec![true];
+        middleware.get_transaction_count = Some(());
+        let mut state = PersistentState::new(StaticTxData::new(
+            transaction.clone(),
+            1u64.into(),
+            1,
+            Priority::Normal,
+        ));
+        state.submitted_txs.add(TRANSACTION_HASH1.parse().unwrap());
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        state.first_submitted_at =
+            Some(an_hour_ago.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64);
+        db.get_state_output = Some(Some(state));
+        db.clear_state_output = Some(());
+        let event_listener = MockEventListener::new();
+        let configuration = Configuration::new(DefaultTime)
+            .set_transaction_mining_time(Duration::ZERO)
+            .set_block_time(Duration::ZERO)
+            .set_stall_threshold(Some(Duration::from_secs(1800)))
+            .add_event_listener(Arc::new(event_listener.clone()));
+        let result = Manager::new(middleware, gas_oracle, db, CHAIN, configuration).await;
+        assert_ok!(result);
+        assert!(matches!(
+            event_listener.events()[0],
+            Event::Stalled { in_flight, .. } if in_flight >= Duration::from_secs(3600)
+        ));
+    };
+
+    // Instantiating a new transaction manager that has one pending transaction,
+    // which gets confirmed while the manager waits for it.
+    {
+        let (mut middleware, gas_oracle, mut db) = setup_dependencies();
+        middleware.get_block_number = vec![0, 1];
+        middleware.get_transaction_receipt = vec![true, true];
+        middleware.get_transaction_count = Some(());
+        db.get_state_output = Some(Some(PersistentState {
+            tx_data: StaticTxData {
+                nonce: 1u64.into(),
+                transaction: transaction.clone(),
+                priority: Priority::Normal,
+                confirmations: 1,
+                precondition: None,
+            },
+            submitted_txs: SubmittedTxs {
+                txs_hashes: vec![TRANSACTION_HASH1.parse().unwrap()],
+            },
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
+        }));
+        db.clear_state_output = Some(());
+        let result = Manager::new(middleware, gas_oracle, db, CHAIN, setup_configuration()).await;
+        assert_ok!(result);
+        let (_, resolution) = result.unwrap();
+        assert!(matches!(resolution, PendingResolution::ConfirmedNow(_)));
+    };
+
+    // Instantiating a new transaction manager that has one pending transaction,
+    // whose nonce was consumed by a transaction sent outside the manager.
+    {
+        let (mut middleware, gas_oracle, mut db) = setup_dependencies();
+        middleware.get_transaction_receipt = vec![false];
+        middleware.get_transaction_count = Some(());
+        middleware.external_transactions = 2;
+        db.get_state_output = Some(Some(PersistentState {
+            tx_data: StaticTxData {
+                nonce: 1u64.into(),
+                transaction: transaction.clone(),
+                priority: Priority::Normal,
+                confirmations: 1,
+                precondition: None,
+            },
+            submitted_txs: SubmittedTxs {
+                txs_hashes: vec![TRANSACTION_HASH1.parse().unwrap()],
+            },
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
+        }));
+        db.clear_state_output = Some(());
+        let result = Manager::new(middleware, gas_oracle, db, CHAIN, setup_configuration()).await;
+        assert_ok!(result);
+        let (_, resolution) = result.unwrap();
+        assert_eq!(
+            resolution,
+            PendingResolution::RecoveredExternally {
+                hash: Some(TRANSACTION_HASH1.parse().unwrap())
+            }
+        );
+        assert_eq!(1, database_counters().clear_state_n);
     };
 
     // Trying to instantiate a new transaction manager that has one pending
@@ -116,21 +290,27 @@ async fn test_manager_new() {
                 transaction: transaction.clone(),
                 priority: Priority::Normal,
                 confirmations: 1,
+                precondition: None,
             },
             submitted_txs: SubmittedTxs {
                 txs_hashes: vec![TRANSACTION_HASH1.parse().unwrap()],
             },
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
         }));
         let result = Manager::new(
             middleware,
             gas_oracle,
             db,
             CHAIN,
-            Configuration {
-                transaction_mining_time: Duration::ZERO,
-                block_time: Duration::ZERO,
-                time: MockTime,
-            },
+            Configuration::new(MockTime)
+                .set_transaction_mining_time(Duration::ZERO)
+                .set_block_time(Duration::ZERO),
         )
         .await;
         let expected_err: MockManagerError =
@@ -139,6 +319,146 @@ async fn test_manager_new() {
     }
 }
 
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_node_errors() {
+    utilities::setup_tracing();
+
+    // The node already knows the resent transaction: the manager keeps
+    // waiting for it.
+    {
+        let result = run_send_transaction2(1, IncrementingGasOracle::new(), |mut middleware| {
+            middleware.get_transaction_receipt = vec![false, true, false];
+            middleware.send_errors = HashMap::from([(1, RpcError::already_known())]);
+            middleware
+        })
+        .await;
+        assert_ok!(result);
+        assert_eq!(2, middleware_counters().send_raw_transaction_n);
+        assert_eq!(3, middleware_counters().get_transaction_receipt_n);
+    }
+
+    // The node rejects the resent transaction as underpriced: the manager
+    // keeps waiting for the previous one.
+    {
+        let result = run_send_transaction2(1, IncrementingGasOracle::new(), |mut middleware| {
+            middleware.get_transaction_receipt = vec![false, true, false];
+            middleware.send_errors = HashMap::from([(1, RpcError::underpriced())]);
+            middleware
+        })
+        .await;
+        assert_ok!(result);
+        assert_eq!(2, middleware_counters().send_raw_transaction_n);
+        assert_eq!(3, middleware_counters().get_transaction_receipt_n);
+    }
+
+    // The parity-derived nodes reject underpriced replacements with other
+    // errors: the manager only recognizes them with their client's rules.
+    for (replacement_rules, accepted) in [
+        (ReplacementRules::default(), false),
+        (ReplacementRules::Client(NodeClient::OpenEthereum), true),
+        (ReplacementRules::Detect, true),
+    ] {
+        let (mut middleware, _, mut db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.get_transaction_receipt = vec![false, true, false];
+        middleware.send_errors = HashMap::from([(1, RpcError::openethereum_underpriced())]);
+        middleware.client_version =
+            Some("OpenEthereum//v3.3.5-stable/x86_64-linux-musl/rustc1.59.0".to_string());
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+        let configuration = setup_configuration().set_replacement_rules(replacement_rules);
+        let manager =
+            setup_manager(middleware, IncrementingGasOracle::new(), db, configuration).await;
+        let expected_client_version_n = (replacement_rules == ReplacementRules::Detect) as i32;
+        assert_eq!(
+            expected_client_version_n,
+            middleware_counters().client_version_n
+        );
+
+        let transaction = Transaction {
+            from: HASH1.parse().unwrap(),
+            to: HASH2.parse().unwrap(),
+            value: Value::Number(Wei::from(5)),
+            call_data: None,
+            metadata: Metadata::new(),
+        };
+        let result = manager
+            .send(transaction, SendOptions::new().set_confirmations(1))
+            .await;
+        assert_eq!(accepted, result.is_ok(), "{:?}", replacement_rules);
+        assert_eq!(2, middleware_counters().send_raw_transaction_n);
+    }
+
+    // The node refuses further replacements: the manager stops replacing the
+    // transaction (flagging it once) and waits for the submitted ones.
+    {
+        let (mut middleware, _, mut db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.get_transaction_receipt = vec![false, false, false, false, false, true, false];
+        middleware.send_errors = HashMap::from([(1, RpcError::replacement_limit_reached())]);
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+        let event_listener = MockEventListener::new();
+        let configuration =
+            setup_configuration().add_event_listener(Arc::new(event_listener.clone()));
+        let manager =
+            setup_manager(middleware, IncrementingGasOracle::new(), db, configuration).await;
+
+        let transaction = Transaction {
+            from: HASH1.parse().unwrap(),
+            to: HASH2.parse().unwrap(),
+            value: Value::Number(Wei::from(5)),
+            call_data: None,
+            metadata: Metadata::new(),
+        };
+        let result = manager
+            .send(transaction, SendOptions::new().set_confirmations(1))
+            .await;
+        assert_ok!(result);
+        assert_eq!(2, middleware_counters().send_raw_transaction_n);
+        assert_eq!(7, middleware_counters().get_transaction_receipt_n);
+        let limit_events = event_listener
+            .events()
+            .into_iter()
+            .filter(|event| matches!(event, Event::ReplacementLimitReached { submissions: 2, .. }))
+            .count();
+        assert_eq!(1, limit_events);
+    }
+
+    // The gas limit is below the intrinsic gas.
+    {
+        let result = run_send_transaction2(1, IncrementingGasOracle::new(), |mut middleware| {
+            middleware.send_errors = HashMap::from([(0, RpcError::intrinsic_gas_too_low())]);
+            middleware
+        })
+        .await
+        .map_err(eth_tx_manager::Error::into_inner);
+        assert!(matches!(
+            result,
+            Err(eth_tx_manager::Error::IntrinsicGasTooLow(Some(gas_limit)))
+                if gas_limit == U256::from(21000)
+        ));
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    }
+
+    // Other errors are returned.
+    {
+        let result = run_send_transaction2(1, IncrementingGasOracle::new(), |mut middleware| {
+            middleware.send_errors = HashMap::from([(0, RpcError::nonce_too_low())]);
+            middleware
+        })
+        .await
+        .map_err(eth_tx_manager::Error::into_inner);
+        assert!(matches!(
+            result,
+            Err(eth_tx_manager::Error::Middleware(MockMiddlewareError::Rpc(err)))
+                if err == RpcError::nonce_too_low()
+        ));
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    }
+}
+
 #[tokio::test]
 #[serial]
 async fn test_manager_send_transaction_advanced() {
@@ -148,38 +468,38 @@ async fn test_manager_send_transaction_advanced() {
     {
         let result = run_send_transaction2(1, IncrementingGasOracle::new(), |mut middleware| {
             middleware.get_block_number = vec![1];
-            middleware.get_transaction_receipt = vec![false, true];
+            middleware.get_transaction_receipt = vec![false, true, false];
             middleware
         })
         .await;
         assert_ok!(result);
 
-        assert_eq!(0, MockMiddleware::global().estimate_eip1559_fees_n);
-        assert_eq!(1, MockMiddleware::global().get_block_number_n);
-        assert_eq!(1, MockMiddleware::global().get_transaction_count_n);
-        assert_eq!(2, MockMiddleware::global().estimate_gas_n);
-        assert_eq!(2, MockMiddleware::global().sign_transaction_n);
-        assert_eq!(2, MockMiddleware::global().send_raw_transaction_n);
-        assert_eq!(2, MockMiddleware::global().get_transaction_receipt_n);
+        assert_eq!(0, middleware_counters().estimate_eip1559_fees_n);
+        assert_eq!(1, middleware_counters().get_block_number_n);
+        assert_eq!(1, middleware_counters().get_transaction_count_n);
+        assert_eq!(2, middleware_counters().estimate_gas_n);
+        assert_eq!(2, middleware_counters().sign_transaction_n);
+        assert_eq!(2, middleware_counters().send_raw_transaction_n);
+        assert_eq!(3, middleware_counters().get_transaction_receipt_n);
     }
 
     // Resends the transaction twice.
     {
         let result = run_send_transaction2(1, IncrementingGasOracle::new(), |mut middleware| {
             middleware.get_block_number = vec![1];
-            middleware.get_transaction_receipt = vec![false, false, false, true];
+            middleware.get_transaction_receipt = vec![false, false, false, true, false, false];
             middleware
         })
         .await;
         assert_ok!(result);
 
-        assert_eq!(0, MockMiddleware::global().estimate_eip1559_fees_n);
-        assert_eq!(1, MockMiddleware::global().get_block_number_n);
-        assert_eq!(1, MockMiddleware::global().get_transaction_count_n);
-        assert_eq!(3, MockMiddleware::global().estimate_gas_n);
-        assert_eq!(3, MockMiddleware::global().sign_transaction_n);
-        assert_eq!(3, MockMiddleware::global().send_raw_transaction_n);
-        assert_eq!(4, MockMiddleware::global().get_transaction_receipt_n);
+        assert_eq!(0, middleware_counters().estimate_eip1559_fees_n);
+        assert_eq!(1, middleware_counters().get_block_number_n);
+        assert_eq!(1, middleware_counters().get_transaction_count_n);
+        assert_eq!(3, middleware_counters().estimate_gas_n);
+        assert_eq!(3, middleware_counters().sign_transaction_n);
+        assert_eq!(3, middleware_counters().send_raw_transaction_n);
+        assert_eq!(6, middleware_counters().get_transaction_receipt_n);
     }
 }
 
@@ -195,13 +515,13 @@ async fn test_manager_send_transaction_basic() {
                 .await;
         assert_ok!(result);
 
-        assert_eq!(0, MockMiddleware::global().estimate_eip1559_fees_n);
-        assert_eq!(1, MockMiddleware::global().get_block_number_n);
-        assert_eq!(1, MockMiddleware::global().get_transaction_count_n);
-        assert_eq!(1, MockMiddleware::global().estimate_gas_n);
-        assert_eq!(1, MockMiddleware::global().sign_transaction_n);
-        assert_eq!(1, MockMiddleware::global().send_raw_transaction_n);
-        assert_eq!(1, MockMiddleware::global().get_transaction_receipt_n);
+        assert_eq!(0, middleware_counters().estimate_eip1559_fees_n);
+        assert_eq!(1, middleware_counters().get_block_number_n);
+        assert_eq!(1, middleware_counters().get_transaction_count_n);
+        assert_eq!(1, middleware_counters().estimate_gas_n);
+        assert_eq!(1, middleware_counters().sign_transaction_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+        assert_eq!(1, middleware_counters().get_transaction_receipt_n);
     }
 
     // Ok (1 confirmation).
@@ -214,13 +534,13 @@ async fn test_manager_send_transaction_basic() {
         .await;
         assert_ok!(result);
 
-        assert_eq!(0, MockMiddleware::global().estimate_eip1559_fees_n);
-        assert_eq!(1, MockMiddleware::global().get_block_number_n);
-        assert_eq!(1, MockMiddleware::global().get_transaction_count_n);
-        assert_eq!(1, MockMiddleware::global().estimate_gas_n);
-        assert_eq!(1, MockMiddleware::global().sign_transaction_n);
-        assert_eq!(1, MockMiddleware::global().send_raw_transaction_n);
-        assert_eq!(1, MockMiddleware::global().get_transaction_receipt_n);
+        assert_eq!(0, middleware_counters().estimate_eip1559_fees_n);
+        assert_eq!(1, middleware_counters().get_block_number_n);
+        assert_eq!(1, middleware_counters().get_transaction_count_n);
+        assert_eq!(1, middleware_counters().estimate_gas_n);
+        assert_eq!(1, middleware_counters().sign_transaction_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+        assert_eq!(1, middleware_counters().get_transaction_receipt_n);
     }
 
     // Ok (2 confirmations).
@@ -233,13 +553,13 @@ async fn test_manager_send_transaction_basic() {
         .await;
         assert_ok!(result);
 
-        assert_eq!(0, MockMiddleware::global().estimate_eip1559_fees_n);
-        assert_eq!(4, MockMiddleware::global().get_block_number_n);
-        assert_eq!(1, MockMiddleware::global().get_transaction_count_n);
-        assert_eq!(1, MockMiddleware::global().estimate_gas_n);
-        assert_eq!(1, MockMiddleware::global().sign_transaction_n);
-        assert_eq!(1, MockMiddleware::global().send_raw_transaction_n);
-        assert_eq!(4, MockMiddleware::global().get_transaction_receipt_n);
+        assert_eq!(0, middleware_counters().estimate_eip1559_fees_n);
+        assert_eq!(4, middleware_counters().get_block_number_n);
+        assert_eq!(1, middleware_counters().get_transaction_count_n);
+        assert_eq!(1, middleware_counters().estimate_gas_n);
+        assert_eq!(1, middleware_counters().sign_transaction_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+        assert_eq!(4, middleware_counters().get_transaction_receipt_n);
     }
 
     // Ok (10 confirmations).
@@ -252,12 +572,12 @@ async fn test_manager_send_transaction_basic() {
         .await;
         assert_ok!(result);
 
-        assert_eq!(10, MockMiddleware::global().get_block_number_n);
-        assert_eq!(1, MockMiddleware::global().get_transaction_count_n);
-        assert_eq!(1, MockMiddleware::global().estimate_gas_n);
-        assert_eq!(1, MockMiddleware::global().sign_transaction_n);
-        assert_eq!(1, MockMiddleware::global().send_raw_transaction_n);
-        assert_eq!(10, MockMiddleware::global().get_transaction_receipt_n);
+        assert_eq!(10, middleware_counters().get_block_number_n);
+        assert_eq!(1, middleware_counters().get_transaction_count_n);
+        assert_eq!(1, middleware_counters().estimate_gas_n);
+        assert_eq!(1, middleware_counters().sign_transaction_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+        assert_eq!(10, middleware_counters().get_transaction_receipt_n);
     }
 }
 
@@ -266,7 +586,7 @@ async fn test_manager_send_transaction_basic() {
 async fn test_manager_send_transaction_basic_middleware_errors() {
     utilities::setup_tracing();
 
-    // "Middleware::estimate_eip1559_fees" is being tested in the
+    // "Middleware::fee_history" is being tested in the
     // test_manager_send_transaction_basic_gas_oracle_errors function bellow.
 
     // When "Middleware::get_transaction_count" fails.
@@ -279,7 +599,7 @@ async fn test_manager_send_transaction_basic_middleware_errors() {
         let expected_err: MockManagerError =
             eth_tx_manager::Error::Middleware(MockMiddlewareError::GetTransactionCount);
         assert_err!(result, expected_err);
-        assert_eq!(1, MockMiddleware::global().get_transaction_count_n)
+        assert_eq!(1, middleware_counters().get_transaction_count_n)
     }
 
     // When "Middleware::estimate_gas" fails.
@@ -292,7 +612,7 @@ async fn test_manager_send_transaction_basic_middleware_errors() {
         let expected_err: MockManagerError =
             eth_tx_manager::Error::Middleware(MockMiddlewareError::EstimateGas);
         assert_err!(result, expected_err);
-        assert_eq!(1, MockMiddleware::global().estimate_gas_n)
+        assert_eq!(1, middleware_counters().estimate_gas_n)
     }
 
     // When "Middleware::sign_transaction" fails.
@@ -305,7 +625,7 @@ async fn test_manager_send_transaction_basic_middleware_errors() {
         let expected_err: MockManagerError =
             eth_tx_manager::Error::Middleware(MockMiddlewareError::SignTransaction);
         assert_err!(result, expected_err);
-        assert_eq!(1, MockMiddleware::global().sign_transaction_n)
+        assert_eq!(1, middleware_counters().sign_transaction_n)
     }
 
     // When "Middleware::send_transaction" fails.
@@ -318,7 +638,7 @@ async fn test_manager_send_transaction_basic_middleware_errors() {
         let expected_err: MockManagerError =
             eth_tx_manager::Error::Middleware(MockMiddlewareError::SendTransaction);
         assert_err!(result, expected_err);
-        assert_eq!(1, MockMiddleware::global().send_raw_transaction_n)
+        assert_eq!(1, middleware_counters().send_raw_transaction_n)
     }
 
     // When "Middleware::get_transaction_receipt" fails.
@@ -331,7 +651,7 @@ async fn test_manager_send_transaction_basic_middleware_errors() {
         let expected_err: MockManagerError =
             eth_tx_manager::Error::Middleware(MockMiddlewareError::GetTransactionReceipt(1));
         assert_err!(result, expected_err);
-        assert_eq!(1, MockMiddleware::global().get_transaction_receipt_n)
+        assert_eq!(1, middleware_counters().get_transaction_receipt_n)
     }
 
     // When "Middleware::get_block_number" fails.
@@ -344,7 +664,73 @@ async fn test_manager_send_transaction_basic_middleware_errors() {
         let expected_err: MockManagerError =
             eth_tx_manager::Error::Middleware(MockMiddlewareError::GetBlockNumber);
         assert_err!(result, expected_err);
-        assert_eq!(1, MockMiddleware::global().get_block_number_n)
+        assert_eq!(1, middleware_counters().get_block_number_n)
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_estimate_gas_errors() {
+    utilities::setup_tracing();
+
+    // Reverts (with the reason decoded from the error data).
+    {
+        let result = run_send_transaction(0, |mut middleware, gas_oracle, db| {
+            let data = ethers::abi::encode(&[ethers::abi::Token::String("nope".to_string())]);
+            middleware.estimate_gas_errors = vec![format!(
+                "(code: 3, message: execution reverted: nope, data: Some(String(\"0x08c379a0{}\")))",
+                hex::encode(data)
+            )];
+            (middleware, gas_oracle, db)
+        })
+        .await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::Reverted(Some("nope".into()));
+        assert_err!(result, expected_err);
+        assert_eq!(1, middleware_counters().estimate_gas_n);
+    }
+
+    // Requires more gas than the node allows.
+    {
+        let result = run_send_transaction(0, |mut middleware, gas_oracle, db| {
+            middleware.estimate_gas_errors =
+                vec!["gas required exceeds allowance (30000000)".to_string()];
+            (middleware, gas_oracle, db)
+        })
+        .await;
+        let expected_err: MockManagerError =
+            eth_tx_manager::Error::GasAllowanceExceeded(Some(30_000_000));
+        assert_err!(result, expected_err);
+        assert_eq!(1, middleware_counters().estimate_gas_n);
+    }
+
+    // Retries after transient errors.
+    {
+        let result = run_send_transaction(0, |mut middleware, gas_oracle, db| {
+            middleware.estimate_gas_errors = vec![
+                "request timed out".to_string(),
+                "429 Too Many Requests".to_string(),
+            ];
+            (middleware, gas_oracle, db)
+        })
+        .await;
+        assert_ok!(result);
+        assert_eq!(3, middleware_counters().estimate_gas_n);
+    }
+
+    // Gives up after too many transient errors.
+    {
+        let configuration = setup_configuration().set_estimate_gas_retries(1);
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.estimate_gas_errors = vec!["request timed out".to_string(); 2];
+                (middleware, b, c)
+            })
+            .await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::Middleware(
+            MockMiddlewareError::Message("request timed out".to_string()),
+        );
+        assert_err!(result, expected_err);
+        assert_eq!(2, middleware_counters().estimate_gas_n);
     }
 }
 
@@ -356,18 +742,17 @@ async fn test_manager_send_transaction_basic_gas_oracle_errors() {
     // When only "GasOracle::gas_info" fails.
     {
         let result = run_send_transaction(0, |mut middleware, mut gas_oracle, db| {
-            middleware.estimate_eip1559_fees = Some((300, 50));
+            middleware.fee_history = Some((300, vec![50]));
             gas_oracle.gas_oracle_info_output = None;
             (middleware, gas_oracle, db)
         })
         .await;
         assert_ok!(result);
-        assert_eq!(1, MockGasOracle::global().gas_info_n);
-        assert_eq!(1, MockMiddleware::global().estimate_eip1559_fees_n);
+        assert_eq!(1, gas_oracle_counters().gas_info_n);
+        assert_eq!(1, middleware_counters().fee_history_n);
     }
 
-    // When both "GasOracle::gas_info" and
-    // "Middleware::estimate_eip1559_fees" fail.
+    // When both "GasOracle::gas_info" and "Middleware::fee_history" fail.
     {
         let result = run_send_transaction(0, |middleware, mut gas_oracle, db| {
             gas_oracle.gas_oracle_info_output = None;
@@ -376,11 +761,22 @@ async fn test_manager_send_transaction_basic_gas_oracle_errors() {
         .await;
         let expected_err: MockManagerError = eth_tx_manager::Error::GasOracle(
             MockGasOracleError::GasInfo,
-            MockMiddlewareError::EstimateEIP1559Fees,
+            MockMiddlewareError::FeeHistory,
         );
         assert_err!(result, expected_err);
-        assert_eq!(1, MockGasOracle::global().gas_info_n);
-        assert_eq!(1, MockMiddleware::global().estimate_eip1559_fees_n);
+        assert_eq!(1, gas_oracle_counters().gas_info_n);
+        assert_eq!(1, middleware_counters().fee_history_n);
+    }
+
+    // Without a gas oracle.
+    {
+        let result = run_send_transaction2(0, NoGasOracle, |mut middleware| {
+            middleware.fee_history = Some((300, vec![50]));
+            middleware
+        })
+        .await;
+        assert_ok!(result);
+        assert_eq!(1, middleware_counters().fee_history_n);
     }
 }
 
@@ -399,7 +795,7 @@ async fn test_manager_send_transaction_basic_database_errors() {
         let expected_err: MockManagerError =
             eth_tx_manager::Error::Database(DatabaseStateError::Set);
         assert_err!(result, expected_err);
-        assert_eq!(1, MockDatabase::global().set_state_n);
+        assert_eq!(1, database_counters().set_state_n);
     }
 
     // When "Database::clear_state" fails.
@@ -412,126 +808,4404 @@ async fn test_manager_send_transaction_basic_database_errors() {
         let expected_err: MockManagerError =
             eth_tx_manager::Error::Database(DatabaseStateError::Clear);
         assert_err!(result, expected_err);
-        assert_eq!(1, MockDatabase::global().clear_state_n);
+        assert_eq!(1, database_counters().clear_state_n);
     }
 }
 
-// ------------------------------------------------------------------------------------------------
-// Auxiliary
-// ------------------------------------------------------------------------------------------------
+#[tokio::test]
+#[serial]
+async fn test_manager_error_kinds() {
+    utilities::setup_tracing();
+    macro_rules! estimate_gas_error {
+        ($message: expr) => {
+            run_send_transaction(0, |mut middleware, gas_oracle, db| {
+                middleware.estimate_gas_errors = vec![$message.to_string(); 4];
+                (middleware, gas_oracle, db)
+            })
+        };
+    }
 
-fn setup_dependencies() -> (MockMiddleware, MockGasOracle, MockDatabase) {
-    (
-        MockMiddleware::new(),
-        MockGasOracle::new(),
-        MockDatabase::new(),
-    )
-}
+    // The errors of the provider are categorized by their messages (the
+    // reports of the failed sends are transparent).
+    let err = estimate_gas_error!("request timed out").await.unwrap_err();
+    assert!(err.report().is_some());
+    assert_eq!(ErrorKind::Transient, err.kind());
+    assert!(err.kind().is_retryable());
 
-const HASH1: &str = "0xba763b97851b653aaaf631723bab41a500f03b29";
-const HASH2: &str = "0x29e425df042e83e4ddb3ee3348d6d745c58fce8f";
+    let err = estimate_gas_error!("insufficient funds for transfer")
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorKind::Funds, err.kind());
+    assert!(!err.kind().is_retryable());
 
-const TRANSACTION_HASH1: &str =
-    "0x2b34df791cc4eb898f6d4437713e946f216cac6a3921b2899db919abe26739b2";
+    let err = estimate_gas_error!("gas required exceeds allowance (30000000)")
+        .await
+        .unwrap_err();
+    assert_eq!(ErrorKind::Gas, err.kind());
 
-async fn setup_manager<GO: eth_tx_manager::gas_oracle::GasOracle>(
-    middleware: MockMiddleware,
-    gas_oracle: GO,
-    mut db: MockDatabase,
-) -> Manager<MockMiddleware, GO, MockDatabase, MockTime>
-where
-    GO: Send + Sync,
-{
-    db.get_state_output = Some(None);
-    let result = Manager::new(
-        middleware,
-        gas_oracle,
-        db,
-        CHAIN,
-        Configuration {
-            transaction_mining_time: Duration::ZERO,
-            block_time: Duration::ZERO,
-            time: MockTime,
-        },
-    )
-    .await;
-    assert_ok!(result);
-    let (manager, transaction_receipt) = result.unwrap();
-    assert!(transaction_receipt.is_none());
-    manager
-}
+    let err = estimate_gas_error!("execution reverted").await.unwrap_err();
+    assert_eq!(ErrorKind::Revert, err.kind());
 
-fn setup_middleware(mut middleware: MockMiddleware) -> MockMiddleware {
-    middleware.estimate_gas = Some(U256::from(21000));
-    middleware.get_block_number = vec![1];
-    middleware.get_transaction_count = Some(());
-    middleware.get_transaction_receipt = vec![true];
-    middleware.send_transaction = Some(());
-    middleware.sign_transaction = Some(());
-    middleware
-}
+    let err = estimate_gas_error!("unknown account").await.unwrap_err();
+    assert_eq!(ErrorKind::Fatal, err.kind());
 
-async fn run_send_transaction(
-    confirmations: usize,
-    f: fn(
-        MockMiddleware,
-        MockGasOracle,
-        MockDatabase,
-    ) -> (MockMiddleware, MockGasOracle, MockDatabase),
-) -> Result<TransactionReceipt, MockManagerError> {
-    let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
-    middleware = setup_middleware(middleware);
-    gas_oracle.gas_oracle_info_output = Some(GasOracleInfo {
-        gas_info: GasInfo::EIP1559(EIP1559GasInfo {
-            max_fee: U256::from(1_000_000_000),
-            max_priority_fee: Some(U256::from(100_000)),
-        }),
-        mining_time: None,
-        block_time: None,
-    });
-    db.get_state_output = None;
-    db.set_state_output = Some(());
-    db.clear_state_output = Some(());
-    let (middleware, gas_oracle, db) = f(middleware, gas_oracle, db);
+    let err = run_send_transaction2(1, IncrementingGasOracle::new(), |mut middleware| {
+        middleware.send_errors = HashMap::from([(0, RpcError::nonce_too_low())]);
+        middleware
+    })
+    .await
+    .unwrap_err();
+    assert_eq!(ErrorKind::Nonce, err.kind());
+    assert_eq!("nonce", err.kind().code());
 
-    let manager = setup_manager(middleware, gas_oracle, db).await;
-    let transaction = Transaction {
-        from: HASH1.parse().unwrap(),
-        to: HASH2.parse().unwrap(),
-        value: Value::Number(U256::from(5u64)),
-        call_data: None,
-    };
-    manager
-        .send_transaction(transaction, confirmations, Priority::Normal)
-        .await
-        .map(|(_, receipt)| receipt)
+    let err = run_send_transaction(0, |middleware, gas_oracle, mut db| {
+        db.set_state_output = None;
+        (middleware, gas_oracle, db)
+    })
+    .await
+    .unwrap_err();
+    assert_eq!(ErrorKind::Storage, err.kind());
+    assert_eq!("storage", err.kind().to_string());
 }
 
-// TODO
-async fn run_send_transaction2<GO: GasOracle>(
-    confirmations: usize,
-    gas_oracle: GO,
-    f: fn(MockMiddleware) -> MockMiddleware,
-) -> Result<TransactionReceipt, MockManagerError2<GO>>
-where
-    GO: Send + Sync,
-{
-    let (mut middleware, _, mut db) = setup_dependencies();
-    middleware = setup_middleware(middleware);
-    db.get_state_output = None;
-    db.set_state_output = Some(());
-    db.clear_state_output = Some(());
-    let middleware = f(middleware);
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_base_fee_spike() {
+    utilities::setup_tracing();
 
-    let manager = setup_manager(middleware, gas_oracle, db).await;
-    let transaction = Transaction {
-        from: HASH1.parse().unwrap(),
-        to: HASH2.parse().unwrap(),
-        value: Value::Number(U256::from(5u64)),
-        call_data: None,
-    };
+    // When the gas oracle's max fee is below the base fee and there is no
+    // configured headroom.
+    {
+        let result = run_send_transaction(0, |mut middleware, mut gas_oracle, db| {
+            middleware.get_block = vec![2_000_000_000];
+            gas_oracle.gas_oracle_info_output = Some(max_fee_only_gas_oracle_info(1_000_000_000));
+            (middleware, gas_oracle, db)
+        })
+        .await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::MaxFeeBelowBaseFee {
+            max_fee: Wei::from(1_000_000_000),
+            base_fee: Wei::from(2_000_000_000),
+        };
+        assert_err!(result, expected_err);
+        assert_eq!(1, middleware_counters().get_block_n);
+        assert_eq!(0, middleware_counters().send_raw_transaction_n);
+    }
+
+    // When the gas oracle's max fee is below the base fee and the manager
+    // raises it using the configured headroom.
+    {
+        let configuration = setup_configuration().set_max_fee_headroom(Some(1.5));
+        let result = run_send_transaction_with_configuration(
+            0,
+            configuration,
+            |mut middleware, mut gas_oracle, db| {
+                middleware.get_block = vec![2_000_000_000];
+                gas_oracle.gas_oracle_info_output =
+                    Some(max_fee_only_gas_oracle_info(1_000_000_000));
+                (middleware, gas_oracle, db)
+            },
+        )
+        .await;
+        assert_ok!(result);
+        assert_eq!(1, middleware_counters().get_block_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    }
+
+    // When the headroom is not enough to cover the base fee.
+    {
+        let configuration = setup_configuration().set_max_fee_headroom(Some(1.0));
+        let result = run_send_transaction_with_configuration(
+            0,
+            configuration,
+            |mut middleware, mut gas_oracle, db| {
+                middleware.get_block = vec![2_000_000_000];
+                gas_oracle.gas_oracle_info_output =
+                    Some(max_fee_only_gas_oracle_info(1_000_000_000));
+                (middleware, gas_oracle, db)
+            },
+        )
+        .await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::MaxFeeBelowBaseFee {
+            max_fee: Wei::from(1_000_000_000),
+            base_fee: Wei::from(2_000_000_000),
+        };
+        assert_err!(result, expected_err);
+    }
+
+    // When the gas oracle's max fee covers the base fee.
+    {
+        let result = run_send_transaction(0, |mut middleware, mut gas_oracle, db| {
+            middleware.get_block = vec![500_000_000];
+            gas_oracle.gas_oracle_info_output = Some(max_fee_only_gas_oracle_info(1_000_000_000));
+            (middleware, gas_oracle, db)
+        })
+        .await;
+        assert_ok!(result);
+        assert_eq!(1, middleware_counters().get_block_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_priority_fee_only_gas_oracle() {
+    utilities::setup_tracing();
+
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+
+    // Without a configured base fee multiplier, the manager uses the headroom,
+    // or the maximum base fee increase per block.
+    for (headroom, multiplier, expected_max_fee) in [
+        (None, None, 2_250_100_000u64),
+        (Some(1.5), None, 3_000_100_000u64),
+        (Some(1.5), Some(2.), 4_000_100_000u64),
+    ] {
+        let (mut middleware, mut gas_oracle, db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.get_block = vec![2_000_000_000];
+        gas_oracle.gas_oracle_info_output = Some(priority_fee_only_gas_oracle_info(100_000));
+        let configuration = setup_configuration()
+            .set_max_fee_headroom(headroom)
+            .set_base_fee_multiplier(multiplier);
+        let manager = setup_manager(middleware, gas_oracle, db, configuration).await;
+
+        let result = manager.estimate_cost(&transaction, Priority::Normal).await;
+        assert_ok!(result);
+        assert_eq!(
+            GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: Some(Wei::from(expected_max_fee)),
+                max_priority_fee: Some(Wei::from(100_000)),
+            }),
+            result.unwrap().gas_info
+        );
+        assert_eq!(1, middleware_counters().get_block_n);
+    }
+
+    // The manager sends the transaction with the calculated fees.
+    {
+        let result = run_send_transaction(0, |mut middleware, mut gas_oracle, db| {
+            middleware.get_block = vec![2_000_000_000];
+            gas_oracle.gas_oracle_info_output = Some(priority_fee_only_gas_oracle_info(100_000));
+            (middleware, gas_oracle, db)
+        })
+        .await;
+        assert_ok!(result);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    }
+
+    // When the gas oracle returns neither fee.
+    {
+        let result = run_send_transaction(0, |middleware, mut gas_oracle, db| {
+            let mut gas_oracle_info = priority_fee_only_gas_oracle_info(0);
+            gas_oracle_info.gas_info = GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: None,
+                max_priority_fee: None,
+            });
+            gas_oracle.gas_oracle_info_output = Some(gas_oracle_info);
+            (middleware, gas_oracle, db)
+        })
+        .await;
+        let expected_err: MockManagerError =
+            eth_tx_manager::Error::IncompatibleGasOracle("no EIP1559 fees");
+        assert_err!(result, expected_err);
+        assert_eq!(0, middleware_counters().send_raw_transaction_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_base_fee_multiplier() {
+    utilities::setup_tracing();
+
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+
+    // The provider's fee estimates (base fee 300, max priority fee 50) cover a
+    // doubling of the base fee by default.
+    for (multiplier, expected_max_fee) in [(None, 650u64), (Some(3.), 950u64)] {
+        let (mut middleware, mut gas_oracle, db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.fee_history = Some((300, vec![50]));
+        gas_oracle.gas_oracle_info_output = None;
+        let configuration = setup_configuration().set_base_fee_multiplier(multiplier);
+        let manager = setup_manager(middleware, gas_oracle, db, configuration).await;
+
+        let result = manager.estimate_cost(&transaction, Priority::Normal).await;
+        assert_ok!(result);
+        assert_eq!(
+            GasInfo::EIP1559(EIP1559GasInfo {
+                max_fee: Some(Wei::from(expected_max_fee)),
+                max_priority_fee: Some(Wei::from(50)),
+            }),
+            result.unwrap().gas_info
+        );
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_update_configuration() {
+    utilities::setup_tracing();
+
+    let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    middleware.get_block = vec![2_000_000_000];
+    gas_oracle.gas_oracle_info_output = Some(max_fee_only_gas_oracle_info(1_000_000_000));
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+
+    let mut manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+    assert_eq!(None, manager.configuration().max_fee_headroom);
+
+    let result = manager.update_configuration(|configuration| {
+        configuration.block_time = Duration::from_secs(30);
+        configuration.max_fee_headroom = Some(1.5);
+    });
+    assert_ok!(result);
+    assert_eq!(Duration::from_secs(30), manager.configuration().block_time);
+    assert_eq!(Some(1.5), manager.configuration().max_fee_headroom);
+
+    // Invalid updates are rejected, and the configuration is kept.
+    let result = manager.update_configuration(|configuration| {
+        configuration.block_time = Duration::from_secs(60);
+        configuration.max_fee_headroom = Some(0.5);
+    });
+    assert!(matches!(
+        result,
+        Err(ConfigurationError::InvalidMaxFeeHeadroom(_))
+    ));
+    assert_eq!(Duration::from_secs(30), manager.configuration().block_time);
+    assert_eq!(Some(1.5), manager.configuration().max_fee_headroom);
+
+    // The updated headroom is used when sending the transaction.
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(0))
+        .await;
+    assert_ok!(result);
+    assert_eq!(1, middleware_counters().send_raw_transaction_n);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_priority_fee_percentiles() {
+    utilities::setup_tracing();
+
+    let percentiles = PriorityFeePercentiles {
+        low: 5.,
+        normal: 20.,
+        high: 60.,
+        asap: 95.,
+    };
+
+    for (priority, percentile) in [
+        (Priority::Low, 5.),
+        (Priority::Normal, 20.),
+        (Priority::High, 60.),
+        (Priority::ASAP, 95.),
+        (Priority::Custom(40), 40.),
+        (Priority::Custom(150), 100.),
+    ] {
+        let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.fee_history = Some((300, vec![0, 50, 70, 60]));
+        gas_oracle.gas_oracle_info_output = None;
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+
+        let configuration = setup_configuration().set_priority_fee_percentiles(percentiles);
+        let manager = setup_manager(middleware, gas_oracle, db, configuration).await;
+        let transaction = Transaction {
+            from: HASH1.parse().unwrap(),
+            to: HASH2.parse().unwrap(),
+            value: Value::Number(Wei::from(5)),
+            call_data: None,
+            metadata: Metadata::new(),
+        };
+        let result = manager
+            .send(
+                transaction,
+                SendOptions::new()
+                    .set_confirmations(0)
+                    .set_priority(priority),
+            )
+            .await;
+        assert_ok!(result);
+        assert_eq!(1, middleware_counters().fee_history_n);
+        assert_eq!(vec![percentile], middleware_counters().reward_percentiles);
+    }
+}
+
+#[test]
+fn test_chain() {
+    let hash: H256 = TRANSACTION_HASH1.parse().unwrap();
+
+    let chain = Chain::new(1337);
+    assert_eq!("chain 1337", chain.to_string());
+    assert_eq!(None, chain.transaction_url(hash));
+    assert_eq!("1000 wei", chain.format_value(U256::from(1000)));
+
+    let chain = Chain::new(1337)
+        .with_name("Devnet")
+        .with_currency("ETH", 18)
+        .with_explorer_url("https://explorer.local/");
+    assert_eq!("Devnet (1337)", chain.to_string());
+    assert_eq!(
+        Some(format!("https://explorer.local/tx/{}", TRANSACTION_HASH1)),
+        chain.transaction_url(hash)
+    );
+    assert_eq!("0.5 ETH", chain.format_value(U256::exp10(17) * 5));
+
+    // Falls back to the ethers chain registry.
+    assert_eq!(
+        Some(format!("https://etherscan.io/tx/{}", TRANSACTION_HASH1)),
+        Chain::new(1).transaction_url(hash)
+    );
+}
+
+#[test]
+fn test_configuration_validate() {
+    let mainnet = Chain::new(1);
+    let recommended = Configuration::new(MockTime).set_recommended_values(&mainnet);
+    assert_eq!(Ok(vec![]), recommended.validate(&mainnet));
+
+    // Zero times are only valid for development chains.
+    assert_eq!(Ok(vec![]), setup_configuration().validate(&CHAIN));
+    assert_eq!(
+        Err(ConfigurationError::ZeroBlockTime),
+        setup_configuration().validate(&mainnet)
+    );
+    assert_eq!(
+        Err(ConfigurationError::ZeroTransactionMiningTime),
+        recommended
+            .clone()
+            .set_transaction_mining_time(Duration::ZERO)
+            .validate(&mainnet)
+    );
+
+    assert_eq!(
+        Err(ConfigurationError::InvalidMaxFeeHeadroom(0.5)),
+        recommended
+            .clone()
+            .set_max_fee_headroom(Some(0.5))
+            .validate(&mainnet)
+    );
+    assert_eq!(
+        Err(ConfigurationError::InvalidBaseFeeMultiplier(0.9)),
+        recommended
+            .clone()
+            .set_base_fee_multiplier(Some(0.9))
+            .validate(&mainnet)
+    );
+    assert_eq!(
+        Err(ConfigurationError::InvalidPriorityFeePercentile(101.)),
+        recommended
+            .clone()
+            .set_priority_fee_percentiles(PriorityFeePercentiles {
+                low: 10.,
+                normal: 25.,
+                high: 50.,
+                asap: 101.,
+            })
+            .validate(&mainnet)
+    );
+    assert_eq!(
+        Err(ConfigurationError::InvalidGasPriceMultiplier(0.)),
+        recommended
+            .clone()
+            .set_gas_price_multipliers(GasPriceMultipliers {
+                low: 0.,
+                normal: 1.,
+                high: 1.25,
+                asap: 1.5,
+            })
+            .validate(&mainnet)
+    );
+    assert_eq!(
+        Err(ConfigurationError::InvalidPatienceFactor(0.5)),
+        recommended
+            .clone()
+            .set_patience(Some(Patience::new(0.5, Duration::from_secs(60))))
+            .validate(&mainnet)
+    );
+
+    // Warnings.
+    let block_time = mainnet.average_block_time().unwrap();
+    assert_eq!(
+        Ok(vec![
+            ConfigurationWarning::BlockTimeMismatch {
+                configured: block_time * 3,
+                recommended: block_time,
+            },
+            ConfigurationWarning::MiningTimeBelowBlockTime {
+                transaction_mining_time: block_time * 2,
+                block_time: block_time * 3,
+            }
+        ]),
+        recommended
+            .clone()
+            .set_block_time(block_time * 3)
+            .set_transaction_mining_time(block_time * 2)
+            .validate(&mainnet)
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_legacy_provider_gas_oracle() {
+    utilities::setup_tracing();
+
+    let multipliers = GasPriceMultipliers {
+        low: 0.5,
+        normal: 1.,
+        high: 2.,
+        asap: 3.,
+    };
+    for (priority, gas_price) in [
+        (Priority::Low, 500),
+        (Priority::Normal, 1000),
+        (Priority::High, 2000),
+        (Priority::ASAP, 3000),
+    ] {
+        let mut middleware = mock_middleware();
+        middleware.get_gas_price = Some(1000);
+        let gas_oracle = LegacyProviderGasOracle::new(middleware).set_multipliers(multipliers);
+        let result = gas_oracle.get_info(priority).await;
+        assert_ok!(result);
+        let gas_info = LegacyGasInfo::try_from(result.unwrap().gas_info).unwrap();
+        assert_eq!(Wei::from(gas_price), gas_info.gas_price);
+        assert_eq!(1, middleware_counters().get_gas_price_n);
+    }
+
+    // Errors.
+    {
+        let gas_oracle = LegacyProviderGasOracle::new(mock_middleware());
+        let result = gas_oracle.get_info(Priority::Normal).await;
+        assert_err!(result, MockMiddlewareError::GetGasPrice);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_legacy_gas_price_multipliers() {
+    utilities::setup_tracing();
+
+    for (priority, gas_price) in [
+        (Priority::Low, 1000),
+        (Priority::Normal, 1000),
+        (Priority::High, 1250),
+        (Priority::ASAP, 1500),
+        (Priority::Custom(0), 1000),
+        (Priority::Custom(25), 1000),
+        (Priority::Custom(70), 1375),
+    ] {
+        let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.get_gas_price = Some(1000);
+        gas_oracle.gas_oracle_info_output = None;
+        db.get_state_output = Some(None);
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+
+        let result = Manager::new(
+            middleware,
+            gas_oracle,
+            db,
+            Chain::legacy(1337),
+            setup_configuration(),
+        )
+        .await;
+        assert_ok!(result);
+        let (manager, _) = result.unwrap();
+        let transaction = Transaction {
+            from: HASH1.parse().unwrap(),
+            to: HASH2.parse().unwrap(),
+            value: Value::Number(Wei::from(5)),
+            call_data: None,
+            metadata: Metadata::new(),
+        };
+        let result = manager
+            .send(
+                transaction,
+                SendOptions::new()
+                    .set_confirmations(0)
+                    .set_priority(priority),
+            )
+            .await;
+        assert_ok!(result);
+        assert_eq!(1, middleware_counters().get_gas_price_n);
+        let signed_transaction = middleware_counters().signed_transaction;
+        assert_eq!(
+            Some(U256::from(gas_price)),
+            signed_transaction.unwrap().gas_price()
+        );
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_events() {
+    utilities::setup_tracing();
+
+    // Submitted and confirmed (with the cost annotated in USD).
+    {
+        let event_listener = MockEventListener::new();
+        let configuration = setup_configuration()
+            .add_event_listener(Arc::new(event_listener.clone()))
+            .set_price_feed(Some(Arc::new(FixedPriceFeed(2000.))));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |a, b, c| (a, b, c)).await;
+        assert_ok!(result);
+        let receipt = result.unwrap();
+
+        let events = event_listener.events();
+        assert_eq!(2, events.len());
+        assert!(
+            matches!(&events[0], Event::Submitted { hash, .. } if *hash == receipt.transaction_hash)
+        );
+        assert!(
+            matches!(&events[1], Event::Confirmed { hash, .. } if *hash == receipt.transaction_hash)
+        );
+        assert!(matches!(
+            &events[1],
+            Event::Confirmed { cost: Some(cost), cost_usd: Some(cost_usd), .. }
+                if *cost == U256::from(21_000_000_000_000u64) && (cost_usd - 0.042).abs() < 1e-9
+        ));
+    }
+
+    // Stalled (once), then confirmed.
+    {
+        let event_listener = MockEventListener::new();
+        let configuration = setup_configuration()
+            .set_stall_threshold(Some(Duration::ZERO))
+            .add_event_listener(Arc::new(event_listener.clone()));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.get_transaction_receipt = vec![false, false, true];
+                (middleware, b, c)
+            })
+            .await;
+        assert_ok!(result);
+
+        let events = event_listener.events();
+        let stalled: Vec<_> = events
+            .iter()
+            .filter(|event| matches!(event, Event::Stalled { .. }))
+            .collect();
+        assert_eq!(1, stalled.len());
+        assert!(matches!(
+            stalled[0],
+            Event::Stalled { in_flight, .. } if *in_flight == Duration::from_secs(1)
+        ));
+        assert!(matches!(events.last(), Some(Event::Confirmed { .. })));
+    }
+
+    // Dropped from the chain by a reorg, then resent and confirmed.
+    {
+        let event_listener = MockEventListener::new();
+        let configuration =
+            setup_configuration().add_event_listener(Arc::new(event_listener.clone()));
+        let result =
+            run_send_transaction_with_configuration(2, configuration, |mut middleware, b, c| {
+                middleware.get_transaction_receipt = vec![true, false, true];
+                middleware.get_block_number = vec![1, 3];
+                (middleware, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        assert_eq!(2, middleware_counters().send_raw_transaction_n);
+
+        let events = event_listener.events();
+        let reorged: Vec<_> = events
+            .iter()
+            .filter(|event| matches!(event, Event::Reorged { .. }))
+            .collect();
+        assert_eq!(1, reorged.len());
+        assert!(matches!(
+            reorged[0],
+            Event::Reorged { hash, .. } if *hash == result.unwrap().transaction_hash
+        ));
+        assert!(matches!(events.last(), Some(Event::Confirmed { .. })));
+    }
+
+    // Failed.
+    {
+        let event_listener = MockEventListener::new();
+        let configuration =
+            setup_configuration().add_event_listener(Arc::new(event_listener.clone()));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.send_transaction = None;
+                (middleware, b, c)
+            })
+            .await;
+        let expected_err: MockManagerError =
+            eth_tx_manager::Error::Middleware(MockMiddlewareError::SendTransaction);
+        assert_err!(result, expected_err);
+
+        let events = event_listener.events();
+        assert_eq!(1, events.len());
+        assert!(
+            matches!(&events[0], Event::Failed { error, .. } if *error == expected_err.to_string())
+        );
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_if() {
+    utilities::setup_tracing();
+
+    // The precondition holds.
+    {
+        let result = run_send_transaction_if(|mut middleware| {
+            middleware.call = vec![true];
+            middleware
+        })
+        .await;
+        assert_ok!(result);
+        assert!(matches!(result.unwrap(), SendOutcome::Confirmed(_)));
+        assert_eq!(1, middleware_counters().call_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    }
+
+    // The precondition does not hold, so the transaction is never sent.
+    {
+        let result = run_send_transaction_if(|mut middleware| {
+            middleware.call = vec![false];
+            middleware
+        })
+        .await;
+        assert_ok!(result);
+        assert_eq!(SendOutcome::Skipped(None), result.unwrap());
+        assert_eq!(1, middleware_counters().call_n);
+        assert_eq!(0, middleware_counters().send_raw_transaction_n);
+    }
+
+    // The precondition stops holding after the transaction was sent, so the
+    // transaction is cancelled.
+    {
+        let result = run_send_transaction_if(|mut middleware| {
+            middleware.call = vec![true, false];
+            middleware.get_transaction_receipt = vec![false, false, true];
+            middleware
+        })
+        .await;
+        assert_ok!(result);
+        let outcome = result.unwrap();
+        assert!(matches!(outcome, SendOutcome::Skipped(Some(_))));
+        assert_eq!(2, middleware_counters().call_n);
+        assert_eq!(2, middleware_counters().send_raw_transaction_n);
+
+        // The cancellation is an empty transfer to the sender itself.
+        let cancellation = middleware_counters().signed_transaction.unwrap();
+        let from: ethers::types::Address = HASH1.parse().unwrap();
+        assert_eq!(Some(&from.into()), cancellation.to());
+        assert_eq!(Some(&U256::zero()), cancellation.value());
+        assert_eq!(None, cancellation.data());
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_replace_pending() {
+    utilities::setup_tracing();
+
+    let replacement = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Nothing,
+        call_data: Some(Bytes::from(vec![0x12, 0x34])),
+        metadata: Metadata::new(),
+    };
+
+    // The replacement is confirmed, with bumped fees.
+    {
+        let result = run_replace_pending(replacement.clone(), |mut middleware| {
+            middleware.get_transaction = Some(Some((2_000_000_000, 200_000)));
+            middleware.get_transaction_receipt = vec![true, false];
+            middleware
+        })
+        .await;
+        assert_ok!(result);
+        let receipt = result.unwrap();
+        assert_ne!(
+            TRANSACTION_HASH1.parse().ok(),
+            Some(receipt.transaction_hash)
+        );
+        assert_eq!(1, middleware_counters().get_transaction_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+
+        let signed_transaction = middleware_counters().signed_transaction.unwrap();
+        assert_eq!(Some(1u64.into()), signed_transaction.nonce().copied());
+        assert_eq!(replacement.call_data.as_ref(), signed_transaction.data());
+        let signed_transaction = signed_transaction.as_eip1559_ref().unwrap();
+        assert_eq!(
+            Some(U256::from(2_200_000_000u64)),
+            signed_transaction.max_fee_per_gas
+        );
+        assert_eq!(
+            Some(U256::from(220_000)),
+            signed_transaction.max_priority_fee_per_gas
+        );
+    }
+
+    // The replaced transaction is not in the transaction pool anymore, so the
+    // fees are not bumped; and it is the one that gets confirmed.
+    {
+        let result = run_replace_pending(replacement.clone(), |mut middleware| {
+            middleware.get_transaction = Some(None);
+            middleware.get_transaction_receipt = vec![false, true];
+            middleware
+        })
+        .await;
+        assert_ok!(result);
+        let receipt = result.unwrap();
+        assert_eq!(
+            TRANSACTION_HASH1.parse().ok(),
+            Some(receipt.transaction_hash)
+        );
+
+        let signed_transaction = middleware_counters().signed_transaction.unwrap();
+        let signed_transaction = signed_transaction.as_eip1559_ref().unwrap();
+        assert_eq!(
+            Some(U256::from(1_000_000_000)),
+            signed_transaction.max_fee_per_gas
+        );
+    }
+
+    // The replacement must come from the same sender.
+    {
+        let result = run_replace_pending(
+            Transaction {
+                from: HASH2.parse().unwrap(),
+                ..replacement.clone()
+            },
+            |middleware| middleware,
+        )
+        .await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::ReplacementSenderMismatch {
+            pending: HASH1.parse().unwrap(),
+            replacement: HASH2.parse().unwrap(),
+        };
+        assert_err!(result, expected_err);
+    }
+
+    // There is no pending transaction to replace.
+    {
+        let (middleware, gas_oracle, mut db) = setup_dependencies();
+        db.get_state_output = Some(None);
+        let result = Manager::replace_pending(
+            middleware,
+            gas_oracle,
+            db,
+            CHAIN,
+            setup_configuration(),
+            replacement,
+        )
+        .await
+        .map(|(_, receipt)| receipt);
+        let expected_err: MockManagerError = eth_tx_manager::Error::NoPendingTransaction;
+        assert_err!(result, expected_err);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_chain_id() {
+    utilities::setup_tracing();
+
+    // The provider reports a different chain id.
+    {
+        let (mut middleware, gas_oracle, mut db) = setup_dependencies();
+        middleware.get_chainid = Some(5);
+        db.get_state_output = Some(None);
+        let result = Manager::new(middleware, gas_oracle, db, CHAIN, setup_configuration()).await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::ChainIdMismatch {
+            configured: CHAIN.id,
+            reported: U256::from(5),
+        };
+        assert_err!(result, expected_err);
+        assert_eq!(0, database_counters().get_state_n);
+    }
+
+    // Adopting the provider's chain id.
+    {
+        let configuration = setup_configuration().set_adopt_provider_chain_id(true);
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.get_chainid = Some(5);
+                (middleware, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        let signed_transaction = middleware_counters().signed_transaction.unwrap();
+        assert_eq!(Some(U64::from(5)), signed_transaction.chain_id());
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_gas_oracle_chain() {
+    utilities::setup_tracing();
+
+    // The gas oracle does not support the chain.
+    {
+        let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+        gas_oracle.supported_chains = Some(vec![CHAIN.id + 1]);
+        db.get_state_output = Some(None);
+        let result = Manager::new(middleware, gas_oracle, db, CHAIN, setup_configuration()).await;
+        let expected_err: MockManagerError =
+            eth_tx_manager::Error::UnsupportedGasOracleChain(CHAIN.id);
+        assert_err!(result, expected_err);
+        assert_eq!(0, database_counters().get_state_n);
+    }
+
+    // The gas oracle supports the chain adopted from the provider.
+    {
+        let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+        middleware.get_chainid = Some(5);
+        gas_oracle.supported_chains = Some(vec![5]);
+        db.get_state_output = Some(None);
+        let configuration = setup_configuration().set_adopt_provider_chain_id(true);
+        let result = Manager::new(middleware, gas_oracle, db, CHAIN, configuration).await;
+        assert_ok!(result);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_gas_oracle_block_time() {
+    utilities::setup_tracing();
+
+    // The block time estimated by the gas oracle only applies to the send.
+    let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+    let middleware = setup_middleware(middleware);
+    gas_oracle.gas_oracle_info_output = Some(GasOracleInfo {
+        block_time: Some(Duration::from_secs(42)),
+        ..default_gas_oracle_info()
+    });
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let configuration = setup_configuration();
+    let block_time = configuration.block_time;
+    let manager = setup_manager(middleware, gas_oracle, db, configuration).await;
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(0))
+        .await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+    assert_eq!(block_time, manager.configuration().block_time);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_database_stats() {
+    utilities::setup_tracing();
+
+    // The mock time measures one second for every operation.
+    let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+    let middleware = setup_middleware(middleware);
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let event_listener = Arc::new(MockEventListener::new());
+    let configuration = setup_configuration()
+        .set_slow_database_threshold(Some(Duration::from_millis(500)))
+        .add_event_listener(event_listener.clone());
+    let manager = setup_manager(middleware, gas_oracle, db, configuration).await;
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(0))
+        .await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+
+    let stats = manager.database_stats();
+    assert_eq!(1, stats.get.count);
+    assert_eq!(2, stats.set.count);
+    assert_eq!(1, stats.clear.count);
+    assert_eq!(Duration::from_secs(4), stats.total_time());
+    assert_eq!(2, manager.last_timings().unwrap().database.set.count);
+
+    let slow_operations: Vec<_> = event_listener
+        .events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::SlowDatabase { operation, .. } => Some(operation),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            DatabaseOperation::Get,
+            DatabaseOperation::Set,
+            DatabaseOperation::Set,
+            DatabaseOperation::Clear
+        ],
+        slow_operations
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_shutdown() {
+    utilities::setup_tracing();
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+
+    // Idle: only closes the database.
+    {
+        let (middleware, gas_oracle, db) = setup_dependencies();
+        let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+        assert_ok!(manager.shutdown().await);
+        assert_eq!(0, database_counters().set_state_n);
+        assert_eq!(1, database_counters().close_n);
+    }
+
+    // Driving a transaction: flushes its state first.
+    {
+        let (middleware, gas_oracle, mut db) = setup_dependencies();
+        let middleware = setup_middleware(middleware);
+        db.set_state_output = Some(());
+        let mut manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+        assert_ok!(manager.drive(transaction.clone(), SendOptions::new()).await);
+        assert_ok!(manager.shutdown().await);
+        assert_eq!(1, database_counters().set_state_n);
+        assert_eq!(1, database_counters().close_n);
+    }
+
+    // The flush fails.
+    {
+        let (middleware, gas_oracle, db) = setup_dependencies();
+        let middleware = setup_middleware(middleware);
+        let mut manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+        assert_ok!(manager.drive(transaction, SendOptions::new()).await);
+        let result = manager.shutdown().await;
+        let expected_err: MockManagerError =
+            eth_tx_manager::Error::Database(DatabaseStateError::Set);
+        assert_err!(result, expected_err);
+        assert_eq!(0, database_counters().close_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_ens() {
+    utilities::setup_tracing();
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: NameOrAddress::Name("recipient.eth".to_string()),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+
+    // The name is resolved before sending.
+    {
+        let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+        let mut middleware = setup_middleware(middleware);
+        middleware
+            .resolve_name
+            .insert("recipient.eth".to_string(), HASH2.parse().unwrap());
+        gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+        let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+        let result = manager
+            .send(transaction.clone(), SendOptions::new().set_confirmations(0))
+            .await;
+        assert_ok!(result);
+        let signed_transaction = middleware_counters().signed_transaction;
+        let expected_to = NameOrAddress::Address(HASH2.parse().unwrap());
+        assert_eq!(Some(&expected_to), signed_transaction.unwrap().to());
+    }
+
+    // The name cannot be resolved.
+    {
+        let (middleware, gas_oracle, db) = setup_dependencies();
+        let middleware = setup_middleware(middleware);
+        let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+        let result = manager
+            .send(transaction, SendOptions::new().set_confirmations(0))
+            .await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::UnresolvableName {
+            name: "recipient.eth".to_string(),
+            error: MockMiddlewareError::ResolveName,
+        };
+        assert_err!(result, expected_err);
+        assert_eq!(0, database_counters().set_state_n);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, EthEvent)]
+struct Claimed {
+    #[ethevent(indexed)]
+    epoch: U256,
+    claim: H256,
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_and_wait_for_event() {
+    utilities::setup_tracing();
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Nothing,
+        call_data: Some(Bytes::from(vec![0x12, 0x34])),
+        metadata: Metadata::new(),
+    };
+    let claimed = |epoch: u64| Log {
+        topics: vec![Claimed::signature(), H256::from_low_u64_be(epoch)],
+        data: abi::encode(&[Token::FixedBytes(vec![0xcc; 32])]).into(),
+        ..Default::default()
+    };
+    let run = |status: u32, logs: Vec<Log>| {
+        let transaction = transaction.clone();
+        async move {
+            let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+            let mut middleware = setup_middleware(middleware);
+            middleware.receipt_status = Some(status);
+            middleware.receipt_logs = logs;
+            gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+            db.set_state_output = Some(());
+            db.clear_state_output = Some(());
+            let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+            manager
+                .send_and_wait_for_event(
+                    transaction,
+                    SendOptions::new().set_confirmations(0),
+                    |event: &Claimed| event.epoch == U256::from(2),
+                )
+                .await
+                .map(|(_, event)| event)
+        }
+    };
+
+    // The event with the expected parameters is decoded.
+    let result = run(1, vec![claimed(1), claimed(2)]).await;
+    assert_ok!(result);
+    let expected_event = Claimed {
+        epoch: U256::from(2),
+        claim: H256::repeat_byte(0xcc),
+    };
+    assert_eq!(expected_event, result.unwrap());
+
+    // The expected event is missing.
+    let result = run(1, vec![claimed(1)]).await;
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::EventMissing { event, .. }) if event == "Claimed"
+    ));
+
+    // The transaction failed.
+    let result = run(0, vec![claimed(2)]).await;
+    assert!(matches!(result, Err(eth_tx_manager::Error::Reverted(None))));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_and_verify() {
+    utilities::setup_tracing();
+    let run = |balance_moved: bool| async move {
+        let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+        let middleware = setup_middleware(middleware);
+        gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+        let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+        let transaction = Transaction {
+            from: HASH1.parse().unwrap(),
+            to: HASH2.parse().unwrap(),
+            value: Value::Number(Wei::from(5)),
+            call_data: None,
+            metadata: Metadata::new(),
+        };
+        manager
+            .send_and_verify(
+                transaction,
+                SendOptions::new().set_confirmations(0),
+                |receipt| async move {
+                    assert!(receipt.block_number.is_some());
+                    match balance_moved {
+                        true => Ok(()),
+                        false => Err("the balance did not move"),
+                    }
+                },
+            )
+            .await
+            .map(|(_, receipt)| receipt)
+    };
+
+    // The post-condition holds.
+    let result = run(true).await;
+    assert_ok!(result);
+
+    // The post-condition fails, after the transaction was confirmed.
+    let result = run(false).await;
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::PostConditionFailed { receipt, reason })
+            if receipt.block_number.is_some() && reason == "the balance did not move"
+    ));
+    assert_eq!(1, database_counters().clear_state_n);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_nonce_management() {
+    utilities::setup_tracing();
+
+    // The provider assigns the nonce.
+    {
+        let configuration = setup_configuration().set_nonce_management(NonceManagement::Provider);
+        let result = run_send_transaction_with_configuration(0, configuration, |mut a, b, c| {
+            a.fill_transaction = Some(7);
+            (a, b, c)
+        })
+        .await;
+        assert_ok!(result);
+        let signed_transaction = middleware_counters().signed_transaction;
+        assert_eq!(Some(&U256::from(7)), signed_transaction.unwrap().nonce());
+    }
+
+    // The provider does not assign nonces.
+    {
+        let configuration = setup_configuration().set_nonce_management(NonceManagement::Provider);
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |a, b, c| (a, b, c)).await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::NonceNotAssigned;
+        assert_err!(result, expected_err);
+        assert_eq!(0, database_counters().set_state_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_signer_mismatch() {
+    utilities::setup_tracing();
+
+    // The signer matches the sender.
+    {
+        let result = run_send_transaction(0, |mut middleware, b, c| {
+            middleware.default_sender = Some(HASH1.parse().unwrap());
+            (middleware, b, c)
+        })
+        .await;
+        assert_ok!(result);
+    }
+
+    // The signer does not match the sender.
+    {
+        let result = run_send_transaction(0, |mut middleware, b, c| {
+            middleware.default_sender = Some(HASH2.parse().unwrap());
+            (middleware, b, c)
+        })
+        .await;
+        let expected_err: MockManagerError = eth_tx_manager::Error::SignerMismatch {
+            signer: HASH2.parse().unwrap(),
+            from: HASH1.parse().unwrap(),
+        };
+        assert_err!(result, expected_err);
+        assert_eq!(0, middleware_counters().get_transaction_count_n);
+        assert_eq!(0, middleware_counters().send_raw_transaction_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_hash_strategy() {
+    utilities::setup_tracing();
+
+    // The node returns the standard hashes.
+    {
+        let configuration = setup_configuration().set_hash_strategy(Arc::new(NodeHash));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |a, b, c| (a, b, c)).await;
+        assert_ok!(result);
+    }
+
+    // The node returns non-standard hashes, which the permissive strategy trusts.
+    {
+        let configuration = setup_configuration().set_hash_strategy(Arc::new(NodeHash));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.wrap_transaction_hashes = true;
+                middleware.get_transaction_receipt = vec![true, false];
+                (middleware, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    }
+
+    // Mismatching hashes with the default strategy and policy.
+    {
+        let result = run_send_transaction(0, |mut middleware, b, c| {
+            middleware.wrap_transaction_hashes = true;
+            (middleware, b, c)
+        })
+        .await
+        .map_err(eth_tx_manager::Error::into_inner);
+        assert!(
+            matches!(result, Err(eth_tx_manager::Error::HashMismatch { .. })),
+            "expected a hash mismatch error, got {:?}",
+            result
+        );
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+        assert_eq!(0, middleware_counters().get_transaction_receipt_n);
+    }
+
+    // Mismatching hashes with the adoption policy.
+    {
+        let configuration =
+            setup_configuration().set_hash_mismatch_policy(HashMismatchPolicy::Adopt);
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.wrap_transaction_hashes = true;
+                middleware.get_transaction_receipt = vec![true, false];
+                (middleware, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        // Stored the derived hash, and then the one returned by the node.
+        assert_eq!(2, database_counters().set_state_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_pool_acceptance() {
+    utilities::setup_tracing();
+
+    // The transaction is visible in the pool.
+    {
+        let configuration = setup_configuration().set_pool_acceptance_window(Some(Duration::ZERO));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.get_transaction = Some(Some((1, 1)));
+                (middleware, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        assert_eq!(1, middleware_counters().get_transaction_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    }
+
+    // The transaction is not visible in the pool, even after being resubmitted
+    // through a fallback endpoint (the manager still waits for it to be mined).
+    // The journal identifies the endpoint by its name.
+    {
+        let path = std::env::temp_dir().join("tx_manager_mock_test_pool_acceptance.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let journal = Arc::new(Journal::open(&path, "mock").unwrap());
+        let mut fallback = MockMiddleware::new();
+        fallback.send_transaction = Some(());
+        let configuration = setup_configuration()
+            .set_pool_acceptance_window(Some(Duration::from_secs(2)))
+            .set_journal(Some(journal))
+            .add_fallback_broadcaster("fallback", Arc::new(fallback.clone()));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.get_transaction = Some(None);
+                (middleware, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        assert_eq!(6, middleware_counters().get_transaction_n);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+        assert_eq!(1, fallback.counters().send_raw_transaction_n);
+        let hash = result.unwrap().transaction_hash;
+        let entries = Journal::verify(&path).unwrap();
+        assert!(matches!(
+            &entries[1].transition,
+            Transition::Resubmitted { hash: h, endpoint } if *h == hash && endpoint == "fallback"
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // Errors while checking the pool.
+    {
+        let configuration = setup_configuration().set_pool_acceptance_window(Some(Duration::ZERO));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |a, b, c| (a, b, c)).await;
+        let expected_err: MockManagerError =
+            eth_tx_manager::Error::Middleware(MockMiddlewareError::GetTransaction);
+        assert_err!(result, expected_err);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_stale_provider() {
+    utilities::setup_tracing();
+
+    #[derive(Debug, Default)]
+    struct CountingFailover(AtomicUsize);
+
+    impl Failover for CountingFailover {
+        fn fail_over(&self) -> bool {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            true
+        }
+    }
+
+    // The provider is stuck at the same block for longer than two block times
+    // (each poll takes a second): without a failover, the send fails.
+    {
+        let event_listener = MockEventListener::new();
+        let configuration = setup_configuration()
+            .set_block_time(Duration::from_millis(400))
+            .set_stale_provider_blocks(Some(2))
+            .add_event_listener(Arc::new(event_listener.clone()));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.get_transaction_receipt = vec![false; 4];
+                middleware.get_block_number = vec![5, 6, 6];
+                (middleware, b, c)
+            })
+            .await
+            .map_err(eth_tx_manager::Error::into_inner);
+        assert!(matches!(
+            result,
+            Err(eth_tx_manager::Error::ProviderStale { block_number, .. })
+                if block_number == U64::from(6)
+        ));
+        assert_eq!(3, middleware_counters().get_block_number_n);
+        assert_eq!(
+            1,
+            event_listener
+                .events()
+                .iter()
+                .filter(|event| matches!(event, Event::ProviderStale { .. }))
+                .count()
+        );
+    }
+
+    // With a failover, the manager keeps confirming the transaction.
+    {
+        let failover = Arc::new(CountingFailover::default());
+        let configuration = setup_configuration()
+            .set_block_time(Duration::from_millis(400))
+            .set_stale_provider_blocks(Some(2))
+            .set_failover(Some(failover.clone()));
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.get_transaction_receipt = vec![false, false, true];
+                middleware.get_block_number = vec![5, 5, 5, 5];
+                (middleware, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        assert_eq!(2, failover.0.load(Ordering::SeqCst));
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_force_legacy() {
+    utilities::setup_tracing();
+    let configuration = setup_configuration().set_force_legacy(true);
+
+    // The gas price is the latest base fee plus the max priority fee.
+    {
+        let result =
+            run_send_transaction_with_configuration(0, configuration.clone(), |mut a, b, c| {
+                a.get_block = vec![500_000_000];
+                (a, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        let signed_transaction = middleware_counters().signed_transaction.unwrap();
+        assert!(matches!(signed_transaction, TypedTransaction::Legacy(_)));
+        assert_eq!(
+            Some(U256::from(500_100_000)),
+            signed_transaction.gas_price()
+        );
+    }
+
+    // The gas price is capped at the max fee.
+    {
+        let result = run_send_transaction_with_configuration(0, configuration, |mut a, b, c| {
+            a.get_block = vec![2_000_000_000];
+            (a, b, c)
+        })
+        .await;
+        assert_ok!(result);
+        let signed_transaction = middleware_counters().signed_transaction.unwrap();
+        assert!(matches!(signed_transaction, TypedTransaction::Legacy(_)));
+        assert_eq!(
+            Some(U256::from(1_000_000_000)),
+            signed_transaction.gas_price()
+        );
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_gas_oracle_staleness() {
+    utilities::setup_tracing();
+    let configuration =
+        setup_configuration().set_gas_oracle_staleness_window(Some(Duration::from_secs(60)));
+
+    // Fresh estimates are used.
+    {
+        let result =
+            run_send_transaction_with_configuration(0, configuration.clone(), |a, mut b, c| {
+                b.gas_oracle_info_output = Some(GasOracleInfo {
+                    timestamp: Some(SystemTime::now() - Duration::from_secs(30)),
+                    ..default_gas_oracle_info()
+                });
+                (a, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        assert_eq!(1, gas_oracle_counters().gas_info_n);
+        assert_eq!(0, middleware_counters().fee_history_n);
+    }
+
+    // Stale estimates are re-queried, then replaced by the provider's.
+    {
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut a, mut b, c| {
+                a.fee_history = Some((300, vec![50]));
+                b.gas_oracle_info_output = Some(GasOracleInfo {
+                    timestamp: Some(SystemTime::now() - Duration::from_secs(600)),
+                    ..default_gas_oracle_info()
+                });
+                (a, b, c)
+            })
+            .await;
+        assert_ok!(result);
+        assert_eq!(3, gas_oracle_counters().gas_info_n);
+        assert_eq!(1, middleware_counters().fee_history_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_journal() {
+    utilities::setup_tracing();
+
+    let path = std::env::temp_dir().join("tx_manager_mock_test_journal.jsonl");
+    let _ = std::fs::remove_file(&path);
+    let journal = Arc::new(Journal::open(&path, "mock").unwrap());
+
+    let configuration = setup_configuration().set_journal(Some(journal));
+    let result =
+        run_send_transaction_with_configuration(0, configuration, |a, b, c| (a, b, c)).await;
+    assert_ok!(result);
+
+    let entries = Journal::verify(&path).unwrap();
+    assert_eq!(2, entries.len());
+    let hash = result.unwrap().transaction_hash;
+    assert!(matches!(
+        &entries[0].transition,
+        Transition::Submitted { hash: h, endpoint, raw_transaction: None, .. }
+            if *h == hash && endpoint == "mock"
+    ));
+    assert!(matches!(
+        &entries[1].transition,
+        Transition::Confirmed { hash: h, .. } if *h == hash
+    ));
+    assert_eq!(
+        HASH1.parse::<ethers::types::Address>().unwrap(),
+        entries[0].sender
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_send_transaction_raw_transactions() {
+    utilities::setup_tracing();
+
+    let path = std::env::temp_dir().join("tx_manager_mock_test_raw_transactions.jsonl");
+    let _ = std::fs::remove_file(&path);
+    let journal = Arc::new(Journal::open(&path, "mock").unwrap());
+    let event_listener = MockEventListener::new();
+
+    let configuration = setup_configuration()
+        .set_journal(Some(journal))
+        .add_event_listener(Arc::new(event_listener.clone()))
+        .set_include_raw_transactions(true);
+    let result =
+        run_send_transaction_with_configuration(0, configuration, |a, b, c| (a, b, c)).await;
+    assert_ok!(result);
+    let hash = result.unwrap().transaction_hash;
+
+    // The event and the journal archive the signed transaction.
+    let raw_transaction = match &event_listener.events()[0] {
+        Event::Submitted {
+            raw_transaction: Some(raw_transaction),
+            ..
+        } => raw_transaction.clone(),
+        event => panic!("unexpected event {:?}", event),
+    };
+    assert_eq!(hash, H256(ethers::utils::keccak256(&raw_transaction)));
+    let entries = Journal::verify(&path).unwrap();
+    assert!(matches!(
+        &entries[0].transition,
+        Transition::Submitted { raw_transaction: Some(r), .. } if *r == raw_transaction
+    ));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_pause() {
+    utilities::setup_tracing();
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::default(),
+    };
+
+    // Pausing the manager holds the submission until it is resumed.
+    {
+        let pause_switch = PauseSwitch::new();
+        pause_switch.pause();
+        let time = ResumingTime::new(pause_switch.clone(), 3);
+        let configuration = Configuration::new(time.clone())
+            .set_transaction_mining_time(Duration::ZERO)
+            .set_block_time(Duration::ZERO)
+            .set_pause_switch(pause_switch.clone());
+
+        let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+        let middleware = setup_middleware(middleware);
+        gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+        db.get_state_output = Some(None);
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+        let result = Manager::new(middleware, gas_oracle, db, CHAIN, configuration).await;
+        assert_ok!(result);
+        let (manager, _) = result.unwrap();
+        assert!(manager.pause_switch().is_paused());
+
+        let result = manager
+            .send(transaction.clone(), SendOptions::new().set_confirmations(0))
+            .await;
+        assert_ok!(result);
+        assert!(!pause_switch.is_paused());
+        assert!(time.sleeps() >= 3);
+        assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    }
+
+    // A pending transaction mined while the manager is paused is confirmed
+    // without being resubmitted.
+    {
+        let pause_switch = PauseSwitch::new();
+        pause_switch.pause();
+        let time = ResumingTime::new(pause_switch.clone(), usize::MAX);
+        let configuration = Configuration::new(time.clone())
+            .set_transaction_mining_time(Duration::ZERO)
+            .set_block_time(Duration::ZERO)
+            .set_pause_switch(pause_switch.clone());
+
+        let (mut middleware, gas_oracle, mut db) = setup_dependencies();
+        middleware.get_block_number = vec![0, 1];
+        middleware.get_transaction_receipt = vec![false, false, true, true, true];
+        middleware.get_transaction_count = Some(());
+        db.get_state_output = Some(Some(PersistentState {
+            tx_data: StaticTxData {
+                nonce: 1u64.into(),
+                transaction: transaction.clone(),
+                priority: Priority::Normal,
+                confirmations: 1,
+                precondition: None,
+            },
+            submitted_txs: SubmittedTxs {
+                txs_hashes: vec![TRANSACTION_HASH1.parse().unwrap()],
+            },
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs::new(),
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
+        }));
+        db.clear_state_output = Some(());
+        let result = Manager::new(middleware, gas_oracle, db, CHAIN, configuration).await;
+        assert_ok!(result);
+        let (_, resolution) = result.unwrap();
+        assert!(matches!(resolution, PendingResolution::ConfirmedNow(_)));
+        assert!(pause_switch.is_paused());
+        assert_eq!(0, middleware_counters().send_raw_transaction_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_get_submitted_hashes() {
+    utilities::setup_tracing();
+    let hash1: H256 = TRANSACTION_HASH1.parse().unwrap();
+    let hash2 = H256::from_low_u64_be(2);
+
+    // With a pending transaction.
+    {
+        let (middleware, gas_oracle, mut db) = setup_dependencies();
+        db.get_state_output = Some(Some(PersistentState {
+            tx_data: StaticTxData {
+                nonce: 1u64.into(),
+                transaction: Transaction {
+                    from: HASH1.parse().unwrap(),
+                    to: HASH2.parse().unwrap(),
+                    value: Value::Number(Wei::from(5)),
+                    call_data: None,
+                    metadata: Metadata::default(),
+                },
+                priority: Priority::Normal,
+                confirmations: 1,
+                precondition: None,
+            },
+            submitted_txs: SubmittedTxs {
+                txs_hashes: vec![hash1],
+            },
+            cancellation_txs: None,
+            replaced_txs: SubmittedTxs {
+                txs_hashes: vec![hash2],
+            },
+            broadcast_statuses: BTreeMap::new(),
+            attempts: Vec::new(),
+            resubmissions: 0,
+            handoff: None,
+            first_submitted_at: None,
+        }));
+        db.clear_state_output = Some(());
+        let manager =
+            Manager::force_new(middleware, gas_oracle, db, CHAIN, setup_configuration()).await;
+        assert_ok!(manager);
+        let result = manager.unwrap().get_submitted_hashes().await;
+        assert_ok!(result);
+        assert_eq!(vec![hash1, hash2], result.unwrap());
+    }
+
+    // Without a pending transaction.
+    {
+        let (middleware, gas_oracle, db) = setup_dependencies();
+        let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+        let result = manager.get_submitted_hashes().await;
+        assert_ok!(result);
+        assert!(result.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_speed_up() {
+    utilities::setup_tracing();
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::default(),
+    };
+    let speed_up_switch = SpeedUpSwitch::new();
+    let time = SpeedingUpTime::new(speed_up_switch.clone(), 2., 2);
+    let configuration = Configuration::new(time.clone())
+        .set_transaction_mining_time(Duration::from_secs(3600))
+        .set_block_time(Duration::ZERO)
+        .set_speed_up_switch(speed_up_switch.clone());
+
+    let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+    let mut middleware = setup_middleware(middleware);
+    middleware.get_transaction_receipt = vec![false, false, true, false];
+    middleware.get_transaction = Some(Some((1_500_000_000, 150_000)));
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = Some(None);
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let result = Manager::new(middleware, gas_oracle, db, CHAIN, configuration).await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+
+    // Resubmitting right away (without waiting for the mining time), at twice
+    // the fees of the last submission.
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(0))
+        .await;
+    assert_ok!(result);
+    assert!(!speed_up_switch.is_requested());
+    assert_eq!(2, middleware_counters().send_raw_transaction_n);
+    assert_eq!(1, middleware_counters().get_transaction_n);
+    let signed_transaction = middleware_counters().signed_transaction.unwrap();
+    let signed_transaction = signed_transaction.as_eip1559_ref().unwrap();
+    assert_eq!(
+        Some(U256::from(3_000_000_000u64)),
+        signed_transaction.max_fee_per_gas
+    );
+    assert_eq!(
+        Some(U256::from(300_000)),
+        signed_transaction.max_priority_fee_per_gas
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_confirmation_updates() {
+    utilities::setup_tracing();
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::default(),
+    };
+    let (middleware, mut gas_oracle, mut db) = setup_dependencies();
+    let mut middleware = setup_middleware(middleware);
+    middleware.get_block_number = vec![0, 1, 1, 3];
+    middleware.get_transaction_receipt = vec![true; 4];
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let mut manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+
+    let updates = manager.confirmation_updates();
+    let dropped_updates = manager.confirmation_updates();
+    drop(dropped_updates);
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(3))
+        .await;
+    assert_ok!(result);
+    let (manager, receipt) = result.unwrap();
+    drop(manager);
+
+    // One update per new block on top of the transaction's, until confirmed.
+    let updates: Vec<_> = updates.collect().await;
+    let depths: Vec<_> = updates.iter().map(|update| update.depth).collect();
+    assert_eq!(vec![0, 1, 3], depths);
+    assert!(updates.iter().all(|update| {
+        update.hash == receipt.transaction_hash
+            && update.block_number == U64::zero()
+            && update.confirmations == 3
+    }));
+    assert_eq!(U64::from(3), updates[2].latest_block);
+    assert!(updates[2].is_confirmed());
+    assert!(!updates[1].is_confirmed());
+
+    // The ETAs are refreshed with the observed block times (one second per
+    // block with the mocked time, then half a second).
+    let etas: Vec<_> = updates.iter().map(|update| update.eta).collect();
+    assert_eq!(
+        vec![Duration::ZERO, Duration::from_secs(2), Duration::ZERO],
+        etas
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_pending_tx_watcher() {
+    utilities::setup_tracing();
+
+    let hash = H256::random();
+    let watcher = PendingTxWatcher::new([hash], 2);
+
+    // Watching until the transaction has enough confirmations.
+    {
+        let mut middleware = mock_middleware();
+        middleware.get_transaction_receipt = vec![false, true, true];
+        middleware.get_block_number = vec![1, 2];
+
+        let result = watcher.watch(&middleware, &MockTime).await;
+        assert_ok!(result);
+        assert_eq!(hash, result.unwrap().transaction_hash);
+        assert_eq!(3, middleware_counters().get_transaction_receipt_n);
+        assert_eq!(2, middleware_counters().get_block_number_n);
+        // Read-only: nothing was sent.
+        assert_eq!(0, middleware_counters().send_raw_transaction_n);
+    }
+
+    // Polling once.
+    {
+        let mut middleware = mock_middleware();
+        middleware.get_transaction_receipt = vec![true];
+        middleware.get_block_number = vec![1];
+
+        let status = watcher.poll(&middleware).await.unwrap().unwrap();
+        assert_eq!(U64::from(1), status.latest_block);
+        assert_eq!(1, status.depth);
+        assert!(!status.confirmed);
+    }
+
+    // Giving up after the timeout.
+    {
+        let mut middleware = mock_middleware();
+        middleware.get_transaction_receipt = vec![false];
+
+        let watcher = watcher.clone().set_policy(WatchPolicy {
+            poll_interval: Duration::from_secs(1),
+            timeout: Some(Duration::ZERO),
+        });
+        let result = watcher.watch(&middleware, &MockTime).await;
+        assert!(matches!(result, Err(WatchError::Timeout(_))));
+        assert_eq!(1, middleware_counters().get_transaction_receipt_n);
+    }
+
+    // When the provider fails.
+    {
+        let middleware = mock_middleware();
+        let result = watcher.watch(&middleware, &MockTime).await;
+        assert!(matches!(
+            result,
+            Err(WatchError::Middleware(
+                MockMiddlewareError::GetTransactionReceipt(0)
+            ))
+        ));
+    }
+}
+
+#[test]
+fn test_confirmation_estimator() {
+    let block_time = Duration::from_secs(12);
+    let estimator = ConfirmationEstimator::default();
+    assert_eq!(None, estimator.observed_block_time());
+
+    // Pending: what is left of the mining time, then the confirmations.
+    let eta = estimator.eta(
+        block_time,
+        Duration::from_secs(60),
+        Duration::from_secs(20),
+        None,
+        2,
+    );
+    assert_eq!(Duration::from_secs(64), eta);
+
+    // Pending for longer than the mining time: at least a block.
+    let eta = estimator.eta(
+        block_time,
+        Duration::from_secs(60),
+        Duration::from_secs(90),
+        None,
+        2,
+    );
+    assert_eq!(Duration::from_secs(36), eta);
+
+    // Mined: the missing confirmations.
+    let eta = estimator.eta(
+        block_time,
+        Duration::from_secs(60),
+        Duration::ZERO,
+        Some(1),
+        2,
+    );
+    assert_eq!(Duration::from_secs(12), eta);
+    let eta = estimator.eta(
+        block_time,
+        Duration::from_secs(60),
+        Duration::ZERO,
+        Some(3),
+        2,
+    );
+    assert_eq!(Duration::ZERO, eta);
+
+    // The observed block time replaces the default one.
+    let mut estimator = ConfirmationEstimator::default();
+    estimator.observe_block(U64::from(10), &MockTime);
+    estimator.observe_block(U64::from(12), &MockTime); // one second for two blocks
+    assert_eq!(
+        Some(Duration::from_millis(500)),
+        estimator.observed_block_time()
+    );
+    let eta = estimator.eta(block_time, Duration::ZERO, Duration::ZERO, Some(0), 4);
+    assert_eq!(Duration::from_secs(2), eta);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_dyn_manager() {
+    utilities::setup_tracing();
+
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+
+    // Managers with different gas oracles in the same collection (the mocks
+    // share their global state, so the scripted results account for both).
+    let managers: Vec<Box<dyn DynManager>> = {
+        let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.get_block_number = vec![1, 1];
+        middleware.get_transaction_receipt = vec![true, true];
+        gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+        let manager1 = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+
+        let (mut middleware, _, mut db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.get_block_number = vec![1, 1];
+        middleware.get_transaction_receipt = vec![true, true];
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+        let manager2 = setup_manager(
+            middleware,
+            IncrementingGasOracle::new(),
+            db,
+            setup_configuration(),
+        )
+        .await;
+
+        vec![manager1.boxed(), manager2.boxed()]
+    };
+
+    for manager in managers {
+        let result = manager
+            .send(transaction.clone(), SendOptions::new().set_confirmations(0))
+            .await;
+        assert_ok!(result);
+    }
+
+    // Errors can be downcast to the manager's error.
+    {
+        let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+        middleware = setup_middleware(middleware);
+        middleware.send_transaction = None;
+        gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+        db.set_state_output = Some(());
+        let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+        let result = manager
+            .boxed()
+            .send(transaction, SendOptions::new().set_confirmations(0))
+            .await;
+        assert!(result.is_err());
+        let err = result.err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<MockManagerError>()
+                .map(|err| err.report().is_some()),
+            Some(true)
+        ));
+        let err = err.downcast::<MockManagerError>().unwrap().into_inner();
+        assert!(matches!(
+            err,
+            eth_tx_manager::Error::Middleware(MockMiddlewareError::SendTransaction)
+        ));
+    }
+}
+
+#[derive(Debug)]
+struct FixedPriceFeed(f64);
+
+impl PriceFeed for FixedPriceFeed {
+    fn eth_price_usd(&self) -> Option<f64> {
+        Some(self.0)
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_dyn_dependencies() {
+    utilities::setup_tracing();
+
+    // Choosing the gas oracle and the database at runtime.
+    fn gas_oracle(name: &str) -> Arc<dyn eth_tx_manager::gas_oracle::DynGasOracle> {
+        let mut mock = mock_gas_oracle();
+        mock.gas_oracle_info_output = Some(default_gas_oracle_info());
+        match name {
+            "mock" => Arc::new(mock),
+            "constant" => Arc::new(ConstantGasOracle::new()),
+            _ => unreachable!(),
+        }
+    }
+    fn database() -> Box<dyn DynDatabase> {
+        let mut db = mock_database();
+        db.get_state_output = Some(None);
+        db.set_state_output = Some(());
+        db.clear_state_output = Some(());
+        Box::new(db)
+    }
+
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::default(),
+    };
+    for name in ["mock", "constant"] {
+        let (middleware, _, _) = setup_dependencies();
+        let middleware = setup_middleware(middleware);
+        let result = Manager::new(
+            middleware,
+            gas_oracle(name),
+            database(),
+            CHAIN,
+            setup_configuration(),
+        )
+        .await;
+        assert_ok!(result);
+        let (manager, _) = result.unwrap();
+        let result = manager
+            .send(transaction.clone(), SendOptions::new().set_confirmations(0))
+            .await;
+        assert_ok!(result);
+    }
+
+    // Errors of the erased dependencies can be downcast.
+    let mut db = mock_database();
+    db.get_state_output = None;
+    let db: Box<dyn DynDatabase> = Box::new(db);
+    let (middleware, _, _) = setup_dependencies();
+    let result = Manager::new(
+        middleware,
+        gas_oracle("mock"),
+        db,
+        CHAIN,
+        setup_configuration(),
+    )
+    .await;
+    match result {
+        Err(eth_tx_manager::Error::Database(err)) => {
+            assert!(err.0.downcast_ref::<DatabaseStateError>().is_some())
+        }
+        result => panic!("expected a database error, got {:?}", result),
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_estimate_cost() {
+    utilities::setup_tracing();
+
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+
+    let (mut middleware, mut gas_oracle, db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    let configuration = setup_configuration().set_price_feed(Some(Arc::new(FixedPriceFeed(2000.))));
+    let manager = setup_manager(middleware, gas_oracle, db, configuration).await;
+
+    let result = manager.estimate_cost(&transaction, Priority::Normal).await;
+    assert_ok!(result);
+    let cost_estimate = result.unwrap();
+    assert_eq!(GasLimit::from(21000), cost_estimate.gas_limit);
+    // 21000 * 1 gwei + 5 wei.
+    assert_eq!(Wei::from(21_000_000_000_005), cost_estimate.max_cost);
+    let max_cost_usd = cost_estimate.max_cost_usd.unwrap();
+    assert!((max_cost_usd - 0.042).abs() < 1e-9);
+
+    // Nothing was sent.
+    assert_eq!(0, middleware_counters().sign_transaction_n);
+    assert_eq!(0, middleware_counters().send_raw_transaction_n);
+    assert_eq!(0, database_counters().set_state_n);
+}
+
+#[cfg(feature = "executor")]
+#[tokio::test]
+#[serial]
+async fn test_executor() {
+    use eth_tx_manager::executor::Executor;
+    use std::collections::HashSet;
+
+    utilities::setup_tracing();
+
+    let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    middleware.get_block_number = vec![1; 2];
+    middleware.get_transaction_receipt = vec![true; 2];
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = Some(None);
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+
+    let sender1: ethers::types::Address = HASH1.parse().unwrap();
+    let sender2: ethers::types::Address = HASH2.parse().unwrap();
+    let senders = vec![
+        (sender1, middleware.clone(), db.clone()),
+        (sender2, middleware, db),
+    ];
+    let result = Executor::new(senders, gas_oracle, CHAIN, setup_configuration()).await;
+    assert_ok!(result);
+    let executor = result.unwrap();
+    let mut events = executor.subscribe();
+
+    // Each transaction goes to a different sender (the one with the shortest queue).
+    let transaction = Transaction {
+        from: ethers::types::Address::zero(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let (result1, result2) = tokio::join!(
+        executor.send(transaction.clone(), SendOptions::new().set_confirmations(0)),
+        executor.send(transaction, SendOptions::new().set_confirmations(0)),
+    );
+    assert_ok!(result1);
+    assert_ok!(result2);
+    assert_eq!(2, middleware_counters().send_raw_transaction_n);
+
+    // The combined event stream has the events of both senders.
+    let mut submitted = HashSet::new();
+    while let Ok(event) = events.try_recv() {
+        if let Event::Submitted { .. } = event.event {
+            submitted.insert(event.sender);
+        }
+    }
+    assert_eq!(HashSet::from([sender1, sender2]), submitted);
+}
+
+/// Database whose recovery (`get_state`) waits until the gate is opened.
+#[cfg(feature = "executor")]
+#[derive(Clone, Debug)]
+struct GatedDatabase {
+    inner: MockDatabase,
+    gate: Option<Arc<tokio::sync::Semaphore>>,
+}
+
+#[cfg(feature = "executor")]
+#[async_trait]
+impl eth_tx_manager::database::Database for GatedDatabase {
+    type Error = DatabaseStateError;
+
+    async fn set_state(&mut self, state: &PersistentState) -> Result<(), Self::Error> {
+        eth_tx_manager::database::Database::set_state(&mut self.inner, state).await
+    }
+
+    async fn get_state(&self) -> Result<Option<PersistentState>, Self::Error> {
+        if let Some(gate) = &self.gate {
+            let _ = gate.acquire().await.unwrap();
+        }
+        eth_tx_manager::database::Database::get_state(&self.inner).await
+    }
+
+    async fn clear_state(&mut self) -> Result<(), Self::Error> {
+        eth_tx_manager::database::Database::clear_state(&mut self.inner).await
+    }
+}
+
+#[cfg(feature = "executor")]
+#[tokio::test]
+#[serial]
+async fn test_executor_recovery_does_not_block() {
+    use eth_tx_manager::executor::Executor;
+
+    utilities::setup_tracing();
+
+    let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    middleware.get_block_number = vec![1];
+    middleware.get_transaction_receipt = vec![true];
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = Some(None);
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+
+    // The recovery of the first sender hangs until the gate is opened.
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    let sender1: ethers::types::Address = HASH1.parse().unwrap();
+    let sender2: ethers::types::Address = HASH2.parse().unwrap();
+    let senders = vec![
+        (
+            sender1,
+            middleware.clone(),
+            GatedDatabase {
+                inner: db.clone(),
+                gate: Some(gate.clone()),
+            },
+        ),
+        (
+            sender2,
+            middleware,
+            GatedDatabase {
+                inner: db,
+                gate: None,
+            },
+        ),
+    ];
+    let executor = Executor::new(senders, gas_oracle, CHAIN, setup_configuration())
+        .await
+        .unwrap();
+
+    // The second sender recovers while the first one is still recovering.
+    let recovered = async {
+        while executor.recovering().len() > 1 {
+            tokio::task::yield_now().await;
+        }
+    };
+    assert_ok!(tokio::time::timeout(Duration::from_secs(5), recovered).await);
+    assert_eq!(vec![sender1], executor.recovering());
+
+    // The second sender sends while the first one is still recovering.
+    let transaction = Transaction {
+        from: ethers::types::Address::zero(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        executor.send(transaction, SendOptions::new().set_confirmations(0)),
+    )
+    .await;
+    assert_ok!(result.unwrap());
+    assert_eq!(1, middleware_counters().send_raw_transaction_n);
+    assert_eq!(vec![sender1], executor.recovering());
+
+    gate.close();
+}
+
+#[cfg(feature = "executor")]
+#[tokio::test]
+#[serial]
+async fn test_executor_tenants() {
+    use eth_tx_manager::executor::{Executor, ExecutorError, TenantPolicy, TENANT};
+
+    utilities::setup_tracing();
+
+    let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    middleware.get_block_number = vec![1; 5];
+    middleware.get_transaction_receipt = vec![true; 5];
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = Some(None);
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+
+    // The transactions queue up while the sender recovers.
+    let gate = Arc::new(tokio::sync::Semaphore::new(0));
+    let sender: ethers::types::Address = HASH1.parse().unwrap();
+    let senders = vec![(
+        sender,
+        middleware,
+        GatedDatabase {
+            inner: db,
+            gate: Some(gate.clone()),
+        },
+    )];
+    let executor = Executor::new(senders, gas_oracle, CHAIN, setup_configuration())
+        .await
+        .unwrap();
+    executor.set_tenant_policy(
+        "limited",
+        TenantPolicy {
+            weight: 1,
+            rate_limit: Some((1, Duration::from_secs(3600))),
+        },
+    );
+    let mut events = executor.subscribe();
+
+    let transaction = |tenant: &str, id: &str| Transaction {
+        from: ethers::types::Address::zero(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::from([
+            (TENANT.to_string(), tenant.to_string()),
+            ("id".to_string(), id.to_string()),
+        ]),
+    };
+    let send = |tenant: &str, id: &str| {
+        executor.send(
+            transaction(tenant, id),
+            SendOptions::new().set_confirmations(0),
+        )
+    };
+
+    // The noisy tenant does not delay the others' transactions behind all of
+    // its own.
+    gate.add_permits(1);
+    let results = tokio::time::timeout(Duration::from_secs(5), async {
+        tokio::join!(
+            send("noisy", "noisy1"),
+            send("noisy", "noisy2"),
+            send("noisy", "noisy3"),
+            send("quiet", "quiet1"),
+            send("limited", "limited1"),
+        )
+    })
+    .await
+    .unwrap();
+    assert_ok!(results.0);
+    assert_ok!(results.3);
+    assert_ok!(results.4);
+    let mut order = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let Event::Submitted { metadata, .. } = event.event {
+            order.push(metadata["id"].clone());
+        }
+    }
+    assert_eq!(
+        vec!["noisy1", "quiet1", "limited1", "noisy2", "noisy3"],
+        order
+    );
+
+    // Rate limits.
+    let result = send("limited", "limited2").await;
+    assert!(matches!(result, Err(ExecutorError::RateLimited(tenant)) if tenant == "limited"));
+
+    let stats = executor.tenant_stats();
+    assert_eq!(
+        vec!["limited", "noisy", "quiet"],
+        stats.iter().map(|s| s.tenant.as_str()).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        (1, 1, 0),
+        (stats[0].sent, stats[0].rate_limited, stats[0].queued)
+    );
+    assert_eq!(3, stats[1].sent);
+    assert_eq!(1, stats[2].sent);
+}
+
+#[derive(Debug, Default)]
+struct MockTopUp(std::sync::Mutex<Vec<ethers::types::Address>>);
+
+impl TopUp for MockTopUp {
+    fn top_up(&self, sender: ethers::types::Address, _: U256) {
+        self.0.lock().unwrap().push(sender);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_funding_monitor() {
+    utilities::setup_tracing();
+
+    let sender1: ethers::types::Address = HASH1.parse().unwrap();
+    let sender2: ethers::types::Address = HASH2.parse().unwrap();
+    let mut middleware = mock_middleware();
+    // (sender1, sender2) balances for each check.
+    middleware.get_balance = vec![10, 1000, 10, 1000, 1000, 1000, 10, 1000];
+    let event_listener = MockEventListener::new();
+    let top_up = Arc::new(MockTopUp::default());
+    let monitor = FundingMonitor::new(middleware, vec![sender1, sender2], U256::from(100))
+        .add_event_listener(Arc::new(event_listener.clone()))
+        .set_top_up(Some(top_up.clone()));
+
+    // Low balance.
+    let result = monitor.check().await;
+    assert_ok!(result);
+    assert_eq!(vec![(sender1, U256::from(10))], result.unwrap());
+    assert_eq!(1, event_listener.events().len());
+    assert_eq!(
+        Event::LowBalance {
+            sender: sender1,
+            balance: U256::from(10),
+            threshold: U256::from(100)
+        },
+        event_listener.events()[0]
+    );
+
+    // Still low (no new notifications).
+    let result = monitor.check().await;
+    assert_eq!(1, result.unwrap().len());
+    assert_eq!(1, event_listener.events().len());
+
+    // Recovered.
+    let result = monitor.check().await;
+    assert!(result.unwrap().is_empty());
+
+    // Low again.
+    let result = monitor.check().await;
+    assert_eq!(1, result.unwrap().len());
+    assert_eq!(2, event_listener.events().len());
+    assert_eq!(vec![sender1, sender1], *top_up.0.lock().unwrap());
+
+    // Errors.
+    let result = monitor.check().await;
+    let expected_err = MockMiddlewareError::GetBalance;
+    assert_err!(result, expected_err);
+}
+
+// ------------------------------------------------------------------------------------------------
+// Scenarios
+// ------------------------------------------------------------------------------------------------
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_confirmations() {
+    utilities::setup_tracing();
+
+    // Every tick mines a block once the script is over.
+    let scenario = Scenario::new(CHAIN.id);
+    let result = run_scenario(&scenario, 3, |configuration| configuration).await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(1, scenario.submitted().len());
+    assert_eq!(Some(1), scenario.mined_in(receipt.transaction_hash));
+    assert_eq!(Some(U64::from(1)), receipt.block_number);
+    assert_eq!(4, scenario.block_number());
+
+    // Waiting for blocks without the transaction.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::MineBlock]);
+    let result = run_scenario(&scenario, 1, |configuration| {
+        configuration.set_transaction_mining_time(Duration::from_secs(3600))
+    })
+    .await;
+    assert_ok!(result);
+    assert_eq!(Some(U64::from(3)), result.unwrap().block_number);
+    assert_eq!(1, scenario.submitted().len());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_timings() {
+    utilities::setup_tracing();
+
+    // The scenario's time measures one second between the checks.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::MineBlock]);
+    let (_, mut gas_oracle, mut db) = setup_dependencies();
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = Some(None);
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let event_listener = Arc::new(MockEventListener::new());
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::from_secs(3600))
+        .set_block_time(Duration::ZERO)
+        .add_event_listener(event_listener.clone());
+    let (manager, _) = Manager::new(scenario.middleware(), gas_oracle, db, CHAIN, configuration)
+        .await
+        .unwrap();
+    assert_eq!(None, manager.last_timings());
+    assert_eq!(None, manager.observed_mining_time());
+
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(1))
+        .await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+    let expected_timings = SendTimings {
+        mining_time: Some(Duration::from_secs(1)),
+        confirmation_time: Some(Duration::from_secs(1)),
+        database: DatabaseStats {
+            set: OperationStats {
+                count: 2,
+                total_time: Duration::from_secs(2),
+                max_time: Duration::from_secs(1),
+            },
+            ..Default::default()
+        },
+    };
+    assert_eq!(Some(expected_timings), manager.last_timings());
+    assert!(matches!(
+        event_listener.events().last(),
+        Some(Event::Confirmed { mining_time, confirmation_time, .. })
+            if *mining_time == expected_timings.mining_time
+                && *confirmation_time == expected_timings.confirmation_time
+    ));
+
+    // The mining time feeds the observed one.
+    assert_eq!(Some(Duration::from_secs(1)), manager.observed_mining_time());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_max_fee_cap() {
+    utilities::setup_tracing();
+
+    // The base fee rises above the cap after the submission, so the
+    // resubmissions are held until it drops.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([
+            ChainEvent::SetBaseFee(2_000_000_000),
+            ChainEvent::MineEmptyBlock,
+        ])
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::SetBaseFee(1), ChainEvent::MineEmptyBlock]);
+    let event_listener = Arc::new(MockEventListener::new());
+    let result = run_scenario(&scenario, 1, |configuration| {
+        configuration
+            .set_max_fee_cap(Some(Wei::from(1_500_000_000)))
+            .add_event_listener(event_listener.clone())
+    })
+    .await;
+    assert_ok!(result);
+    assert_eq!(1, scenario.submitted().len());
+    let waiting: Vec<_> = event_listener
+        .events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::WaitingForFeeDrop {
+                base_fee,
+                max_fee_cap,
+                ..
+            } => Some((base_fee, max_fee_cap)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        vec![(U256::from(2_000_000_000), U256::from(1_500_000_000))],
+        waiting
+    );
+
+    // The fees are capped.
+    let scenario = Scenario::new(CHAIN.id).now([ChainEvent::SetBaseFee(100_000_000)]);
+    let result = run_scenario(&scenario, 1, |configuration| {
+        configuration.set_max_fee_cap(Some(Wei::from(500_000_000)))
+    })
+    .await;
+    assert_ok!(result);
+    let transaction = scenario.transaction(scenario.submitted()[0]).unwrap();
+    assert_eq!(Some(U256::from(500_000_000)), transaction.max_fee_per_gas);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_patience() {
+    utilities::setup_tracing();
+
+    // The scenario's time measures one second between the checks, so a
+    // half-second wait resubmits the transaction on every check...
+    let scenario = || {
+        Scenario::new(CHAIN.id)
+            .tick([ChainEvent::MineEmptyBlock])
+            .tick([ChainEvent::MineEmptyBlock])
+            .tick([ChainEvent::MineEmptyBlock])
+            .tick([ChainEvent::MineBlock])
+    };
+    let impatient = scenario();
+    let result = run_scenario(&impatient, 1, |configuration| {
+        configuration.set_transaction_mining_time(Duration::from_millis(500))
+    })
+    .await;
+    assert_ok!(result);
+    assert_eq!(4, impatient.calls(Call::SignTransaction));
+
+    // ...while doubling it after the first resubmission stops them.
+    let patient = scenario();
+    let result = run_scenario(&patient, 1, |configuration| {
+        configuration
+            .set_transaction_mining_time(Duration::from_millis(500))
+            .set_patience(Some(Patience::new(2., Duration::from_secs(3600))))
+    })
+    .await;
+    assert_ok!(result);
+    assert_eq!(2, patient.calls(Call::SignTransaction));
+
+    // The schedule grows up to the longest wait.
+    let patience = Patience::new(2., Duration::from_secs(5));
+    let wait_time = Duration::from_secs(1);
+    assert_eq!(wait_time, patience.wait_time(wait_time, 0));
+    assert_eq!(Duration::from_secs(4), patience.wait_time(wait_time, 2));
+    assert_eq!(Duration::from_secs(5), patience.wait_time(wait_time, 3));
+    assert_eq!(
+        Duration::from_secs(5),
+        patience.wait_time(wait_time, u32::MAX)
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_reorg() {
+    utilities::setup_tracing();
+
+    // The receipt disappears before enough confirmations, and the transaction
+    // is mined again (in another block) from the pool.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::MineBlock])
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::Reorg { depth: 2 }])
+        .tick([ChainEvent::MineEmptyBlock, ChainEvent::MineEmptyBlock]);
+    let event_listener = Arc::new(MockEventListener::new());
+    let result = run_scenario(&scenario, 2, |configuration| {
+        configuration
+            .set_transaction_mining_time(Duration::from_secs(3600))
+            .add_event_listener(event_listener.clone())
+    })
+    .await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(1, scenario.submitted().len());
+    assert_eq!(Some(U64::from(3)), receipt.block_number);
+    let reorged = event_listener
+        .events()
+        .into_iter()
+        .filter(|event| matches!(event, Event::Reorged { .. }))
+        .count();
+    assert_eq!(1, reorged);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_multiple_receipts() {
+    utilities::setup_tracing();
+
+    // The first transaction is mined, reorged out, and evicted, so the manager
+    // sends a replacement; the node keeps serving the stale receipt of the
+    // first one after the replacement is mined.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::MineBlock])
+        .tick([ChainEvent::Reorg { depth: 1 }, ChainEvent::ClearPool])
+        .tick([ChainEvent::ServeStaleReceipts, ChainEvent::MineBlock]);
+    let (_, _, mut db) = setup_dependencies();
+    db.get_state_output = Some(None);
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let event_listener = Arc::new(MockEventListener::new());
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::ZERO)
+        .set_block_time(Duration::ZERO)
+        .add_event_listener(event_listener.clone());
+    let result = Manager::new(
+        scenario.middleware(),
+        IncrementingGasOracle::new(),
+        db,
+        CHAIN,
+        configuration,
+    )
+    .await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(2))
+        .await;
+    assert_ok!(result);
+    let (_, receipt) = result.unwrap();
+
+    // The receipt is the replacement's, from the canonical chain.
+    let submitted = scenario.submitted();
+    assert_eq!(2, submitted.len());
+    assert_eq!(submitted[1], receipt.transaction_hash);
+    assert_eq!(Some(1), scenario.mined_in(receipt.transaction_hash));
+
+    // The anomaly is flagged once.
+    let flagged: Vec<_> = event_listener
+        .events()
+        .into_iter()
+        .filter(|event| matches!(event, Event::MultipleReceipts { .. }))
+        .collect();
+    assert_eq!(1, flagged.len());
+    assert!(matches!(
+        &flagged[0],
+        Event::MultipleReceipts { hashes, canonical, .. }
+            if *hashes == submitted && *canonical == Some(submitted[1])
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_confirmation_updates() {
+    utilities::setup_tracing();
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::MineBlock])
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::Reorg { depth: 2 }])
+        .tick([ChainEvent::MineEmptyBlock]);
+    let (_, mut gas_oracle, mut db) = setup_dependencies();
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::from_secs(3600))
+        .set_block_time(Duration::ZERO);
+    let result =
+        Manager::force_new(scenario.middleware(), gas_oracle, db, CHAIN, configuration).await;
+    assert_ok!(result);
+    let mut manager = result.unwrap();
+
+    let updates = manager.confirmation_updates();
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(3))
+        .await;
+    assert_ok!(result);
+    drop(result);
+
+    // The depth goes back down after the reorg (which removes the blocks on
+    // top of the transaction's).
+    let depths: Vec<_> = updates.map(|update| update.depth).collect().await;
+    assert_eq!(vec![0, 1, 2, 0, 1, 2, 3], depths);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_rpc_errors() {
+    utilities::setup_tracing();
+
+    // Transient errors are retried.
+    let scenario = Scenario::new(CHAIN.id).now([ChainEvent::Fail(
+        Call::EstimateGas,
+        "connection reset".into(),
+    )]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration).await;
+    assert_ok!(result);
+    assert_eq!(2, scenario.calls(Call::EstimateGas));
+
+    // Other errors are not.
+    let scenario = Scenario::new(CHAIN.id).now([ChainEvent::Fail(
+        Call::SendRawTransaction,
+        "insufficient funds for gas * price + value".into(),
+    )]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration).await;
+    assert!(result.is_err());
+    assert!(scenario.submitted().is_empty());
+
+    // Errors while confirming.
+    let scenario = Scenario::new(CHAIN.id).tick([
+        ChainEvent::MineBlock,
+        ChainEvent::Fail(
+            Call::GetTransactionReceipt,
+            "503 service unavailable".into(),
+        ),
+    ]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration).await;
+    assert!(result.is_err());
+    assert_eq!(1, scenario.calls(Call::GetTransactionReceipt));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_resubmission() {
+    utilities::setup_tracing();
+
+    // The transaction is not mined while the base fee exceeds its max fee. The
+    // manager resubmits it (with the same fees, as the gas oracle's did not
+    // change), skipping every other resubmission, as nodes already know it.
+    let scenario = Scenario::new(CHAIN.id)
+        .now([ChainEvent::SetBaseFee(1_050_000_000)])
+        .tick([ChainEvent::MineBlock])
+        .tick([ChainEvent::MineBlock])
+        .tick([ChainEvent::SetBaseFee(1_000_000_000), ChainEvent::MineBlock]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration).await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(vec![receipt.transaction_hash], scenario.submitted());
+    assert_eq!(Some(3), scenario.mined_in(receipt.transaction_hash));
+    assert_eq!(2, scenario.calls(Call::SendRawTransaction));
+
+    // The transaction is evicted from the pool, and resubmitted.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::ClearPool])
+        .tick([ChainEvent::MineBlock]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration).await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(
+        vec![receipt.transaction_hash, receipt.transaction_hash],
+        scenario.submitted()
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_resubmission_modes() {
+    utilities::setup_tracing();
+    let congested = || {
+        Scenario::new(CHAIN.id)
+            .now([ChainEvent::SetBaseFee(1_050_000_000)])
+            .tick([ChainEvent::MineBlock])
+            .tick([ChainEvent::MineBlock])
+            .tick([ChainEvent::SetBaseFee(1_000_000_000), ChainEvent::MineBlock])
+    };
+
+    // Rebroadcasting: the manager resends the same transaction every time
+    // (instead of every other time, see `test_scenario_resubmission`).
+    let scenario = congested();
+    let result = run_scenario(&scenario, 0, |configuration| {
+        configuration.set_resubmission_mode(ResubmissionMode::RebroadcastOnly)
+    })
+    .await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(Some(3), scenario.mined_in(receipt.transaction_hash));
+    assert_eq!(vec![receipt.transaction_hash], scenario.submitted());
+    assert_eq!(3, scenario.calls(Call::SendRawTransaction));
+
+    // Rebroadcasting brings back transactions evicted from the pool.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::ClearPool])
+        .tick([ChainEvent::MineBlock]);
+    let result = run_scenario(&scenario, 0, |configuration| {
+        configuration.set_resubmission_mode(ResubmissionMode::RebroadcastOnly)
+    })
+    .await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(
+        vec![receipt.transaction_hash, receipt.transaction_hash],
+        scenario.submitted()
+    );
+
+    // Disabled: the manager only waits.
+    let scenario = congested();
+    let result = run_scenario(&scenario, 0, |configuration| {
+        configuration.set_resubmission_mode(ResubmissionMode::Disabled)
+    })
+    .await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(Some(3), scenario.mined_in(receipt.transaction_hash));
+    assert_eq!(1, scenario.calls(Call::SendRawTransaction));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_broadcast_provider() {
+    use eth_tx_manager::broadcaster::Broadcaster;
+    utilities::setup_tracing();
+
+    /// Submits through the scenario's chain, counting the submissions.
+    #[derive(Debug)]
+    struct CountingBroadcaster(ScenarioMiddleware, AtomicUsize);
+
+    #[async_trait]
+    impl Broadcaster for CountingBroadcaster {
+        async fn send_raw_transaction(&self, raw_transaction: Bytes) -> Result<H256, DynError> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            Broadcaster::send_raw_transaction(&self.0, raw_transaction).await
+        }
+    }
+
+    // The transaction is submitted through the broadcast provider (even when
+    // resent after the node rejected its max fee), and confirmed through the
+    // provider.
+    let scenario = Scenario::new(CHAIN.id)
+        .now([ChainEvent::Fail(
+            Call::SendRawTransaction,
+            "max fee per gas less than block base fee".into(),
+        )])
+        .tick([ChainEvent::MineBlock]);
+    let broadcaster = Arc::new(CountingBroadcaster(
+        scenario.middleware(),
+        AtomicUsize::new(0),
+    ));
+    let result = run_scenario(&scenario, 0, |configuration| {
+        configuration.set_broadcast_provider(Some(broadcaster.clone()))
+    })
+    .await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(vec![receipt.transaction_hash], scenario.submitted());
+    assert_eq!(2, broadcaster.1.load(Ordering::SeqCst));
+    assert_eq!(2, scenario.calls(Call::SendRawTransaction));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_fee_model() {
+    utilities::setup_tracing();
+
+    /// Builds legacy transactions paying the max fee, with a fixed gas limit.
+    #[derive(Debug)]
+    struct FixedGasFeeModel;
+
+    #[async_trait]
+    impl FeeModel for FixedGasFeeModel {
+        async fn build(
+            &self,
+            tx_data: &StaticTxData,
+            chain: &Chain,
+            gas_info: GasInfo,
+        ) -> Result<TypedTransaction, DynError> {
+            let gas_price = match gas_info {
+                GasInfo::Legacy(legacy_gas_info) => legacy_gas_info.gas_price,
+                GasInfo::EIP1559(eip1559_gas_info) => eip1559_gas_info.max_fee.unwrap(),
+            };
+            Ok(TransactionRequest::new()
+                .from(tx_data.transaction.from)
+                .to(tx_data.transaction.to.clone())
+                .value(tx_data.transaction.value)
+                .nonce(tx_data.nonce)
+                .gas(50_000)
+                .gas_price(gas_price)
+                .chain_id(chain.id)
+                .into())
+        }
+    }
+
+    // The transaction is built by the fee model, and its gas limit is not
+    // estimated.
+    let scenario = Scenario::new(CHAIN.id).tick([ChainEvent::MineBlock]);
+    let result = run_scenario(&scenario, 0, |configuration| {
+        configuration.set_fee_model(Arc::new(FixedGasFeeModel))
+    })
+    .await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(vec![receipt.transaction_hash], scenario.submitted());
+    assert_eq!(0, scenario.calls(Call::EstimateGas));
+    let transaction = scenario.transaction(receipt.transaction_hash).unwrap();
+    assert_eq!(U256::from(50_000), transaction.gas);
+    assert!(transaction.gas_price.is_some());
+    assert_eq!(None, transaction.max_fee_per_gas);
+
+    // With the default model, the gas limit is estimated.
+    let scenario = Scenario::new(CHAIN.id).tick([ChainEvent::MineBlock]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration).await;
+    assert_ok!(result);
+    assert_eq!(1, scenario.calls(Call::EstimateGas));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_max_fee_below_base_fee() {
+    utilities::setup_tracing();
+    const REJECTION: &str = "max fee per gas less than block base fee: \
+        maxFeePerGas: 1000000000 baseFee: 1100000000";
+
+    // The node rejects the max fee, and the manager resends the transaction
+    // with a raised one (in the same attempt).
+    let scenario =
+        Scenario::new(CHAIN.id).now([ChainEvent::Fail(Call::SendRawTransaction, REJECTION.into())]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration).await;
+    assert_ok!(result);
+    let receipt = result.unwrap();
+    assert_eq!(vec![receipt.transaction_hash], scenario.submitted());
+    assert_eq!(2, scenario.calls(Call::SendRawTransaction));
+    let transaction = scenario.transaction(receipt.transaction_hash).unwrap();
+    assert_eq!(
+        Some(Wei::from(1_000_000_000).multiply(1.125).into()),
+        transaction.max_fee_per_gas
+    );
+
+    // With the configured headroom.
+    let scenario =
+        Scenario::new(CHAIN.id).now([ChainEvent::Fail(Call::SendRawTransaction, REJECTION.into())]);
+    let result = run_scenario(&scenario, 0, |configuration| {
+        configuration.set_max_fee_headroom(Some(2.))
+    })
+    .await;
+    assert_ok!(result);
+    let transaction = scenario
+        .transaction(result.unwrap().transaction_hash)
+        .unwrap();
+    assert_eq!(
+        Some(Wei::from(2_000_000_000).into()),
+        transaction.max_fee_per_gas
+    );
+
+    // The manager gives up when the node keeps rejecting the max fee.
+    let scenario = Scenario::new(CHAIN.id)
+        .now((0..4).map(|_| ChainEvent::Fail(Call::SendRawTransaction, REJECTION.into())));
+    let result = run_scenario(&scenario, 0, |configuration| configuration).await;
+    let err = result.unwrap_err();
+    let report = err.report().unwrap().clone();
+    assert!(matches!(
+        err.into_inner(),
+        eth_tx_manager::Error::MaxFeeBelowBaseFee { .. }
+    ));
+    assert_eq!(4, scenario.calls(Call::SendRawTransaction));
+
+    // The error reports the rejected attempts (and what the node said).
+    assert_eq!(4, report.attempts.len());
+    assert_eq!(4, report.rpc_errors.len());
+    assert!(report
+        .rpc_errors
+        .iter()
+        .all(|error| error.contains(REJECTION)));
+    assert_eq!(None, report.in_flight);
+    assert_eq!(9, report.to_string().lines().count());
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(4, json["attempts"].as_array().unwrap().len());
+    assert!(json["in_flight_ms"].is_null());
+    assert!(scenario.submitted().is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_gas_limit_errors() {
+    utilities::setup_tracing();
+
+    // The gas limit is below the intrinsic gas.
+    let scenario = Scenario::new(CHAIN.id).now([ChainEvent::Fail(
+        Call::SendRawTransaction,
+        "intrinsic gas too low".into(),
+    )]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration)
+        .await
+        .map_err(eth_tx_manager::Error::into_inner);
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::IntrinsicGasTooLow(Some(_)))
+    ));
+
+    // The operation does not fit in a block.
+    let scenario = Scenario::new(CHAIN.id).now([ChainEvent::Fail(
+        Call::EstimateGas,
+        "exceeds block gas limit".into(),
+    )]);
+    let result = run_scenario(&scenario, 0, |configuration| configuration)
+        .await
+        .map_err(eth_tx_manager::Error::into_inner);
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::ExceedsBlockGasLimit { .. })
+    ));
+    assert_eq!(1, scenario.calls(Call::EstimateGas));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_send_split() {
+    utilities::setup_tracing();
+
+    // Splits transfers into two halves.
+    let splitter = |transaction: &Transaction| match transaction.value {
+        Value::Number(value) if value > Wei::from(1) => {
+            let half = Wei(U256::from(value) / 2);
+            Some(vec![
+                Transaction {
+                    value: Value::Number(half),
+                    ..transaction.clone()
+                },
+                Transaction {
+                    value: Value::Number(value - half),
+                    ..transaction.clone()
+                },
+            ])
+        }
+        _ => None,
+    };
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+
+    // The transaction and its first half do not fit in a block.
+    let scenario = Scenario::new(CHAIN.id).now([
+        ChainEvent::Fail(Call::EstimateGas, "exceeds block gas limit".into()),
+        ChainEvent::Fail(Call::EstimateGas, "exceeds block gas limit".into()),
+    ]);
+    let manager = scenario_manager(&scenario).await;
+    let result = manager
+        .send_split(transaction.clone(), SendOptions::new(), &splitter)
+        .await;
+    assert_ok!(result);
+    let (_, receipts) = result.unwrap();
+    assert_eq!(3, receipts.len());
+    let values: Vec<_> = receipts
+        .iter()
+        .map(|receipt| {
+            let transaction = scenario.transaction(receipt.transaction_hash).unwrap();
+            (transaction.nonce.as_u64(), transaction.value.as_u64())
+        })
+        .collect();
+    assert_eq!(vec![(0, 1), (1, 1), (2, 3)], values);
+
+    // The transaction cannot be split any further.
+    let scenario = Scenario::new(CHAIN.id)
+        .now((0..3).map(|_| ChainEvent::Fail(Call::EstimateGas, "exceeds block gas limit".into())));
+    let manager = scenario_manager(&scenario).await;
+    let result = manager
+        .send_split(transaction, SendOptions::new(), &splitter)
+        .await;
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::ExceedsBlockGasLimit { .. })
+    ));
+    assert!(scenario.submitted().is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_rebroadcast_prepared_transaction() {
+    utilities::setup_tracing();
+    let path = std::env::temp_dir().join("tx_manager_mock_test_rebroadcast.json");
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+
+    // The manager stops after storing the transaction, before the node gets it.
+    let scenario = Scenario::new(CHAIN.id).now([ChainEvent::Fail(
+        Call::SendRawTransaction,
+        "connection refused".into(),
+    )]);
+    let (_, mut gas_oracle, _) = setup_dependencies();
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::ZERO)
+        .set_block_time(Duration::ZERO);
+    let result = Manager::new(
+        scenario.middleware(),
+        gas_oracle.clone(),
+        FileSystemDatabase::new(path.clone()),
+        CHAIN,
+        configuration.clone(),
+    )
+    .await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(0))
+        .await;
+    assert!(result.is_err());
+    assert!(scenario.submitted().is_empty());
+
+    let database = FileSystemDatabase::new(path.clone());
+    let state = eth_tx_manager::database::Database::get_state(&database)
+        .await
+        .unwrap()
+        .unwrap();
+    let prepared: Vec<_> = state.unbroadcast_txs().map(|(&hash, _)| hash).collect();
+    assert_eq!(state.submitted_txs.txs_hashes, prepared);
+
+    // The recovery rebroadcasts it.
+    let result = Manager::new(
+        scenario.middleware(),
+        gas_oracle,
+        FileSystemDatabase::new(path.clone()),
+        CHAIN,
+        configuration,
+    )
+    .await;
+    assert_ok!(result);
+    let (_, resolution) = result.unwrap();
+    assert_eq!(prepared, scenario.submitted());
+    match resolution {
+        PendingResolution::ConfirmedNow(receipt) => {
+            assert_eq!(prepared, vec![receipt.transaction_hash])
+        }
+        resolution => panic!("unexpected resolution: {:?}", resolution),
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_recovered_fees() {
+    utilities::setup_tracing();
+    let path = std::env::temp_dir().join("tx_manager_mock_test_recovered_fees.json");
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+
+    // The manager stops after sending a sped-up transaction (at three times
+    // the oracle's fees), which is then evicted from the pool.
+    let scenario = Scenario::new(CHAIN.id).now([ChainEvent::Fail(
+        Call::SendRawTransaction,
+        "connection refused".into(),
+    )]);
+    let (_, mut gas_oracle, _) = setup_dependencies();
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    let speed_up_switch = SpeedUpSwitch::new();
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::ZERO)
+        .set_block_time(Duration::ZERO);
+    let result = Manager::new(
+        scenario.middleware(),
+        gas_oracle.clone(),
+        FileSystemDatabase::new(path.clone()),
+        CHAIN,
+        configuration
+            .clone()
+            .set_speed_up_switch(speed_up_switch.clone()),
+    )
+    .await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+    speed_up_switch.speed_up(3.);
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(0))
+        .await;
+    assert!(result.is_err());
+
+    let mut database = FileSystemDatabase::new(path.clone());
+    let mut state = eth_tx_manager::database::Database::get_state(&database)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(1, state.attempts.len());
+    let escalated = state.attempts[0].gas_info;
+    assert!(matches!(
+        escalated,
+        GasInfo::EIP1559(EIP1559GasInfo { max_fee, .. }) if max_fee == Some(Wei::from(3_000_000_000))
+    ));
+    state.broadcast_statuses.clear();
+    eth_tx_manager::database::Database::set_state(&mut database, &state)
+        .await
+        .unwrap();
+
+    // The recovery resends it with the escalated fees, not the oracle's.
+    let result = Manager::new(
+        scenario.middleware(),
+        gas_oracle,
+        FileSystemDatabase::new(path.clone()),
+        CHAIN,
+        configuration,
+    )
+    .await;
+    assert_ok!(result);
+    let submitted = scenario.submitted();
+    assert_eq!(1, submitted.len());
+    let resent = scenario.transaction(submitted[0]).unwrap();
+    assert_eq!(Some(U256::from(3_000_000_000u64)), resent.max_fee_per_gas);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_check_once() {
+    utilities::setup_tracing();
+    let path = std::env::temp_dir().join("tx_manager_mock_test_check_once.json");
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+
+    let scenario = Scenario::new(CHAIN.id).now([ChainEvent::Fail(
+        Call::SendRawTransaction,
+        "connection refused".into(),
+    )]);
+    let (_, mut gas_oracle, _) = setup_dependencies();
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::from_secs(3600))
+        .set_block_time(Duration::ZERO);
+    let new_manager = || {
+        Manager::new(
+            scenario.middleware(),
+            gas_oracle.clone(),
+            FileSystemDatabase::new(path.clone()),
+            CHAIN,
+            configuration.clone(),
+        )
+    };
+
+    // Without a pending transaction.
+    let (mut checker, _) = new_manager().await.unwrap();
+    let summary = checker.check_once().await;
+    assert_ok!(summary);
+    assert_eq!(CheckSummary::Idle, summary.unwrap());
+
+    // Another manager stores a transaction, but fails to send it.
+    let (manager, _) = new_manager().await.unwrap();
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let result = manager
+        .send(transaction, SendOptions::new().set_confirmations(1))
+        .await;
+    assert!(result.is_err());
+
+    let summary = checker.check_once().await.unwrap();
+    assert_eq!(
+        CheckSummary::Pending {
+            nonce: U256::zero(),
+            submitted: 1,
+            resubmission_due: false,
+        },
+        summary
+    );
+    checker.speed_up(1.5);
+    let summary = checker.check_once().await.unwrap();
+    assert!(matches!(
+        summary,
+        CheckSummary::Pending {
+            resubmission_due: true,
+            ..
+        }
+    ));
+
+    // The transaction gets broadcast and mined (checks never sleep, so the
+    // scenario only moves on when the test ticks it).
+    let database = FileSystemDatabase::new(path.clone());
+    let state = eth_tx_manager::database::Database::get_state(&database)
+        .await
+        .unwrap()
+        .unwrap();
+    let (&hash, raw_transaction) = state.unbroadcast_txs().next().unwrap();
+    assert_ok!(
+        ethers::providers::Middleware::send_raw_transaction(
+            &scenario.middleware(),
+            raw_transaction.clone()
+        )
+        .await
+    );
+    scenario.time().sleep(Duration::ZERO).await;
+    let summary = checker.check_once().await.unwrap();
+    match &summary {
+        CheckSummary::Mined { receipt, update } => {
+            assert_eq!(hash, receipt.transaction_hash);
+            assert_eq!(0, update.depth);
+        }
+        summary => panic!("unexpected summary: {:?}", summary),
+    }
+    assert!(!summary.is_confirmed());
+
+    scenario.time().sleep(Duration::ZERO).await;
+    let summary = checker.check_once().await.unwrap();
+    assert!(summary.is_confirmed());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_caller_driven() {
+    utilities::setup_tracing();
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+
+    // The caller sleeps and broadcasts for the manager.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::MineBlock]);
+    let mut manager = scenario_manager(&scenario).await;
+    assert!(matches!(
+        manager.next_action().await,
+        Err(eth_tx_manager::Error::Driven(_))
+    ));
+    let result = manager
+        .drive(transaction.clone(), SendOptions::new().set_confirmations(2))
+        .await;
+    assert_ok!(result);
+    let (actions, receipt) = drive_scenario(&mut manager, &scenario).await;
+    assert_eq!(vec![receipt.transaction_hash], scenario.submitted());
+    assert_eq!(Some(2), scenario.mined_in(receipt.transaction_hash));
+    assert!(matches!(actions[0], Action::Submit(_)));
+    assert!(matches!(actions[1], Action::Sleep(_)));
+    // The resubmission after the empty block (as the mining time is zero) is
+    // skipped, as the node already knows the transaction.
+    let submissions = actions
+        .iter()
+        .filter(|action| matches!(action, Action::Submit(_)))
+        .count();
+    assert_eq!(1, submissions);
+    assert!(matches!(
+        manager.next_action().await,
+        Err(eth_tx_manager::Error::Driven(_))
+    ));
+
+    // The manager asks for the same submission until its result is applied,
+    // and fails when the broadcast does.
+    let scenario = Scenario::new(CHAIN.id);
+    let mut manager = scenario_manager(&scenario).await;
+    assert_ok!(manager.drive(transaction.clone(), SendOptions::new()).await);
+    let action1 = manager.next_action().await.unwrap();
+    let action2 = manager.next_action().await.unwrap();
+    assert!(matches!(action1, Action::Submit(_)));
+    assert_eq!(action1, action2);
+    let result = manager
+        .apply(ActionResult::Submitted(Err("insufficient funds".into())))
+        .await;
+    assert!(matches!(result, Err(eth_tx_manager::Error::Broadcast(_))));
+    let result = manager
+        .apply(ActionResult::Submitted(Ok(H256::zero())))
+        .await;
+    assert!(matches!(result, Err(eth_tx_manager::Error::Driven(_))));
+
+    // The driven transaction is held while the manager is paused, resent with
+    // a raised max fee when the node rejects it, validated, and journaled.
+    let path = std::env::temp_dir().join("tx_manager_mock_test_driven_journal.jsonl");
+    let _ = std::fs::remove_file(&path);
+    let journal = Arc::new(Journal::open(&path, "mock").unwrap());
+    let pause_switch = PauseSwitch::new();
+    pause_switch.pause();
+    let scenario = Scenario::new(CHAIN.id)
+        .now([ChainEvent::Fail(
+            Call::SendRawTransaction,
+            "max fee per gas less than block base fee".into(),
+        )])
+        .tick([ChainEvent::MineBlock]);
+    let mut manager = scenario_manager_with_configuration(&scenario, |configuration| {
+        configuration
+            .set_journal(Some(journal))
+            .set_pause_switch(pause_switch.clone())
+            .set_validate_receipts(true)
+    })
+    .await;
+    assert_ok!(manager.drive(transaction, SendOptions::new()).await);
+    assert!(matches!(manager.next_action().await, Ok(Action::Sleep(_))));
+    assert_ok!(manager.apply(ActionResult::Slept).await);
+    pause_switch.resume();
+    let (_, receipt) = drive_scenario(&mut manager, &scenario).await;
+    assert_eq!(vec![receipt.transaction_hash], scenario.submitted());
+    assert_eq!(2, scenario.calls(Call::SendRawTransaction));
+    let entries = Journal::verify(&path).unwrap();
+    assert_eq!(2, entries.len());
+    assert!(matches!(
+        &entries[0].transition,
+        Transition::Submitted { hash, gas_limit: Some(_), .. }
+            if *hash == receipt.transaction_hash
+    ));
+    assert!(matches!(
+        &entries[1].transition,
+        Transition::Confirmed { hash, .. } if *hash == receipt.transaction_hash
+    ));
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Drives the transaction until it is done, performing the actions against
+/// the scenario, and returns them with the receipt.
+async fn drive_scenario(
+    manager: &mut Manager<ScenarioMiddleware, MockGasOracle, MockDatabase, ScenarioTime>,
+    scenario: &Scenario,
+) -> (Vec<Action>, Box<TransactionReceipt>) {
+    let mut actions = Vec::new();
+    loop {
+        assert!(actions.len() < 20, "the transaction was never confirmed");
+        let action = manager.next_action().await;
+        assert_ok!(action);
+        let action = action.unwrap();
+        actions.push(action.clone());
+        match action {
+            Action::Sleep(duration) => {
+                scenario.time().sleep(duration).await;
+                assert_ok!(manager.apply(ActionResult::Slept).await);
+            }
+            Action::Submit(signed) => {
+                let result = ethers::providers::Middleware::send_raw_transaction(
+                    &scenario.middleware(),
+                    signed.raw_transaction,
+                )
+                .await
+                .map(|pending_transaction| H256(*pending_transaction.as_fixed_bytes()))
+                .map_err(|err| err.into());
+                assert_ok!(manager.apply(ActionResult::Submitted(result)).await);
+            }
+            Action::Done(receipt) => return (actions, receipt),
+        }
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_batch() {
+    utilities::setup_tracing();
+    let transactions: Vec<_> = (1..=3)
+        .map(|i| Transaction {
+            from: Scenario::signer(),
+            to: HASH2.parse().unwrap(),
+            value: Value::Number(Wei::from(i)),
+            call_data: None,
+            metadata: Metadata::new(),
+        })
+        .collect();
+    let options = SendOptions::new().set_confirmations(0);
+    let reverted = || ChainEvent::Fail(Call::EstimateGas, "execution reverted: nope".into());
+
+    // The second transaction reverts before being submitted, so its nonce is
+    // free for the third one.
+    let scenario = Scenario::new(CHAIN.id).tick([ChainEvent::MineBlock, reverted()]);
+    let manager = scenario_manager(&scenario).await;
+    let result = manager.send_batch(transactions.clone(), options).await;
+    assert_ok!(result);
+    let (_, results) = result.unwrap();
+    assert_eq!(3, results.len());
+    assert!(matches!(results[0], Ok(SendOutcome::Confirmed(_))));
+    assert!(matches!(
+        results[1].as_ref().map_err(|err| err.report()),
+        Err(Some(_))
+    ));
+    assert!(matches!(
+        results[1].as_ref().map_err(|err| err.inner()),
+        Err(eth_tx_manager::Error::Reverted(Some(_)))
+    ));
+    assert!(matches!(results[2], Ok(SendOutcome::Confirmed(_))));
+    assert_eq!(2, scenario.submitted().len());
+    let nonces: Vec<_> = scenario
+        .submitted()
+        .into_iter()
+        .map(|hash| scenario.transaction(hash).unwrap().nonce)
+        .collect();
+    assert_eq!(vec![U256::zero(), U256::one()], nonces);
+
+    // The first transaction reverts when resubmitted, so it is cancelled with
+    // a no-op for the second one to be mined.
+    let scenario = Scenario::new(CHAIN.id).tick([ChainEvent::MineEmptyBlock, reverted()]);
+    let manager = scenario_manager(&scenario).await;
+    let result = manager
+        .send_batch(transactions[..2].to_vec(), options)
+        .await;
+    assert_ok!(result);
+    let (_, results) = result.unwrap();
+    assert_eq!(2, results.len());
+    assert!(matches!(
+        results[0].as_ref().map_err(|err| err.inner()),
+        Err(eth_tx_manager::Error::Reverted(Some(_)))
+    ));
+    assert!(matches!(results[1], Ok(SendOutcome::Confirmed(_))));
+    let submitted = scenario.submitted();
+    assert_eq!(3, submitted.len());
+    let cancellation = scenario.transaction(submitted[1]).unwrap();
+    assert_eq!(U256::zero(), cancellation.nonce);
+    assert_eq!(Some(Scenario::signer()), cancellation.to);
+    assert_eq!(Some(2), scenario.mined_in(submitted[1]));
+    assert_eq!(
+        U256::one(),
+        scenario.transaction(submitted[2]).unwrap().nonce
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_adopt() {
+    utilities::setup_tracing();
+
+    // A transaction sent outside the manager, with fees too low for the base
+    // fee.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::MineEmptyBlock]);
+    let middleware = scenario.middleware();
+    let external: TypedTransaction = Eip1559TransactionRequest::new()
+        .from(Scenario::signer())
+        .to(HASH2.parse::<Address>().unwrap())
+        .value(5)
+        .nonce(0)
+        .gas(21_000)
+        .max_fee_per_gas(1)
+        .max_priority_fee_per_gas(1)
+        .chain_id(CHAIN.id)
+        .into();
+    let signature = middleware
+        .sign_transaction(&external, Scenario::signer())
+        .await
+        .unwrap();
+    let external_hash = middleware
+        .send_raw_transaction(external.rlp_signed(&signature))
+        .await
+        .unwrap()
+        .tx_hash();
+
+    // The manager adopts it, and replaces it when it is not mined in time.
+    let manager = scenario_manager(&scenario).await;
+    let result = manager
+        .adopt(external_hash, SendOptions::new().set_confirmations(1))
+        .await;
+    assert_ok!(result);
+    let (manager, receipt) = result.unwrap();
+    let submitted = scenario.submitted();
+    assert_eq!(2, submitted.len());
+    assert_eq!(external_hash, submitted[0]);
+    assert_eq!(submitted[1], receipt.transaction_hash);
+    let replacement = scenario.transaction(receipt.transaction_hash).unwrap();
+    assert_eq!(U256::zero(), replacement.nonce);
+    assert_eq!(U256::from(5), replacement.value);
+
+    // Unknown transactions cannot be adopted.
+    let result = manager
+        .adopt(H256::repeat_byte(1), SendOptions::new())
+        .await;
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::NotAdoptable {
+            reason: "not found",
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_receipt_validation() {
+    utilities::setup_tracing();
+
+    // The mined transaction is the one sent.
+    {
+        let scenario = Scenario::new(CHAIN.id).tick([ChainEvent::MineBlock]);
+        let result = run_scenario(&scenario, 0, |configuration| {
+            configuration.set_validate_receipts(true)
+        })
+        .await;
+        assert_ok!(result);
+        assert_eq!(1, scenario.calls(Call::GetTransaction));
+    }
+
+    // The node serves a different transaction for the hash.
+    {
+        let configuration = setup_configuration().set_validate_receipts(true);
+        let result =
+            run_send_transaction_with_configuration(0, configuration, |mut middleware, b, c| {
+                middleware.get_transaction = Some(Some((1, 1)));
+                (middleware, b, c)
+            })
+            .await
+            .map_err(eth_tx_manager::Error::into_inner);
+        assert!(matches!(
+            result,
+            Err(eth_tx_manager::Error::ReceiptMismatch {
+                mismatch: "sender",
+                ..
+            })
+        ));
+        assert_eq!(1, middleware_counters().get_transaction_n);
+    }
+
+    // Another transaction consumes the nonce.
+    {
+        let scenario = Scenario::new(CHAIN.id).tick([ChainEvent::MineForeignTransaction]);
+        let result = run_scenario(&scenario, 0, |configuration| {
+            configuration.set_validate_receipts(true)
+        })
+        .await
+        .map_err(eth_tx_manager::Error::into_inner);
+        assert!(matches!(
+            result,
+            Err(eth_tx_manager::Error::NonceConsumed { nonce }) if nonce.is_zero()
+        ));
+        assert_eq!(1, scenario.submitted().len());
+    }
+
+    // Not validated by default.
+    {
+        let result = run_send_transaction(0, |middleware, b, c| (middleware, b, c)).await;
+        assert_ok!(result);
+        assert_eq!(0, middleware_counters().get_transaction_n);
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_handoff() {
+    utilities::setup_tracing();
+    let path = std::env::temp_dir().join("tx_manager_mock_test_handoff.json");
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+
+    let scenario = Scenario::new(CHAIN.id);
+    let (_, mut gas_oracle, _) = setup_dependencies();
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::ZERO)
+        .set_block_time(Duration::ZERO);
+    let instantiate = |token: Option<HandoffToken>| {
+        let database = FileSystemDatabase::new(path.clone());
+        let (middleware, gas_oracle) = (scenario.middleware(), gas_oracle.clone());
+        let configuration = configuration.clone();
+        async move {
+            match token {
+                Some(token) => {
+                    Manager::take_over(
+                        middleware,
+                        gas_oracle,
+                        database,
+                        CHAIN,
+                        configuration,
+                        token,
+                    )
+                    .await
+                }
+                None => Manager::new(middleware, gas_oracle, database, CHAIN, configuration).await,
+            }
+        }
+    };
+
+    // Nothing in flight: no token.
+    let (manager, _) = instantiate(None).await.unwrap();
+    assert_eq!(None, manager.hand_off().await.unwrap());
+
+    // The old instance hands off the transaction it was driving.
+    let (mut manager, _) = instantiate(None).await.unwrap();
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    assert_ok!(
+        manager
+            .drive(transaction, SendOptions::new().set_confirmations(0))
+            .await
+    );
+    let token = manager.hand_off().await.unwrap().unwrap();
+
+    // Other instances cannot start with the handed off state.
+    let result = instantiate(None).await;
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::HandoffMismatch { expected: None, found: Some(ref found) })
+            if *found == token
+    ));
+    let result = instantiate(Some(HandoffToken::from("other".to_string()))).await;
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::HandoffMismatch { found: Some(ref found), .. })
+            if *found == token
+    ));
+
+    // The new instance takes over and resumes the transaction.
+    let result = instantiate(Some(token.clone())).await;
+    assert_ok!(result);
+    let (_, resolution) = result.unwrap();
+    assert!(matches!(resolution, PendingResolution::ConfirmedNow(_)));
+    assert_eq!(1, scenario.submitted().len());
+
+    // The transaction was resolved, so the state was cleared.
+    let database = FileSystemDatabase::new(path.clone());
+    let state = eth_tx_manager::database::Database::get_state(&database)
+        .await
+        .unwrap();
+    assert_eq!(None, state);
+    let _ = std::fs::remove_file(&path);
+}
+
+// ------------------------------------------------------------------------------------------------
+// Auxiliary
+// ------------------------------------------------------------------------------------------------
+
+#[tokio::test]
+#[serial]
+async fn test_scenario_wait_for() {
+    utilities::setup_tracing();
+
+    // A transaction sent outside the manager, not mined until the base fee
+    // drops, then moved to another block by a reorg.
+    let scenario = Scenario::new(CHAIN.id)
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::MineEmptyBlock])
+        .tick([ChainEvent::SetBaseFee(1), ChainEvent::MineBlock])
+        .tick([ChainEvent::Reorg { depth: 1 }, ChainEvent::MineEmptyBlock]);
+    let middleware = scenario.middleware();
+    let external: TypedTransaction = Eip1559TransactionRequest::new()
+        .from(Scenario::signer())
+        .to(HASH2.parse::<Address>().unwrap())
+        .value(5)
+        .nonce(0)
+        .gas(21_000)
+        .max_fee_per_gas(1)
+        .max_priority_fee_per_gas(1)
+        .chain_id(CHAIN.id)
+        .into();
+    let signature = middleware
+        .sign_transaction(&external, Scenario::signer())
+        .await
+        .unwrap();
+    let external_hash = middleware
+        .send_raw_transaction(external.rlp_signed(&signature))
+        .await
+        .unwrap()
+        .tx_hash();
+
+    let (_, mut gas_oracle, mut db) = setup_dependencies();
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = Some(None);
+    let event_listener = MockEventListener::new();
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::ZERO)
+        .set_block_time(Duration::ZERO)
+        .add_event_listener(Arc::new(event_listener.clone()));
+    let result = Manager::new(scenario.middleware(), gas_oracle, db, CHAIN, configuration).await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+
+    // The manager waits for it, without persisting or resubmitting it.
+    let result = manager
+        .wait_for(external_hash, SendOptions::new().set_confirmations(2))
+        .await;
+    assert_ok!(result);
+    let (manager, receipt) = result.unwrap();
+    assert_eq!(external_hash, receipt.transaction_hash);
+    assert_eq!(vec![external_hash], scenario.submitted());
+    assert_eq!(1, scenario.calls(Call::SendRawTransaction)); // the external one
+    assert_eq!(0, database_counters().set_state_n);
+    let events = event_listener.events();
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::Reorged { hash, .. } if *hash == external_hash)));
+    assert!(matches!(
+        events.last(),
+        Some(Event::Confirmed { hash, .. }) if *hash == external_hash
+    ));
+    let block_number = receipt.block_number.unwrap().as_u64();
+    assert_eq!(Some(block_number), scenario.mined_in(external_hash));
+    assert!(scenario.block_number() > block_number);
+
+    // Unknown transactions cannot be waited for.
+    let result = manager
+        .wait_for(H256::repeat_byte(1), SendOptions::new())
+        .await;
+    assert!(matches!(
+        result,
+        Err(eth_tx_manager::Error::UnknownTransaction(hash)) if hash == H256::repeat_byte(1)
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_manager_wait_for_contract_creation() {
+    utilities::setup_tracing();
+
+    // A contract deployment (without a recipient) sent outside the manager,
+    // waited for with the chain's default confirmations.
+    let (mut middleware, gas_oracle, mut db) = setup_dependencies();
+    middleware.get_block_number = vec![1];
+    middleware.get_transaction = Some(Some((1, 1)));
+    middleware.get_transaction_receipt = vec![true];
+    db.get_state_output = Some(None);
+    let configuration = Configuration::new(MockTime)
+        .set_transaction_mining_time(Duration::ZERO)
+        .set_block_time(Duration::ZERO)
+        .set_validate_receipts(true);
+    let result = Manager::new(middleware, gas_oracle, db, CHAIN, configuration).await;
+    assert_ok!(result);
+    let (manager, _) = result.unwrap();
+    let result = manager
+        .wait_for(TRANSACTION_HASH1.parse().unwrap(), SendOptions::new())
+        .await;
+    assert_ok!(result);
+    assert_eq!(0, middleware_counters().send_raw_transaction_n);
+    assert_eq!(0, database_counters().set_state_n);
+}
+
+async fn scenario_manager(
+    scenario: &Scenario,
+) -> Manager<ScenarioMiddleware, MockGasOracle, MockDatabase, ScenarioTime> {
+    scenario_manager_with_configuration(scenario, |configuration| configuration).await
+}
+
+async fn scenario_manager_with_configuration(
+    scenario: &Scenario,
+    f: impl FnOnce(Configuration<ScenarioTime>) -> Configuration<ScenarioTime>,
+) -> Manager<ScenarioMiddleware, MockGasOracle, MockDatabase, ScenarioTime> {
+    let (_, mut gas_oracle, mut db) = setup_dependencies();
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = Some(None);
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let configuration = Configuration::new(scenario.time())
+        .set_transaction_mining_time(Duration::ZERO)
+        .set_block_time(Duration::ZERO);
+    let configuration = f(configuration);
+    let result = Manager::new(scenario.middleware(), gas_oracle, db, CHAIN, configuration).await;
+    assert_ok!(result);
+    result.unwrap().0
+}
+
+#[tokio::test]
+#[serial]
+async fn test_replay() {
+    utilities::setup_tracing();
+    let sender = HASH1.parse().unwrap();
+    let entry = |sequence, transition| JournalEntry {
+        sequence,
+        timestamp: 0,
+        sender,
+        nonce: Some(U256::from(7)),
+        transition,
+        previous: H256::zero(),
+    };
+    let submitted = |max_fee: u64| Transition::Submitted {
+        hash: H256::zero(),
+        endpoint: "production".to_string(),
+        gas_limit: Some(U256::from(21000)),
+        gas_price: None,
+        max_fee: Some(U256::from(max_fee)),
+        max_priority_fee: Some(U256::from(1_000_000)),
+        raw_transaction: None,
+    };
+
+    // Replays the submissions, with the recorded fees, and the confirmation.
+    let entries = [
+        entry(0, submitted(2_000_000_000)),
+        entry(1, submitted(2_200_000_000)),
+        entry(
+            2,
+            Transition::Confirmed {
+                hash: H256::zero(),
+                block_number: Some(U64::from(2)),
+                gas_used: Some(U256::from(21000)),
+                effective_gas_price: Some(U256::from(2_001_000_000)),
+            },
+        ),
+    ];
+    let replay = Replay::from_entries(&entries, sender, U256::from(7)).unwrap();
+    let report = replay.run(CHAIN, |configuration| configuration).await;
+    assert_ok!(report.result);
+    assert_eq!(None, report.divergence(), "{:?}", report.replayed);
+    let fees: Vec<_> = report
+        .replayed
+        .iter()
+        .filter_map(|transition| match transition {
+            Transition::Submitted { max_fee, .. } => *max_fee,
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        vec![U256::from(2_000_000_000u64), U256::from(2_200_000_000u64)],
+        fees
+    );
+    let receipt = report.result.unwrap();
+    assert_eq!(Some(U256::from(2_001_000_000)), receipt.effective_gas_price);
+
+    // Replays the failures.
+    let entries = [
+        entry(0, submitted(2_000_000_000)),
+        entry(
+            1,
+            Transition::Failed {
+                error: "insufficient funds for gas * price + value".to_string(),
+            },
+        ),
+    ];
+    let replay = Replay::from_entries(&entries, sender, U256::from(7)).unwrap();
+    let report = replay.run(CHAIN, |configuration| configuration).await;
+    assert!(report.result.is_err());
+    assert_eq!(None, report.divergence(), "{:?}", report.replayed);
+
+    // Reports divergences.
+    let entries = [
+        entry(0, submitted(2_000_000_000)),
+        entry(1, Transition::Skipped),
+    ];
+    let replay = Replay::from_entries(&entries, sender, U256::from(7)).unwrap();
+    let report = replay.run(CHAIN, |configuration| configuration).await;
+    assert_ok!(report.result);
+    assert_eq!(Some(1), report.divergence());
+
+    // Only replays the sends in the journal.
+    assert!(Replay::from_entries(&entries, sender, U256::from(8)).is_err());
+}
+
+async fn run_scenario(
+    scenario: &Scenario,
+    confirmations: usize,
+    f: impl FnOnce(Configuration<ScenarioTime>) -> Configuration<ScenarioTime>,
+) -> Result<TransactionReceipt, MockManagerError3> {
+    let manager = scenario_manager_with_configuration(scenario, f).await;
+    let transaction = Transaction {
+        from: Scenario::signer(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    manager
+        .send(
+            transaction,
+            SendOptions::new().set_confirmations(confirmations),
+        )
+        .await
+        .map(|(_, receipt)| receipt)
+}
+
+fn setup_dependencies() -> (MockMiddleware, MockGasOracle, MockDatabase) {
+    let mut middleware = mock_middleware();
+    middleware.get_chainid = Some(CHAIN.id);
+    (middleware, mock_gas_oracle(), mock_database())
+}
+
+thread_local! {
+    /// The mocks created last (on the test's thread), whose counters the
+    /// tests check.
+    static MOCKS: RefCell<(Option<MockMiddleware>, Option<MockGasOracle>, Option<MockDatabase>)> =
+        RefCell::default();
+}
+
+fn mock_middleware() -> MockMiddleware {
+    let middleware = MockMiddleware::new();
+    MOCKS.with(|mocks| mocks.borrow_mut().0 = Some(middleware.clone()));
+    middleware
+}
+
+fn mock_gas_oracle() -> MockGasOracle {
+    let gas_oracle = MockGasOracle::new();
+    MOCKS.with(|mocks| mocks.borrow_mut().1 = Some(gas_oracle.clone()));
+    gas_oracle
+}
+
+fn mock_database() -> MockDatabase {
+    let db = MockDatabase::new();
+    MOCKS.with(|mocks| mocks.borrow_mut().2 = Some(db.clone()));
+    db
+}
+
+fn middleware_counters() -> middleware::Counters {
+    MOCKS.with(|mocks| mocks.borrow().0.as_ref().unwrap().counters())
+}
+
+fn gas_oracle_counters() -> MockGasOracleCounters {
+    MOCKS.with(|mocks| mocks.borrow().1.as_ref().unwrap().counters())
+}
+
+fn database_counters() -> database::Counters {
+    MOCKS.with(|mocks| mocks.borrow().2.as_ref().unwrap().counters())
+}
+
+const HASH1: &str = "0xba763b97851b653aaaf631723bab41a500f03b29";
+const HASH2: &str = "0x29e425df042e83e4ddb3ee3348d6d745c58fce8f";
+
+const TRANSACTION_HASH1: &str =
+    "0x2b34df791cc4eb898f6d4437713e946f216cac6a3921b2899db919abe26739b2";
+
+fn setup_configuration() -> Configuration<MockTime> {
+    Configuration::new(MockTime)
+        .set_transaction_mining_time(Duration::ZERO)
+        .set_block_time(Duration::ZERO)
+}
+
+async fn setup_manager<GO: eth_tx_manager::gas_oracle::GasOracle>(
+    middleware: MockMiddleware,
+    gas_oracle: GO,
+    mut db: MockDatabase,
+    configuration: Configuration<MockTime>,
+) -> Manager<MockMiddleware, GO, MockDatabase, MockTime>
+where
+    GO: Send + Sync,
+{
+    db.get_state_output = Some(None);
+    let result = Manager::new(middleware, gas_oracle, db, CHAIN, configuration).await;
+    assert_ok!(result);
+    let (manager, resolution) = result.unwrap();
+    assert_eq!(resolution, PendingResolution::None);
+    manager
+}
+
+fn default_gas_oracle_info() -> GasOracleInfo {
+    GasOracleInfo {
+        gas_info: GasInfo::EIP1559(EIP1559GasInfo {
+            max_fee: Some(Wei::from(1_000_000_000)),
+            max_priority_fee: Some(Wei::from(100_000)),
+        }),
+        mining_time: None,
+        block_time: None,
+        timestamp: None,
+    }
+}
+
+/// Gas oracle info without a max fee (the manager derives it from the latest
+/// base fee).
+fn priority_fee_only_gas_oracle_info(max_priority_fee: u32) -> GasOracleInfo {
+    GasOracleInfo {
+        gas_info: GasInfo::EIP1559(EIP1559GasInfo {
+            max_fee: None,
+            max_priority_fee: Some(U256::from(max_priority_fee).into()),
+        }),
+        mining_time: None,
+        block_time: None,
+        timestamp: None,
+    }
+}
+
+/// Gas oracle info without a max priority fee (forces the manager to query the
+/// latest base fee).
+fn max_fee_only_gas_oracle_info(max_fee: u32) -> GasOracleInfo {
+    GasOracleInfo {
+        gas_info: GasInfo::EIP1559(EIP1559GasInfo {
+            max_fee: Some(U256::from(max_fee).into()),
+            max_priority_fee: None,
+        }),
+        mining_time: None,
+        block_time: None,
+        timestamp: None,
+    }
+}
+
+/// Time that resumes the pause switch after a number of sleeps.
+#[derive(Clone, Debug)]
+struct ResumingTime {
+    pause_switch: PauseSwitch,
+    resume_after: usize,
+    sleeps: Arc<AtomicUsize>,
+}
+
+impl ResumingTime {
+    fn new(pause_switch: PauseSwitch, resume_after: usize) -> Self {
+        Self {
+            pause_switch,
+            resume_after,
+            sleeps: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn sleeps(&self) -> usize {
+        self.sleeps.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Time for ResumingTime {
+    async fn sleep(&self, _: Duration) {
+        if self.sleeps.fetch_add(1, Ordering::SeqCst) + 1 == self.resume_after {
+            self.pause_switch.resume();
+        }
+    }
+
+    fn elapsed(&self, _: Instant) -> Duration {
+        Duration::from_secs(1)
+    }
+}
+
+/// Time that requests a speed-up after the given number of sleeps.
+#[derive(Clone, Debug)]
+struct SpeedingUpTime {
+    speed_up_switch: SpeedUpSwitch,
+    multiplier: f64,
+    speed_up_after: usize,
+    sleeps: Arc<AtomicUsize>,
+}
+
+impl SpeedingUpTime {
+    fn new(speed_up_switch: SpeedUpSwitch, multiplier: f64, speed_up_after: usize) -> Self {
+        Self {
+            speed_up_switch,
+            multiplier,
+            speed_up_after,
+            sleeps: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+#[async_trait]
+impl Time for SpeedingUpTime {
+    async fn sleep(&self, _: Duration) {
+        if self.sleeps.fetch_add(1, Ordering::SeqCst) + 1 == self.speed_up_after {
+            self.speed_up_switch.speed_up(self.multiplier);
+        }
+    }
+
+    fn elapsed(&self, _: Instant) -> Duration {
+        Duration::from_secs(1)
+    }
+}
+
+fn setup_middleware(mut middleware: MockMiddleware) -> MockMiddleware {
+    middleware.estimate_gas = Some(U256::from(21000));
+    middleware.get_block_number = vec![1];
+    middleware.get_transaction_count = Some(());
+    middleware.get_transaction_receipt = vec![true];
+    middleware.send_transaction = Some(());
+    middleware.sign_transaction = Some(());
+    middleware
+}
+
+async fn run_send_transaction(
+    confirmations: usize,
+    f: fn(
+        MockMiddleware,
+        MockGasOracle,
+        MockDatabase,
+    ) -> (MockMiddleware, MockGasOracle, MockDatabase),
+) -> Result<TransactionReceipt, MockManagerError> {
+    run_send_transaction_with_configuration(confirmations, setup_configuration(), f).await
+}
+
+async fn run_send_transaction_with_configuration(
+    confirmations: usize,
+    configuration: Configuration<MockTime>,
+    f: fn(
+        MockMiddleware,
+        MockGasOracle,
+        MockDatabase,
+    ) -> (MockMiddleware, MockGasOracle, MockDatabase),
+) -> Result<TransactionReceipt, MockManagerError> {
+    let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = None;
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let (middleware, gas_oracle, db) = f(middleware, gas_oracle, db);
+
+    let manager = setup_manager(middleware, gas_oracle, db, configuration).await;
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    manager
+        .send(
+            transaction,
+            SendOptions::new().set_confirmations(confirmations),
+        )
+        .await
+        .map(|(_, receipt)| receipt)
+}
+
+async fn run_replace_pending(
+    replacement: Transaction,
+    f: fn(MockMiddleware) -> MockMiddleware,
+) -> Result<TransactionReceipt, MockManagerError> {
+    let (mut middleware, mut gas_oracle, mut db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    gas_oracle.gas_oracle_info_output = Some(default_gas_oracle_info());
+    db.get_state_output = Some(Some(PersistentState {
+        tx_data: StaticTxData {
+            nonce: 1u64.into(),
+            transaction: Transaction {
+                from: HASH1.parse().unwrap(),
+                to: HASH2.parse().unwrap(),
+                value: Value::Number(Wei::from(5)),
+                call_data: None,
+                metadata: Metadata::new(),
+            },
+            priority: Priority::Normal,
+            confirmations: 0,
+            precondition: None,
+        },
+        submitted_txs: SubmittedTxs {
+            txs_hashes: vec![TRANSACTION_HASH1.parse().unwrap()],
+        },
+        cancellation_txs: None,
+        replaced_txs: SubmittedTxs::new(),
+        broadcast_statuses: BTreeMap::new(),
+        attempts: Vec::new(),
+        resubmissions: 0,
+        handoff: None,
+        first_submitted_at: None,
+    }));
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let middleware = f(middleware);
+
+    Manager::replace_pending(
+        middleware,
+        gas_oracle,
+        db,
+        CHAIN,
+        setup_configuration(),
+        replacement,
+    )
+    .await
+    .map(|(_, receipt)| receipt)
+}
+
+async fn run_send_transaction_if(
+    f: fn(MockMiddleware) -> MockMiddleware,
+) -> Result<SendOutcome, MockManagerError2<IncrementingGasOracle>> {
+    let (mut middleware, _, mut db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    db.get_state_output = None;
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let middleware = f(middleware);
+
+    let gas_oracle = IncrementingGasOracle::new();
+    let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let precondition = Precondition {
+        to: HASH2.parse().unwrap(),
+        call_data: Bytes::from(vec![0x12, 0x34]),
+        expected_output: Bytes::from(vec![1]),
+    };
+    manager
+        .send_if(
+            transaction,
+            precondition,
+            SendOptions::new().set_confirmations(0),
+        )
+        .await
+        .map(|(_, outcome)| outcome)
+}
+
+// TODO
+async fn run_send_transaction2<GO: GasOracle>(
+    confirmations: usize,
+    gas_oracle: GO,
+    f: fn(MockMiddleware) -> MockMiddleware,
+) -> Result<TransactionReceipt, MockManagerError2<GO>>
+where
+    GO: Send + Sync,
+{
+    let (mut middleware, _, mut db) = setup_dependencies();
+    middleware = setup_middleware(middleware);
+    db.get_state_output = None;
+    db.set_state_output = Some(());
+    db.clear_state_output = Some(());
+    let middleware = f(middleware);
+
+    let manager = setup_manager(middleware, gas_oracle, db, setup_configuration()).await;
+    let transaction = Transaction {
+        from: HASH1.parse().unwrap(),
+        to: HASH2.parse().unwrap(),
+        value: Value::Number(Wei::from(5)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
     manager
-        .send_transaction(transaction, confirmations, Priority::Normal)
+        .send(
+            transaction,
+            SendOptions::new().set_confirmations(confirmations),
+        )
         .await
         .map(|(_, receipt)| receipt)
 }
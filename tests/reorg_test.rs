@@ -0,0 +1,171 @@
+//! Reorg scenarios against an anvil node (requires `anvil` in the PATH).
+//!
+//! Run with `cargo test --features expensive-tests --test reorg_test`.
+#![cfg(feature = "expensive-tests")]
+
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    types::{Address, BlockNumber, H256, U256},
+    utils::{Anvil, AnvilInstance},
+};
+use std::time::Duration;
+
+use eth_tx_manager::{
+    database::EphemeralDatabase,
+    gas_oracle::NoGasOracle,
+    manager::{Configuration, Manager, SendOptions},
+    transaction::{Metadata, Transaction, Value},
+    Chain, Wei,
+};
+
+const CONFIRMATIONS: usize = 3;
+const AMOUNT: u64 = 1000;
+
+/// Anvil without automatic mining: blocks are only mined on demand, so the
+/// test controls when the transaction is mined and when it is reorged out.
+struct Node {
+    _anvil: AnvilInstance,
+    provider: Provider<Http>,
+    wallet: LocalWallet,
+    chain: Chain,
+    to: Address,
+}
+
+impl Node {
+    fn start() -> Node {
+        let anvil = Anvil::new().arg("--no-mining").spawn();
+        let provider = Provider::<Http>::try_from(anvil.endpoint())
+            .unwrap()
+            .interval(Duration::from_millis(10));
+        let wallet = LocalWallet::from(anvil.keys()[0].clone()).with_chain_id(anvil.chain_id());
+        let chain = Chain::new(anvil.chain_id());
+        let to = anvil.addresses()[1];
+        Node {
+            _anvil: anvil,
+            provider,
+            wallet,
+            chain,
+            to,
+        }
+    }
+
+    async fn snapshot(&self) -> U256 {
+        self.provider.request("evm_snapshot", ()).await.unwrap()
+    }
+
+    async fn revert(&self, snapshot: U256) {
+        let reverted: bool = self
+            .provider
+            .request("evm_revert", [snapshot])
+            .await
+            .unwrap();
+        assert!(reverted);
+    }
+
+    async fn mine(&self, blocks: usize) {
+        for _ in 0..blocks {
+            let _: String = self.provider.request("evm_mine", ()).await.unwrap();
+        }
+    }
+
+    /// Waits for the sender's transaction to reach the transaction pool.
+    async fn wait_for_pending(&self, nonce: U256) {
+        for _ in 0..100 {
+            let pending = self
+                .provider
+                .get_transaction_count(self.wallet.address(), Some(BlockNumber::Pending.into()))
+                .await
+                .unwrap();
+            if pending > nonce {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("the transaction never reached the transaction pool");
+    }
+
+    async fn block_hash(&self, hash: H256) -> Option<H256> {
+        self.provider
+            .get_transaction_receipt(hash)
+            .await
+            .unwrap()
+            .and_then(|receipt| receipt.block_hash)
+    }
+}
+
+#[tokio::test]
+async fn test_reorg_while_confirming() {
+    utilities::setup_tracing();
+    let node = Node::start();
+    let initial_balance = node.provider.get_balance(node.to, None).await.unwrap();
+
+    let configuration = Configuration::default()
+        .set_block_time(Duration::from_millis(100))
+        .set_transaction_mining_time(Duration::from_millis(500));
+    let provider = SignerMiddleware::new(node.provider.clone(), node.wallet.clone());
+    let (manager, _) = Manager::new(
+        provider,
+        NoGasOracle,
+        EphemeralDatabase::new(),
+        node.chain,
+        configuration,
+    )
+    .await
+    .unwrap();
+
+    let snapshot = node.snapshot().await;
+    let transaction = Transaction {
+        from: node.wallet.address(),
+        to: node.to.into(),
+        value: Value::Number(Wei::from(AMOUNT)),
+        call_data: None,
+        metadata: Metadata::new(),
+    };
+    let handle = tokio::spawn(manager.send(
+        transaction,
+        SendOptions::new().set_confirmations(CONFIRMATIONS),
+    ));
+
+    // Mining the transaction (with one confirmation short of enough).
+    node.wait_for_pending(U256::zero()).await;
+    node.mine(CONFIRMATIONS).await;
+    let block = node.provider.get_block(1).await.unwrap().unwrap();
+    let first_hash = block.transactions[0];
+    let first_block_hash = block.hash;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!handle.is_finished());
+
+    // Reorging it out with a longer chain of empty blocks.
+    node.revert(snapshot).await;
+    node.mine(CONFIRMATIONS + 1).await;
+    assert_eq!(None, node.block_hash(first_hash).await);
+
+    // Mining until the manager resends and confirms the transaction.
+    let mut iterations = 0;
+    while !handle.is_finished() {
+        assert!(
+            iterations < 300,
+            "the manager never confirmed the transaction"
+        );
+        node.mine(1).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        iterations += 1;
+    }
+    let (_, receipt) = handle.await.unwrap().unwrap();
+
+    // The receipt is from the canonical chain, with enough confirmations.
+    let block_number = receipt.block_number.unwrap().as_usize();
+    let current_block = node.provider.get_block_number().await.unwrap().as_usize();
+    assert!(current_block - block_number >= CONFIRMATIONS);
+    assert_eq!(
+        receipt.block_hash,
+        node.block_hash(receipt.transaction_hash).await
+    );
+    assert_ne!(first_block_hash, receipt.block_hash);
+
+    // The transfer happened exactly once.
+    let balance = node.provider.get_balance(node.to, None).await.unwrap();
+    assert_eq!(initial_balance + AMOUNT, balance);
+}
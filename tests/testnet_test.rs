@@ -9,10 +9,10 @@ use std::{fs::remove_file, time::Duration};
 use eth_tx_manager::{
     database::{Database, FileSystemDatabase},
     gas_oracle::{GasInfo, GasOracle, GasOracleInfo, LegacyGasInfo},
-    manager::{Configuration, Manager},
+    manager::{Configuration, Manager, SendOptions},
     time::Time,
-    transaction::{Priority, Transaction, Value},
-    Chain,
+    transaction::{Metadata, Priority, Transaction, Value},
+    Chain, Gwei,
 };
 
 use utilities::{Account, ProviderWrapper, TestConfiguration, TEST_CONFIGURATION_PATH};
@@ -143,11 +143,12 @@ where
     let transaction = Transaction {
         from: from.into(),
         to: to.into(),
-        value: Value::Number(utilities::gwei_to_wei(AMOUNT)),
+        value: Value::Number(Gwei::from(AMOUNT).into()),
         call_data: None,
+        metadata: Metadata::new(),
     };
     manager
-        .send_transaction(transaction, 3, Priority::Normal)
+        .send(transaction, SendOptions::new().set_confirmations(3))
         .await
 }
 
@@ -179,9 +180,12 @@ impl GasOracle for TestnetGasOracle {
             let gas_price = self.provider.inner.get_gas_price().await.unwrap();
             let gas_price = gas_price.checked_mul(2.into()).unwrap();
             Ok(GasOracleInfo {
-                gas_info: GasInfo::Legacy(LegacyGasInfo { gas_price }),
+                gas_info: GasInfo::Legacy(LegacyGasInfo {
+                    gas_price: gas_price.into(),
+                }),
                 mining_time: None,
                 block_time: None,
+                timestamp: None,
             })
         } else {
             Err(TestnetGasOracleError::Default)
@@ -0,0 +1,80 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+    utils::{Anvil, AnvilInstance},
+};
+
+use eth_tx_manager::{Chain, Wei};
+
+use crate::{Account, ProviderWrapper};
+
+/// The environment variable with the RPC URL of the chain to fork.
+pub const FORK_URL: &str = "FORK_URL";
+
+/// An anvil fork of a live chain (e.g., mainnet), for dry-running contract
+/// calls, gas estimation, and confirmations against real state before pointing
+/// the manager at production RPCs.
+///
+/// The fork has the chain id of the forked chain, and its account is one of
+/// anvil's (funded) development accounts.
+pub struct Fork {
+    anvil: AnvilInstance,
+    pub account: Account,
+    pub provider: ProviderWrapper,
+}
+
+impl Fork {
+    /// Forks the chain at the URL, mining a block every `block_time` seconds.
+    pub async fn start(fork_url: String, block_time: u64) -> Fork {
+        let anvil = Anvil::new().fork(fork_url).block_time(block_time).spawn();
+        let account = Account {
+            address: hex::encode(anvil.addresses()[0]),
+            private_key: hex::encode(anvil.keys()[0].to_be_bytes()),
+        };
+
+        let chain_id = ProviderWrapper::new(anvil.endpoint(), Chain::new(0), &account)
+            .inner
+            .get_chainid()
+            .await
+            .expect("could not get the chain id of the fork")
+            .as_u64();
+        let provider = ProviderWrapper::new(anvil.endpoint(), Chain::new(chain_id), &account);
+
+        Fork {
+            anvil,
+            account,
+            provider,
+        }
+    }
+
+    /// Forks the chain at the URL in the `FORK_URL` environment variable, if
+    /// set.
+    pub async fn from_env(block_time: u64) -> Option<Fork> {
+        match std::env::var(FORK_URL) {
+            Ok(fork_url) => Some(Fork::start(fork_url, block_time).await),
+            Err(_) => None,
+        }
+    }
+
+    /// The chain of the fork (with the forked chain's id).
+    pub fn chain(&self) -> Chain {
+        self.provider.chain
+    }
+
+    /// A provider for the fork that signs with the account.
+    pub fn provider_for(&self, account: &Account) -> ProviderWrapper {
+        ProviderWrapper::new(self.anvil.endpoint(), self.chain(), account)
+    }
+
+    /// Sets the balance of an address on the fork (e.g., to fund a production
+    /// sender for a dry run).
+    pub async fn set_balance(&self, address: Address, balance: Wei) {
+        let _: () = self
+            .provider
+            .inner
+            .provider()
+            .request("anvil_setBalance", (address, U256::from(balance)))
+            .await
+            .expect("could not set the balance");
+    }
+}
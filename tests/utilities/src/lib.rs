@@ -1,9 +1,11 @@
 mod configuration;
+mod fork;
 mod geth;
 mod utilities;
 
 pub mod mocks;
 
 pub use configuration::{Configuration as TestConfiguration, TEST_CONFIGURATION_PATH};
+pub use fork::{Fork, FORK_URL};
 pub use geth::{Geth, Geth_};
-pub use utilities::{gwei_to_wei, setup_tracing, wei_to_gwei, Account, ProviderWrapper};
+pub use utilities::{setup_tracing, Account, ProviderWrapper};
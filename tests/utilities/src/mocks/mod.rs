@@ -1,4 +1 @@
-pub mod database;
-pub mod gas_oracle;
-pub mod middleware;
-pub mod time;
+pub use eth_tx_manager::test_util::{database, events, gas_oracle, middleware, replay, scenario, time};
@@ -4,12 +4,12 @@ use ethers::{
     prelude::k256::ecdsa::SigningKey,
     providers::{Http, Middleware, Provider},
     signers::{LocalWallet, Signer, Wallet},
-    types::{Address, H160, U256},
+    types::{Address, NameOrAddress, H160},
 };
 use serde::{Deserialize, Serialize};
 use tracing_subscriber::filter::EnvFilter;
 
-use eth_tx_manager::Chain;
+use eth_tx_manager::{Chain, Wei};
 
 // ------------------------------------------------------------------------------------------------
 // Macros
@@ -66,7 +66,7 @@ impl ProviderWrapper {
     pub async fn get_balance_in_gwei(&self, account: &Account) -> u64 {
         let address: Address = account.clone().into();
         let balance_in_wei = self.inner.get_balance(address, None).await.unwrap();
-        wei_to_gwei(balance_in_wei)
+        Wei(balance_in_wei).to_gwei().0.as_u64()
     }
 
     pub async fn get_balance(&self, account1: Account, account2: Account) -> Balance {
@@ -138,6 +138,12 @@ impl From<Account> for H160 {
     }
 }
 
+impl From<Account> for NameOrAddress {
+    fn from(account: Account) -> Self {
+        NameOrAddress::Address(account.into())
+    }
+}
+
 impl From<Account> for LocalWallet {
     fn from(account: Account) -> Self {
         let wallet = account.private_key.parse::<LocalWallet>().unwrap();
@@ -154,14 +160,6 @@ impl From<Account> for LocalWallet {
 // Miscellaneous
 // ------------------------------------------------------------------------------------------------
 
-pub fn wei_to_gwei(wei: U256) -> u64 {
-    wei.as_u64() / (1e9 as u64)
-}
-
-pub fn gwei_to_wei(gwei: u64) -> U256 {
-    U256::from(gwei * (1e9 as u64))
-}
-
 pub fn setup_tracing() {
     // RUST_LOG="tx_manager::manager=trace"
     let _ = tracing_subscriber::FmtSubscriber::builder()